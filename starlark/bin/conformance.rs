@@ -0,0 +1,271 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Running conformance test files, in the format used by the Go and Java Starlark test suites,
+//! where cases are separated by `---` lines and `### error` marks the line a case fails on.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use starlark::{assert::Assert, environment::LibraryExtension};
+
+use crate::expand_dirs;
+
+/// Each case is identified by `file: code`, where `file` is relative to the path it was found
+/// under and `code` is the first line of code in the case, so the names don't change as other
+/// cases are edited. If several cases in a file start with the same line, the later ones have
+/// ` #2`, ` #3` and so on appended.
+struct Case {
+    name: String,
+    failure: Option<String>,
+    known_failure: bool,
+}
+
+impl Case {
+    fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    fn status(&self) -> &'static str {
+        match (self.passed(), self.known_failure) {
+            (true, false) => "PASS",
+            (false, false) => "FAIL",
+            (true, true) => "XPASS",
+            (false, true) => "XFAIL",
+        }
+    }
+}
+
+/// The name of `file`, found by searching `root`, relative to `root` with `/` separators.
+fn relative_name(root: &Path, file: &Path) -> String {
+    let relative = match file.strip_prefix(root) {
+        Ok(x) if x.components().next().is_some() => x,
+        // The root was the file itself
+        _ => file.file_name().map_or(file, Path::new),
+    };
+    relative
+        .components()
+        .map(|x| x.as_os_str().to_string_lossy())
+        .join("/")
+}
+
+/// The first line of a case which isn't blank or a comment.
+fn first_code_line(code: &str) -> &str {
+    code.lines()
+        .map(str::trim)
+        .find(|x| !x.is_empty() && !x.starts_with('#'))
+        .unwrap_or("")
+}
+
+// A list of case names, one per line, ignoring blank lines and `#` comments.
+fn read_known_failures(file: &Path) -> anyhow::Result<HashSet<String>> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| anyhow!("Can't read known failures `{}`: {}", file.display(), e))?;
+    Ok(content
+        .lines()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(|x| x.to_owned())
+        .collect())
+}
+
+// The results are written one case per line, as `PASS name` or `FAIL name`.
+fn read_results(file: &Path) -> anyhow::Result<HashMap<String, bool>> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| anyhow!("Can't read previous results `{}`: {}", file.display(), e))?;
+    content
+        .lines()
+        .filter(|x| !x.is_empty())
+        .map(|x| match x.split_once(' ') {
+            Some(("PASS", name)) => Ok((name.to_owned(), true)),
+            Some(("FAIL", name)) => Ok((name.to_owned(), false)),
+            _ => Err(anyhow!(
+                "Invalid line in previous results `{}`: {}",
+                file.display(),
+                x
+            )),
+        })
+        .collect()
+}
+
+fn write_results(cases: &[Case], file: &Path) -> anyhow::Result<()> {
+    let mut res = String::new();
+    for case in cases {
+        let status = if case.passed() { "PASS" } else { "FAIL" };
+        writeln!(res, "{} {}", status, case.name).unwrap();
+    }
+    fs::write(file, res).map_err(|e| anyhow!("Can't write results to `{}`: {}", file.display(), e))
+}
+
+fn print_diff(cases: &[Case], previous: &HashMap<String, bool>) {
+    let mut fixed = Vec::new();
+    let mut broken = Vec::new();
+    for case in cases {
+        match previous.get(&case.name) {
+            Some(false) if case.passed() => fixed.push(&case.name),
+            Some(true) if !case.passed() => broken.push(&case.name),
+            _ => {}
+        }
+    }
+    let added = cases
+        .iter()
+        .filter(|x| !previous.contains_key(&x.name))
+        .count();
+    let current = cases
+        .iter()
+        .map(|x| x.name.as_str())
+        .collect::<HashSet<_>>();
+    let removed = previous
+        .keys()
+        .filter(|x| !current.contains(x.as_str()))
+        .count();
+
+    println!(
+        "Compared to the previous run: {} newly passing, {} newly failing, {} new cases, {} removed cases",
+        fixed.len(),
+        broken.len(),
+        added,
+        removed
+    );
+    for name in fixed {
+        println!("  newly passing {}", name);
+    }
+    for name in broken {
+        println!("  newly failing {}", name);
+    }
+}
+
+fn run_file(
+    assert: &Assert,
+    file: &str,
+    content: &str,
+    known_failures: &HashSet<String>,
+    cases: &mut Vec<Case>,
+) {
+    let mut seen = HashMap::new();
+    for res in assert.conformance_results(content) {
+        let line = first_code_line(&res.code);
+        let count = seen.entry(line.to_owned()).or_insert(0);
+        *count += 1;
+        let name = match *count {
+            1 => format!("{}: {}", file, line),
+            n => format!("{}: {} #{}", file, line, n),
+        };
+        let case = Case {
+            known_failure: known_failures.contains(&name),
+            name,
+            failure: res.failure,
+        };
+        println!("{} {}", case.status(), case.name);
+        if let (Some(failure), false) = (&case.failure, case.known_failure) {
+            println!("{}", failure.trim_end());
+        }
+        cases.push(case);
+    }
+}
+
+/// Run the cases of every conformance test file in `roots`, searching directories for files
+/// with the extension `ext`, and printing the result of each case.
+/// Cases listed in `known_failures` are expected to fail, and the run fails if any other
+/// case fails or any known failure passes. The results can be written to `output`, and
+/// compared with those written by an earlier run to `previous`.
+pub fn run(
+    roots: Vec<PathBuf>,
+    ext: &str,
+    known_failures: Option<&Path>,
+    output: Option<&Path>,
+    previous: Option<&Path>,
+) -> anyhow::Result<()> {
+    let known_failures = match known_failures {
+        None => HashSet::new(),
+        Some(file) => read_known_failures(file)?,
+    };
+    let previous = previous.map(read_results).transpose()?;
+
+    let mut assert = Assert::new();
+    // The test suites use the `json` module of Go Starlark
+    assert.globals_add(|x| LibraryExtension::JsonModule.add(x));
+    // Otherwise each case is run once with each garbage collection strategy
+    assert.disable_gc();
+    let mut cases = Vec::new();
+    for root in roots {
+        for file in expand_dirs(ext, vec![root.clone()]) {
+            let content = fs::read_to_string(&file)
+                .map_err(|e| anyhow!("Can't read `{}`: {}", file.display(), e))?;
+            let name = relative_name(&root, &file);
+            run_file(&assert, &name, &content, &known_failures, &mut cases);
+        }
+    }
+
+    let count = |status: &str| cases.iter().filter(|x| x.status() == status).count();
+    let (failed, unexpected) = (count("FAIL"), count("XPASS"));
+    println!(
+        "{} cases, {} passed, {} failed, {} known failures, {} known failures passed",
+        cases.len(),
+        count("PASS"),
+        failed,
+        count("XFAIL"),
+        unexpected
+    );
+    if let Some(previous) = &previous {
+        print_diff(&cases, previous);
+    }
+    if let Some(output) = output {
+        write_results(&cases, output)?;
+    }
+
+    if failed > 0 || unexpected > 0 {
+        return Err(anyhow!(
+            "Failed with {} failed cases and {} known failures which passed",
+            failed,
+            unexpected
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_names() {
+        let root = Path::new("testdata");
+        assert_eq!(
+            relative_name(root, Path::new("testdata/go/int.star")),
+            "go/int.star"
+        );
+        assert_eq!(
+            relative_name(
+                Path::new("testdata/int.star"),
+                Path::new("testdata/int.star")
+            ),
+            "int.star"
+        );
+        assert_eq!(
+            first_code_line("\n# Tests of ints\n\n  assert.eq(1, 1)\nx = 2"),
+            "assert.eq(1, 1)"
+        );
+        assert_eq!(first_code_line("# only a comment"), "");
+    }
+}
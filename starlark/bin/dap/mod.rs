@@ -16,10 +16,11 @@
  */
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
+    fmt::{self, Write},
     path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc::{channel, Receiver, Sender},
         Arc, Mutex,
     },
@@ -35,6 +36,7 @@ use starlark::{
     environment::Module,
     eval::Evaluator,
     syntax::{AstModule, Dialect},
+    PrintHandler,
 };
 
 use crate::eval::{dialect, globals};
@@ -48,9 +50,15 @@ struct Backend {
 
     // These breakpoints must all match statements as per before_stmt.
     // Those values for which we abort the execution.
-    breakpoints: Arc<Mutex<HashMap<String, HashSet<FileSpan>>>>,
+    breakpoints: Arc<Mutex<HashMap<String, HashMap<FileSpan, BreakpointConfig>>>>,
     // Set while we are doing evaluate calls (>= 1 means disable)
     disable_breakpoints: Arc<AtomicUsize>,
+    // Should we pause where an error is raised, as per the `raised` exception filter.
+    break_on_error: Arc<AtomicBool>,
+    // Set by a step request, cleared when we next stop.
+    step: Arc<Mutex<Option<Step>>>,
+    // The expandable values handed out by variables requests, cleared when we next stop.
+    variables: Arc<Mutex<Vec<VariablePath>>>,
 
     sender: Sender<Box<dyn Fn(FileSpanRef, &mut Evaluator) -> Next + Send>>,
     receiver: Arc<Mutex<Receiver<Box<dyn Fn(FileSpanRef, &mut Evaluator) -> Next + Send>>>>,
@@ -61,6 +69,321 @@ enum Next {
     RemainPaused,
 }
 
+/// How far to run before pausing again, relative to the call-stack depth
+/// (as per `call_stack_count`) of the statement we were paused at.
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    /// Stop at the next statement, wherever it is.
+    Into,
+    /// Stop at the next statement in the same function, or after it returns.
+    Over(usize),
+    /// Stop at the next statement after the function returns.
+    Out(usize),
+}
+
+impl Step {
+    fn should_stop(self, depth: usize) -> bool {
+        match self {
+            Step::Into => true,
+            Step::Over(start) => depth <= start,
+            Step::Out(start) => depth < start,
+        }
+    }
+}
+
+/// The exception filter for pausing where an error is raised. Errors may still be caught
+/// (e.g. by `assert.fails`), which we can't know when they are raised, so this is off by default.
+const RAISED_FILTER: &str = "raised";
+
+/// The `variablesReference` of the "Locals" scope.
+const LOCALS_REFERENCE: i64 = 1;
+/// The `variablesReference` of the "Globals" scope.
+const GLOBALS_REFERENCE: i64 = 2;
+/// The `variablesReference` of the first entry in `Backend.variables`.
+const FIRST_VARIABLE_REFERENCE: i64 = 3;
+
+/// A step from a value to one of its children, as per `inspect_indexed` and `inspect_named`.
+#[derive(Debug, Clone, Copy)]
+enum Child {
+    Indexed(usize),
+    Named(usize),
+}
+
+/// Where to find an expandable value while paused: follow `path` from the variable at
+/// index `variable` of the locals (or globals).
+#[derive(Debug, Clone)]
+struct VariablePath {
+    globals: bool,
+    variable: usize,
+    path: Vec<Child>,
+}
+
+impl VariablePath {
+    fn child(&self, child: Child) -> Self {
+        let mut res = self.clone();
+        res.path.push(child);
+        res
+    }
+
+    fn resolve<'v>(&self, eval: &Evaluator<'v, '_>) -> Option<starlark::values::Value<'v>> {
+        let vars = if self.globals {
+            eval.module_variables()
+        } else {
+            eval.local_variables()
+        };
+        let mut value = vars.into_iter().nth(self.variable)?.1;
+        for child in &self.path {
+            value = match *child {
+                Child::Indexed(i) => value
+                    .inspect_indexed(i, 1, eval.heap())
+                    .into_iter()
+                    .next()?,
+                Child::Named(i) => value.inspect_named(eval.heap()).into_iter().nth(i)?.1,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// The text shown for a value, which for a large collection would otherwise be enormous.
+fn preview(value: starlark::values::Value) -> String {
+    const MAX_CHARS: usize = 200;
+
+    /// Fails when written to beyond `MAX_CHARS`, so the value stops writing its `repr`.
+    #[derive(Default)]
+    struct Bounded {
+        res: String,
+        chars: usize,
+        truncated: bool,
+    }
+
+    impl Write for Bounded {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            for c in s.chars() {
+                if self.chars == MAX_CHARS {
+                    self.truncated = true;
+                    return Err(fmt::Error);
+                }
+                self.res.push(c);
+                self.chars += 1;
+            }
+            Ok(())
+        }
+    }
+
+    let mut res = Bounded::default();
+    // Only fails if truncated, as `Display` for a value is its `repr`
+    let _ = write!(res, "{}", value);
+    if res.truncated {
+        format!("{}...", res.res)
+    } else {
+        res.res
+    }
+}
+
+/// When a breakpoint with a `hitCondition` stops, given how many times it has been hit.
+/// A plain number `N` means ignore the first `N - 1` hits, i.e. `>= N`.
+#[derive(Debug, Clone, Copy)]
+enum HitCondition {
+    Equal(usize),
+    AtLeast(usize),
+    Greater(usize),
+    AtMost(usize),
+    Less(usize),
+    Multiple(usize),
+}
+
+impl HitCondition {
+    fn parse(x: &str) -> anyhow::Result<Self> {
+        let operators: [(&str, fn(usize) -> Self); 7] = [
+            ("==", HitCondition::Equal),
+            (">=", HitCondition::AtLeast),
+            ("<=", HitCondition::AtMost),
+            (">", HitCondition::Greater),
+            ("<", HitCondition::Less),
+            ("%", HitCondition::Multiple),
+            ("", HitCondition::AtLeast),
+        ];
+        let x = x.trim();
+        let (f, n) = operators
+            .iter()
+            .find_map(|(op, f)| Some((f, x.strip_prefix(op)?)))
+            .unwrap();
+        match n.trim().parse::<usize>() {
+            Ok(n) if n > 0 => Ok(f(n)),
+            _ => Err(anyhow::anyhow!(
+                "Invalid hit condition `{}`, expected a positive number, optionally preceded by one of `==`, `>=`, `<=`, `>`, `<` or `%`",
+                x
+            )),
+        }
+    }
+
+    fn matches(self, hits: usize) -> bool {
+        match self {
+            HitCondition::Equal(n) => hits == n,
+            HitCondition::AtLeast(n) => hits >= n,
+            HitCondition::Greater(n) => hits > n,
+            HitCondition::AtMost(n) => hits <= n,
+            HitCondition::Less(n) => hits < n,
+            HitCondition::Multiple(n) => hits % n == 0,
+        }
+    }
+}
+
+/// The options of a `SourceBreakpoint`.
+#[derive(Debug, Clone)]
+struct BreakpointConfig {
+    /// Only stop if this Starlark expression is true.
+    condition: Option<String>,
+    /// Only stop on some hits, counting those where `condition` is true.
+    hit_condition: Option<HitCondition>,
+    /// Print this message (with `{expr}` interpolated) instead of stopping.
+    log_message: Option<String>,
+    /// Shared between clones, since we clone the config to evaluate it.
+    hits: Arc<AtomicUsize>,
+}
+
+impl BreakpointConfig {
+    fn new(x: &SourceBreakpoint) -> anyhow::Result<Self> {
+        fn non_empty(x: &Option<String>) -> Option<&String> {
+            x.as_ref().filter(|x| !x.trim().is_empty())
+        }
+
+        Ok(Self {
+            condition: non_empty(&x.condition).cloned(),
+            hit_condition: non_empty(&x.hit_condition)
+                .map(|x| HitCondition::parse(x))
+                .transpose()?,
+            log_message: non_empty(&x.log_message).cloned(),
+            hits: Default::default(),
+        })
+    }
+}
+
+/// Sends the output of `print` (and logpoints) to the debugger console.
+struct DapPrintHandler(Client);
+
+impl PrintHandler for DapPrintHandler {
+    fn println(&self, text: &str) -> anyhow::Result<()> {
+        self.0.event_output(OutputEventBody {
+            output: format!("{}\n", text),
+            category: Some("stdout".to_owned()),
+            column: None,
+            data: None,
+            line: None,
+            source: None,
+            variables_reference: None,
+        });
+        Ok(())
+    }
+}
+
+/// Evaluate some code in the frame we are paused at. Breakpoints are disabled while it runs,
+/// not least because we currently don't allow reentrant evaluation.
+fn evaluate_paused<'v>(
+    disable_breakpoints: &AtomicUsize,
+    eval: &mut Evaluator<'v, '_>,
+    code: &str,
+) -> anyhow::Result<starlark::values::Value<'v>> {
+    disable_breakpoints.fetch_add(1, Ordering::SeqCst);
+    let ast = AstModule::parse("interactive", code.to_owned(), &Dialect::Extended);
+    let res = ast.and_then(|ast| eval.eval_statements(ast));
+    disable_breakpoints.fetch_sub(1, Ordering::SeqCst);
+    res
+}
+
+/// Expand the `{expr}` parts of a logpoint message, replacing each with `eval(expr)`.
+/// Use `{{` and `}}` for literal braces. A `{` without a matching `}` is kept as it is.
+fn interpolate(message: &str, mut eval: impl FnMut(&str) -> String) -> String {
+    let mut res = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                res.push(c);
+            }
+            '{' => {
+                let mut code = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    code.push(c);
+                }
+                if closed {
+                    res.push_str(&eval(&code));
+                } else {
+                    res.push('{');
+                    res.push_str(&code);
+                }
+            }
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// Whether we should stop at a statement because of a breakpoint, taking into account its
+/// condition, hit count and log message.
+fn hit_breakpoint(
+    breakpoints: &Mutex<HashMap<String, HashMap<FileSpan, BreakpointConfig>>>,
+    disable_breakpoints: &AtomicUsize,
+    print_handler: &dyn PrintHandler,
+    span: FileSpanRef,
+    eval: &mut Evaluator,
+) -> bool {
+    let span = span.to_file_span();
+    // Take a copy, so we don't hold the lock while evaluating.
+    let config = match breakpoints
+        .lock()
+        .unwrap()
+        .get(span.file().filename())
+        .and_then(|x| x.get(&span))
+    {
+        None => return false,
+        Some(config) => config.clone(),
+    };
+
+    if let Some(condition) = &config.condition {
+        match evaluate_paused(disable_breakpoints, eval, condition) {
+            Ok(v) if !v.to_bool() => return false,
+            Ok(_) => {}
+            Err(e) => {
+                // Stop, so the broken condition gets noticed
+                let _ = print_handler.println(&format!(
+                    "Failed to evaluate breakpoint condition `{}`: {:#}",
+                    condition, e
+                ));
+                return true;
+            }
+        }
+    }
+
+    let hits = config.hits.fetch_add(1, Ordering::SeqCst) + 1;
+    if let Some(hit_condition) = config.hit_condition {
+        if !hit_condition.matches(hits) {
+            return false;
+        }
+    }
+
+    match &config.log_message {
+        Some(message) => {
+            let message = interpolate(message, |code| {
+                match evaluate_paused(disable_breakpoints, eval, code) {
+                    Ok(v) => v.to_str(),
+                    Err(e) => format!("<{}>", e),
+                }
+            });
+            let _ = print_handler.println(&message);
+            false
+        }
+        None => true,
+    }
+}
+
 impl Backend {
     fn inject<T: 'static + Send>(
         &self,
@@ -81,6 +404,15 @@ impl Backend {
         self.inject(box |_, _| (Next::Continue, ()))
     }
 
+    /// Resume execution, pausing again as determined by `f` applied to the current depth.
+    fn inject_step(&self, f: fn(usize) -> Step) {
+        let step = self.step.dupe();
+        self.inject(box move |_, eval| {
+            *step.lock().unwrap() = Some(f(eval.call_stack_count()));
+            (Next::Continue, ())
+        })
+    }
+
     fn with_ctx<T: 'static + Send>(
         &self,
         f: Box<dyn Fn(FileSpanRef, &mut Evaluator) -> T + Send>,
@@ -94,6 +426,9 @@ impl Backend {
         let path = PathBuf::from(path);
         let breakpoints = self.breakpoints.dupe();
         let disable_breakpoints = self.disable_breakpoints.dupe();
+        let break_on_error = self.break_on_error.dupe();
+        let step = self.step.dupe();
+        let variables = self.variables.dupe();
         let receiver = self.receiver.dupe();
 
         let go = move || -> anyhow::Result<String> {
@@ -101,36 +436,69 @@ impl Backend {
             let ast = AstModule::parse_file(&path, &dialect())?;
             let module = Module::new();
             let globals = globals();
+            let print_handler = DapPrintHandler(client.dupe());
             let mut eval = Evaluator::new(&module);
+            eval.set_print_handler(&print_handler);
+            let pause = |reason: &str,
+                         text: Option<String>,
+                         span_loc: FileSpanRef,
+                         eval: &mut Evaluator| {
+                variables.lock().unwrap().clear();
+                client.event_stopped(StoppedEventBody {
+                    reason: reason.to_owned(),
+                    thread_id: Some(0),
+                    description: None,
+                    all_threads_stopped: Some(true),
+                    preserve_focus_hint: None,
+                    text,
+                });
+                loop {
+                    let msg = receiver.lock().unwrap().recv().unwrap();
+                    match msg(span_loc, eval) {
+                        Next::Continue => break,
+                        Next::RemainPaused => continue,
+                    }
+                }
+            };
             let fun = |span_loc: FileSpanRef, eval: &mut Evaluator| {
-                let stop = if disable_breakpoints.load(Ordering::SeqCst) > 0 {
-                    false
+                let reason = if disable_breakpoints.load(Ordering::SeqCst) > 0 {
+                    None
                 } else {
-                    let breaks = breakpoints.lock().unwrap();
-                    breaks
-                        .get(span_loc.file().filename())
-                        .map(|set| set.contains(&span_loc.to_file_span()))
-                        .unwrap_or_default()
-                };
-                if stop {
-                    client.event_stopped(StoppedEventBody {
-                        reason: "breakpoint".to_owned(),
-                        thread_id: Some(0),
-                        description: Some("Hello".to_owned()),
-                        all_threads_stopped: Some(true),
-                        preserve_focus_hint: None,
-                        text: None,
-                    });
-                    loop {
-                        let msg = receiver.lock().unwrap().recv().unwrap();
-                        match msg(span_loc, eval) {
-                            Next::Continue => break,
-                            Next::RemainPaused => continue,
-                        }
+                    let hit = hit_breakpoint(
+                        &breakpoints,
+                        &disable_breakpoints,
+                        &print_handler,
+                        span_loc,
+                        eval,
+                    );
+                    let mut step = step.lock().unwrap();
+                    let reason = if hit {
+                        Some("breakpoint")
+                    } else if step.map_or(false, |x| x.should_stop(eval.call_stack_count())) {
+                        Some("step")
+                    } else {
+                        None
+                    };
+                    // Whatever we were doing, once we stop any step is complete
+                    if reason.is_some() {
+                        *step = None;
                     }
+                    reason
+                };
+                if let Some(reason) = reason {
+                    pause(reason, None, span_loc, eval);
+                }
+            };
+            let on_error = |span_loc: FileSpanRef, e: &anyhow::Error, eval: &mut Evaluator| {
+                if disable_breakpoints.load(Ordering::SeqCst) == 0
+                    && break_on_error.load(Ordering::SeqCst)
+                {
+                    // Once we continue, the error carries on unwinding the stack
+                    pause("exception", Some(format!("{:#}", e)), span_loc, eval);
                 }
             };
             eval.before_stmt(&fun);
+            eval.on_error(&on_error);
             // No way to pass back success/failure to the caller
             client.log(&format!("EVALUATION START: {}", path.display()));
             let v = eval.eval_module(ast, &globals)?;
@@ -162,14 +530,14 @@ impl Backend {
     }
 }
 
-fn breakpoint(verified: bool) -> Breakpoint {
+fn breakpoint(verified: bool, message: Option<String>) -> Breakpoint {
     Breakpoint {
         column: None,
         end_column: None,
         end_line: None,
         id: None,
         line: None,
-        message: None,
+        message,
         source: None,
         verified,
     }
@@ -180,9 +548,19 @@ impl DebugServer for Backend {
         self.client.event_initialized(None);
         Ok(Some(Capabilities {
             supports_configuration_done_request: Some(true),
+            supports_conditional_breakpoints: Some(true),
+            supports_hit_conditional_breakpoints: Some(true),
+            supports_log_points: Some(true),
             supports_evaluate_for_hovers: Some(true),
             supports_set_variable: Some(true),
             supports_step_in_targets_request: Some(true),
+            // Stepping (`next`, `stepIn` and `stepOut`) only goes forwards.
+            supports_step_back: Some(false),
+            exception_breakpoint_filters: Some(vec![ExceptionBreakpointsFilter {
+                filter: RAISED_FILTER.to_owned(),
+                label: "Raised Errors".to_owned(),
+                default: Some(false),
+            }]),
             ..Capabilities::default()
         }))
     }
@@ -204,7 +582,7 @@ impl DebugServer for Backend {
                 Err(_) => {
                     self.breakpoints.lock().unwrap().remove(&source);
                     Ok(SetBreakpointsResponseBody {
-                        breakpoints: vec![breakpoint(false); breakpoints.len()],
+                        breakpoints: vec![breakpoint(false, None); breakpoints.len()],
                     })
                 }
                 Ok(ast) => {
@@ -213,21 +591,36 @@ impl DebugServer for Backend {
                         .iter()
                         .map(|span| (span.resolve_span().begin_line, span.dupe()))
                         .collect();
-                    let list = breakpoints.map(|x| poss.get(&(x.line as usize - 1)));
-                    self.breakpoints
-                        .lock()
-                        .unwrap()
-                        .insert(source, list.iter().filter_map(|x| x.duped()).collect());
+                    let list = breakpoints.map(|x| {
+                        (
+                            poss.get(&(x.line as usize - 1)).duped(),
+                            BreakpointConfig::new(x),
+                        )
+                    });
+                    self.breakpoints.lock().unwrap().insert(
+                        source,
+                        list.iter()
+                            .filter_map(|(span, config)| {
+                                Some((span.dupe()?, config.as_ref().ok()?.clone()))
+                            })
+                            .collect(),
+                    );
                     Ok(SetBreakpointsResponseBody {
-                        breakpoints: list.map(|x| breakpoint(x.is_some())),
+                        breakpoints: list.map(|(span, config)| match config {
+                            Ok(_) => breakpoint(span.is_some(), None),
+                            Err(e) => breakpoint(false, Some(e.to_string())),
+                        }),
                     })
                 }
             }
         }
     }
 
-    fn set_exception_breakpoints(&self, _: SetExceptionBreakpointsArguments) -> anyhow::Result<()> {
-        // We just assume that break on error is always useful
+    fn set_exception_breakpoints(&self, x: SetExceptionBreakpointsArguments) -> anyhow::Result<()> {
+        self.break_on_error.store(
+            x.filters.iter().any(|x| x == RAISED_FILTER),
+            Ordering::SeqCst,
+        );
         Ok(())
     }
 
@@ -308,40 +701,111 @@ impl DebugServer for Backend {
     }
 
     fn scopes(&self, _: ScopesArguments) -> anyhow::Result<ScopesResponseBody> {
+        fn scope(name: &str, variables_reference: i64, len: usize) -> Scope {
+            Scope {
+                name: name.to_owned(),
+                named_variables: Some(len as i64),
+                variables_reference,
+                expensive: false,
+                column: None,
+                end_column: None,
+                end_line: None,
+                indexed_variables: None,
+                line: None,
+                source: None,
+            }
+        }
+
         self.with_ctx(box |_, eval| {
-            let vars = eval.local_variables();
             Ok(ScopesResponseBody {
-                scopes: vec![Scope {
-                    name: "Locals".to_owned(),
-                    named_variables: Some(vars.len() as i64),
-                    variables_reference: 2000,
-                    expensive: false,
-                    column: None,
-                    end_column: None,
-                    end_line: None,
-                    indexed_variables: None,
-                    line: None,
-                    source: None,
-                }],
+                scopes: vec![
+                    scope("Locals", LOCALS_REFERENCE, eval.local_variables().len()),
+                    scope("Globals", GLOBALS_REFERENCE, eval.module_variables().len()),
+                ],
             })
         })
     }
 
-    fn variables(&self, _: VariablesArguments) -> anyhow::Result<VariablesResponseBody> {
-        self.with_ctx(box |_, eval| {
-            let vars = eval.local_variables();
+    fn variables(&self, x: VariablesArguments) -> anyhow::Result<VariablesResponseBody> {
+        let table = self.variables.dupe();
+        self.with_ctx(box move |_, eval| {
+            let heap = eval.heap();
+            let mut table = table.lock().unwrap();
+            // The path, name and value of each variable to return
+            let mut children = Vec::new();
+            match x.variables_reference {
+                LOCALS_REFERENCE | GLOBALS_REFERENCE => {
+                    let globals = x.variables_reference == GLOBALS_REFERENCE;
+                    let vars = if globals {
+                        eval.module_variables()
+                    } else {
+                        eval.local_variables()
+                    };
+                    for (i, (name, value)) in vars.into_iter().enumerate() {
+                        let path = VariablePath {
+                            globals,
+                            variable: i,
+                            path: Vec::new(),
+                        };
+                        children.push((path, name, value));
+                    }
+                }
+                reference => {
+                    let path = usize::try_from(reference - FIRST_VARIABLE_REFERENCE)
+                        .ok()
+                        .and_then(|i| table.get(i).cloned())
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("Unknown variables reference {}", reference)
+                        })?;
+                    let value = path
+                        .resolve(eval)
+                        .ok_or_else(|| anyhow::anyhow!("Variable no longer exists"))?;
+                    let filter = x.filter.as_deref();
+                    if filter != Some("named") {
+                        // A missing or zero count means all the remaining children
+                        let start = x.start.unwrap_or(0).max(0) as usize;
+                        let count = match x.count {
+                            Some(count) if count > 0 => count as usize,
+                            _ => usize::MAX,
+                        };
+                        for (i, v) in value
+                            .inspect_indexed(start, count, heap)
+                            .into_iter()
+                            .enumerate()
+                        {
+                            let i = start + i;
+                            children.push((path.child(Child::Indexed(i)), format!("[{}]", i), v));
+                        }
+                    }
+                    if filter != Some("indexed") {
+                        for (i, (name, v)) in value.inspect_named(heap).into_iter().enumerate() {
+                            children.push((path.child(Child::Named(i)), name, v));
+                        }
+                    }
+                }
+            }
             Ok(VariablesResponseBody {
-                variables: vars
+                variables: children
                     .into_iter()
-                    .map(|(name, value)| Variable {
-                        name,
-                        value: value.to_string(),
-                        type_: Some(value.get_type().to_owned()),
-                        evaluate_name: None,
-                        indexed_variables: None,
-                        named_variables: None,
-                        presentation_hint: None,
-                        variables_reference: 0,
+                    .map(|(path, name, value)| {
+                        let indexed = value.inspect_indexed_count(heap);
+                        let named = value.inspect_named_count();
+                        let variables_reference = if indexed == 0 && named == 0 {
+                            0
+                        } else {
+                            table.push(path);
+                            table.len() as i64 - 1 + FIRST_VARIABLE_REFERENCE
+                        };
+                        Variable {
+                            name,
+                            value: preview(value),
+                            type_: Some(value.get_type().to_owned()),
+                            evaluate_name: None,
+                            indexed_variables: Some(indexed as i64),
+                            named_variables: Some(named as i64),
+                            presentation_hint: None,
+                            variables_reference,
+                        }
                     })
                     .collect(),
             })
@@ -353,18 +817,28 @@ impl DebugServer for Backend {
         Ok(ContinueResponseBody::default())
     }
 
+    fn next(&self, _: NextArguments) -> anyhow::Result<()> {
+        self.inject_step(Step::Over);
+        Ok(())
+    }
+
+    fn step_in(&self, _: StepInArguments) -> anyhow::Result<()> {
+        self.inject_step(|_| Step::Into);
+        Ok(())
+    }
+
+    fn step_out(&self, _: StepOutArguments) -> anyhow::Result<()> {
+        self.inject_step(Step::Out);
+        Ok(())
+    }
+
     fn evaluate(&self, x: EvaluateArguments) -> anyhow::Result<EvaluateResponseBody> {
         let disable_breakpoints = self.disable_breakpoints.dupe();
         self.with_ctx(box move |_, eval| {
-            // We don't want to trigger breakpoints during an evaluate,
-            // not least because we currently don't allow reenterant evaluate
-            disable_breakpoints.fetch_add(1, Ordering::SeqCst);
-            let ast = AstModule::parse("interactive", x.expression.clone(), &Dialect::Extended);
-            let s = match ast.and_then(|ast| eval.eval_statements(ast)) {
+            let s = match evaluate_paused(&disable_breakpoints, eval, &x.expression) {
                 Err(e) => format!("{:#}", e),
                 Ok(v) => v.to_string(),
             };
-            disable_breakpoints.fetch_sub(1, Ordering::SeqCst);
             Ok(EvaluateResponseBody {
                 indexed_variables: None,
                 named_variables: None,
@@ -383,8 +857,58 @@ pub fn server() {
         client,
         breakpoints: Default::default(),
         disable_breakpoints: Default::default(),
+        break_on_error: Default::default(),
+        step: Default::default(),
+        variables: Default::default(),
         file: Default::default(),
         sender,
         receiver: Arc::new(Mutex::new(receiver)),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview() {
+        let heap = starlark::values::Heap::new();
+        assert_eq!(preview(heap.alloc("x")), "\"x\"");
+        let xs = heap.alloc_list(&vec![starlark::values::Value::new_int(1); 1000000]);
+        assert_eq!(preview(xs), format!("[{}1...", "1, ".repeat(66)));
+    }
+
+    #[test]
+    fn test_hit_condition_parse() {
+        let matches = |x: &str| {
+            let condition = HitCondition::parse(x).unwrap();
+            (1..=6)
+                .filter(|x| condition.matches(*x))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matches("3"), vec![3, 4, 5, 6]);
+        assert_eq!(matches(" == 3 "), vec![3]);
+        assert_eq!(matches(">=3"), vec![3, 4, 5, 6]);
+        assert_eq!(matches(">3"), vec![4, 5, 6]);
+        assert_eq!(matches("<=3"), vec![1, 2, 3]);
+        assert_eq!(matches("<3"), vec![1, 2]);
+        assert_eq!(matches("%2"), vec![2, 4, 6]);
+        for x in ["", "0", "==", "-1", "x", "=3", "%0"] {
+            assert!(HitCondition::parse(x).is_err(), "{}", x);
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let eval = |x: &str| format!("<{}>", x.trim());
+        assert_eq!(interpolate("x = {x}, y = { y }", eval), "x = <x>, y = <y>");
+        assert_eq!(interpolate("{{x}} {{{x}}}", eval), "{x} {<x>}");
+        assert_eq!(interpolate("no braces", eval), "no braces");
+        assert_eq!(
+            interpolate("{x} and {unterminated", eval),
+            "<x> and {unterminated"
+        );
+        assert_eq!(interpolate("trailing {", eval), "trailing {");
+        assert_eq!(interpolate("}", eval), "}");
+    }
+}
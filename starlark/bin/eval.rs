@@ -16,19 +16,25 @@
  */
 
 use std::{
+    cell::RefCell,
     fs, iter,
     path::{Path, PathBuf},
+    time::Instant,
 };
 
 use gazebo::prelude::*;
 use itertools::Either;
 use starlark::{
-    environment::{FrozenModule, Globals, Module},
-    eval::Evaluator,
+    environment::{FrozenModule, Globals, LibraryExtension, Module},
+    errors::LintConfig,
+    eval::{Coverage, Evaluator, FilesystemFileLoader, ProfileData, ProfileMode},
     syntax::{AstModule, Dialect},
 };
 
-use crate::types::Message;
+use crate::{
+    test::{TestCase, TestSuite},
+    types::Message,
+};
 
 #[derive(Debug)]
 pub struct Context {
@@ -37,23 +43,50 @@ pub struct Context {
     pub run: bool,
     pub prelude: Vec<FrozenModule>,
     pub module: Option<Module>,
+    pub loader: FilesystemFileLoader,
+    pub lint_config: LintConfig,
+    /// The line coverage of everything evaluated, if enabled. Modules which are loaded are
+    /// covered by the loader instead.
+    pub coverage: Option<RefCell<Coverage>>,
+    /// The globals files are evaluated with, see [`globals_with`].
+    pub globals: Globals,
 }
 
 impl Context {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         check: bool,
         info: bool,
         run: bool,
         prelude: &[PathBuf],
         module: bool,
+        root: Option<PathBuf>,
+        lint_config: LintConfig,
+        coverage: bool,
+        test: bool,
+        json_module: bool,
     ) -> anyhow::Result<Self> {
-        let globals = globals();
+        let globals = globals_with(test, json_module);
+        let mut loader = FilesystemFileLoader::new(&globals, &dialect());
+        if let Some(root) = root {
+            loader = loader.with_root(root);
+        }
+        let coverage = if coverage {
+            loader = loader.with_coverage();
+            Some(RefCell::new(Coverage::new()))
+        } else {
+            None
+        };
         let prelude = prelude.try_map(|x| {
             let env = Module::new();
 
+            let file_loader = loader.for_file(x);
             let mut eval = Evaluator::new(&env);
+            eval.set_loader(&file_loader);
+            Self::enable_coverage(&coverage, &mut eval);
             let module = AstModule::parse_file(x, &dialect())?;
             eval.eval_module(module, &globals)?;
+            Self::collect_coverage(&coverage, &eval)?;
             env.freeze()
         })?;
 
@@ -69,9 +102,40 @@ impl Context {
             run,
             prelude,
             module,
+            loader,
+            lint_config,
+            coverage,
+            globals,
         })
     }
 
+    // Every evaluator must collect coverage if the loader instruments loaded modules for it
+    fn enable_coverage(coverage: &Option<RefCell<Coverage>>, eval: &mut Evaluator) {
+        if coverage.is_some() {
+            eval.enable_coverage();
+        }
+    }
+
+    fn collect_coverage(
+        coverage: &Option<RefCell<Coverage>>,
+        eval: &Evaluator,
+    ) -> anyhow::Result<()> {
+        if let Some(coverage) = coverage {
+            coverage.borrow_mut().merge(&eval.coverage()?);
+        }
+        Ok(())
+    }
+
+    /// The line coverage of everything evaluated so far, including loaded modules,
+    /// or `None` if coverage is not enabled.
+    pub fn coverage(&self) -> Option<Coverage> {
+        let mut res = self.coverage.as_ref()?.borrow().clone();
+        if let Some(loaded) = self.loader.coverage() {
+            res.merge(&loaded);
+        }
+        Some(res)
+    }
+
     fn new_module(prelude: &[FrozenModule]) -> Module {
         let module = Module::new();
         for p in prelude {
@@ -144,10 +208,136 @@ impl Context {
                 &new_module
             }
         };
+        let loader = self.loader.for_file(Path::new(file));
         let mut eval = Evaluator::new(module);
+        eval.set_loader(&loader);
+        eval.enable_terminal_breakpoint_console();
+        Self::enable_coverage(&self.coverage, &mut eval);
+        let res = eval.eval_module(ast, &self.globals).map(|_| ());
+        // Collect the coverage even if evaluation failed
+        let coverage = Self::collect_coverage(&self.coverage, &eval);
+        Self::err(file, res.and(coverage).map(|_| iter::empty()))
+    }
+
+    /// Evaluate the expressions and files `repeat` times with profiling enabled, passing the
+    /// messages from each evaluation to `handle`, then write the profile to `output`.
+    /// Each evaluation is in a fresh module, as without profiling, and their profiles are
+    /// merged, so the profile covers all the evaluations.
+    pub fn profile(
+        &self,
+        mode: &ProfileMode,
+        output: &Path,
+        repeat: usize,
+        expressions: &[String],
+        files: &[PathBuf],
+        mut handle: impl FnMut(Vec<Message>),
+    ) -> anyhow::Result<()> {
+        let expression_loader = self.loader.for_file(Path::new("expression"));
+        let file_loaders = files.map(|x| self.loader.for_file(x));
+        let mut profile = None;
+        for _ in 0..repeat {
+            for e in expressions {
+                let file = "expression";
+                let ast = AstModule::parse(file, e.clone(), &dialect());
+                handle(self.profile_module(mode, file, ast, &expression_loader, &mut profile)?);
+            }
+            for (file, loader) in files.iter().zip(&file_loaders) {
+                let ast = AstModule::parse_file(file, &dialect());
+                let file = &file.to_string_lossy();
+                handle(self.profile_module(mode, file, ast, loader, &mut profile)?);
+            }
+        }
+        match profile {
+            Some(profile) => profile.write(output),
+            // Nothing was evaluated, so write an empty profile
+            None => {
+                let module = Module::new();
+                let mut eval = Evaluator::new(&module);
+                eval.enable_profile(mode);
+                eval.write_profile(mode, output)
+            }
+        }
+    }
+
+    // Evaluate in a fresh module, as it would be without profiling, and add the profile of
+    // the evaluation to `profile`.
+    fn profile_module(
+        &self,
+        mode: &ProfileMode,
+        file: &str,
+        ast: anyhow::Result<AstModule>,
+        loader: &FilesystemFileLoader,
+        profile: &mut Option<ProfileData>,
+    ) -> anyhow::Result<Vec<Message>> {
+        let module = Self::new_module(&self.prelude);
+        let mut eval = Evaluator::new(&module);
         eval.enable_terminal_breakpoint_console();
-        let globals = globals();
-        Self::err(file, eval.eval_module(ast, &globals).map(|_| iter::empty()))
+        eval.enable_profile(mode);
+        Self::enable_coverage(&self.coverage, &mut eval);
+        eval.set_loader(loader);
+        let res = ast.and_then(|ast| eval.eval_module(ast, &self.globals));
+        let messages = Self::err(file, res.map(|_| iter::empty())).collect();
+        Self::collect_coverage(&self.coverage, &eval)?;
+        let data = eval.gen_profile(mode)?;
+        match profile {
+            Some(profile) => profile.merge(&data)?,
+            None => *profile = Some(data),
+        }
+        Ok(messages)
+    }
+
+    /// Run the tests in a file, which are its top-level functions whose names start with
+    /// `test_`. Each test evaluates the file in a fresh module, so tests can't affect each other.
+    pub fn test(&self, file: &Path) -> TestSuite {
+        let filename = file.to_string_lossy().into_owned();
+        let mut cases = Vec::new();
+        let res = fs::read_to_string(file)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| {
+                let ast = AstModule::parse(&filename, content.clone(), &dialect())?;
+                for (_, name) in ast.exported_symbols() {
+                    if name.starts_with("test_") {
+                        cases.extend(self.test_case(&filename, &content, name));
+                    }
+                }
+                Ok(())
+            });
+        TestSuite {
+            error: res.err().map(|e| Message::from_anyhow(&filename, e)),
+            file: filename,
+            cases,
+        }
+    }
+
+    // `None` if `name` turns out not to be a function
+    fn test_case(&self, file: &str, content: &str, name: &str) -> Option<TestCase> {
+        let module = Self::new_module(&self.prelude);
+        let loader = self.loader.for_file(Path::new(file));
+        let mut eval = Evaluator::new(&module);
+        eval.set_loader(&loader);
+        Self::enable_coverage(&self.coverage, &mut eval);
+        let start = Instant::now();
+        let res = AstModule::parse(file, content.to_owned(), &dialect())
+            .and_then(|ast| eval.eval_module(ast, &self.globals))
+            .and_then(|_| match module.get(name) {
+                Some(f) if f.get_type() == "function" => {
+                    eval.eval_function(f, &[], &[]).map(|_| true)
+                }
+                _ => Ok(false),
+            });
+        let time = start.elapsed();
+        // Collect the coverage even if the test failed
+        let coverage = Self::collect_coverage(&self.coverage, &eval);
+        let failure = match res.and_then(|x| coverage.map(|_| x)) {
+            Ok(false) => return None,
+            Ok(true) => None,
+            Err(e) => Some(Message::from_anyhow(file, e)),
+        };
+        Some(TestCase {
+            name: name.to_owned(),
+            time,
+            failure,
+        })
     }
 
     fn info(&self, module: &AstModule) {
@@ -169,7 +359,10 @@ impl Context {
             Some(globals.as_slice())
         };
 
-        module.lint(globals).into_iter().map(Message::from_lint)
+        module
+            .lint_with_config(globals, &self.lint_config)
+            .into_iter()
+            .map(Message::from_lint)
     }
 }
 
@@ -177,6 +370,66 @@ pub fn globals() -> Globals {
     Globals::extended()
 }
 
+/// The extended globals, plus the `assert` module when running tests with `--test`, and
+/// the `json` module in place of the `json()` function with `--json-module`.
+pub fn globals_with(test: bool, json_module: bool) -> Globals {
+    if !test && !json_module {
+        return globals();
+    }
+    let mut extensions = LibraryExtension::all().to_vec();
+    if json_module {
+        extensions.push(LibraryExtension::JsonModule);
+    }
+    if test {
+        extensions.push(LibraryExtension::Assert);
+    }
+    Globals::extended_by(&extensions)
+}
+
 pub fn dialect() -> Dialect {
     Dialect::Extended
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn test_profile_module_per_file() {
+        let ctx = Context::new(
+            false,
+            false,
+            true,
+            &[],
+            false,
+            None,
+            LintConfig::default(),
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+        // Both define `x`, but only the first defines `y`, so the second can't see it
+        let expressions = ["x = 1\ny = x".to_owned(), "x = 2\nz = y".to_owned()];
+        let output = env::temp_dir().join(format!("starlark_profile_test_{}.csv", process::id()));
+        let mut messages = Vec::new();
+        let res = ctx.profile(&ProfileMode::Stmt, &output, 2, &expressions, &[], |x| {
+            messages.push(x)
+        });
+        let csv = fs::read_to_string(&output);
+        let _ = fs::remove_file(&output);
+        res.unwrap();
+        assert_eq!(messages.map(|x| x.len()), vec![0, 1, 0, 1]);
+        assert!(messages[1][0].description.contains("`y`"), "{:?}", messages);
+        // The statements of the first expression, evaluated twice, in one profile
+        let csv = csv.unwrap();
+        let rows = csv
+            .lines()
+            .filter(|x| x.starts_with("\"expression\","))
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 2, "{}", csv);
+        assert!(rows.iter().all(|x| x.ends_with(",2")), "{}", csv);
+    }
+}
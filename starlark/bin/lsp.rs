@@ -17,27 +17,63 @@
 
 //! Based on the reference lsp-server example at <https://github.com/rust-analyzer/lsp-server/blob/master/examples/goto_def.rs>.
 
-use lsp_server::{Connection, Message, Notification};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
 use lsp_types::{
     notification::{
         DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, LogMessage,
         PublishDiagnostics,
     },
+    request::{Completion, Formatting, GotoDefinition, HoverRequest},
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
     Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
-    DidOpenTextDocumentParams, InitializeParams, LogMessageParams, MessageType, NumberOrString,
-    Position, PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability,
-    TextDocumentSyncKind, Url,
+    DidOpenTextDocumentParams, DocumentFormattingParams, Documentation, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability,
+    InitializeParams, Location, LogMessageParams, MarkupContent, MarkupKind, MessageType,
+    NumberOrString, OneOf, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use starlark::{
+    codemap::ResolvedSpan,
+    environment::Globals,
+    eval::FilesystemFileLoader,
+    syntax::AstModule,
+    values::docs::{self, Doc, DocItem, DocString, Identifier},
 };
-use serde::de::DeserializeOwned;
 
 use crate::{
-    eval::Context,
+    eval::{dialect, globals, Context},
     types::{Message as StarlarkMessage, Severity},
 };
 
+/// Resolves the path given to a `load()` statement to a file on disk,
+/// so that definitions can be followed into loaded modules.
+pub trait LoadResolver {
+    /// Resolve `path`, as written in a `load()` statement in `current_file`.
+    fn resolve_load(&self, path: &str, current_file: &Path) -> anyhow::Result<PathBuf>;
+}
+
+impl LoadResolver for FilesystemFileLoader {
+    fn resolve_load(&self, path: &str, current_file: &Path) -> anyhow::Result<PathBuf> {
+        self.for_file(current_file).resolve(path)
+    }
+}
+
 struct Backend {
     connection: Connection,
     starlark: Context,
+    resolver: Box<dyn LoadResolver>,
+    globals: Globals,
+    /// The contents of the documents currently open in the editor.
+    documents: RefCell<HashMap<Url, String>>,
 }
 
 fn to_severity(x: Severity) -> DiagnosticSeverity {
@@ -49,14 +85,44 @@ fn to_severity(x: Severity) -> DiagnosticSeverity {
     }
 }
 
-fn to_diagnostic(x: StarlarkMessage) -> Diagnostic {
-    let range = match x.span {
-        Some(s) => Range::new(
-            Position::new(s.begin_line as u32, s.begin_column as u32),
-            Position::new(s.end_line as u32, s.end_column as u32),
-        ),
-        _ => Range::default(),
-    };
+// LSP counts columns in UTF-16 code units, while spans count them in characters, so converting
+// between the two needs the text of the line.
+fn line_text(text: &str, line: usize) -> &str {
+    text.lines().nth(line).unwrap_or_default()
+}
+
+fn from_position(text: &str, x: Position) -> (usize, usize) {
+    let line = x.line as usize;
+    let mut units = 0;
+    let mut column = 0;
+    for c in line_text(text, line).chars() {
+        if units >= x.character as usize {
+            break;
+        }
+        units += c.len_utf16();
+        column += 1;
+    }
+    (line, column)
+}
+
+fn to_position(text: &str, line: usize, column: usize) -> Position {
+    let units: usize = line_text(text, line)
+        .chars()
+        .take(column)
+        .map(char::len_utf16)
+        .sum();
+    Position::new(line as u32, units as u32)
+}
+
+fn to_range(text: &str, x: ResolvedSpan) -> Range {
+    Range::new(
+        to_position(text, x.begin_line, x.begin_column),
+        to_position(text, x.end_line, x.end_column),
+    )
+}
+
+fn to_diagnostic(text: &str, x: StarlarkMessage) -> Diagnostic {
+    let range = x.span.map(|x| to_range(text, x)).unwrap_or_default();
     Diagnostic::new(
         range,
         Some(to_severity(x.severity)),
@@ -68,11 +134,155 @@ fn to_diagnostic(x: StarlarkMessage) -> Diagnostic {
     )
 }
 
+fn render_doc_string(x: &DocString) -> String {
+    match &x.details {
+        Some(details) => format!("{}\n\n{}", x.summary, details),
+        None => x.summary.clone(),
+    }
+}
+
+fn render_signature(name: &str, x: &docs::Function) -> String {
+    fn with_type(name: String, typ: &Option<docs::Type>) -> String {
+        match typ {
+            Some(typ) => format!("{}: {}", name, typ.raw_type),
+            None => name,
+        }
+    }
+
+    let params: Vec<String> = x
+        .params
+        .iter()
+        .map(|p| match p {
+            docs::Param::Arg {
+                name,
+                typ,
+                default_value,
+                ..
+            } => {
+                let res = with_type(name.clone(), typ);
+                match default_value {
+                    Some(default) => format!("{} = {}", res, default),
+                    None => res,
+                }
+            }
+            docs::Param::NoArgs => "*".to_owned(),
+            docs::Param::Args { name, typ, .. } => with_type(format!("*{}", name), typ),
+            docs::Param::Kwargs { name, typ, .. } => with_type(format!("**{}", name), typ),
+        })
+        .collect();
+    let ret = match &x.ret.typ {
+        Some(typ) => format!(" -> {}", typ.raw_type),
+        None => String::new(),
+    };
+    format!("def {}({}){}", name, params.join(", "), ret)
+}
+
+fn render_function(name: &str, x: &docs::Function) -> String {
+    let mut sections = vec![format!("```python\n{}\n```", render_signature(name, x))];
+    if let Some(docs) = &x.docs {
+        sections.push(render_doc_string(docs));
+    }
+    let params: Vec<String> = x
+        .params
+        .iter()
+        .filter_map(|p| match p {
+            docs::Param::Arg {
+                name,
+                docs: Some(docs),
+                ..
+            }
+            | docs::Param::Args {
+                name,
+                docs: Some(docs),
+                ..
+            }
+            | docs::Param::Kwargs {
+                name,
+                docs: Some(docs),
+                ..
+            } => Some(format!("* `{}`: {}", name, render_doc_string(docs))),
+            _ => None,
+        })
+        .collect();
+    if !params.is_empty() {
+        sections.push(format!("**Parameters**\n\n{}", params.join("\n")));
+    }
+    if let Some(docs) = &x.ret.docs {
+        sections.push(format!("**Returns**\n\n{}", render_doc_string(docs)));
+    }
+    sections.join("\n\n")
+}
+
+/// Render documentation as Markdown for a hover card.
+fn render_doc(x: &Doc) -> String {
+    let docs = match &x.item {
+        DocItem::Function(f) => return render_function(&x.id.name, f),
+        DocItem::Object(x) => &x.docs,
+        DocItem::Module(x) => &x.docs,
+    };
+    let mut sections = vec![format!("```python\n{}\n```", x.id.name)];
+    if let Some(docs) = docs {
+        sections.push(render_doc_string(docs));
+    }
+    sections.join("\n\n")
+}
+
+fn completion_item(label: String, doc: Option<&Doc>) -> CompletionItem {
+    let (kind, detail, docs) = match doc.map(|x| (&x.id.name, &x.item)) {
+        Some((name, DocItem::Function(x))) => (
+            CompletionItemKind::Function,
+            Some(render_signature(name, x)),
+            &x.docs,
+        ),
+        Some((_, DocItem::Object(x))) => (CompletionItemKind::Module, None, &x.docs),
+        Some((_, DocItem::Module(x))) => (CompletionItemKind::Module, None, &x.docs),
+        None => (CompletionItemKind::Variable, None, &None),
+    };
+    CompletionItem {
+        label,
+        kind: Some(kind),
+        detail,
+        documentation: docs
+            .as_ref()
+            .map(|x| Documentation::String(x.summary.clone())),
+        ..CompletionItem::default()
+    }
+}
+
+/// An identifier to make incomplete code parse, see [`Backend::parse_for_completion`].
+const COMPLETION_PLACEHOLDER: &str = "__completion__";
+
+/// Insert `x` at the given line and column (counted in characters) of `text`.
+fn insert_at(text: &str, line: usize, column: usize, x: &str) -> String {
+    let mut offset = 0;
+    for (i, line_text) in text.split_inclusive('\n').enumerate() {
+        if i == line {
+            let line_text = line_text.trim_end_matches(&['\n', '\r'][..]);
+            offset += line_text
+                .char_indices()
+                .nth(column)
+                .map_or(line_text.len(), |(i, _)| i);
+            break;
+        }
+        offset += line_text.len();
+    }
+    let mut res = text.to_owned();
+    res.insert_str(offset, x);
+    res
+}
+
 /// The logic implementations of stuff
 impl Backend {
     fn server_capabilities() -> ServerCapabilities {
         ServerCapabilities {
             text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::Full)),
+            definition_provider: Some(OneOf::Left(true)),
+            hover_provider: Some(HoverProviderCapability::Simple(true)),
+            completion_provider: Some(CompletionOptions {
+                trigger_characters: Some(vec![".".to_owned()]),
+                ..CompletionOptions::default()
+            }),
+            document_formatting_provider: Some(OneOf::Left(true)),
             ..ServerCapabilities::default()
         }
     }
@@ -80,9 +290,10 @@ impl Backend {
     fn validate(&self, uri: Url, version: Option<i64>, text: String) {
         let diags = self
             .starlark
-            .file_with_contents(&uri.to_string(), text)
-            .map(to_diagnostic)
+            .file_with_contents(&uri.to_string(), text.clone())
+            .map(|x| to_diagnostic(&text, x))
             .collect();
+        self.documents.borrow_mut().insert(uri.clone(), text);
         self.publish_diagnostics(uri, diags, version)
     }
 
@@ -105,8 +316,231 @@ impl Backend {
     }
 
     fn did_close(&self, params: DidCloseTextDocumentParams) {
+        self.documents
+            .borrow_mut()
+            .remove(&params.text_document.uri);
         self.publish_diagnostics(params.text_document.uri, Vec::new(), None)
     }
+
+    /// The text of a document, preferring the editor's copy if it is open.
+    fn text(&self, uri: &Url) -> anyhow::Result<String> {
+        match self.documents.borrow().get(uri) {
+            Some(text) => Ok(text.clone()),
+            None => Ok(fs::read_to_string(to_path(uri)?)?),
+        }
+    }
+
+    fn parse(&self, uri: &Url) -> anyhow::Result<AstModule> {
+        AstModule::parse(uri.as_str(), self.text(uri)?, &dialect())
+    }
+
+    /// Parse the `text` of a document for completion at `line` and `column`. Code that is being
+    /// typed is often incomplete (e.g. `x.`), so if it doesn't parse, try again with an identifier
+    /// at the cursor.
+    fn parse_for_completion(
+        &self,
+        uri: &Url,
+        text: &str,
+        line: usize,
+        column: usize,
+    ) -> anyhow::Result<AstModule> {
+        match AstModule::parse(uri.as_str(), text.to_owned(), &dialect()) {
+            Ok(ast) => Ok(ast),
+            Err(_) => AstModule::parse(
+                uri.as_str(),
+                insert_at(text, line, column, COMPLETION_PLACEHOLDER),
+                &dialect(),
+            ),
+        }
+    }
+
+    /// Resolve the module loaded by `path` from `uri`.
+    fn resolve_load(&self, uri: &Url, path: &str) -> anyhow::Result<Url> {
+        let target = self.resolver.resolve_load(path, &to_path(uri)?)?;
+        Url::from_file_path(&target)
+            .map_err(|_| anyhow!("Could not convert `{}` to a URI", target.display()))
+    }
+
+    /// Find where the symbol `name` is defined in the module loaded by `path` from `uri`.
+    fn resolve_loaded(&self, uri: &Url, path: &str, name: &str) -> anyhow::Result<Location> {
+        let target = self.resolve_load(uri, path)?;
+        let text = self.text(&target)?;
+        let ast = AstModule::parse(target.as_str(), text.clone(), &dialect())?;
+        // If the symbol isn't exported, just go to the top of the file.
+        let range = ast
+            .find_exported_symbol(name)
+            .map(|x| to_range(&text, x))
+            .unwrap_or_default();
+        Ok(Location::new(target, range))
+    }
+
+    /// The symbols exported by the module loaded by `path` from `uri`, with their documentation.
+    fn loaded_exports(&self, uri: &Url, path: &str) -> anyhow::Result<Vec<(String, Option<Doc>)>> {
+        let ast = self.parse(&self.resolve_load(uri, path)?)?;
+        Ok(ast
+            .exported_symbols()
+            .into_iter()
+            .map(|(span, name)| {
+                (
+                    name.to_owned(),
+                    ast.find_function_documentation(span.resolve_span()),
+                )
+            })
+            .collect())
+    }
+
+    /// The documentation for the symbol `name` in the module loaded by `path` from `uri`.
+    fn loaded_documentation(
+        &self,
+        uri: &Url,
+        path: &str,
+        name: &str,
+    ) -> anyhow::Result<Option<Doc>> {
+        let ast = self.parse(&self.resolve_load(uri, path)?)?;
+        Ok(ast
+            .find_exported_symbol(name)
+            .and_then(|span| ast.find_function_documentation(span)))
+    }
+
+    fn find_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> anyhow::Result<Option<GotoDefinitionResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let text = self.text(&uri)?;
+        let (line, column) = from_position(&text, params.text_document_position_params.position);
+        let ast = AstModule::parse(uri.as_str(), text.clone(), &dialect())?;
+        if let Some((path, name)) = ast.find_loaded_symbol(line, column) {
+            match self.resolve_loaded(&uri, &path, &name) {
+                Ok(location) => return Ok(Some(GotoDefinitionResponse::Scalar(location))),
+                Err(e) => {
+                    // Fall back to the `load()` statement, which is still better than nothing.
+                    self.log_message(
+                        MessageType::Warning,
+                        &format!("Could not resolve load of `{}`: {:#}", path, e),
+                    );
+                }
+            }
+        }
+        let location = ast
+            .find_definition_location(line, column)
+            .map(|span| Location::new(uri, to_range(&text, span)));
+        Ok(location.map(GotoDefinitionResponse::Scalar))
+    }
+
+    fn goto_definition(&self, id: RequestId, params: GotoDefinitionParams) {
+        self.send_response(new_response(id, self.find_definition(params)));
+    }
+
+    fn find_hover(&self, params: HoverParams) -> anyhow::Result<Option<Hover>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let text = self.text(&uri)?;
+        let (line, column) = from_position(&text, params.text_document_position_params.position);
+        let ast = AstModule::parse(uri.as_str(), text, &dialect())?;
+        let doc = ast.find_documentation(line, column, &self.globals, |path, name| {
+            self.ignore_load_error(path, self.loaded_documentation(&uri, path, name))
+        });
+        Ok(doc.map(|doc| Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: render_doc(&doc),
+            }),
+            range: None,
+        }))
+    }
+
+    fn hover(&self, id: RequestId, params: HoverParams) {
+        self.send_response(new_response(id, self.find_hover(params)));
+    }
+
+    /// Log a failure to follow a `load()`, which shouldn't stop the rest of the request.
+    fn ignore_load_error<T: Default>(&self, path: &str, x: anyhow::Result<T>) -> T {
+        x.unwrap_or_else(|e| {
+            self.log_message(
+                MessageType::Warning,
+                &format!("Could not resolve load of `{}`: {:#}", path, e),
+            );
+            T::default()
+        })
+    }
+
+    fn find_completions(&self, params: CompletionParams) -> anyhow::Result<Vec<CompletionItem>> {
+        let uri = params.text_document_position.text_document.uri;
+        let text = self.text(&uri)?;
+        let (line, column) = from_position(&text, params.text_document_position.position);
+
+        let mut items = Vec::new();
+        let mut seen = HashSet::new();
+        if let Ok(ast) = self.parse_for_completion(&uri, &text, line, column) {
+            if let Some(methods) = ast.find_method_completions(line, column) {
+                return Ok(methods
+                    .iter()
+                    .map(|doc| completion_item(doc.id.name.clone(), Some(doc)))
+                    .collect());
+            }
+            if let Some(path) = ast.find_load_module(line, column) {
+                let names = self.ignore_load_error(path, self.loaded_exports(&uri, path));
+                return Ok(names
+                    .into_iter()
+                    .map(|(name, doc)| completion_item(name, doc.as_ref()))
+                    .collect());
+            }
+            let names = ast.find_names_documentation(line, column, |path, name| {
+                self.ignore_load_error(path, self.loaded_documentation(&uri, path, name))
+            });
+            for (name, doc) in names {
+                seen.insert(name.clone());
+                items.push(completion_item(name, doc.as_ref()));
+            }
+        }
+
+        let mut global_docs = self.globals.member_documentation();
+        for name in self.globals.names() {
+            if seen.contains(&name) {
+                continue;
+            }
+            let doc = global_docs.remove(&name).flatten().map(|item| Doc {
+                id: Identifier {
+                    name: name.clone(),
+                    location: None,
+                },
+                item,
+            });
+            items.push(completion_item(name, doc.as_ref()));
+        }
+        Ok(items)
+    }
+
+    fn completion(&self, id: RequestId, params: CompletionParams) {
+        let items = self
+            .find_completions(params)
+            .map(|items| Some(CompletionResponse::Array(items)));
+        self.send_response(new_response(id, items));
+    }
+
+    /// Format a document, as a single edit replacing the whole text.
+    fn find_formatting(&self, params: DocumentFormattingParams) -> anyhow::Result<Vec<TextEdit>> {
+        let uri = params.text_document.uri;
+        let text = self.text(&uri)?;
+        let formatted = AstModule::parse(uri.as_str(), text.clone(), &dialect())?.format();
+        if formatted == text {
+            return Ok(Vec::new());
+        }
+        let last_line = text.rsplit('\n').next().unwrap_or_default();
+        let end = Position::new(
+            text.matches('\n').count() as u32,
+            last_line.encode_utf16().count() as u32,
+        );
+        Ok(vec![TextEdit::new(
+            Range::new(Position::new(0, 0), end),
+            formatted,
+        )])
+    }
+
+    fn formatting(&self, id: RequestId, params: DocumentFormattingParams) {
+        let edits = self.find_formatting(params).map(Some);
+        self.send_response(new_response(id, edits));
+    }
 }
 
 /// The library style pieces
@@ -118,6 +552,10 @@ impl Backend {
             .unwrap()
     }
 
+    fn send_response(&self, x: Response) {
+        self.connection.sender.send(Message::Response(x)).unwrap()
+    }
+
     fn log_message(&self, typ: MessageType, message: &str) {
         self.send_notification(new_notification::<LogMessage>(LogMessageParams {
             typ,
@@ -139,6 +577,15 @@ impl Backend {
                     if self.connection.handle_shutdown(&req)? {
                         return Ok(());
                     }
+                    if let Some((id, params)) = as_request::<GotoDefinition>(&req) {
+                        self.goto_definition(id, params);
+                    } else if let Some((id, params)) = as_request::<HoverRequest>(&req) {
+                        self.hover(id, params);
+                    } else if let Some((id, params)) = as_request::<Completion>(&req) {
+                        self.completion(id, params);
+                    } else if let Some((id, params)) = as_request::<Formatting>(&req) {
+                        self.formatting(id, params);
+                    }
                     // Currently don't handle any other requests
                 }
                 Message::Notification(x) => {
@@ -159,7 +606,7 @@ impl Backend {
     }
 }
 
-pub fn server(starlark: Context) -> anyhow::Result<()> {
+pub fn server(starlark: Context, resolver: Box<dyn LoadResolver>) -> anyhow::Result<()> {
     // Note that  we must have our logging only write out to stderr.
     eprintln!("Starting Rust Starlark server");
 
@@ -171,6 +618,9 @@ pub fn server(starlark: Context) -> anyhow::Result<()> {
     Backend {
         connection,
         starlark,
+        resolver,
+        globals: globals(),
+        documents: RefCell::new(HashMap::new()),
     }
     .main_loop(initialization_params)?;
     io_threads.join()?;
@@ -179,6 +629,35 @@ pub fn server(starlark: Context) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn to_path(uri: &Url) -> anyhow::Result<PathBuf> {
+    uri.to_file_path()
+        .map_err(|_| anyhow!("`{}` is not a file URI", uri))
+}
+
+fn as_request<T>(x: &Request) -> Option<(RequestId, T::Params)>
+where
+    T: lsp_types::request::Request,
+    T::Params: DeserializeOwned,
+{
+    if x.method == T::METHOD {
+        let params = serde_json::from_value(x.params.clone())
+            .unwrap_or_else(|err| panic!("Invalid request\nMethod: {}\n error: {}", x.method, err));
+        Some((x.id.clone(), params))
+    } else {
+        None
+    }
+}
+
+fn new_response<T>(id: RequestId, result: anyhow::Result<T>) -> Response
+where
+    T: Serialize,
+{
+    match result {
+        Ok(result) => Response::new_ok(id, result),
+        Err(e) => Response::new_err(id, ErrorCode::InternalError as i32, format!("{:#}", e)),
+    }
+}
+
 fn as_notification<T>(x: &Notification) -> Option<T::Params>
 where
     T: lsp_types::notification::Notification,
@@ -206,3 +685,30 @@ where
         params: serde_json::to_value(&params).unwrap(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_positions() {
+        let text = "x = 1\ny = \"😀\" + z\n";
+        // The emoji is one character but two UTF-16 code units.
+        assert_eq!(from_position(text, Position::new(1, 11)), (1, 10));
+        assert_eq!(to_position(text, 1, 10), Position::new(1, 11));
+        assert_eq!(from_position(text, Position::new(0, 4)), (0, 4));
+        assert_eq!(from_position(text, Position::new(0, 100)), (0, 5));
+        assert_eq!(
+            to_range(
+                text,
+                ResolvedSpan {
+                    begin_line: 1,
+                    begin_column: 10,
+                    end_line: 1,
+                    end_column: 11,
+                }
+            ),
+            Range::new(Position::new(1, 11), Position::new(1, 12))
+        );
+    }
+}
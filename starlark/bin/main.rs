@@ -25,21 +25,35 @@
 // Disagree these are good hints
 #![allow(clippy::type_complexity)]
 
-use std::{ffi::OsStr, fmt, fmt::Display, path::PathBuf, sync::Arc};
+use std::{
+    ffi::OsStr,
+    fmt,
+    fmt::Display,
+    fs, iter,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::anyhow;
 use eval::Context;
 use gazebo::prelude::*;
 use itertools::Either;
-use starlark::read_line::ReadLine;
+use starlark::{
+    errors::LintConfig,
+    eval::{Coverage, ProfileMode},
+    read_line::ReadLine,
+    syntax::AstModule,
+};
 use structopt::{clap::AppSettings, StructOpt};
 use walkdir::WalkDir;
 
 use crate::types::{LintMessage, Message, Severity};
 
+mod conformance;
 mod dap;
 mod eval;
 mod lsp;
+mod test;
 mod types;
 
 #[derive(Debug, StructOpt)]
@@ -66,12 +80,63 @@ pub struct Args {
     #[structopt(long = "check", help = "Run checks and lints.")]
     check: bool,
 
+    #[structopt(long = "fmt", help = "Format the files in place.")]
+    fmt: bool,
+
+    #[structopt(
+        long = "fmt-check",
+        help = "Report the files which are not formatted, without changing them."
+    )]
+    fmt_check: bool,
+
     #[structopt(long = "info", help = "Show information about the code.")]
     info: bool,
 
+    #[structopt(
+        long = "test",
+        help = "Run the `test_*` functions in the files, with directories searched for `*_test.star` files. Each test runs in its own module, with an `assert` module of `assert.eq`, `assert.fails` and similar."
+    )]
+    test: bool,
+
+    #[structopt(
+        long = "junit",
+        help = "File to write the test results to in the JUnit XML format, used with `--test`."
+    )]
+    junit: Option<PathBuf>,
+
+    #[structopt(
+        long = "conformance",
+        help = "Run the files as conformance tests, in the format of the Go and Java Starlark test suites. Directories are searched for `.star` files unless `--extension` is given."
+    )]
+    conformance: bool,
+
+    #[structopt(
+        long = "known-failures",
+        help = "File listing the conformance cases expected to fail, one `file: code` per line, where `file` is relative to the path given and `code` is the first line of code in the case, used with `--conformance`."
+    )]
+    known_failures: Option<PathBuf>,
+
+    #[structopt(
+        long = "conformance-output",
+        help = "File to write the result of each conformance case to, used with `--conformance`."
+    )]
+    conformance_output: Option<PathBuf>,
+
+    #[structopt(
+        long = "conformance-previous",
+        help = "File written by `--conformance-output` in a previous run, to compare the results against."
+    )]
+    conformance_previous: Option<PathBuf>,
+
     #[structopt(long = "json", help = "Show output as JSON lines.")]
     json: bool,
 
+    #[structopt(
+        long = "json-module",
+        help = "Offer a `json` module with `json.encode`, `json.decode` and `json.indent`, in place of the `json()` function."
+    )]
+    json_module: bool,
+
     #[structopt(
         long = "repeat",
         help = "Number of times to repeat the execution",
@@ -79,12 +144,42 @@ pub struct Args {
     )]
     repeat: usize,
 
+    #[structopt(
+        long = "profile-mode",
+        help = "Profile the evaluation, one of `heap`, `heap-flame`, `stmt`, `flame`, `chrome-trace`, `bytecode` or `bytecode-pairs`. Each file is evaluated in its own module, and one profile is written covering every repetition of every file."
+    )]
+    profile_mode: Option<ProfileMode>,
+
+    #[structopt(
+        long = "profile-output",
+        help = "File to write the profile to, required with `--profile-mode`."
+    )]
+    profile_output: Option<PathBuf>,
+
+    #[structopt(
+        long = "coverage",
+        help = "File to write the line coverage of the evaluated files and the files they load to, as Cobertura XML if the file ends in `.xml`, otherwise in the lcov format."
+    )]
+    coverage: Option<PathBuf>,
+
     #[structopt(
         long = "extension",
         help = "File extension when searching directories."
     )]
     extension: Option<String>,
 
+    #[structopt(
+        long = "root",
+        help = "Directory that `//` paths in `load()` statements are relative to."
+    )]
+    root: Option<PathBuf>,
+
+    #[structopt(
+        long = "lint-config",
+        help = "JSON file configuring which lints are reported, and their severity."
+    )]
+    lint_config: Option<PathBuf>,
+
     #[structopt(long = "prelude", help = "Files to load in advance.")]
     prelude: Vec<PathBuf>,
 
@@ -185,6 +280,77 @@ fn interactive(ctx: &Context) -> anyhow::Result<()> {
     }
 }
 
+/// Format a file, returning `true` if it was already formatted.
+/// If `write` is set, unformatted files are rewritten.
+fn format_file(file: &Path, write: bool) -> anyhow::Result<bool> {
+    let content = fs::read_to_string(file)?;
+    let formatted =
+        AstModule::parse(&file.to_string_lossy(), content.clone(), &eval::dialect())?.format();
+    if formatted == content {
+        return Ok(true);
+    }
+    if write {
+        fs::write(file, formatted)?;
+    }
+    Ok(false)
+}
+
+fn format_files(
+    files: impl Iterator<Item = PathBuf>,
+    write: bool,
+    json: bool,
+) -> anyhow::Result<()> {
+    let mut stats = Stats::default();
+    let mut unformatted = 0;
+    for file in files {
+        stats.increment_file();
+        match format_file(&file, write) {
+            Ok(true) => {}
+            Ok(false) => {
+                unformatted += 1;
+                if write {
+                    println!("Formatted {}", file.display());
+                } else {
+                    println!("Not formatted {}", file.display());
+                }
+            }
+            Err(e) => drain(
+                iter::once(Message::from_anyhow(&file.to_string_lossy(), e)),
+                json,
+                &mut stats,
+            ),
+        }
+    }
+    if !json {
+        println!("{}", stats);
+    }
+    if stats.error > 0 {
+        return Err(anyhow!("Failed with {} errors", stats.error));
+    }
+    if !write && unformatted > 0 {
+        return Err(anyhow!("{} files are not formatted", unformatted));
+    }
+    Ok(())
+}
+
+fn lint_config(file: Option<&Path>) -> anyhow::Result<LintConfig> {
+    match file {
+        None => Ok(LintConfig::default()),
+        Some(file) => serde_json::from_str(&fs::read_to_string(file)?)
+            .map_err(|e| anyhow!("Invalid lint config `{}`: {}", file.display(), e)),
+    }
+}
+
+fn write_coverage(coverage: &Coverage, file: &Path) -> anyhow::Result<()> {
+    let output = if file.extension() == Some(OsStr::new("xml")) {
+        coverage.to_cobertura()
+    } else {
+        coverage.to_lcov()
+    };
+    fs::write(file, output)
+        .map_err(|e| anyhow!("Can't write coverage to `{}`: {}", file.display(), e))
+}
+
 fn main() -> anyhow::Result<()> {
     let args = argfile::expand_args(argfile::parse_fromfile, argfile::PREFIX)?;
     let args = Args::from_iter(args);
@@ -193,24 +359,89 @@ fn main() -> anyhow::Result<()> {
         .as_ref()
         .map_or("bzl", |x| x.as_str())
         .trim_start_match('.');
+    if args.fmt || args.fmt_check {
+        return format_files(expand_dirs(ext, args.files), args.fmt, args.json);
+    }
+    if !args.conformance
+        && (args.known_failures.is_some()
+            || args.conformance_output.is_some()
+            || args.conformance_previous.is_some())
+    {
+        return Err(anyhow!(
+            "`--known-failures`, `--conformance-output` and `--conformance-previous` require `--conformance`"
+        ));
+    }
+    if args.conformance {
+        let ext = args.extension.as_ref().map_or("star", |x| x.as_str());
+        return conformance::run(
+            args.files,
+            ext.trim_start_match('.'),
+            args.known_failures.as_deref(),
+            args.conformance_output.as_deref(),
+            args.conformance_previous.as_deref(),
+        );
+    }
     let mut ctx = Context::new(
         args.check,
         args.info,
         !args.check && !args.info,
         &expand_dirs(ext, args.prelude).collect::<Vec<_>>(),
         args.interactive,
+        args.root,
+        lint_config(args.lint_config.as_deref())?,
+        args.coverage.is_some(),
+        args.test,
+        args.json_module,
     )?;
 
+    if args.junit.is_some() && !args.test {
+        return Err(anyhow!("`--junit` requires `--test`"));
+    }
+    if args.test {
+        if !ctx.run {
+            return Err(anyhow!("`--test` can't be used with `--check` or `--info`"));
+        }
+        let res = test::run(&ctx, test::test_files(args.files), args.junit.as_deref());
+        if let (Some(file), Some(coverage)) = (&args.coverage, ctx.coverage()) {
+            write_coverage(&coverage, file)?;
+        }
+        return res;
+    }
+
     let mut stats = Stats::default();
-    for _ in 0..args.repeat {
-        for e in args.evaluate.clone() {
-            stats.increment_file();
-            drain(ctx.expression(e), args.json, &mut stats);
+    if let Some(mode) = &args.profile_mode {
+        let output = args
+            .profile_output
+            .as_ref()
+            .ok_or_else(|| anyhow!("`--profile-mode` requires `--profile-output`"))?;
+        if !ctx.run {
+            return Err(anyhow!(
+                "`--profile-mode` can't be used with `--check` or `--info`"
+            ));
         }
+        let files = expand_dirs(ext, args.files.clone()).collect::<Vec<_>>();
+        ctx.profile(
+            mode,
+            output,
+            args.repeat,
+            &args.evaluate,
+            &files,
+            |messages| {
+                stats.increment_file();
+                drain(messages.into_iter(), args.json, &mut stats);
+            },
+        )?;
+    } else {
+        for _ in 0..args.repeat {
+            for e in args.evaluate.clone() {
+                stats.increment_file();
+                drain(ctx.expression(e), args.json, &mut stats);
+            }
 
-        for file in expand_dirs(ext, args.files.clone()) {
-            stats.increment_file();
-            drain(ctx.file(&file), args.json, &mut stats);
+            for file in expand_dirs(ext, args.files.clone()) {
+                stats.increment_file();
+                drain(ctx.file(&file), args.json, &mut stats);
+            }
         }
     }
 
@@ -218,11 +449,16 @@ fn main() -> anyhow::Result<()> {
         interactive(&ctx)?;
     }
 
+    if let (Some(file), Some(coverage)) = (&args.coverage, ctx.coverage()) {
+        write_coverage(&coverage, file)?;
+    }
+
     if args.lsp {
         ctx.check = true;
         ctx.info = false;
         ctx.run = false;
-        lsp::server(ctx)?;
+        let loader = box ctx.loader.clone();
+        lsp::server(ctx, loader)?;
     } else if args.dap {
         dap::server()
     }
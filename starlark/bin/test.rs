@@ -0,0 +1,299 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Running the `test_*` functions in `*_test.star` files, and reporting the results.
+
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use itertools::Either;
+use walkdir::WalkDir;
+
+use crate::{eval::Context, types::Message};
+
+/// The result of running one test function.
+pub struct TestCase {
+    pub name: String,
+    pub time: Duration,
+    pub failure: Option<Message>,
+}
+
+/// The results of running the tests in one file.
+pub struct TestSuite {
+    pub file: String,
+    pub cases: Vec<TestCase>,
+    /// Set if the file could not be read or parsed, so no tests were run.
+    pub error: Option<Message>,
+}
+
+impl TestSuite {
+    /// The number of test cases, including the one reporting an error loading the file.
+    fn tests(&self) -> usize {
+        self.cases.len() + self.errors()
+    }
+
+    fn failures(&self) -> usize {
+        self.cases.iter().filter(|x| x.failure.is_some()).count()
+    }
+
+    fn errors(&self) -> usize {
+        if self.error.is_some() {
+            1
+        } else {
+            0
+        }
+    }
+
+    fn time(&self) -> Duration {
+        self.cases.iter().map(|x| x.time).sum()
+    }
+}
+
+// Directories are walked for files ending in `_test.star`, everything else is a test file.
+pub fn test_files(xs: Vec<PathBuf>) -> impl Iterator<Item = PathBuf> {
+    xs.into_iter().flat_map(|x| {
+        if x.is_dir() {
+            Either::Left(
+                WalkDir::new(x)
+                    .sort_by_file_name()
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| {
+                        e.file_type().is_file()
+                            && e.file_name().to_string_lossy().ends_with("_test.star")
+                    })
+                    .map(|e| e.into_path()),
+            )
+        } else {
+            Either::Right(box vec![x].into_iter())
+        }
+    })
+}
+
+fn describe(x: &Message) -> String {
+    match &x.full_error_with_span {
+        Some(error) => error.trim_end().to_owned(),
+        None => x.to_string(),
+    }
+}
+
+fn escape(x: &str) -> String {
+    x.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The results in the JUnit XML format, as understood by most CI systems.
+fn junit(suites: &[TestSuite]) -> String {
+    let tests: usize = suites.iter().map(|x| x.tests()).sum();
+    let failures: usize = suites.iter().map(|x| x.failures()).sum();
+    let errors: usize = suites.iter().map(|x| x.errors()).sum();
+    let time: Duration = suites.iter().map(|x| x.time()).sum();
+
+    let mut res = String::new();
+    writeln!(res, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        res,
+        r#"<testsuites name="starlark" tests="{}" failures="{}" errors="{}" time="{:.3}">"#,
+        tests,
+        failures,
+        errors,
+        time.as_secs_f64()
+    )
+    .unwrap();
+    for suite in suites {
+        let file = escape(&suite.file);
+        writeln!(
+            res,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" time="{:.3}">"#,
+            file,
+            suite.tests(),
+            suite.failures(),
+            suite.errors(),
+            suite.time().as_secs_f64()
+        )
+        .unwrap();
+        if let Some(error) = &suite.error {
+            writeln!(
+                res,
+                r#"    <testcase name="{}" classname="{}" time="0">"#,
+                file, file
+            )
+            .unwrap();
+            writeln!(
+                res,
+                r#"      <error message="{}">{}</error>"#,
+                escape(&error.description),
+                escape(&describe(error))
+            )
+            .unwrap();
+            writeln!(res, "    </testcase>").unwrap();
+        }
+        for case in &suite.cases {
+            write!(
+                res,
+                r#"    <testcase name="{}" classname="{}" time="{:.3}""#,
+                escape(&case.name),
+                file,
+                case.time.as_secs_f64()
+            )
+            .unwrap();
+            match &case.failure {
+                None => writeln!(res, "/>").unwrap(),
+                Some(failure) => {
+                    writeln!(res, ">").unwrap();
+                    writeln!(
+                        res,
+                        r#"      <failure message="{}">{}</failure>"#,
+                        escape(&failure.description),
+                        escape(&describe(failure))
+                    )
+                    .unwrap();
+                    writeln!(res, "    </testcase>").unwrap();
+                }
+            }
+        }
+        writeln!(res, "  </testsuite>").unwrap();
+    }
+    writeln!(res, "</testsuites>").unwrap();
+    res
+}
+
+/// Run the tests in every file, printing the result of each test as it finishes, and
+/// optionally writing a JUnit XML report. Fails if any test failed.
+pub fn run(
+    ctx: &Context,
+    files: impl Iterator<Item = PathBuf>,
+    junit_output: Option<&Path>,
+) -> anyhow::Result<()> {
+    let mut suites = Vec::new();
+    for file in files {
+        let suite = ctx.test(&file);
+        if let Some(error) = &suite.error {
+            println!("ERROR {}", suite.file);
+            println!("{}", describe(error));
+        }
+        for case in &suite.cases {
+            let status = if case.failure.is_some() {
+                "FAIL"
+            } else {
+                "PASS"
+            };
+            println!(
+                "{} {} {} ({:.3}s)",
+                status,
+                suite.file,
+                case.name,
+                case.time.as_secs_f64()
+            );
+            if let Some(failure) = &case.failure {
+                println!("{}", describe(failure));
+            }
+        }
+        suites.push(suite);
+    }
+
+    let tests: usize = suites.iter().map(|x| x.tests()).sum();
+    let failures: usize = suites.iter().map(|x| x.failures()).sum();
+    let errors: usize = suites.iter().map(|x| x.errors()).sum();
+    println!(
+        "{} tests, {} passed, {} failed, {} files with errors",
+        tests,
+        tests - failures,
+        failures,
+        errors
+    );
+
+    if let Some(file) = junit_output {
+        fs::write(file, junit(&suites))
+            .map_err(|e| anyhow!("Can't write JUnit report to `{}`: {}", file.display(), e))?;
+    }
+    if failures > 0 || errors > 0 {
+        return Err(anyhow!(
+            "Failed with {} failed tests and {} files with errors",
+            failures,
+            errors
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_junit() {
+        let failure = |file, msg| Some(Message::from_anyhow(file, anyhow!("{}", msg)));
+        let suites = [
+            TestSuite {
+                file: "a_test.star".to_owned(),
+                cases: vec![
+                    TestCase {
+                        name: "test_ok".to_owned(),
+                        time: Duration::from_millis(1500),
+                        failure: None,
+                    },
+                    TestCase {
+                        name: "test_bad".to_owned(),
+                        time: Duration::from_millis(250),
+                        failure: failure("a_test.star", r#"1 < 2 & "x""#),
+                    },
+                ],
+                error: None,
+            },
+            TestSuite {
+                file: "b_test.star".to_owned(),
+                cases: Vec::new(),
+                error: failure("b_test.star", "bad syntax"),
+            },
+        ];
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="starlark" tests="3" failures="1" errors="1" time="1.750">
+  <testsuite name="a_test.star" tests="2" failures="1" errors="0" time="1.750">
+    <testcase name="test_ok" classname="a_test.star" time="1.500"/>
+    <testcase name="test_bad" classname="a_test.star" time="0.250">
+      <failure message="1 &lt; 2 &amp; &quot;x&quot;">Error: a_test.star: 1 &lt; 2 &amp; &quot;x&quot;</failure>
+    </testcase>
+  </testsuite>
+  <testsuite name="b_test.star" tests="1" failures="0" errors="1" time="0.000">
+    <testcase name="b_test.star" classname="b_test.star" time="0">
+      <error message="bad syntax">Error: b_test.star: bad syntax</error>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+        assert_eq!(junit(&suites), expected);
+    }
+
+    #[test]
+    fn test_junit_empty() {
+        assert_eq!(
+            junit(&[]),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="starlark" tests="0" failures="0" errors="0" time="0.000">
+</testsuites>
+"#
+        );
+    }
+}
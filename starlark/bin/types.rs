@@ -21,7 +21,7 @@ use gazebo::prelude::*;
 use serde::Serialize;
 use starlark::{
     codemap::ResolvedSpan,
-    errors::{Diagnostic, Lint},
+    errors::{Diagnostic, Lint, LintSeverity},
 };
 
 /// A standardised set of severities.
@@ -30,8 +30,6 @@ use starlark::{
 pub enum Severity {
     Error,
     Warning,
-    // Not all severities are used right now
-    #[allow(dead_code)]
     Advice,
     Disabled,
 }
@@ -107,11 +105,11 @@ impl Message {
         Self {
             path: x.location.file().filename().to_owned(),
             span: Some(x.location.resolve_span()),
-            severity: if x.serious {
-                Severity::Warning
-            } else {
-                // Start with all non-serious errors disabled, and ramp up from there
-                Severity::Disabled
+            severity: match x.severity {
+                LintSeverity::Error => Severity::Error,
+                LintSeverity::Warning => Severity::Warning,
+                LintSeverity::Advice => Severity::Advice,
+                LintSeverity::Disabled => Severity::Disabled,
             },
             name: x.short_name,
             description: x.problem,
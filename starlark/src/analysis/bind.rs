@@ -48,12 +48,13 @@ pub(crate) enum Bind {
 #[derive(Debug)]
 pub(crate) struct Scope {
     pub inner: Vec<Bind>,
+    pub(crate) span: Span,                  // The code this scope covers
     pub(crate) free: HashMap<String, Span>, // Things referred to in this scope, or inner scopes, that we don't define
     pub(crate) bound: HashMap<String, (Assigner, Span)>, // Things bound in this scope, doesn't include inner scope bindings
 }
 
 impl Scope {
-    fn new(inner: Vec<Bind>) -> Self {
+    fn new(inner: Vec<Bind>, span: Span) -> Self {
         let mut bound: HashMap<String, _> = HashMap::new();
         let mut free: HashMap<String, _> = HashMap::new();
         for x in &inner {
@@ -74,7 +75,12 @@ impl Scope {
             free.remove(x);
         }
 
-        Self { inner, free, bound }
+        Self {
+            inner,
+            span,
+            free,
+            bound,
+        }
     }
 }
 
@@ -85,6 +91,7 @@ fn opt_expr(x: Option<&AstExpr>, res: &mut Vec<Bind>) {
 }
 
 fn comprehension(
+    span: Span,
     for_: &ForClause,
     clauses: &[Clause],
     res: &mut Vec<Bind>,
@@ -103,26 +110,29 @@ fn comprehension(
         }
     }
     end(&mut inner);
-    res.push(Bind::Scope(Scope::new(inner)))
+    res.push(Bind::Scope(Scope::new(inner, span)))
 }
 
 fn expr(x: &AstExpr, res: &mut Vec<Bind>) {
+    let span = x.span;
     match &**x {
         Expr::Identifier(x, _) => res.push(Bind::Get(x.clone())),
         Expr::Lambda(args, body, _) => {
             let mut inner = Vec::new();
             parameters(args, res, &mut inner);
             expr(body, &mut inner);
-            res.push(Bind::Scope(Scope::new(inner)));
+            res.push(Bind::Scope(Scope::new(inner, span)));
         }
 
         Expr::ListComprehension(x, for_, clauses) => {
-            comprehension(for_, clauses, res, |res| expr(x, res))
+            comprehension(span, for_, clauses, res, |res| expr(x, res))
+        }
+        Expr::DictComprehension(x, for_, clauses) => {
+            comprehension(span, for_, clauses, res, |res| {
+                expr(&x.0, res);
+                expr(&x.1, res)
+            })
         }
-        Expr::DictComprehension(x, for_, clauses) => comprehension(for_, clauses, res, |res| {
-            expr(&x.0, res);
-            expr(&x.1, res)
-        }),
 
         // Uninteresting - just recurse
         _ => x.visit_expr(|x| expr(x, res)),
@@ -183,7 +193,7 @@ fn stmt(x: &AstStmt, res: &mut Vec<Bind>) {
             parameters(args, res, &mut inner);
             res.push(Bind::Set(Assigner::Assign, name.clone()));
             stmt(body, &mut inner);
-            res.push(Bind::Scope(Scope::new(inner)));
+            res.push(Bind::Scope(Scope::new(inner, x.span)));
         }
         Stmt::Assign(lhs, rhs) => {
             expr(rhs, res);
@@ -206,6 +216,13 @@ fn stmt(x: &AstStmt, res: &mut Vec<Bind>) {
             stmt(body, res);
             flow(res)
         }
+        Stmt::While(cond, box body) => {
+            flow(res);
+            expr(cond, res);
+            flow(res);
+            stmt(body, res);
+            flow(res)
+        }
         Stmt::Load(load) => {
             for x in &load.node.args {
                 res.push(Bind::Set(Assigner::Load, x.0.clone()))
@@ -217,5 +234,5 @@ fn stmt(x: &AstStmt, res: &mut Vec<Bind>) {
 pub(crate) fn scope(module: &AstModule) -> Scope {
     let mut res = Vec::new();
     stmt(&module.statement, &mut res);
-    Scope::new(res)
+    Scope::new(res, module.statement.span)
 }
@@ -0,0 +1,161 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Configuration deciding which lints are reported, and how severe they are.
+
+use std::collections::{HashMap, HashSet};
+
+use gazebo::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::types::Lint;
+
+/// How severe a [`Lint`] is.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    /// The code should be rejected.
+    Error,
+    /// The code is highly-likely to be wrong.
+    Warning,
+    /// The code could be improved.
+    Advice,
+    /// The lint is produced, but not worth showing by default.
+    Disabled,
+}
+
+/// Configuration for [`AstModule::lint_with_config`](crate::syntax::AstModule::lint_with_config).
+///
+/// Lints are identified by their [`short_name`](Lint::short_name). By default serious lints
+/// are a [`Warning`](LintSeverity::Warning) and all others are [`Disabled`](LintSeverity::Disabled).
+/// The configuration can be loaded with `serde`, e.g. from the JSON:
+///
+/// ```json
+/// {
+///     "disable": ["unused-load"],
+///     "enable": ["underscore-function"],
+///     "severity": {"missing-return": "error"},
+///     "warnings_as_errors": false
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    /// Lints which are not reported at all.
+    pub disable: HashSet<String>,
+    /// Lints which are reported as a [`Warning`](LintSeverity::Warning), even if not serious.
+    pub enable: HashSet<String>,
+    /// Lints whose severity is overridden, taking priority over [`enable`](LintConfig::enable).
+    pub severity: HashMap<String, LintSeverity>,
+    /// Report every [`Warning`](LintSeverity::Warning) as an [`Error`](LintSeverity::Error).
+    pub warnings_as_errors: bool,
+}
+
+impl LintConfig {
+    /// The severity of a lint, or `None` if it should not be reported.
+    fn severity(&self, lint: &Lint) -> Option<LintSeverity> {
+        let name = &lint.short_name;
+        if self.disable.contains(name) {
+            return None;
+        }
+        let severity = match self.severity.get(name) {
+            Some(severity) => *severity,
+            None if self.enable.contains(name) => LintSeverity::Warning,
+            None => lint.severity,
+        };
+        if self.warnings_as_errors && severity == LintSeverity::Warning {
+            Some(LintSeverity::Error)
+        } else {
+            Some(severity)
+        }
+    }
+
+    pub(crate) fn apply(&self, lints: Vec<Lint>) -> Vec<Lint> {
+        lints
+            .into_iter()
+            .filter_map(|mut lint| {
+                lint.severity = self.severity(&lint)?;
+                Some(lint)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::{AstModule, Dialect};
+
+    fn module(x: &str) -> AstModule {
+        AstModule::parse("X", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    fn severities(lints: &[Lint]) -> Vec<(&str, LintSeverity)> {
+        lints.map(|x| (x.short_name.as_str(), x.severity))
+    }
+
+    const CODE: &str = r#"
+def f() -> int:
+    pass
+def g():
+    print(1)
+    return
+"#;
+
+    #[test]
+    fn test_lint_config_default() {
+        let m = module(CODE);
+        assert_eq!(
+            severities(&m.lint(None)),
+            vec![
+                ("missing-return", LintSeverity::Warning),
+                ("redundant-return", LintSeverity::Disabled),
+            ]
+        );
+        assert_eq!(
+            severities(&m.lint(None)),
+            severities(&m.lint_with_config(None, &LintConfig::default()))
+        );
+    }
+
+    #[test]
+    fn test_lint_config() {
+        let m = module(CODE);
+        let config: LintConfig = serde_json::from_str(
+            r#"{"disable": ["missing-return"], "enable": ["redundant-return"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            severities(&m.lint_with_config(None, &config)),
+            vec![("redundant-return", LintSeverity::Warning)]
+        );
+
+        let config: LintConfig = serde_json::from_str(
+            r#"{"severity": {"redundant-return": "advice"}, "warnings_as_errors": true}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            severities(&m.lint_with_config(None, &config)),
+            vec![
+                ("missing-return", LintSeverity::Error),
+                ("redundant-return", LintSeverity::Advice),
+            ]
+        );
+
+        assert!(serde_json::from_str::<LintConfig>(r#"{"disabled": []}"#).is_err());
+    }
+}
@@ -0,0 +1,626 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::{
+    analysis::bind::{self, Assigner, Bind, Scope},
+    codemap::{Pos, ResolvedSpan, Span},
+    environment::{Globals, Methods},
+    syntax::{
+        ast::{AstExpr, AstLiteral, AstParameter, AstStmt, AstString, Expr, Parameter, Stmt},
+        AstModule,
+    },
+    values::{
+        dict::dict_methods,
+        docs::{self, Doc, DocItem, DocString, DocStringKind, Identifier},
+        list::list_methods,
+        string::str_methods,
+    },
+};
+
+/// The location a symbol was defined, as found by [`AstModule::find_definition`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Definition {
+    /// The symbol is defined in this module at the given location.
+    Location(ResolvedSpan),
+    /// The symbol was brought into scope by a `load()` statement.
+    LoadedLocation {
+        /// The location of the symbol in the `load()` statement of this module.
+        destination: ResolvedSpan,
+        /// The module path, as written in the `load()` statement.
+        path: String,
+        /// The name of the symbol in the loaded module.
+        name: String,
+    },
+    /// The identifier is not defined in this module, so is either a global or an error.
+    Global(String),
+    /// There is no identifier at the position.
+    NotFound,
+}
+
+/// Find the identifier at `pos`, along with where it was first bound in the innermost
+/// enclosing scope, or `None` if it is not bound in this module.
+fn find_binding<'a>(
+    scope: &'a Scope,
+    pos: Pos,
+    stack: &mut Vec<&'a Scope>,
+) -> Option<(&'a str, Option<(Assigner, Span)>)> {
+    fn lookup<'a>(stack: &[&Scope], name: &'a str) -> Option<(&'a str, Option<(Assigner, Span)>)> {
+        Some((
+            name,
+            stack.iter().rev().find_map(|x| x.bound.get(name).copied()),
+        ))
+    }
+
+    stack.push(scope);
+    for x in &scope.inner {
+        let res = match x {
+            Bind::Get(x) if x.span.contains(pos) => lookup(stack, &x.node),
+            Bind::Set(_, x) if x.span.contains(pos) => lookup(stack, &x.0),
+            Bind::Scope(inner) => find_binding(inner, pos, stack),
+            _ => None,
+        };
+        if res.is_some() {
+            return res;
+        }
+    }
+    stack.pop();
+    None
+}
+
+/// Collect the scopes enclosing `pos`, outermost first. The module scope encloses everything.
+fn enclosing_scopes<'a>(scope: &'a Scope, pos: Pos, res: &mut Vec<&'a Scope>) {
+    res.push(scope);
+    for x in &scope.inner {
+        match x {
+            Bind::Scope(inner) if inner.span.contains(pos) => {
+                return enclosing_scopes(inner, pos, res);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Find the innermost `.` access whose attribute name contains `pos`.
+fn find_dot<'a>(x: &'a AstExpr, pos: Pos, res: &mut Option<(&'a AstExpr, &'a AstString)>) {
+    if let Expr::Dot(receiver, name) = &x.node {
+        if name.span.contains(pos) {
+            *res = Some((receiver, name));
+        }
+    }
+    x.visit_expr(|x| find_dot(x, pos, res));
+}
+
+/// Find the `def` statement whose name is at `span`.
+fn find_def<'a>(x: &'a AstStmt, span: Span, res: &mut Option<&'a AstStmt>) {
+    match &x.node {
+        Stmt::Def(name, ..) if name.span == span => *res = Some(x),
+        _ => x.visit_stmt(|x| find_def(x, span, res)),
+    }
+}
+
+/// The documentation of each method in `methods`.
+fn methods_documentation(methods: &Methods) -> Vec<Doc> {
+    let members = match methods.documentation() {
+        DocItem::Object(obj) => obj.members,
+        _ => return Vec::new(),
+    };
+    members
+        .into_iter()
+        .filter_map(|(name, member)| match member {
+            docs::Member::Function(f) => Some(Doc {
+                id: Identifier {
+                    name,
+                    location: None,
+                },
+                item: DocItem::Function(f),
+            }),
+            docs::Member::Property(_) => None,
+        })
+        .collect()
+}
+
+/// The methods available on an expression, if it is a literal of a type which has methods.
+pub(crate) fn literal_methods(x: &AstExpr) -> Option<&'static Methods> {
+    match &x.node {
+        Expr::Literal(AstLiteral::String(_)) => str_methods(),
+        Expr::List(_) | Expr::ListComprehension(..) => list_methods(),
+        Expr::Dict(_) | Expr::DictComprehension(..) => dict_methods(),
+        _ => None,
+    }
+}
+
+impl AstModule {
+    /// Find the definition of the identifier at the given line and column, both 0-indexed,
+    /// with the column counted in characters. Local variables, parameters and top-level
+    /// assignments resolve to the place they were first assigned. Symbols brought in by
+    /// `load()` resolve to a [`Definition::LoadedLocation`], which can be followed into the
+    /// loaded module using [`find_exported_symbol`](AstModule::find_exported_symbol).
+    pub(crate) fn find_definition(&self, line: usize, column: usize) -> Definition {
+        let pos = match self.codemap.find_pos(line, column) {
+            Some(pos) => pos,
+            None => return Definition::NotFound,
+        };
+        let scope = bind::scope(self);
+        match find_binding(&scope, pos, &mut Vec::new()) {
+            None => Definition::NotFound,
+            Some((name, None)) => Definition::Global(name.to_owned()),
+            Some((_, Some((assigner, span)))) => self.binding_definition(assigner, span),
+        }
+    }
+
+    /// The definition corresponding to a binding at `span`.
+    fn binding_definition(&self, assigner: Assigner, span: Span) -> Definition {
+        if assigner == Assigner::Load {
+            for load in self.load_statements() {
+                for (local, their) in &load.args {
+                    if local.span == span {
+                        return Definition::LoadedLocation {
+                            destination: self.codemap.resolve_span(span),
+                            path: load.module.node.clone(),
+                            name: their.node.clone(),
+                        };
+                    }
+                }
+            }
+        }
+        Definition::Location(self.codemap.resolve_span(span))
+    }
+
+    /// All the names defined in this module which are in scope at the given line and column,
+    /// sorted by name, along with where they are defined. Includes top-level assignments,
+    /// symbols brought in by `load()`, and the parameters and locals of enclosing functions,
+    /// but not globals.
+    pub(crate) fn find_names_in_scope(
+        &self,
+        line: usize,
+        column: usize,
+    ) -> Vec<(String, Definition)> {
+        let pos = match self.codemap.find_pos(line, column) {
+            Some(pos) => pos,
+            None => return Vec::new(),
+        };
+        let scope = bind::scope(self);
+        let mut scopes = Vec::new();
+        enclosing_scopes(&scope, pos, &mut scopes);
+        // Inner scopes shadow outer ones
+        let mut names = HashMap::new();
+        for scope in scopes {
+            names.extend(scope.bound.iter().map(|(name, x)| (name.as_str(), *x)));
+        }
+        names
+            .into_iter()
+            .sorted_by_key(|(name, _)| *name)
+            .map(|(name, (assigner, span))| {
+                (name.to_owned(), self.binding_definition(assigner, span))
+            })
+            .collect()
+    }
+
+    /// If the given position is within a `load()` statement, but not in the module path,
+    /// the module path being loaded. Used to suggest the symbols that module exports.
+    pub fn find_load_module(&self, line: usize, column: usize) -> Option<&str> {
+        let pos = self.codemap.find_pos(line, column)?;
+        self.load_statements()
+            .into_iter()
+            .find(|x| x.span.contains(pos) && !x.module.span.contains(pos))
+            .map(|x| x.module.node.as_str())
+    }
+
+    /// The location of a symbol exported by this module, as listed by
+    /// [`exported_symbols`](AstModule::exported_symbols).
+    pub fn find_exported_symbol(&self, name: &str) -> Option<ResolvedSpan> {
+        self.exported_symbols()
+            .into_iter()
+            .find(|(_, x)| *x == name)
+            .map(|(span, _)| span.resolve_span())
+    }
+
+    /// The documentation for the `def` whose name is at `location`, built from its signature
+    /// and docstring. The `location` will usually come from a [`Definition::Location`]
+    /// or [`find_exported_symbol`](AstModule::find_exported_symbol).
+    pub fn find_function_documentation(&self, location: ResolvedSpan) -> Option<Doc> {
+        let begin = self
+            .codemap
+            .find_pos(location.begin_line, location.begin_column)?;
+        let end = self
+            .codemap
+            .find_pos(location.end_line, location.end_column)?;
+        let mut res = None;
+        find_def(&self.statement, Span::new(begin, end), &mut res);
+        match &res?.node {
+            Stmt::Def(name, params, ret, body, _) => Some(Doc {
+                id: Identifier {
+                    name: name.0.clone(),
+                    location: Some(docs::Location {
+                        path: self.codemap.filename().to_owned(),
+                        position: Some(docs::Pos {
+                            line: location.begin_line,
+                            column: location.begin_column,
+                        }),
+                    }),
+                },
+                item: DocItem::Function(self.def_documentation(params, ret.as_deref(), body)),
+            }),
+            _ => None,
+        }
+    }
+
+    fn def_documentation(
+        &self,
+        params: &[AstParameter],
+        ret: Option<&AstExpr>,
+        body: &AstStmt,
+    ) -> docs::Function {
+        let typ = |x: &AstExpr| docs::Type {
+            raw_type: self.codemap.source_span(x.span).to_owned(),
+        };
+        docs::Function::from_docstring(
+            DocStringKind::Starlark,
+            |mut param_docs| {
+                // Docstrings may name `*args` and `**kwargs` with or without their stars.
+                let mut param_doc = |name: &str, stars: &str| {
+                    param_docs
+                        .remove(&format!("{}{}", stars, name))
+                        .or_else(|| param_docs.remove(name))
+                        .flatten()
+                };
+                params
+                    .iter()
+                    .map(|p| match &p.node {
+                        Parameter::Normal(name, t) => docs::Param::Arg {
+                            name: name.0.clone(),
+                            docs: param_doc(&name.0, ""),
+                            typ: t.as_deref().map(typ),
+                            default_value: None,
+                        },
+                        Parameter::WithDefaultValue(name, t, default) => docs::Param::Arg {
+                            name: name.0.clone(),
+                            docs: param_doc(&name.0, ""),
+                            typ: t.as_deref().map(typ),
+                            default_value: Some(self.codemap.source_span(default.span).to_owned()),
+                        },
+                        Parameter::NoArgs => docs::Param::NoArgs,
+                        Parameter::Args(name, t) => docs::Param::Args {
+                            name: name.0.clone(),
+                            docs: param_doc(&name.0, "*"),
+                            typ: t.as_deref().map(typ),
+                        },
+                        Parameter::KwArgs(name, t) => docs::Param::Kwargs {
+                            name: name.0.clone(),
+                            docs: param_doc(&name.0, "**"),
+                            typ: t.as_deref().map(typ),
+                        },
+                    })
+                    .collect()
+            },
+            ret.map(typ),
+            DocString::extract_raw_starlark_docstring(body).as_deref(),
+        )
+    }
+
+    /// Where the identifier at the given line and column, both 0-indexed with the column
+    /// counted in characters, is defined in this module. Symbols brought in by `load()`
+    /// resolve to their place in the `load()` statement, see
+    /// [`find_loaded_symbol`](AstModule::find_loaded_symbol) to follow them further.
+    pub fn find_definition_location(&self, line: usize, column: usize) -> Option<ResolvedSpan> {
+        match self.find_definition(line, column) {
+            Definition::Location(span) => Some(span),
+            Definition::LoadedLocation { destination, .. } => Some(destination),
+            Definition::Global(_) | Definition::NotFound => None,
+        }
+    }
+
+    /// If the identifier at the given line and column was brought into scope by `load()`,
+    /// the module path, as written in the `load()` statement, and the name of the symbol in
+    /// the loaded module.
+    pub fn find_loaded_symbol(&self, line: usize, column: usize) -> Option<(String, String)> {
+        match self.find_definition(line, column) {
+            Definition::LoadedLocation { path, name, .. } => Some((path, name)),
+            _ => None,
+        }
+    }
+
+    /// The documentation for the identifier or method at the given line and column.
+    /// Functions defined in this module are documented by their docstrings, and globals by
+    /// `globals`. Symbols brought in by `load()` are documented by `loaded`, which is given
+    /// the module path and the name of the symbol in that module.
+    pub fn find_documentation(
+        &self,
+        line: usize,
+        column: usize,
+        globals: &Globals,
+        loaded: impl FnOnce(&str, &str) -> Option<Doc>,
+    ) -> Option<Doc> {
+        match self.find_definition(line, column) {
+            Definition::Location(span) => self.find_function_documentation(span),
+            Definition::LoadedLocation { path, name, .. } => loaded(&path, &name),
+            Definition::Global(name) => {
+                globals
+                    .member_documentation()
+                    .remove(&name)
+                    .flatten()
+                    .map(|item| Doc {
+                        id: Identifier {
+                            name,
+                            location: None,
+                        },
+                        item,
+                    })
+            }
+            Definition::NotFound => self.find_method_documentation(line, column),
+        }
+    }
+
+    /// All the names defined in this module which are in scope at the given line and column,
+    /// sorted by name, along with their documentation, found as for
+    /// [`find_documentation`](AstModule::find_documentation). Globals are not included.
+    pub fn find_names_documentation(
+        &self,
+        line: usize,
+        column: usize,
+        mut loaded: impl FnMut(&str, &str) -> Option<Doc>,
+    ) -> Vec<(String, Option<Doc>)> {
+        self.find_names_in_scope(line, column)
+            .into_iter()
+            .map(|(name, definition)| {
+                let doc = match definition {
+                    Definition::Location(span) => self.find_function_documentation(span),
+                    Definition::LoadedLocation { path, name, .. } => loaded(&path, &name),
+                    Definition::Global(_) | Definition::NotFound => None,
+                };
+                (name, doc)
+            })
+            .collect()
+    }
+
+    /// If the given position is on a method of a literal value (e.g. `upper` in `"x".upper()`),
+    /// the documentation for that method.
+    pub fn find_method_documentation(&self, line: usize, column: usize) -> Option<Doc> {
+        let (receiver, name) = self.find_dot_at(line, column)?;
+        methods_documentation(literal_methods(receiver)?)
+            .into_iter()
+            .find(|x| x.id.name == name.node)
+    }
+
+    /// If the given position is on the attribute of a `.` access, the documentation of all
+    /// the methods that could go there. That is only known if the receiver is a literal
+    /// (e.g. `"x".`), otherwise the result is empty.
+    pub fn find_method_completions(&self, line: usize, column: usize) -> Option<Vec<Doc>> {
+        let (receiver, _) = self.find_dot_at(line, column)?;
+        Some(literal_methods(receiver).map_or_else(Vec::new, methods_documentation))
+    }
+
+    fn find_dot_at(&self, line: usize, column: usize) -> Option<(&AstExpr, &AstString)> {
+        let pos = self.codemap.find_pos(line, column)?;
+        let mut res = None;
+        self.statement.visit_expr(|x| find_dot(x, pos, &mut res));
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::Dialect;
+
+    fn module(x: &str) -> AstModule {
+        AstModule::parse("X", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    fn location(line: usize, begin: usize, end: usize) -> ResolvedSpan {
+        ResolvedSpan {
+            begin_line: line,
+            begin_column: begin,
+            end_line: line,
+            end_column: end,
+        }
+    }
+
+    #[test]
+    fn test_find_definition() {
+        let modu = module(
+            r#"
+load("foo.bzl", bar = "baz")
+x = 1
+def f(y):
+    z = x + y
+    return [z for x in bar(z)]
+x = 2
+"#,
+        );
+        // `x` in `z = x + y` is the module-level assignment
+        assert_eq!(
+            modu.find_definition(4, 8),
+            Definition::Location(location(2, 0, 1))
+        );
+        // `y` is the parameter
+        assert_eq!(
+            modu.find_definition(4, 12),
+            Definition::Location(location(3, 6, 7))
+        );
+        // `z` in the comprehension is the local
+        assert_eq!(
+            modu.find_definition(5, 12),
+            Definition::Location(location(4, 4, 5))
+        );
+        // The second assignment to `x` goes to the first
+        assert_eq!(
+            modu.find_definition(6, 0),
+            Definition::Location(location(2, 0, 1))
+        );
+        // `bar` was loaded
+        assert_eq!(
+            modu.find_definition(5, 24),
+            Definition::LoadedLocation {
+                destination: location(1, 16, 19),
+                path: "foo.bzl".to_owned(),
+                name: "baz".to_owned(),
+            }
+        );
+        // Non-identifiers are not found
+        assert_eq!(modu.find_definition(0, 0), Definition::NotFound);
+        assert_eq!(modu.find_definition(2, 4), Definition::NotFound);
+        assert_eq!(modu.find_definition(10, 0), Definition::NotFound);
+    }
+
+    #[test]
+    fn test_find_definition_global() {
+        let modu = module("x = len([])\n");
+        assert_eq!(
+            modu.find_definition(0, 5),
+            Definition::Global("len".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_find_loaded_symbol() {
+        let modu = module("load(\"foo.bzl\", bar = \"baz\")\nx = bar\ny = x\n");
+        assert_eq!(
+            modu.find_loaded_symbol(1, 4),
+            Some(("foo.bzl".to_owned(), "baz".to_owned()))
+        );
+        assert_eq!(
+            modu.find_definition_location(1, 4),
+            Some(location(0, 16, 19))
+        );
+        assert_eq!(modu.find_loaded_symbol(2, 4), None);
+        assert_eq!(modu.find_definition_location(2, 4), Some(location(1, 0, 1)));
+    }
+
+    #[test]
+    fn test_find_function_documentation() {
+        let modu = module(
+            r#"
+def f(a, b: "string" = "x", *args, **kwargs) -> "int":
+    """Summary of f.
+
+    Args:
+        a: Docs for a
+        *args: Docs for args
+    """
+    pass
+"#,
+        );
+        let doc = modu.find_function_documentation(location(1, 4, 5)).unwrap();
+        assert_eq!(doc.id.name, "f");
+        let docs = match doc.item {
+            DocItem::Function(docs) => docs,
+            _ => panic!("Expected function documentation"),
+        };
+        assert_eq!(docs.docs.unwrap().summary, "Summary of f.");
+        assert_eq!(docs.ret.typ.unwrap().raw_type, "\"int\"");
+        assert_eq!(
+            docs.params,
+            vec![
+                docs::Param::Arg {
+                    name: "a".to_owned(),
+                    docs: DocString::from_docstring(DocStringKind::Starlark, "Docs for a"),
+                    typ: None,
+                    default_value: None,
+                },
+                docs::Param::Arg {
+                    name: "b".to_owned(),
+                    docs: None,
+                    typ: Some(docs::Type {
+                        raw_type: "\"string\"".to_owned()
+                    }),
+                    default_value: Some("\"x\"".to_owned()),
+                },
+                docs::Param::Args {
+                    name: "args".to_owned(),
+                    docs: DocString::from_docstring(DocStringKind::Starlark, "Docs for args"),
+                    typ: None,
+                },
+                docs::Param::Kwargs {
+                    name: "kwargs".to_owned(),
+                    docs: None,
+                    typ: None,
+                },
+            ]
+        );
+        assert_eq!(modu.find_function_documentation(location(1, 6, 7)), None);
+    }
+
+    #[test]
+    fn test_find_method_documentation() {
+        let modu = module("x = 'a'.upper()\ny = x.upper()\n");
+        let doc = modu.find_method_documentation(0, 10).unwrap();
+        assert_eq!(doc.id.name, "upper");
+        // We don't know the type of `x`
+        assert!(modu.find_method_documentation(1, 7).is_none());
+    }
+
+    #[test]
+    fn test_find_names_in_scope() {
+        let modu = module(
+            r#"
+load("foo.bzl", "bar")
+x = 1
+def f(y):
+    z = 2
+    return z
+w = [a for a in []]
+"#,
+        );
+        let names = |line, column| {
+            modu.find_names_in_scope(line, column)
+                .into_iter()
+                .map(|(x, _)| x)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(5, 4), &["bar", "f", "w", "x", "y", "z"]);
+        assert_eq!(names(2, 0), &["bar", "f", "w", "x"]);
+        assert_eq!(names(6, 5), &["a", "bar", "f", "w", "x"]);
+        assert_eq!(
+            modu.find_names_in_scope(2, 0)[0].1,
+            Definition::LoadedLocation {
+                destination: location(1, 16, 21),
+                path: "foo.bzl".to_owned(),
+                name: "bar".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_load_module() {
+        let modu = module("load(\"foo.bzl\", \"bar\")\n");
+        assert_eq!(modu.find_load_module(0, 17), Some("foo.bzl"));
+        assert_eq!(modu.find_load_module(0, 7), None);
+        assert_eq!(modu.find_load_module(1, 0), None);
+    }
+
+    #[test]
+    fn test_find_method_completions() {
+        let modu = module("x = 'a'.up\ny = z.up\n");
+        let completions = modu.find_method_completions(0, 10).unwrap();
+        assert!(completions.iter().any(|x| x.id.name == "upper"));
+        assert_eq!(modu.find_method_completions(1, 7), Some(Vec::new()));
+        assert_eq!(modu.find_method_completions(0, 0), None);
+    }
+
+    #[test]
+    fn test_find_exported_symbol() {
+        let modu = module("def _a(): pass\nb = 1\n");
+        assert_eq!(modu.find_exported_symbol("b"), Some(location(1, 0, 1)));
+        assert_eq!(modu.find_exported_symbol("_a"), None);
+        assert_eq!(modu.find_exported_symbol("c"), None);
+    }
+}
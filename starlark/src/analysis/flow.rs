@@ -78,6 +78,19 @@ fn is_fail(x: &AstExpr) -> bool {
     }
 }
 
+// Does the loop body contain a `break` for this loop (not for a nested one)
+fn has_break(x: &AstStmt) -> bool {
+    match &**x {
+        Stmt::Break => true,
+        Stmt::For(..) | Stmt::While(..) | Stmt::Def(..) => false,
+        _ => {
+            let mut res = false;
+            x.visit_stmt(|x| res = res || has_break(x));
+            res
+        }
+    }
+}
+
 fn final_return(x: &AstStmt) -> bool {
     match &**x {
         Stmt::Return(_) => true,
@@ -87,6 +100,11 @@ fn final_return(x: &AstStmt) -> bool {
             Some(x) => final_return(x),
         },
         Stmt::IfElse(_, box (x, y)) => final_return(x) && final_return(y),
+        // `while True` only finishes by `break` (or by `return`, which is fine)
+        Stmt::While(cond, box body) => match &**cond {
+            Expr::Identifier(name, _) => name.node == "True" && !has_break(body),
+            _ => false,
+        },
         _ => false,
     }
 }
@@ -211,7 +229,9 @@ fn redundant(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) {
 
     fn f(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) {
         match &**x {
-            Stmt::For(_, box (_, body)) => check(true, codemap, body, res),
+            Stmt::For(_, box (_, body)) | Stmt::While(_, box body) => {
+                check(true, codemap, body, res)
+            }
             Stmt::Def(_, _, _, body, _payload) => check(false, codemap, body, res),
             _ => {}
         }
@@ -320,13 +340,21 @@ def yes3():
     pass
 def yes4() -> "string":
     fail("die")
+def yes5() -> "string":
+    while True:
+        if x:
+            return "x"
+def no5() -> "string":
+    while True:
+        if x:
+            break
 "#,
         );
         let mut res = Vec::new();
         stmt(&m.codemap, &m.statement, &mut res);
         assert_eq!(
             res.map(|x| x.problem.about()),
-            &["no1", "no2", "no3", "no4"]
+            &["no1", "no2", "no3", "no4", "no5"]
         );
     }
 
@@ -410,13 +438,17 @@ def test7():
         if x:
             continue
         return
+def test8(): # 30
+    while x:
+        x = x - 1
+        continue # bad: 33
 "#,
         );
         let mut res = Vec::new();
         redundant(&m.codemap, &m.statement, &mut res);
         assert_eq!(
             res.map(|x| x.location.resolve_span().begin_line),
-            &[3, 9, 19]
+            &[3, 9, 19, 33]
         );
     }
 
@@ -15,24 +15,41 @@
  * limitations under the License.
  */
 
+pub use config::{LintConfig, LintSeverity};
+pub(crate) use suppression::Suppression;
 pub use types::Lint;
 
 use crate::{analysis::types::LintT, syntax::AstModule};
 
 mod bind;
+mod config;
+mod definition;
 mod dubious;
 mod exported;
 mod flow;
 mod incompatible;
 mod names;
 mod performance;
+mod suppression;
+mod typecheck;
 mod types;
 
 impl AstModule {
     /// Run a static linter over the module. If the complete set of global variables are known
     /// they can be passed as the `globals` argument, resulting in name-resolution lint errors.
     /// The precise checks run by the linter are not considered stable between versions.
+    ///
+    /// Lints can be suppressed with a `# starlark: disable=short-name` comment, either
+    /// on the same line, on the line before, or before the first statement for the whole file.
+    /// Suppressions which don't suppress anything are reported as `unused-suppression`,
+    /// which can itself be suppressed.
     pub fn lint(&self, globals: Option<&[&str]>) -> Vec<Lint> {
+        self.lint_with_config(globals, &LintConfig::default())
+    }
+
+    /// Like [`lint`](AstModule::lint), but with a [`LintConfig`] deciding which lints
+    /// are reported and with what [`severity`](Lint::severity).
+    pub fn lint_with_config(&self, globals: Option<&[&str]>, config: &LintConfig) -> Vec<Lint> {
         let mut res = Vec::new();
         res.extend(flow::flow_issues(self).into_iter().map(LintT::erase));
         res.extend(
@@ -47,6 +64,7 @@ impl AstModule {
                 .map(LintT::erase),
         );
         res.extend(performance::performance(self).into_iter().map(LintT::erase));
-        res
+        res.extend(typecheck::type_issues(self).into_iter().map(LintT::erase));
+        config.apply(suppression::suppress(self, res))
     }
 }
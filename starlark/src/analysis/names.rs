@@ -421,6 +421,22 @@ def foo():
         assert_eq!(res, &["no1", "no2"])
     }
 
+    #[test]
+    fn test_lint_undefined_in_f_string() {
+        let m = module(
+            r#"
+x = 1
+y = f"{x} {no1!r} {[z for z in no2]:>5}"
+"#,
+        );
+        let mut res = Vec::new();
+        let scope = bind::scope(&m);
+        undefined_variable(&m.codemap, &scope, &[], &mut res);
+        let mut res = res.map(|x| x.problem.about());
+        res.sort();
+        assert_eq!(res, &["no1", "no2"])
+    }
+
     #[test]
     fn test_lint_inappropriate_underscore() {
         let m = module(
@@ -0,0 +1,250 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Inline suppression of lints with `# starlark: disable=name` comments.
+//!
+//! The names are the kebab-case [`short_name`](crate::analysis::Lint::short_name) of the lints,
+//! separated by commas. A comment following code on the same line suppresses lints starting
+//! on that line, a comment on its own line suppresses lints starting on the next line,
+//! and comments before the first statement suppress lints in the whole file.
+
+use gazebo::{dupe::Dupe, variants::VariantName};
+use thiserror::Error;
+
+use crate::{
+    analysis::types::{Lint, LintT, LintWarning},
+    codemap::{CodeMap, Pos, Span},
+    syntax::{
+        lexer::{Lexer, Token},
+        AstModule, Dialect,
+    },
+};
+
+#[derive(Error, Debug, VariantName)]
+pub(crate) enum SuppressionIssue {
+    #[error("Suppression of `{0}` does not suppress any lint")]
+    UnusedSuppression(String),
+}
+
+/// The short name of [`SuppressionIssue::UnusedSuppression`].
+const UNUSED_SUPPRESSION: &str = "unused-suppression";
+
+impl LintWarning for SuppressionIssue {
+    fn is_serious(&self) -> bool {
+        false
+    }
+}
+
+/// A lint name disabled by a comment.
+pub(crate) struct Suppression {
+    /// The comment containing the suppression.
+    span: Span,
+    name: String,
+    /// The line the suppression applies to, or `None` for the whole file.
+    line: Option<usize>,
+}
+
+impl Suppression {
+    fn applies(&self, name: &str, line: usize) -> bool {
+        self.name == name && self.line.map_or(true, |l| l == line)
+    }
+}
+
+/// The lint names in a comment of the form `starlark: disable=a,b`.
+fn parse_comment(text: &str) -> Option<impl Iterator<Item = &str>> {
+    let names = text
+        .trim()
+        .strip_prefix("starlark:")?
+        .trim_start()
+        .strip_prefix("disable=")?;
+    Some(names.split(',').map(str::trim).filter(|x| !x.is_empty()))
+}
+
+fn find_suppressions(codemap: &CodeMap) -> Vec<Suppression> {
+    let source = codemap.source();
+    let mut res = Vec::new();
+    let mut seen_code = false;
+    // The module has already been parsed, so accept anything the lexer might see.
+    for token in Lexer::new_with_comments(source, &Dialect::Extended, codemap.dupe()) {
+        let (begin, text, end) = match token {
+            Ok((begin, Token::Comment, end)) => (begin, &source[begin + 1..end], end),
+            Ok((_, Token::Newline | Token::Indent | Token::Dedent, _)) => continue,
+            _ => {
+                seen_code = true;
+                continue;
+            }
+        };
+        let names = match parse_comment(text) {
+            Some(names) => names,
+            None => continue,
+        };
+        let span = Span::new(Pos::new(begin as u32), Pos::new(end as u32));
+        let line = codemap.find_line(span.begin());
+        let line_begin = codemap.line_span(line).begin();
+        let own_line = source[line_begin.get() as usize..begin].trim().is_empty();
+        let line = match (seen_code, own_line) {
+            (false, _) => None,
+            (true, true) => Some(line + 1),
+            (true, false) => Some(line),
+        };
+        res.extend(names.map(|name| Suppression {
+            span,
+            name: name.to_owned(),
+            line,
+        }));
+    }
+    res
+}
+
+/// Remove the lints which are suppressed by comments in the module,
+/// and add a lint for every suppression which did not suppress anything.
+///
+/// Those `unused-suppression` lints can themselves be suppressed, either as usual
+/// or by naming `unused-suppression` in the same comment. Suppressions of
+/// `unused-suppression` are never reported as unused.
+pub(crate) fn suppress(module: &AstModule, lints: Vec<Lint>) -> Vec<Lint> {
+    // The comments are only lexed once, however many times the module is linted.
+    let suppressions = module
+        .suppressions
+        .get_or_init(|| find_suppressions(&module.codemap));
+    if suppressions.is_empty() {
+        return lints;
+    }
+    let mut used = vec![false; suppressions.len()];
+    let mut res = Vec::with_capacity(lints.len());
+    for lint in lints {
+        let line = module.codemap.find_line(lint.location.span.begin());
+        let mut suppressed = false;
+        for (s, used) in suppressions.iter().zip(used.iter_mut()) {
+            if s.applies(&lint.short_name, line) {
+                *used = true;
+                suppressed = true;
+            }
+        }
+        if !suppressed {
+            res.push(lint);
+        }
+    }
+
+    for (s, used) in suppressions.iter().zip(used) {
+        if used || s.name == UNUSED_SUPPRESSION {
+            continue;
+        }
+        let line = module.codemap.find_line(s.span.begin());
+        let suppressed = suppressions.iter().any(|x| {
+            x.applies(UNUSED_SUPPRESSION, line)
+                || (x.span == s.span && x.name == UNUSED_SUPPRESSION)
+        });
+        if !suppressed {
+            res.push(
+                LintT::new(
+                    &module.codemap,
+                    s.span,
+                    SuppressionIssue::UnusedSuppression(s.name.clone()),
+                )
+                .erase(),
+            );
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use gazebo::prelude::*;
+
+    use crate::syntax::{AstModule, Dialect};
+
+    fn lint(x: &str) -> Vec<(String, usize)> {
+        let m = AstModule::parse("X", x.to_owned(), &Dialect::Extended).unwrap();
+        m.lint(None)
+            .map(|x| (x.short_name.clone(), x.location.resolve_span().begin_line))
+    }
+
+    #[test]
+    fn test_suppression() {
+        let res = lint(
+            r#"
+# starlark: disable=unused-load
+load("a", "no1")
+def f(x):
+    y = x  # starlark: disable=unused-assign
+    # starlark: disable=unused-assign, unreachable
+    z = x
+    no2 = x
+    return x
+"#,
+        );
+        assert_eq!(
+            res,
+            &[
+                ("unused-assign".to_owned(), 7),
+                ("unused-suppression".to_owned(), 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suppression_other_comments() {
+        let res = lint(
+            r#"
+x = 1
+# starlark: enable=unused-assign
+# a comment about starlark: disable=unused-assign
+def f():
+    no = 1
+"#,
+        );
+        assert_eq!(res, &[("unused-assign".to_owned(), 5)]);
+    }
+
+    #[test]
+    fn test_suppression_unused_suppression() {
+        let mut res = lint(
+            r#"
+def f(x):
+    y = x  # starlark: disable=unreachable, unused-suppression
+    # starlark: disable=unused-suppression
+    z = x  # starlark: disable=unreachable
+    w = x  # starlark: disable=unreachable
+    return x
+"#,
+        );
+        res.sort();
+        assert_eq!(
+            res,
+            &[
+                ("unused-assign".to_owned(), 2),
+                ("unused-assign".to_owned(), 4),
+                ("unused-assign".to_owned(), 5),
+                ("unused-suppression".to_owned(), 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suppression_lint_twice() {
+        let m = AstModule::parse(
+            "X",
+            "def f(x):\n    y = x  # starlark: disable=unused-assign\n".to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        assert!(m.lint(None).is_empty());
+        assert!(m.lint(None).is_empty());
+    }
+}
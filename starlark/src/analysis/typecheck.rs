@@ -0,0 +1,751 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A static check of the type annotations on `def` parameters and return types.
+//!
+//! We infer types for literals, operators, some builtin functions and the variables
+//! assigned from them, although the contents of lists and dicts in variables may change.
+//! Anything we can't infer is [`Ty::Any`], and we only report mismatches which are
+//! certain to fail the runtime check in `values/typing.rs`.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+use gazebo::{prelude::*, variants::VariantName};
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{
+    analysis::types::{LintT, LintWarning},
+    codemap::CodeMap,
+    syntax::{
+        ast::{
+            Argument, Assign, AstArgument, AstAssign, AstExpr, AstLiteral, AstParameter, AstStmt,
+            BinOp, Clause, Expr, ForClause, Parameter, Stmt,
+        },
+        lexer::TokenInt,
+        uniplate::Visit,
+        AstModule,
+    },
+};
+
+#[derive(Error, Debug, VariantName)]
+pub(crate) enum TypeIssue {
+    #[error("Argument `{0}` of `{1}` expects type `{3}`, but got `{2}`")]
+    ArgumentTypeMismatch(String, String, Ty, Ty),
+    #[error("Function `{0}` returns type `{2}`, but got `{1}`")]
+    ReturnTypeMismatch(String, Ty, Ty),
+}
+
+impl LintWarning for TypeIssue {
+    fn is_serious(&self) -> bool {
+        true
+    }
+}
+
+/// The builtin types whose values we can infer, and whose names are always
+/// checked by comparing with the type of the value.
+const BUILTIN_TYPES: &[&str] = &[
+    "bool", "bytes", "dict", "float", "function", "int", "list", "NoneType", "range", "string",
+    "tuple",
+];
+
+/// A static approximation to the type of a value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Ty {
+    /// Could be any value.
+    Any,
+    None,
+    /// A value whose type has this name, e.g. `int` or `string`.
+    Name(String),
+    List(Box<Ty>),
+    Dict(Box<(Ty, Ty)>),
+    Tuple(Vec<Ty>),
+    /// Could be any of these types.
+    Union(Vec<Ty>),
+}
+
+impl Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Any => write!(f, "\"\""),
+            Ty::None => write!(f, "None"),
+            Ty::Name(x) => write!(f, "{}", x),
+            Ty::List(x) => write!(f, "[{}]", x),
+            Ty::Dict(box (k, v)) => write!(f, "{{{}: {}}}", k, v),
+            Ty::Tuple(xs) if xs.len() == 1 => write!(f, "({},)", xs[0]),
+            Ty::Tuple(xs) => write!(f, "({})", xs.iter().join(", ")),
+            Ty::Union(xs) => write!(f, "{}", xs.iter().join(" | ")),
+        }
+    }
+}
+
+impl Ty {
+    fn name(x: &str) -> Self {
+        Ty::Name(x.to_owned())
+    }
+
+    fn union(xs: impl IntoIterator<Item = Ty>) -> Self {
+        let mut res = Vec::new();
+        for x in xs {
+            match x {
+                Ty::Any => return Ty::Any,
+                Ty::Union(ys) => {
+                    for y in ys {
+                        if !res.contains(&y) {
+                            res.push(y);
+                        }
+                    }
+                }
+                x => {
+                    if !res.contains(&x) {
+                        res.push(x);
+                    }
+                }
+            }
+        }
+        match res.len() {
+            0 => Ty::Any,
+            1 => res.pop().unwrap(),
+            _ => Ty::Union(res),
+        }
+    }
+
+    /// Forget the element types of the mutable values, i.e. lists and dicts.
+    fn forget_contents(self) -> Self {
+        match self {
+            Ty::List(_) => Ty::List(box Ty::Any),
+            Ty::Dict(_) => Ty::Dict(box (Ty::Any, Ty::Any)),
+            Ty::Tuple(xs) => Ty::Tuple(xs.into_map(Ty::forget_contents)),
+            Ty::Union(xs) => Ty::Union(xs.into_map(Ty::forget_contents)),
+            x => x,
+        }
+    }
+
+    /// The name of the type, as `type()` would return at runtime.
+    fn type_name(&self) -> Option<&str> {
+        match self {
+            Ty::None => Some("NoneType"),
+            Ty::Name(x) => Some(x),
+            Ty::List(_) => Some("list"),
+            Ty::Dict(_) => Some("dict"),
+            Ty::Tuple(_) => Some("tuple"),
+            Ty::Any | Ty::Union(_) => None,
+        }
+    }
+
+    /// Could a value of this type match the `expected` type annotation.
+    fn may_match(&self, expected: &Ty) -> bool {
+        match (self, expected) {
+            (Ty::Any, _) | (_, Ty::Any) => true,
+            (Ty::Union(xs), _) => xs.iter().any(|x| x.may_match(expected)),
+            (_, Ty::Union(ys)) => ys.iter().any(|y| self.may_match(y)),
+            (Ty::List(x), Ty::List(y)) => x.may_match(y),
+            (Ty::Dict(box (xk, xv)), Ty::Dict(box (yk, yv))) => {
+                xk.may_match(yk) && xv.may_match(yv)
+            }
+            (Ty::Tuple(xs), Ty::Tuple(ys)) => {
+                xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| x.may_match(y))
+            }
+            _ => match expected.type_name() {
+                // User types (e.g. records) may accept values of other types
+                Some(y) if BUILTIN_TYPES.contains(&y) => self.type_name() == Some(y),
+                _ => true,
+            },
+        }
+    }
+
+    /// The type described by a type annotation, following `TypeCompiled`.
+    fn from_annotation(x: &AstExpr) -> Self {
+        match &**x {
+            Expr::Identifier(x, _) if x.node == "None" => Ty::None,
+            Expr::Literal(AstLiteral::String(x)) => {
+                if x.node.is_empty() || x.node.starts_with('_') {
+                    Ty::Any
+                } else {
+                    Ty::name(&x.node)
+                }
+            }
+            Expr::Dot(x, attr) if attr.node == "type" => match &***x {
+                Expr::Identifier(x, _) => match builtin_type(&x.node) {
+                    Some(x) => Ty::name(x),
+                    None => Ty::Any,
+                },
+                _ => Ty::Any,
+            },
+            Expr::List(xs) => match xs.as_slice() {
+                [] => Ty::Any,
+                [x] => Ty::List(box Ty::from_annotation(x)),
+                xs => Ty::union(xs.iter().map(Ty::from_annotation)),
+            },
+            Expr::Dict(xs) => match xs.as_slice() {
+                [(k, v)] => Ty::Dict(box (Ty::from_annotation(k), Ty::from_annotation(v))),
+                _ => Ty::name("dict"),
+            },
+            Expr::Tuple(xs) => Ty::Tuple(xs.map(Ty::from_annotation)),
+            _ => Ty::Any,
+        }
+    }
+}
+
+/// The name of the type `x.type` evaluates to, for builtin `x`.
+fn builtin_type(x: &str) -> Option<&'static str> {
+    match x {
+        "bool" => Some("bool"),
+        "bytes" => Some("bytes"),
+        "dict" => Some("dict"),
+        "float" => Some("float"),
+        "int" => Some("int"),
+        "list" => Some("list"),
+        "range" => Some("range"),
+        "str" => Some("string"),
+        "tuple" => Some("tuple"),
+        _ => None,
+    }
+}
+
+/// The type returned by calling the builtin function `x`.
+fn builtin_call(x: &str) -> Ty {
+    match x {
+        "len" | "hash" | "ord" | "int" => Ty::name("int"),
+        "str" | "repr" | "type" | "chr" => Ty::name("string"),
+        "bool" | "any" | "all" | "hasattr" => Ty::name("bool"),
+        "float" => Ty::name("float"),
+        "list" | "sorted" | "reversed" | "dir" => Ty::List(box Ty::Any),
+        "dict" => Ty::Dict(box (Ty::Any, Ty::Any)),
+        "tuple" => Ty::name("tuple"),
+        "range" => Ty::name("range"),
+        _ => Ty::Any,
+    }
+}
+
+/// The type returned by calling the string method `x`.
+fn string_method(x: &str) -> Ty {
+    match x {
+        "format" | "join" | "lower" | "upper" | "strip" | "lstrip" | "rstrip" | "replace"
+        | "capitalize" | "title" => Ty::name("string"),
+        "split" | "rsplit" | "splitlines" => Ty::List(box Ty::name("string")),
+        "startswith" | "endswith" | "isalpha" | "isdigit" | "isspace" => Ty::name("bool"),
+        "find" | "rfind" | "index" | "rindex" | "count" => Ty::name("int"),
+        _ => Ty::Any,
+    }
+}
+
+/// How a variable in a scope gets its value.
+#[derive(Default)]
+struct Binding<'a> {
+    /// Types the variable is assigned, e.g. from parameter annotations.
+    types: Vec<Ty>,
+    /// Expressions the variable is assigned from.
+    exprs: Vec<&'a AstExpr>,
+    /// The `def` statements which assign the variable.
+    defs: Vec<&'a AstStmt>,
+    /// Assigned in a way we don't track, e.g. as a `for` variable.
+    unknown: bool,
+}
+
+/// The variables bound in a module, `def`, `lambda` or comprehension.
+#[derive(Default)]
+struct Scope<'a> {
+    bindings: HashMap<&'a str, Binding<'a>>,
+    /// The types of the bindings, or `None` while they are being computed.
+    resolved: RefCell<HashMap<&'a str, Option<Ty>>>,
+}
+
+impl<'a> Scope<'a> {
+    fn module(module: &'a AstModule) -> Self {
+        let mut res = Self::default();
+        res.collect(&module.statement);
+        res
+    }
+
+    fn def(params: &'a [AstParameter], body: &'a AstStmt) -> Self {
+        let mut res = Self::parameters(params);
+        res.collect(body);
+        res
+    }
+
+    fn parameters(params: &'a [AstParameter]) -> Self {
+        let mut res = Self::default();
+        for p in params {
+            if let (Some(name), ty, _) = p.split() {
+                let binding = res.bindings.entry(&name.0).or_default();
+                match ty {
+                    Some(ty) => binding.types.push(Ty::from_annotation(ty)),
+                    None => binding.unknown = true,
+                }
+            }
+        }
+        res
+    }
+
+    fn comprehension(for_: &'a ForClause, clauses: &'a [Clause]) -> Self {
+        let mut res = Self::default();
+        res.unknown(&for_.var);
+        for x in clauses {
+            if let Clause::For(x) = x {
+                res.unknown(&x.var);
+            }
+        }
+        res
+    }
+
+    fn unknown(&mut self, x: &'a AstAssign) {
+        x.visit_lvalue(|x| self.bindings.entry(&x.0).or_default().unknown = true)
+    }
+
+    fn assign(&mut self, lhs: &'a AstAssign, rhs: &'a AstExpr) {
+        match (&**lhs, &**rhs) {
+            (Assign::Identifier(x), _) => self.bindings.entry(&x.0).or_default().exprs.push(rhs),
+            (Assign::Tuple(xs), Expr::Tuple(ys) | Expr::List(ys)) if xs.len() == ys.len() => {
+                xs.iter().zip(ys).for_each(|(x, y)| self.assign(x, y))
+            }
+            _ => self.unknown(lhs),
+        }
+    }
+
+    /// Collect the bindings of a statement, but not those inside nested `def`.
+    fn collect(&mut self, x: &'a AstStmt) {
+        match &**x {
+            Stmt::Assign(lhs, rhs) => self.assign(lhs, rhs),
+            Stmt::AssignModify(lhs, _, _) => self.unknown(lhs),
+            Stmt::For(var, box (_, body)) => {
+                self.unknown(var);
+                self.collect(body);
+            }
+            Stmt::Def(name, ..) => {
+                let binding = self.bindings.entry(&name.0).or_default();
+                binding.types.push(Ty::name("function"));
+                binding.defs.push(x);
+            }
+            Stmt::Load(load) => {
+                for (name, _) in &load.args {
+                    self.bindings.entry(&name.0).or_default().unknown = true;
+                }
+            }
+            _ => x.visit_stmt(|x| self.collect(x)),
+        }
+    }
+
+    /// The type of the variable `name`, bound in this scope, the first in `scopes`.
+    fn resolve(&self, name: &'a str, scopes: &[&Scope<'a>]) -> Ty {
+        if let Some(res) = self.resolved.borrow().get(name) {
+            // Recursive definitions could be anything
+            return res.clone().unwrap_or(Ty::Any);
+        }
+        let binding = &self.bindings[name];
+        if binding.unknown {
+            return Ty::Any;
+        }
+        self.resolved.borrow_mut().insert(name, None);
+        let res = Ty::union(
+            binding
+                .types
+                .iter()
+                .cloned()
+                .chain(binding.exprs.iter().map(|x| expr_type(x, scopes))),
+        );
+        self.resolved.borrow_mut().insert(name, Some(res.clone()));
+        res
+    }
+}
+
+/// Find the innermost scope binding `name`, returning it and its enclosing scopes.
+fn lookup<'s, 'a>(name: &str, scopes: &'s [&'s Scope<'a>]) -> Option<&'s [&'s Scope<'a>]> {
+    let i = scopes.iter().position(|x| x.bindings.contains_key(name))?;
+    Some(&scopes[i..])
+}
+
+/// The `def` statement `name` refers to, if it always refers to the same one.
+fn lookup_def<'a>(name: &str, scopes: &[&Scope<'a>]) -> Option<&'a AstStmt> {
+    let binding = &lookup(name, scopes)?[0].bindings[name];
+    match binding.defs.as_slice() {
+        [def] if binding.types.len() == 1 && binding.exprs.is_empty() && !binding.unknown => {
+            Some(*def)
+        }
+        _ => None,
+    }
+}
+
+fn expr_type<'a>(x: &'a AstExpr, scopes: &[&Scope<'a>]) -> Ty {
+    let int = || Ty::name("int");
+    let float = || Ty::name("float");
+    let string = || Ty::name("string");
+    match &**x {
+        Expr::Literal(AstLiteral::Int(_)) => int(),
+        Expr::Literal(AstLiteral::Float(_)) => float(),
+        Expr::Literal(AstLiteral::String(_)) | Expr::FString(_) => string(),
+        Expr::Literal(AstLiteral::Bytes(_)) => Ty::name("bytes"),
+        Expr::Identifier(name, _) => match lookup(&name.node, scopes) {
+            // The contents of a list or dict may have changed since it was assigned
+            Some(scopes) => scopes[0].resolve(&name.node, scopes).forget_contents(),
+            None => match name.node.as_str() {
+                "True" | "False" => Ty::name("bool"),
+                "None" => Ty::None,
+                _ => Ty::Any,
+            },
+        },
+        Expr::Tuple(xs) => Ty::Tuple(xs.map(|x| expr_type(x, scopes))),
+        Expr::List(xs) => Ty::List(box Ty::union(xs.iter().map(|x| expr_type(x, scopes)))),
+        Expr::Dict(xs) => Ty::Dict(box (
+            Ty::union(xs.iter().map(|x| expr_type(&x.0, scopes))),
+            Ty::union(xs.iter().map(|x| expr_type(&x.1, scopes))),
+        )),
+        Expr::ListComprehension(..) => Ty::List(box Ty::Any),
+        Expr::DictComprehension(..) => Ty::Dict(box (Ty::Any, Ty::Any)),
+        Expr::Lambda(..) => Ty::name("function"),
+        Expr::Not(_) => Ty::name("bool"),
+        Expr::Minus(x) | Expr::Plus(x) => match expr_type(x, scopes) {
+            x @ Ty::Name(_) if x == int() || x == float() => x,
+            _ => Ty::Any,
+        },
+        Expr::BitNot(_) => int(),
+        Expr::If(box (_, x, y)) => Ty::union([expr_type(x, scopes), expr_type(y, scopes)]),
+        Expr::Op(x, op, y) => {
+            let (x, y) = (expr_type(x, scopes), expr_type(y, scopes));
+            let is_number = |t: &Ty| *t == int() || *t == float();
+            let numeric = || {
+                if x == int() && y == int() {
+                    int()
+                } else if is_number(&x) && is_number(&y) {
+                    float()
+                } else {
+                    Ty::Any
+                }
+            };
+            match op {
+                BinOp::Or | BinOp::And => Ty::union([x, y]),
+                BinOp::Equal
+                | BinOp::NotEqual
+                | BinOp::Less
+                | BinOp::Greater
+                | BinOp::LessOrEqual
+                | BinOp::GreaterOrEqual
+                | BinOp::In
+                | BinOp::NotIn => Ty::name("bool"),
+                BinOp::Add => match (&x, &y) {
+                    (Ty::List(a), Ty::List(b)) => {
+                        Ty::List(box Ty::union([(**a).clone(), (**b).clone()]))
+                    }
+                    (Ty::Tuple(a), Ty::Tuple(b)) => Ty::Tuple(a.iter().chain(b).cloned().collect()),
+                    _ if x == string() && y == string() => string(),
+                    _ => numeric(),
+                },
+                BinOp::Subtract | BinOp::Multiply | BinOp::FloorDivide => numeric(),
+                BinOp::Percent if x == string() => string(),
+                BinOp::Percent => numeric(),
+                BinOp::Divide if numeric() != Ty::Any => float(),
+                BinOp::BitAnd
+                | BinOp::BitOr
+                | BinOp::BitXor
+                | BinOp::LeftShift
+                | BinOp::RightShift
+                    if x == int() && y == int() =>
+                {
+                    int()
+                }
+                _ => Ty::Any,
+            }
+        }
+        Expr::ArrayIndirection(box (x, i)) => match expr_type(x, scopes) {
+            Ty::List(x) => *x,
+            Ty::Dict(box (_, v)) => v,
+            Ty::Tuple(xs) => match &**i {
+                Expr::Literal(AstLiteral::Int(i)) => match i.node {
+                    TokenInt::I32(i) if i >= 0 && (i as usize) < xs.len() => xs[i as usize].clone(),
+                    _ => Ty::Any,
+                },
+                _ => Ty::Any,
+            },
+            x if x == string() => x,
+            _ => Ty::Any,
+        },
+        Expr::Slice(x, ..) => match expr_type(x, scopes) {
+            x @ Ty::List(_) => x,
+            x if x == string() => x,
+            _ => Ty::Any,
+        },
+        Expr::Call(f, _) => match &***f {
+            Expr::Identifier(name, _) => match lookup(&name.node, scopes) {
+                None => builtin_call(&name.node),
+                Some(_) => match lookup_def(&name.node, scopes).map(|x| &**x) {
+                    Some(Stmt::Def(_, _, Some(ret), ..)) => Ty::from_annotation(ret),
+                    _ => Ty::Any,
+                },
+            },
+            Expr::Dot(x, method) if expr_type(x, scopes) == string() => string_method(&method.node),
+            _ => Ty::Any,
+        },
+        Expr::Dot(..) => Ty::Any,
+    }
+}
+
+struct Checker<'a> {
+    codemap: &'a CodeMap,
+    res: Vec<LintT<TypeIssue>>,
+}
+
+impl<'a> Checker<'a> {
+    /// Check the statement, where `ret` is the name and return type of the enclosing `def`.
+    fn stmt(&mut self, x: &'a AstStmt, scopes: &[&Scope<'a>], ret: Option<&(&'a str, Ty)>) {
+        match &**x {
+            Stmt::Def(name, params, ret_type, body, _) => {
+                for p in params {
+                    p.visit_expr(|x| self.expr(x, scopes));
+                }
+                let scope = Scope::def(params, body);
+                let inner = [&scope].iter().chain(scopes).copied().collect::<Vec<_>>();
+                let ret = ret_type
+                    .as_ref()
+                    .map(|x| (name.0.as_str(), Ty::from_annotation(x)));
+                self.stmt(body, &inner, ret.as_ref());
+            }
+            Stmt::Return(e) => {
+                if let Some(e) = e {
+                    self.expr(e, scopes);
+                }
+                if let Some((name, expected)) = ret {
+                    let actual = e.as_ref().map_or(Ty::None, |e| expr_type(e, scopes));
+                    if !actual.may_match(expected) {
+                        self.res.push(LintT::new(
+                            self.codemap,
+                            x.span,
+                            TypeIssue::ReturnTypeMismatch(
+                                (*name).to_owned(),
+                                actual,
+                                expected.clone(),
+                            ),
+                        ));
+                    }
+                }
+            }
+            _ => x.visit_children(|x| match x {
+                Visit::Stmt(x) => self.stmt(x, scopes, ret),
+                Visit::Expr(x) => self.expr(x, scopes),
+            }),
+        }
+    }
+
+    fn expr(&mut self, x: &'a AstExpr, scopes: &[&Scope<'a>]) {
+        match &**x {
+            Expr::Call(f, args) => {
+                if let Expr::Identifier(name, _) = &***f {
+                    if let Some(def) = lookup_def(&name.node, scopes) {
+                        self.call(&name.node, def, args, scopes);
+                    }
+                }
+            }
+            Expr::Lambda(params, body, _) => {
+                for p in params {
+                    p.visit_expr(|x| self.expr(x, scopes));
+                }
+                let scope = Scope::parameters(params);
+                let inner = [&scope].iter().chain(scopes).copied().collect::<Vec<_>>();
+                self.expr(body, &inner);
+                return;
+            }
+            Expr::ListComprehension(_, for_, clauses)
+            | Expr::DictComprehension(_, for_, clauses) => {
+                let scope = Scope::comprehension(for_, clauses);
+                let inner = [&scope].iter().chain(scopes).copied().collect::<Vec<_>>();
+                x.visit_expr(|x| self.expr(x, &inner));
+                return;
+            }
+            _ => {}
+        }
+        x.visit_expr(|x| self.expr(x, scopes));
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        def: &'a AstStmt,
+        args: &'a [AstArgument],
+        scopes: &[&Scope<'a>],
+    ) {
+        let params = match &**def {
+            Stmt::Def(_, params, ..) => params,
+            _ => return,
+        };
+        let mut positional = params.iter().take_while(|x| {
+            matches!(
+                &***x,
+                Parameter::Normal(..) | Parameter::WithDefaultValue(..)
+            )
+        });
+        for arg in args {
+            let (param, e) = match &**arg {
+                Argument::Positional(e) => (positional.next(), e),
+                Argument::Named(arg, e) => (
+                    params.iter().find(|x| match &***x {
+                        Parameter::Normal(x, _) | Parameter::WithDefaultValue(x, ..) => {
+                            x.0 == arg.node
+                        }
+                        _ => false,
+                    }),
+                    e,
+                ),
+                Argument::Args(_) | Argument::KwArgs(_) => continue,
+            };
+            if let Some((Some(param), Some(ty), _)) = param.map(|x| x.split()) {
+                let expected = Ty::from_annotation(ty);
+                let actual = expr_type(e, scopes);
+                if !actual.may_match(&expected) {
+                    self.res.push(LintT::new(
+                        self.codemap,
+                        e.span,
+                        TypeIssue::ArgumentTypeMismatch(
+                            param.0.clone(),
+                            name.to_owned(),
+                            actual,
+                            expected,
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn type_issues(module: &AstModule) -> Vec<LintT<TypeIssue>> {
+    let scope = Scope::module(module);
+    let mut checker = Checker {
+        codemap: &module.codemap,
+        res: Vec::new(),
+    };
+    checker.stmt(&module.statement, &[&scope], None);
+    checker.res
+}
+
+#[cfg(test)]
+mod test {
+    use gazebo::prelude::*;
+
+    use super::*;
+    use crate::syntax::Dialect;
+
+    fn module(x: &str) -> AstModule {
+        AstModule::parse("bad.py", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    fn issues(x: &str) -> Vec<String> {
+        type_issues(&module(x)).map(|x| x.to_string())
+    }
+
+    #[test]
+    fn test_lint_types_arguments() {
+        assert_eq!(
+            issues(
+                r#"
+def f(x: int.type, y: [str.type] = [], z: {str.type: int.type} = {}):
+    pass
+f(1)
+f("a")
+f(1, ["a"], {"a": 1})
+f(1, [1], z = {1: 2})
+f(len([]), y = [str(1)])
+f(y = 1, *[1])
+x = True
+f(x)
+"#
+            ),
+            &[
+                "bad.py:5:3-6: Argument `x` of `f` expects type `int`, but got `string`",
+                "bad.py:7:6-9: Argument `y` of `f` expects type `[string]`, but got `[int]`",
+                "bad.py:7:15-21: Argument `z` of `f` expects type `{string: int}`, but got `{int: int}`",
+                "bad.py:9:7-8: Argument `y` of `f` expects type `[string]`, but got `int`",
+                "bad.py:11:3-4: Argument `x` of `f` expects type `int`, but got `bool`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_types_returns() {
+        assert_eq!(
+            issues(
+                r#"
+def f(x: ("", str.type)) -> [int.type, None]:
+    if x[0]:
+        return None
+    y = x[1]
+    return y
+def g() -> (int.type, "string"):
+    return (1, 2)
+def h(x) -> int.type:
+    def inner() -> str.type:
+        return x
+    return x
+def i(x) -> int.type:
+    if x:
+        return
+    return 1
+def j(x) -> [int.type, None]:
+    if x:
+        return
+    return 1
+"#
+            ),
+            &[
+                "bad.py:6:5-13: Function `f` returns type `int | None`, but got `string`",
+                "bad.py:8:5-18: Function `g` returns type `(int, string)`, but got `(int, int)`",
+                "bad.py:15:9-15: Function `i` returns type `int`, but got `None`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_types_inference() {
+        // None of these are certain to fail, so should not be reported
+        assert_eq!(
+            issues(
+                r#"
+load("foo", "g")
+def f(x: int.type):
+    pass
+f(g())
+y = 1
+y = "a"
+f(y)
+for z in ["a"]:
+    f(z)
+f(w)
+w = w + 1
+def shadow(f):
+    f("a")
+[f(f) for f in ["a"]]
+f(x = 1)
+g(lambda f: f("a"))
+def record_type(x: "Foo"):
+    pass
+record_type(1)
+def ints(x: [int.type]):
+    pass
+xs = ["a"]
+xs[0] = 1
+ints(xs)
+ys = {"a": ["b"]}
+ys["a"].append(1)
+ints(ys["a"])
+"#
+            ),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -19,7 +19,10 @@ use std::fmt::{self, Display};
 
 use gazebo::variants::VariantName;
 
-use crate::codemap::{CodeMap, FileSpan, Span};
+use crate::{
+    analysis::config::LintSeverity,
+    codemap::{CodeMap, FileSpan, Span},
+};
 
 pub(crate) trait LintWarning: Display + VariantName {
     fn is_serious(&self) -> bool;
@@ -43,6 +46,8 @@ pub struct Lint {
     /// Is this code highly-likely to be wrong, rather
     /// than merely stylistically non-ideal.
     pub serious: bool,
+    /// How this lint should be reported, see [`LintConfig`](crate::errors::LintConfig).
+    pub severity: LintSeverity,
     /// A description of the underlying problem.
     pub problem: String,
     /// The source code at [`location`](Lint::location).
@@ -72,10 +77,16 @@ impl<T: LintWarning> LintT<T> {
     }
 
     pub(crate) fn erase(self) -> Lint {
+        let serious = self.problem.is_serious();
         Lint {
             location: self.location,
             short_name: kebab(self.problem.variant_name()),
-            serious: self.problem.is_serious(),
+            serious,
+            severity: if serious {
+                LintSeverity::Warning
+            } else {
+                LintSeverity::Disabled
+            },
             problem: self.problem.to_string(),
             original: self.original,
         }
@@ -32,7 +32,7 @@ use crate::{
     self as starlark,
     codemap::{CodeMap, FileSpanRef, Pos, Span},
     collections::SmallMap,
-    environment::{FrozenModule, Globals, GlobalsBuilder, Module},
+    environment::{FrozenModule, Globals, GlobalsBuilder, LibraryExtension, Module},
     errors::Diagnostic,
     eval::{Evaluator, ReturnFileLoader},
     stdlib::PrintHandler,
@@ -44,7 +44,8 @@ use crate::{
 };
 
 fn mk_environment() -> GlobalsBuilder {
-    GlobalsBuilder::extended().with(test_methods)
+    GlobalsBuilder::extended_by(&[LibraryExtension::all(), &[LibraryExtension::Assert]].concat())
+        .with(test_methods)
 }
 
 static GLOBALS: Lazy<Globals> = Lazy::new(|| mk_environment().build());
@@ -213,7 +214,8 @@ pub struct Assert<'a> {
 impl<'a> Assert<'a> {
     /// Create a new assert object, which will by default use
     /// [`Dialect::Extended`] and [`Globals::extended()`],
-    /// plus some additional global functions like `assert_eq`.
+    /// plus the `assert` module of [`LibraryExtension::Assert`]
+    /// and some additional global functions like `assert_eq`.
     /// The usual pattern is to create a `mut` `Assert`, modify some properties
     /// and then execute some tests.
     pub fn new() -> Self {
@@ -285,6 +287,15 @@ impl<'a> Assert<'a> {
         eval.eval_module(ast, &self.globals)
     }
 
+    /// Run a program, returning any error rather than panicking. Like the assertions, it
+    /// is run with each garbage collection strategy, returning the result with the default one.
+    pub(crate) fn run(&self, program: &str) -> anyhow::Result<()> {
+        self.with_gc(|gc| {
+            let module = Module::new();
+            self.execute("assert.bzl", program, &module, gc).map(|_| ())
+        })
+    }
+
     fn execute_fail<'v>(
         &self,
         func: &str,
@@ -23,6 +23,11 @@
 // We want to carefully control the panic message.
 #![allow(clippy::if_then_panic)]
 
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+};
+
 use gazebo::prelude::*;
 use itertools::Itertools;
 
@@ -56,23 +61,79 @@ impl<'a> Assert<'a> {
             panic!("Exception given but not used, `{}`", missed);
         }
     }
+
+    /// Run every case of a conformance test, returning the result of each rather than
+    /// panicking on the first failure. A case which panics is reported as failing.
+    pub fn conformance_results(&self, code: &str) -> Vec<ConformanceResult> {
+        ConformanceTest::parse(code).into_map(|x| {
+            let failure = match panic::catch_unwind(AssertUnwindSafe(|| x.check(self))) {
+                Ok(res) => res.err(),
+                Err(e) => Some(format!("Panicked: {}", panic_message(&*e))),
+            };
+            ConformanceResult {
+                line: x.line,
+                code: x.code,
+                failure,
+            }
+        })
+    }
+}
+
+/// The result of running one case of a conformance test,
+/// see [`conformance_results`](Assert::conformance_results).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceResult {
+    /// The 1-based line of the test file on which the case starts.
+    pub line: usize,
+    /// The code of the case.
+    pub code: String,
+    /// `None` if the case passed, otherwise a description of why it failed.
+    pub failure: Option<String>,
 }
 
 /// Describe a conformance test
 struct ConformanceTest {
+    /// The line the test starts on in the original file
+    line: usize,
     /// The code of the test
     code: String,
     /// If this might throw an error, what is it
     error: Option<(usize, String)>,
 }
 
+fn panic_message(e: &(dyn Any + Send)) -> &str {
+    match e.downcast_ref::<&str>() {
+        Some(x) => x,
+        None => match e.downcast_ref::<String>() {
+            Some(x) => x,
+            None => "unknown panic",
+        },
+    }
+}
+
+fn get_line(err: &anyhow::Error) -> Option<usize> {
+    match err.downcast_ref::<Diagnostic>() {
+        Some(Diagnostic {
+            span: Some(span), ..
+        }) => Some(span.resolve_span().begin_line + 1),
+        _ => None,
+    }
+}
+
 impl ConformanceTest {
     fn parse(code: &str) -> Vec<Self> {
         // First split on "---"
+        let mut line = 1;
         code.lines()
             .collect::<Vec<_>>()
             .split(|x| *x == "---")
             .map(|xs| Self {
+                line: {
+                    let start = line;
+                    // Skip over the lines of the test and the separator
+                    line += xs.len() + 1;
+                    start
+                },
                 code: xs.join("\n"),
                 error: xs
                     .iter()
@@ -83,15 +144,6 @@ impl ConformanceTest {
     }
 
     fn test(&self, assert: &Assert) {
-        fn get_line(err: &anyhow::Error) -> Option<usize> {
-            match err.downcast_ref::<Diagnostic>() {
-                Some(Diagnostic {
-                    span: Some(span), ..
-                }) => Some(span.resolve_span().begin_line + 1),
-                _ => None,
-            }
-        }
-
         match &self.error {
             None => {
                 assert.pass(&self.code);
@@ -110,4 +162,57 @@ impl ConformanceTest {
             }
         }
     }
+
+    /// Like `test`, but describing the failure instead of panicking.
+    fn check(&self, assert: &Assert) -> Result<(), String> {
+        match (&self.error, assert.run(&self.code)) {
+            (None, Ok(())) => Ok(()),
+            (None, Err(err)) => Err(format!("Failed to execute:\n{}", err)),
+            (Some((line, _msg)), Ok(())) => {
+                Err(format!("Didn't fail, expected an error at line {}", line))
+            }
+            (Some((line, _msg)), Err(err)) => match get_line(&err) {
+                Some(got) if got == *line => Ok(()),
+                got => Err(format!(
+                    "Failed at the wrong line, expected {}, got {:?}:\n{}",
+                    line, got, err
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conformance_results() {
+        let code = r#"
+assert_eq(1, 1)
+---
+x = 1 // 0 ### division by zero
+---
+assert_eq(1, 2)
+---
+1 // 0
+assert_eq(1, 1) ### wrong line
+"#;
+        let res = Assert::new().conformance_results(code);
+        assert_eq!(res.map(|x| x.line), vec![1, 4, 6, 8]);
+        assert_eq!(
+            res.map(|x| x.failure.is_none()),
+            vec![true, true, false, false]
+        );
+        assert!(res[3].failure.as_ref().unwrap().contains("wrong line"));
+        assert_eq!(res[1].code, "x = 1 // 0 ### division by zero");
+    }
+
+    #[test]
+    fn test_conformance_results_panic() {
+        let mut a = Assert::new();
+        a.setup_eval(|_| panic!("oops"));
+        let res = a.conformance_results("assert_eq(1, 1)");
+        assert_eq!(res[0].failure.as_deref(), Some("Panicked: oops"));
+    }
 }
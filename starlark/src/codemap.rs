@@ -42,6 +42,11 @@ impl Pos {
     pub fn new(x: u32) -> Self {
         Self(x)
     }
+
+    /// The byte offset of the position within the file.
+    pub fn get(self) -> u32 {
+        self.0
+    }
 }
 
 impl Add<u32> for Pos {
@@ -68,6 +73,11 @@ impl Span {
         Span { begin, end }
     }
 
+    /// The position of the first byte of the span.
+    pub fn begin(self) -> Pos {
+        self.begin
+    }
+
     /// The position after the last byte of the span.
     pub fn end(self) -> Pos {
         self.end
@@ -85,6 +95,11 @@ impl Span {
             end: cmp::max(self.end, other.end),
         }
     }
+
+    /// Determines whether a `pos` is within this span, including the position after the last byte.
+    pub fn contains(self, pos: Pos) -> bool {
+        self.begin <= pos && pos <= self.end
+    }
 }
 
 /// Associate a Span with a value of arbitrary type (e.g. an AST node).
@@ -231,6 +246,22 @@ impl CodeMap {
         LineCol { line, column }
     }
 
+    /// Gets the `Pos` of a line and column, both 0-indexed, with the column counted in characters.
+    ///
+    /// Returns `None` if the line is out of range. Columns past the end of the line are
+    /// clamped to the end of the line.
+    pub(crate) fn find_pos(&self, line: usize, column: usize) -> Option<Pos> {
+        if line >= self.0.lines.len() {
+            return None;
+        }
+        let text = self.source_line(line);
+        let byte_col = text
+            .char_indices()
+            .nth(column)
+            .map_or(text.len(), |(i, _)| i);
+        Some(self.0.lines[line] + byte_col as u32)
+    }
+
     /// Gets the full source text of the file
     pub fn source(&self) -> &str {
         &self.0.source
@@ -453,6 +484,12 @@ mod test {
             LineCol { line: 2, column: 4 }
         );
 
+        // Test .find_pos()
+        assert_eq!(codemap.find_pos(0, 0), Some(start));
+        assert_eq!(codemap.find_pos(1, 2), Some(start + 7));
+        assert_eq!(codemap.find_pos(1, 100), Some(start + 11));
+        assert_eq!(codemap.find_pos(3, 0), None);
+
         // Test .source() and .num_lines()
         assert_eq!(codemap.source(), source);
         assert_eq!(codemap.num_lines(), 3);
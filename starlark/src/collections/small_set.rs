@@ -22,14 +22,23 @@ use std::{
     iter::FromIterator,
 };
 
-use gazebo::prelude::*;
+use gazebo::{
+    coerce::{Coerce, CoerceKey},
+    prelude::*,
+};
 use indexmap::Equivalent;
 
-use crate::collections::small_map::SmallMap;
+use crate::collections::{
+    hash::{BorrowHashed, Hashed},
+    small_map::SmallMap,
+};
 
 /// An memory-efficient set with determinstic order, based on [`SmallMap`].
+#[repr(transparent)]
 #[derive(Clone, Default_)]
-pub struct SmallSet<T>(SmallMap<T, ()>);
+pub struct SmallSet<T>(pub(crate) SmallMap<T, ()>);
+
+unsafe impl<From, To> Coerce<SmallSet<To>> for SmallSet<From> where From: CoerceKey<To> {}
 
 impl<T: Debug> Debug for SmallSet<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -103,6 +112,38 @@ impl<T> SmallSet<T> {
         self.0.into_iter().map(|(t, _)| t)
     }
 
+    pub fn iter_hashed(&self) -> impl ExactSizeIterator<Item = BorrowHashed<T>> {
+        self.0.iter_hashed().map(|(t, _)| t)
+    }
+
+    pub fn into_iter_hashed(self) -> impl ExactSizeIterator<Item = Hashed<T>> {
+        self.0.into_iter_hashed().map(|(t, _)| t)
+    }
+
+    pub fn insert_hashed(&mut self, key: Hashed<T>) -> bool
+    where
+        T: Eq,
+    {
+        self.0.insert_hashed(key, ()).is_none()
+    }
+
+    pub fn contains_hashed<Q>(&self, key: BorrowHashed<Q>) -> bool
+    where
+        Q: Equivalent<T> + ?Sized,
+        T: Eq,
+    {
+        self.0.contains_key_hashed(key)
+    }
+
+    /// Remove the element from the set, returning `true` if it was present.
+    pub fn remove_hashed<Q>(&mut self, key: BorrowHashed<Q>) -> bool
+    where
+        Q: Equivalent<T> + ?Sized,
+        T: Eq,
+    {
+        self.0.remove_hashed(key).is_some()
+    }
+
     pub fn insert(&mut self, key: T) -> bool
     where
         T: Hash + Eq,
@@ -174,6 +215,10 @@ impl<T> SmallSet<T> {
     pub fn clear(&mut self) {
         self.0.clear()
     }
+
+    pub(crate) fn extra_memory(&self) -> usize {
+        self.0.extra_memory()
+    }
 }
 
 /// Create a [`SmallSet`](SmallSet) from a list of values.
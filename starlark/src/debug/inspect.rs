@@ -18,7 +18,7 @@
 use crate::{
     collections::SmallMap,
     eval::{Def, Evaluator, FrozenDef, ScopeNames},
-    values::{Value, ValueLike},
+    values::{dict::Dict, Heap, Value, ValueLike},
 };
 
 pub(crate) fn to_scope_names<'v>(x: Value<'v>) -> Option<&'v ScopeNames> {
@@ -36,6 +36,60 @@ impl<'v, 'a> Evaluator<'v, 'a> {
     pub fn local_variables(&self) -> SmallMap<String, Value<'v>> {
         inspect_local_variables(self).unwrap_or_else(|| inspect_module_variables(self))
     }
+
+    /// Obtain the [`Module`](crate::environment::Module) variables, whether or not we are
+    /// currently inside a function. The only legitimate use of this function is for debugging.
+    pub fn module_variables(&self) -> SmallMap<String, Value<'v>> {
+        inspect_module_variables(self)
+    }
+}
+
+/// Debuggers show values as a tree, which these functions describe. Values have _indexed_
+/// children (the elements of a list, tuple, set or other iterable) and _named_ children (the
+/// entries of a dictionary, or the fields of a struct, record or enumeration value), which can
+/// be large, so indexed children are fetched a page at a time.
+impl<'v> Value<'v> {
+    /// The number of indexed children, see [`inspect_indexed`](Value::inspect_indexed).
+    pub fn inspect_indexed_count(self, heap: &'v Heap) -> usize {
+        if Dict::from_value(self).is_some() || self.with_iterator(heap, |_| ()).is_err() {
+            return 0;
+        }
+        self.length().map_or(0, |n| n as usize)
+    }
+
+    /// The indexed children from `start`, at most `count` of them. These are the values
+    /// produced by iterating over the value, except for dictionaries, which have named children.
+    pub fn inspect_indexed(self, start: usize, count: usize, heap: &'v Heap) -> Vec<Value<'v>> {
+        if Dict::from_value(self).is_some() {
+            return Vec::new();
+        }
+        self.with_iterator(heap, |it| it.skip(start).take(count).collect())
+            .unwrap_or_default()
+    }
+
+    /// The number of named children, see [`inspect_named`](Value::inspect_named).
+    pub fn inspect_named_count(self) -> usize {
+        match Dict::from_value(self) {
+            Some(dict) => dict.len(),
+            None => self.get_ref().dir_attr().len(),
+        }
+    }
+
+    /// The named children, which are the entries of a dictionary (named by the `repr` of
+    /// their key), or the attributes of a value (not including any methods).
+    pub fn inspect_named(self, heap: &'v Heap) -> Vec<(String, Value<'v>)> {
+        if let Some(dict) = Dict::from_value(self) {
+            return dict.iter().map(|(k, v)| (k.to_repr(), v)).collect();
+        }
+        let aref = self.get_ref();
+        aref.dir_attr()
+            .into_iter()
+            .filter_map(|name| {
+                let v = aref.get_attr(&name, heap)?;
+                Some((name, v))
+            })
+            .collect()
+    }
 }
 
 fn inspect_local_variables<'v>(eval: &Evaluator<'v, '_>) -> Option<SmallMap<String, Value<'v>>> {
@@ -66,8 +120,10 @@ mod tests {
     use gazebo::{coerce::coerce, prelude::*};
 
     use crate::{
-        self as starlark, assert, collections::SmallMap, environment::GlobalsBuilder,
-        values::dict::Dict,
+        self as starlark, assert,
+        collections::SmallMap,
+        environment::GlobalsBuilder,
+        values::{dict::Dict, Value},
     };
 
     #[starlark_module]
@@ -76,6 +132,41 @@ mod tests {
             Ok(eval.call_stack().map(ToString::to_string))
         }
 
+        fn debug_inspect_stack_count() -> anyhow::Result<i32> {
+            Ok(eval.call_stack_count() as i32)
+        }
+
+        fn debug_inspect_indexed(
+            ref x: Value,
+            ref start: i32,
+            ref count: i32,
+        ) -> anyhow::Result<Vec<Value<'v>>> {
+            Ok(x.inspect_indexed(start as usize, count as usize, heap))
+        }
+
+        fn debug_inspect_named(ref x: Value) -> anyhow::Result<Dict<'v>> {
+            let mut sm = SmallMap::new();
+            for (k, v) in x.inspect_named(heap) {
+                sm.insert_hashed(heap.alloc_str(&k).get_hashed(), v);
+            }
+            Ok(Dict::new(coerce(sm)))
+        }
+
+        fn debug_inspect_counts(ref x: Value) -> anyhow::Result<(i32, i32)> {
+            Ok((
+                x.inspect_indexed_count(heap) as i32,
+                x.inspect_named_count() as i32,
+            ))
+        }
+
+        fn debug_inspect_module_variables() -> anyhow::Result<Dict<'v>> {
+            let mut sm = SmallMap::new();
+            for (k, v) in eval.module_variables() {
+                sm.insert_hashed(heap.alloc_str(&k).get_hashed(), v);
+            }
+            Ok(Dict::new(coerce(sm)))
+        }
+
         fn debug_inspect_variables() -> anyhow::Result<Dict<'v>> {
             let mut sm = SmallMap::new();
             for (k, v) in eval.local_variables() {
@@ -104,6 +195,21 @@ g()
         );
     }
 
+    #[test]
+    fn test_debug_stack_count() {
+        let mut a = assert::Assert::new();
+        a.globals_add(debugger);
+        a.pass(
+            r#"
+top = debug_inspect_stack_count()
+def f(): return debug_inspect_stack_count()
+def g(): return f()
+assert_eq(f(), top + 1)
+assert_eq(g(), top + 2)
+"#,
+        );
+    }
+
     #[test]
     fn test_debug_variables() {
         let mut a = assert::Assert::new();
@@ -119,6 +225,54 @@ def f(x = 1, y = "test"):
     assert_eq(debug_inspect_variables(), {"x": 1, "y": "hello", "z": 6, "_magic": True})
 f(y = "hello")
 assert_eq(debug_inspect_variables(), {"root": 12, "f": f, "_ignore": [True]})
+"#,
+        );
+    }
+
+    #[test]
+    fn test_debug_module_variables() {
+        let mut a = assert::Assert::new();
+        a.globals_add(debugger);
+        a.pass(
+            r#"
+root = 12
+def f(x):
+    return debug_inspect_module_variables()
+assert_eq(f(1), {"root": 12, "f": f})
+"#,
+        );
+    }
+
+    #[test]
+    fn test_debug_inspect_children() {
+        let mut a = assert::Assert::new();
+        a.globals_add(debugger);
+        a.pass(
+            r#"
+xs = list(range(100))
+assert_eq(debug_inspect_counts(xs), (100, 0))
+assert_eq(debug_inspect_indexed(xs, 10, 3), [10, 11, 12])
+assert_eq(debug_inspect_indexed(xs, 98, 10), [98, 99])
+assert_eq(debug_inspect_indexed((1, [2]), 0, 10), [1, [2]])
+assert_eq(debug_inspect_counts(range(5)), (5, 0))
+
+d = {"a": 1, 2: [3]}
+assert_eq(debug_inspect_counts(d), (0, 2))
+assert_eq(debug_inspect_indexed(d, 0, 10), [])
+assert_eq(debug_inspect_named(d), {'"a"': 1, "2": [3]})
+
+s = struct(x = 1, y = [2])
+assert_eq(debug_inspect_counts(s), (0, 2))
+assert_eq(debug_inspect_named(s), {"x": 1, "y": [2]})
+
+r = record(a = int, b = str)(a = 1, b = "x")
+assert_eq(debug_inspect_named(r), {"a": 1, "b": "x"})
+
+e = enum("p", "q")("q")
+assert_eq(debug_inspect_named(e), {"index": 1, "value": "q"})
+
+assert_eq(debug_inspect_counts("abc"), (0, 0))
+assert_eq(debug_inspect_counts(1), (0, 0))
 "#,
         );
     }
@@ -309,6 +309,7 @@ impl Module {
         // they are used.
         let freezer = Freezer::new(frozen_heap);
         let slots = slots.freeze(&freezer)?;
+        freezer.heap.check_allocation(0)?;
         let rest = FrozenModuleRef(Arc::new(FrozenModuleData {
             names: names.freeze(),
             slots,
@@ -20,6 +20,7 @@
 use std::fmt::Write;
 
 use crate::{
+    errors::Diagnostic,
     eval::{
         bc::{
             addr::BcPtrAddr,
@@ -51,6 +52,15 @@ impl Bc {
     #[cold]
     #[inline(never)]
     pub(crate) fn slow_arg_at_ptr(addr_ptr: BcPtrAddr) -> &BcInstrSlowArg {
+        match Self::find_slow_arg_at_ptr(addr_ptr) {
+            Some(slow_arg) => slow_arg,
+            None => panic!("span not found for opcode: {:?}", addr_ptr.get_opcode()),
+        }
+    }
+
+    /// Find span for instruction, if the instruction has one
+    /// (instructions which cannot fail may not).
+    fn find_slow_arg_at_ptr(addr_ptr: BcPtrAddr) -> Option<&BcInstrSlowArg> {
         let mut ptr = addr_ptr;
         loop {
             let opcode = ptr.get_opcode();
@@ -59,12 +69,10 @@ impl Bc {
                 let (code_len, spans) = &end_of_bc.arg;
                 let code_start_ptr = ptr.sub(*code_len);
                 let addr = addr_ptr.offset_from(code_start_ptr);
-                for (next_addr, next_span) in spans {
-                    if *next_addr == addr {
-                        return next_span;
-                    }
-                }
-                panic!("span not found for addr: {}", addr);
+                return spans
+                    .iter()
+                    .find(|(next_addr, _)| *next_addr == addr)
+                    .map(|(_, next_span)| next_span);
             }
             ptr = ptr.add(opcode.size_of_repr());
         }
@@ -75,12 +83,29 @@ impl Bc {
     pub(crate) fn wrap_error_for_instr_ptr(
         ptr: BcPtrAddr,
         e: anyhow::Error,
-        eval: &Evaluator,
+        eval: &mut Evaluator,
     ) -> EvalException {
         let span = Self::slow_arg_at_ptr(ptr).span;
         add_span_to_expr_error(e, span, eval)
     }
 
+    /// Like [`wrap_error_for_instr_ptr`](Bc::wrap_error_for_instr_ptr), but for errors
+    /// raised before the instruction is executed, so the instruction might not have a span.
+    #[cold]
+    #[inline(never)]
+    fn wrap_limit_error_for_instr_ptr(
+        ptr: BcPtrAddr,
+        e: anyhow::Error,
+        eval: &mut Evaluator,
+    ) -> EvalException {
+        match Self::find_slow_arg_at_ptr(ptr) {
+            Some(slow_arg) => add_span_to_expr_error(e, slow_arg.span, eval),
+            None => EvalException(Diagnostic::modify(e, |d| {
+                d.set_call_stack(|| eval.call_stack.to_diagnostic_frames())
+            })),
+        }
+    }
+
     /// Run the bytecode in the current frame allocated in the evaluator.
     ///
     /// Frame must be allocated properly, otherwise it will likely result in memory corruption.
@@ -171,6 +196,9 @@ pub(crate) fn run_block<'v>(
     mut ip: BcPtrAddr,
 ) -> RunBlockResult<'v> {
     loop {
+        if let Err(e) = eval.limits.step(eval.heap()) {
+            return RunBlockResult::Err(Bc::wrap_limit_error_for_instr_ptr(ip, e, eval));
+        }
         // Note most functions called from here must be carefully annotated
         // as `#[inline(always)]` otherwise LLVM considers them too large to inline.
         //
@@ -288,6 +288,20 @@ impl IrSpanned<ExprCompiled> {
                 arg.write_bc(bc);
                 bc.write_instr::<InstrFormatOne>(span, (before, after));
             }
+            ExprCompiled::FString(box (before, ref args)) => {
+                write_exprs(args.iter().map(|(arg, ..)| arg), bc);
+                let args = args.map(|(_, format, after)| (*format, *after));
+                bc.write_instr::<InstrFString>(
+                    span,
+                    (
+                        ArgPopsStack(args.len() as u32),
+                        InstrFStringData {
+                            before,
+                            args: args.into_boxed_slice(),
+                        },
+                    ),
+                );
+            }
             ExprCompiled::Call(ref call) => call.write_bc(bc),
             ExprCompiled::Def(ref def) => def.write_bc(span, bc),
         }
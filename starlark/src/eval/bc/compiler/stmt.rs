@@ -137,6 +137,17 @@ impl IrSpanned<StmtCompiled> {
                     body.write_bc(compiler, bc);
                 });
             }
+            StmtCompiled::While(box (ref cond, ref body)) => {
+                bc.write_while(span, |bc| {
+                    // `while True` has no exit condition to check.
+                    if cond.is_pure_infallible_to_bool() != Some(true) {
+                        Self::write_if_then(compiler, bc, cond, MaybeNot::Not, &|_, bc| {
+                            bc.write_instr::<InstrBreak>(span, ())
+                        });
+                    }
+                    body.write_bc(compiler, bc);
+                });
+            }
             StmtCompiled::Break => {
                 bc.write_instr::<InstrBreak>(span, ());
             }
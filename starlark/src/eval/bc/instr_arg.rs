@@ -32,7 +32,7 @@ use crate::{
             addr::{BcAddr, BcAddrOffset, BcPtrAddr},
             call::{BcCallArgsFull, BcCallArgsPos},
             instr::BcInstr,
-            instr_impl::InstrDefData,
+            instr_impl::{InstrDefData, InstrFStringData},
             opcode::{BcOpcode, BcOpcodeHandler},
             slow_arg::BcInstrSlowArg,
         },
@@ -655,6 +655,26 @@ impl BcInstrArg for InstrDefData {
     }
 }
 
+impl BcInstrArg for InstrFStringData {
+    fn fmt_append(param: &Self, _ip: BcAddr, f: &mut dyn Write) -> fmt::Result {
+        write!(f, " {}", TruncateValueRepr(param.before.unpack()))?;
+        for (format, after) in param.args.iter() {
+            write!(f, " {{{}}} {}", format, TruncateValueRepr(after.unpack()))?;
+        }
+        Ok(())
+    }
+
+    fn visit_jump_addr(_param: &Self, _consumer: &mut dyn FnMut(BcAddrOffset)) {}
+
+    fn pops_stack(_param: &Self) -> u32 {
+        0
+    }
+
+    fn pushes_stack(_param: &Self) -> u32 {
+        0
+    }
+}
+
 impl BcInstrArg for BcCallArgsFull {
     fn fmt_append(param: &Self, _ip: BcAddr, f: &mut dyn Write) -> fmt::Result {
         write!(f, " {{{}}}", param)
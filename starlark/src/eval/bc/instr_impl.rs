@@ -48,12 +48,13 @@ use crate::{
         runtime::{call_stack::FrozenFileSpan, slots::LocalSlotId},
         Arguments, Def, Evaluator, FrozenDef, ParametersSpec,
     },
+    syntax::ast::FStringFormat,
     values::{
         dict::Dict,
         function::NativeFunction,
         known_methods::KnownMethod,
         list::List,
-        string::interpolation::{format_one, percent_s_one},
+        string::interpolation::{format_one, fstring, percent_s_one},
         typed::FrozenValueTyped,
         typing::TypeCompiled,
         FrozenRef, FrozenStringValue, FrozenValue, Heap, StarlarkValue, StringValue, Value,
@@ -493,13 +494,14 @@ impl InstrNoFlowImpl for InstrSetArrayIndexImpl {
 
     #[inline(always)]
     fn run_with_args<'v>(
-        _eval: &mut Evaluator<'v, '_>,
+        eval: &mut Evaluator<'v, '_>,
         _stack: &mut BcStackPtr<'v, '_>,
         _ip: BcPtrAddr,
         (): &(),
         [value, array, index]: [Value<'v>; 3],
     ) -> anyhow::Result<()> {
-        array.set_at(index, value)
+        eval.heap()
+            .track_extra_memory(array, || array.set_at(index, value))
     }
 }
 
@@ -510,13 +512,14 @@ impl InstrNoFlowImpl for InstrArrayIndexSetImpl {
 
     #[inline(always)]
     fn run_with_args<'v>(
-        _eval: &mut Evaluator<'v, '_>,
+        eval: &mut Evaluator<'v, '_>,
         _stack: &mut BcStackPtr<'v, '_>,
         _ip: BcPtrAddr,
         (): &(),
         [array, index, value]: [Value<'v>; 3],
     ) -> anyhow::Result<()> {
-        array.set_at(index, value)
+        eval.heap()
+            .track_extra_memory(array, || array.set_at(index, value))
     }
 }
 
@@ -649,8 +652,8 @@ impl InstrUnOpImpl for InstrMinusImpl {
 
 impl InstrUnOpImpl for InstrBitNotImpl {
     #[inline(always)]
-    fn eval<'v>(v: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        Ok(Value::new_int(!v.to_int()?))
+    fn eval<'v>(v: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        v.bit_not(heap)
     }
 }
 
@@ -795,36 +798,36 @@ impl InstrBinOpImpl for InstrDivideImpl {
 
 impl InstrBinOpImpl for InstrBitAndImpl {
     #[inline(always)]
-    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        v0.bit_and(v1)
+    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        v0.bit_and(v1, heap)
     }
 }
 
 impl InstrBinOpImpl for InstrBitOrImpl {
     #[inline(always)]
-    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        v0.bit_or(v1)
+    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        v0.bit_or(v1, heap)
     }
 }
 
 impl InstrBinOpImpl for InstrBitXorImpl {
     #[inline(always)]
-    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        v0.bit_xor(v1)
+    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        v0.bit_xor(v1, heap)
     }
 }
 
 impl InstrBinOpImpl for InstrLeftShiftImpl {
     #[inline(always)]
-    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        v0.left_shift(v1)
+    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        v0.left_shift(v1, heap)
     }
 }
 
 impl InstrBinOpImpl for InstrRightShiftImpl {
     #[inline(always)]
-    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        v0.right_shift(v1)
+    fn eval<'v>(v0: Value<'v>, v1: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        v0.right_shift(v1, heap)
     }
 }
 
@@ -1026,6 +1029,38 @@ impl InstrNoFlowImpl for InstrListNPopImpl {
     }
 }
 
+/// The text of an f-string, around the values of its expressions which are on the stack.
+#[derive(Debug)]
+pub(crate) struct InstrFStringData {
+    pub(crate) before: FrozenStringValue,
+    pub(crate) args: Box<[(FStringFormat, FrozenStringValue)]>,
+}
+
+pub(crate) struct InstrFStringImpl;
+pub(crate) type InstrFString = InstrNoFlow<InstrFStringImpl>;
+
+impl InstrNoFlowImpl for InstrFStringImpl {
+    type Pop<'v> = ();
+    type Push<'v> = Value<'v>;
+    type Arg = (ArgPopsStack, InstrFStringData);
+
+    #[inline(always)]
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_>,
+        stack: &mut BcStackPtr<'v, '_>,
+        _: BcPtrAddr,
+        (npops, data): &Self::Arg,
+        _pops: (),
+    ) -> anyhow::Result<Value<'v>> {
+        let values = stack.pop_slice(*npops);
+        let args = values
+            .iter()
+            .zip(data.args.iter())
+            .map(|(v, (format, after))| (*v, *format, after.as_str()));
+        Ok(fstring(data.before.as_str(), args, eval.heap()).to_value())
+    }
+}
+
 impl InstrNoFlowImpl for InstrListOfConstsImpl {
     type Pop<'v> = ();
     type Push<'v> = Value<'v>;
@@ -1267,6 +1302,7 @@ impl BcInstr for InstrIfNotBr {
 }
 
 pub(crate) struct InstrForLoop;
+pub(crate) struct InstrWhileLoop;
 pub(crate) struct InstrBreak;
 pub(crate) struct InstrContinue;
 
@@ -1320,6 +1356,30 @@ impl BcInstr for InstrForLoop {
     }
 }
 
+impl BcInstr for InstrWhileLoop {
+    type Pop<'v> = ();
+    type Push<'v> = ();
+    type Arg = BcAddrOffset;
+
+    fn run<'v, 'b>(
+        eval: &mut Evaluator<'v, '_>,
+        stack: &mut BcStackPtr<'v, '_>,
+        ip: BcPtrAddr<'b>,
+        loop_end: &BcAddrOffset,
+    ) -> InstrControl<'v, 'b> {
+        // Loop body checks the condition and breaks when it is false.
+        let loop_start = ip.add_instr::<Self>();
+        loop {
+            match run_block(eval, stack, loop_start) {
+                RunBlockResult::Continue => {}
+                RunBlockResult::Break => return InstrControl::Next(ip.add_rel(*loop_end)),
+                RunBlockResult::Return(v) => return InstrControl::Return(v),
+                RunBlockResult::Err(e) => return InstrControl::Err(e.0),
+            }
+        }
+    }
+}
+
 impl BcInstr for InstrBreak {
     type Pop<'v> = ();
     type Push<'v> = ();
@@ -1617,13 +1677,14 @@ fn call_method_common<'v>(
     span: FrozenRef<'static, FrozenFileSpan>,
 ) -> anyhow::Result<Value<'v>> {
     // TODO: wrong span: should be span of `object.method`, not of the whole expression
-    let method = get_attr_hashed_raw(this, symbol, eval.heap())?;
-    match method {
+    let heap = eval.heap();
+    let method = get_attr_hashed_raw(this, symbol, heap)?;
+    heap.track_extra_memory(this, || match method {
         MemberOrValue::Member(member) => {
             member.to_value().invoke_method(this, span, arguments, eval)
         }
         MemberOrValue::Value(value) => value.invoke_with_loc(Some(span), arguments, eval),
-    }
+    })
 }
 
 /// Common of method invocation instructions where a method is likely stdlib method.
@@ -1641,13 +1702,15 @@ fn call_maybe_known_method_common<'v>(
         // If pointers are equal, getattr would return the same method
         // we already have.
         if ptr::eq(methods, known_method.type_methods) {
-            return eval.with_call_stack(known_method.method.to_value(), Some(span), |eval| {
-                known_method.method.invoke_method(
-                    known_method.method.to_value(),
-                    this,
-                    arguments,
-                    eval,
-                )
+            return eval.heap().track_extra_memory(this, || {
+                eval.with_call_stack(known_method.method.to_value(), Some(span), |eval| {
+                    known_method.method.invoke_method(
+                        known_method.method.to_value(),
+                        this,
+                        arguments,
+                        eval,
+                    )
+                })
             });
         }
     }
@@ -27,13 +27,16 @@ use std::{
 
 use either::Either;
 
-use crate::eval::bc::{
-    addr::{BcAddr, BcAddrOffset, BcPtrAddr},
-    instr::BcInstr,
-    instr_impl::{InstrEnd, InstrForLoop},
-    opcode::{BcOpcode, BcOpcodeHandler},
-    repr::{BcInstrHeader, BcInstrRepr, BC_INSTR_ALIGN},
-    slow_arg::BcInstrSlowArg,
+use crate::eval::{
+    bc::{
+        addr::{BcAddr, BcAddrOffset, BcPtrAddr},
+        instr::BcInstr,
+        instr_impl::{InstrBeforeStmt, InstrEnd, InstrForLoop, InstrWhileLoop},
+        opcode::{BcOpcode, BcOpcodeHandler},
+        repr::{BcInstrHeader, BcInstrRepr, BC_INSTR_ALIGN},
+        slow_arg::BcInstrSlowArg,
+    },
+    runtime::call_stack::FrozenFileSpan,
 };
 
 impl BcOpcode {
@@ -193,6 +196,17 @@ impl BcInstrs {
         })
     }
 
+    /// The spans of the `BeforeStmt` instructions, in the order they were written.
+    pub(crate) fn before_stmt_spans(&self) -> impl Iterator<Item = FrozenFileSpan> + '_ {
+        self.iter().filter_map(|(ptr, _)| {
+            if ptr.get_opcode() == BcOpcode::BeforeStmt {
+                Some(ptr.get_instr::<InstrBeforeStmt>().arg)
+            } else {
+                None
+            }
+        })
+    }
+
     pub(crate) fn fmt_impl(&self, f: &mut dyn Write, newline: bool) -> fmt::Result {
         let mut loop_ends = Vec::new();
         let mut jump_targets = HashSet::new();
@@ -234,6 +248,10 @@ impl BcInstrs {
                 let for_loop = ptr.get_instr::<InstrForLoop>();
                 loop_ends.push(ip.offset(for_loop.arg));
             }
+            if opcode == BcOpcode::WhileLoop {
+                let while_loop = ptr.get_instr::<InstrWhileLoop>();
+                loop_ends.push(ip.offset(while_loop.arg));
+            }
         }
         Ok(())
     }
@@ -73,6 +73,7 @@ pub(crate) enum BcOpcode {
     Percent,
     PercentSOne,
     FormatOne,
+    FString,
     Divide,
     FloorDivide,
     BitAnd,
@@ -97,6 +98,7 @@ pub(crate) enum BcOpcode {
     IfBr,
     IfNotBr,
     ForLoop,
+    WhileLoop,
     Break,
     Continue,
     Return,
@@ -29,7 +29,7 @@ use crate::{
                 InstrBr, InstrConst, InstrConst2, InstrConst3, InstrConst4, InstrContinue,
                 InstrForLoop, InstrIfBr, InstrIfNotBr, InstrLoadLocal, InstrLoadLocal2,
                 InstrLoadLocal3, InstrLoadLocal4, InstrLoadLocalAndConst, InstrLoadLocalCaptured,
-                InstrProfileBc, InstrStoreLocal, InstrStoreLocalCaptured,
+                InstrProfileBc, InstrStoreLocal, InstrStoreLocalCaptured, InstrWhileLoop,
             },
             instrs::{BcInstrsWriter, PatchAddr},
             opcode::BcOpcode,
@@ -381,6 +381,21 @@ impl<'f> BcWriter<'f> {
         self.patch_addr(end_patch);
     }
 
+    /// Write while loop. The body is responsible for checking the condition
+    /// and writing `break` when the loop is done.
+    pub(crate) fn write_while(&mut self, span: FrozenFileSpan, body: impl FnOnce(&mut Self)) {
+        let arg = self.write_instr_ret_arg::<InstrWhileLoop>(span, BcAddrOffset::FORWARD);
+        let end_patch = self.instrs.addr_to_patch(arg);
+        let ss = self.stack_size();
+        body(self);
+        assert!(
+            self.stack_size() == ss,
+            "Loop body must not leave values on the stack"
+        );
+        self.write_instr::<InstrContinue>(span, ());
+        self.patch_addr(end_patch);
+    }
+
     pub(crate) fn stack_add(&mut self, add: u32) {
         self.stack_size += add;
         self.max_stack_size = cmp::max(self.max_stack_size, self.stack_size);
@@ -28,6 +28,7 @@ use crate::{
     errors::Diagnostic,
     eval::{
         compiler::scope::{ScopeData, ScopeId},
+        fragment::stmt::on_error,
         runtime::call_stack::FrozenFileSpan,
         Evaluator, ScopeNames,
     },
@@ -47,14 +48,23 @@ fn add_span_to_error(e: anyhow::Error, span: FrozenFileSpan, eval: &Evaluator) -
     })
 }
 
+/// Add the span to an error, and if this is where the error was raised (so it had no span),
+/// run the [`on_error`](Evaluator::on_error) callbacks.
 #[cold]
 #[inline(never)]
 pub(crate) fn add_span_to_expr_error(
     e: anyhow::Error,
     span: FrozenFileSpan,
-    eval: &Evaluator,
+    eval: &mut Evaluator,
 ) -> EvalException {
-    EvalException(add_span_to_error(e, span, eval))
+    let raised = !eval.on_error.is_empty()
+        && e.downcast_ref::<Diagnostic>()
+            .map_or(true, |d| d.span.is_none());
+    let e = add_span_to_error(e, span, eval);
+    if raised {
+        on_error(&e, eval);
+    }
+    EvalException(e)
 }
 
 /// Convert syntax error to spanned evaluation exception
@@ -62,7 +72,7 @@ pub(crate) fn add_span_to_expr_error(
 pub(crate) fn expr_throw<'v, T>(
     r: anyhow::Result<T>,
     span: FrozenFileSpan,
-    eval: &Evaluator<'v, '_>,
+    eval: &mut Evaluator<'v, '_>,
 ) -> Result<T, EvalException> {
     match r {
         Ok(v) => Ok(v),
@@ -79,6 +89,8 @@ pub(crate) struct Compiler<'v, 'a, 'e> {
     pub(crate) constants: Constants,
     pub(crate) has_before_stmt: bool,
     pub(crate) bc_profile: bool,
+    /// Defined functions may call themselves.
+    pub(crate) enable_recursion: bool,
 }
 
 impl Compiler<'_, '_, '_> {
@@ -520,6 +520,9 @@ impl Stmt {
                 Assign::collect_defines_lvalue(dest, InLoop::Yes, scope_data, result);
                 StmtP::collect_defines(body, InLoop::Yes, scope_data, result);
             }
+            StmtP::While(_, box body) => {
+                StmtP::collect_defines(body, InLoop::Yes, scope_data, result);
+            }
             StmtP::Def(name, ..) => AssignIdent::collect_assign_ident(
                 name,
                 in_loop,
@@ -28,6 +28,7 @@ use derivative::Derivative;
 use derive_more::Display;
 use gazebo::{any::AnyLifetime, prelude::*};
 use once_cell::sync::Lazy;
+use thiserror::Error;
 
 use crate::{
     codemap::CodeMap,
@@ -62,6 +63,12 @@ use crate::{
     },
 };
 
+#[derive(Error, Debug)]
+enum DefError {
+    #[error("Function `{0}` called recursively, which is not allowed in this dialect")]
+    Recursion(String),
+}
+
 /// Store frozen `StmtCompiled`.
 /// This is initialized in `post_freeze`.
 struct StmtCompiledCell {
@@ -182,6 +189,8 @@ pub(crate) struct DefInfo {
     /// Globals captured during function or module creation.
     /// Only needed for debugger evaluation.
     pub(crate) globals: FrozenRef<'static, Globals>,
+    /// Function may call itself, directly or indirectly.
+    enable_recursion: bool,
 }
 
 impl DefInfo {
@@ -196,6 +205,7 @@ impl DefInfo {
             stmt_compile_context: StmtCompileContext::default(),
             inline_def_body: None,
             globals: FrozenRef::new(Globals::empty()),
+            enable_recursion: true,
         });
         FrozenRef::new(&EMPTY)
     }
@@ -214,6 +224,7 @@ impl DefInfo {
             stmt_compile_context: StmtCompileContext::default(),
             inline_def_body: None,
             globals,
+            enable_recursion: true,
         }
     }
 }
@@ -368,6 +379,15 @@ impl Compiler<'_, '_, '_> {
                 let v = Compiler::is_safe_to_inline_expr_spanned(v)?;
                 ExprCompiled::FormatOne(box (*before, v, *after))
             }
+            ExprCompiled::FString(box (before, ref args)) => {
+                // Like `FormatOne`, f-strings are infallible.
+                let args = args.try_map(|(arg, format, after)| {
+                    Compiler::is_safe_to_inline_expr_spanned(arg)
+                        .map(|arg| (arg, *format, *after))
+                        .ok_or(())
+                });
+                ExprCompiled::FString(box (*before, args.ok()?))
+            }
         })
     }
 
@@ -446,19 +466,23 @@ impl Compiler<'_, '_, '_> {
 
         let inline_def_body = Self::inline_def_body(&params, &body);
 
+        let stmt_compiled = body.as_bc(
+            &self.compile_context(),
+            local_count,
+            self.eval.module_env.frozen_heap(),
+        );
+        self.eval.coverage.add_bc(&stmt_compiled);
+
         let info = self.eval.module_env.frozen_heap().alloc_any(DefInfo {
             codemap: self.codemap,
             docstring,
             scope_names,
-            stmt_compiled: body.as_bc(
-                &self.compile_context(),
-                local_count,
-                self.eval.module_env.frozen_heap(),
-            ),
+            stmt_compiled,
             body_stmts: body,
             inline_def_body,
             stmt_compile_context: self.compile_context(),
             globals: self.globals,
+            enable_recursion: self.enable_recursion,
         });
 
         ExprCompiled::Def(DefCompiled {
@@ -630,10 +654,13 @@ where
 
     fn invoke(
         &self,
-        _me: Value<'v>,
+        me: Value<'v>,
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
+        if !self.def_info.enable_recursion && eval.call_stack.top_is_recursive() {
+            return Err(DefError::Recursion(me.to_repr()).into());
+        }
         let bc = self.bc();
         alloca_frame(eval, bc.local_count, bc.max_stack_size, |eval| {
             let slots = eval.current_frame.locals();
@@ -39,17 +39,25 @@ use crate::{
         runtime::{call_stack::FrozenFileSpan, slots::LocalSlotId},
         FrozenDef,
     },
-    syntax::ast::{AstExprP, AstLiteral, AstPayload, AstString, BinOp, ExprP, StmtP},
+    syntax::{
+        ast::{
+            AstExprP, AstLiteral, AstPayload, AstString, BinOp, ExprP, FStringFormat, FStringPartP,
+            StmtP,
+        },
+        lexer::TokenInt,
+    },
     values::{
+        bigint::StarlarkBigInt,
         function::BoundMethodGen,
         string::{interpolation::parse_percent_s_one, StarlarkStr},
         types::{
             bool::StarlarkBool,
+            bytes::Bytes,
             dict::Dict,
             float::StarlarkFloat,
             list::{FrozenList, List},
             range::Range,
-            string::interpolation::{format_one, percent_s_one},
+            string::interpolation::{format_one, fstring, percent_s_one},
             tuple::Tuple,
             unbound::MaybeUnboundValue,
         },
@@ -106,7 +114,7 @@ impl ExprUnOp {
         match self {
             ExprUnOp::Minus => v.minus(heap),
             ExprUnOp::Plus => v.plus(heap),
-            ExprUnOp::BitNot => Ok(Value::new_int(!v.to_int()?)),
+            ExprUnOp::BitNot => v.bit_not(heap),
         }
     }
 }
@@ -137,11 +145,11 @@ impl ExprBinOp {
             ExprBinOp::Percent => a.percent(b, heap),
             ExprBinOp::Divide => a.div(b, heap),
             ExprBinOp::FloorDivide => a.floor_div(b, heap),
-            ExprBinOp::BitAnd => a.bit_and(b),
-            ExprBinOp::BitOr => a.bit_or(b),
-            ExprBinOp::BitXor => a.bit_xor(b),
-            ExprBinOp::LeftShift => a.left_shift(b),
-            ExprBinOp::RightShift => a.right_shift(b),
+            ExprBinOp::BitAnd => a.bit_and(b, heap),
+            ExprBinOp::BitOr => a.bit_or(b, heap),
+            ExprBinOp::BitXor => a.bit_xor(b, heap),
+            ExprBinOp::LeftShift => a.left_shift(b, heap),
+            ExprBinOp::RightShift => a.right_shift(b, heap),
         }
     }
 }
@@ -216,6 +224,14 @@ pub(crate) enum ExprCompiled {
             FrozenStringValue,
         )>,
     ),
+    /// `f"aaa{x}bbb{y!r}ccc"`: the text before the first expression,
+    /// then each expression with its format and the text after it.
+    FString(
+        Box<(
+            FrozenStringValue,
+            Vec<(IrSpanned<ExprCompiled>, FStringFormat, FrozenStringValue)>,
+        )>,
+    ),
     Call(IrSpanned<CallCompiled>),
     Def(DefCompiled),
 }
@@ -444,6 +460,11 @@ impl IrSpanned<ExprCompiled> {
                 let arg = arg.optimize_on_freeze(ctx);
                 ExprCompiled::format_one(before, arg, after, ctx.heap, ctx.frozen_heap)
             }
+            ExprCompiled::FString(box (before, ref args)) => {
+                let args =
+                    args.map(|(arg, format, after)| (arg.optimize_on_freeze(ctx), *format, *after));
+                ExprCompiled::fstring(before, args, ctx.heap, ctx.frozen_heap)
+            }
             ref d @ ExprCompiled::Def(..) => d.clone(),
             ExprCompiled::Call(ref call) => call.optimize_on_freeze(ctx),
         };
@@ -555,6 +576,29 @@ impl ExprCompiled {
         ExprCompiled::FormatOne(box (before, arg, after))
     }
 
+    pub(crate) fn fstring(
+        before: FrozenStringValue,
+        args: Vec<(IrSpanned<ExprCompiled>, FStringFormat, FrozenStringValue)>,
+        heap: &Heap,
+        frozen_heap: &FrozenHeap,
+    ) -> ExprCompiled {
+        if args.iter().all(|(arg, ..)| arg.as_value().is_some()) {
+            let args = args.iter().map(|(arg, format, after)| {
+                (arg.as_value().unwrap().to_value(), *format, after.as_str())
+            });
+            let value = fstring(&before, args, heap);
+            let value = frozen_heap.alloc_str(value.as_str());
+            return ExprCompiled::Value(value.unpack());
+        }
+        match args.as_slice() {
+            [(_, format, _)] if *format == FStringFormat::default() => {
+                let (arg, _, after) = args.into_iter().next().unwrap();
+                ExprCompiled::format_one(before, arg, after, heap, frozen_heap)
+            }
+            _ => ExprCompiled::FString(box (before, args)),
+        }
+    }
+
     fn add(l: IrSpanned<ExprCompiled>, r: IrSpanned<ExprCompiled>) -> ExprCompiled {
         let span = l.span.merge(&r.span);
         if let (Some(l), Some(r)) = (l.as_list_of_consts(), r.as_list_of_consts()) {
@@ -902,9 +946,13 @@ fn eval_equals(l: IrSpanned<ExprCompiled>, r: IrSpanned<ExprCompiled>) -> ExprCo
 impl AstLiteral {
     fn compile(&self, heap: &FrozenHeap) -> FrozenValue {
         match self {
-            AstLiteral::Int(i) => FrozenValue::new_int(i.node),
+            AstLiteral::Int(i) => match &i.node {
+                TokenInt::I32(i) => FrozenValue::new_int(*i),
+                TokenInt::BigInt(i) => StarlarkBigInt::alloc_bigint_frozen((**i).clone(), heap),
+            },
             AstLiteral::Float(f) => heap.alloc(f.node),
             AstLiteral::String(x) => heap.alloc(x.node.as_str()),
+            AstLiteral::Bytes(x) => heap.alloc(Bytes::new(x.node.clone())),
         }
     }
 }
@@ -1310,6 +1358,31 @@ impl Compiler<'_, '_, '_> {
                 let val = x.compile(self.eval.module_env.frozen_heap());
                 ExprCompiled::Value(val)
             }
+            ExprP::FString(parts) => {
+                // Merge adjacent text, so we have the text before each expression and after it.
+                let mut before = String::new();
+                let mut args: Vec<(IrSpanned<ExprCompiled>, FStringFormat, String)> = Vec::new();
+                for part in parts {
+                    match part {
+                        FStringPartP::Text(text) => match args.last_mut() {
+                            Some((.., after)) => after.push_str(&text.node),
+                            None => before.push_str(&text.node),
+                        },
+                        FStringPartP::Expr(expr, format) => {
+                            args.push((self.expr(expr), format, String::new()))
+                        }
+                    }
+                }
+                let frozen_heap = self.eval.module_env.frozen_heap();
+                ExprCompiled::fstring(
+                    frozen_heap.alloc_str(&before),
+                    args.into_map(|(arg, format, after)| {
+                        (arg, format, frozen_heap.alloc_str(&after))
+                    }),
+                    self.eval.module_env.heap(),
+                    frozen_heap,
+                )
+            }
         };
         IrSpanned { node: expr, span }
     }
@@ -96,6 +96,7 @@ impl<'v> Compiler<'v, '_, '_> {
                     local_count,
                     self.eval.module_env.frozen_heap(),
                 );
+                self.eval.coverage.add_bc(&bc);
                 // We don't preserve locals between top level statements.
                 // That is OK for now: the only locals used in module evaluation
                 // are comprehension bindings.
@@ -32,6 +32,7 @@ use thiserror::Error;
 use crate::{
     codemap::{FileSpanRef, Span, Spanned},
     environment::{slots::ModuleSlotId, FrozenModuleRef},
+    errors::Diagnostic,
     eval::{
         compiler::{
             scope::{Captured, CstAssign, CstExpr, CstStmt, Slot},
@@ -74,6 +75,7 @@ pub(crate) enum StmtCompiled {
             StmtsCompiled,
         )>,
     ),
+    While(Box<(IrSpanned<ExprCompiled>, StmtsCompiled)>),
     Break,
     Continue,
 }
@@ -140,6 +142,11 @@ impl IrSpanned<StmtCompiled> {
                 let body = body.optimize_on_freeze(ctx);
                 StmtsCompiled::for_stmt(span, var, over, body)
             }
+            StmtCompiled::While(box (ref cond, ref body)) => {
+                let cond = cond.optimize_on_freeze(ctx);
+                let body = body.optimize_on_freeze(ctx);
+                StmtsCompiled::while_stmt(span, cond, body)
+            }
             ref s @ (StmtCompiled::PossibleGc | StmtCompiled::Break | StmtCompiled::Continue) => {
                 StmtsCompiled::one(IrSpanned {
                     span,
@@ -314,6 +321,20 @@ impl StmtsCompiled {
             node: StmtCompiled::For(box (var, over, body)),
         })
     }
+
+    fn while_stmt(
+        span: FrozenFileSpan,
+        cond: IrSpanned<ExprCompiled>,
+        body: StmtsCompiled,
+    ) -> StmtsCompiled {
+        if cond.is_pure_infallible_to_bool() == Some(false) {
+            return StmtsCompiled::empty();
+        }
+        StmtsCompiled::one(IrSpanned {
+            span,
+            node: StmtCompiled::While(box (cond, body)),
+        })
+    }
 }
 
 #[derive(Debug, Error)]
@@ -488,6 +509,24 @@ pub(crate) fn before_stmt(span: FrozenFileSpan, eval: &mut Evaluator) {
     );
 }
 
+// This function is called where an error is raised, before the frame which raised it
+// is unwound. The error must already have its span set.
+pub(crate) fn on_error(e: &anyhow::Error, eval: &mut Evaluator) {
+    let span = match e.downcast_ref::<Diagnostic>().and_then(|d| d.span.as_ref()) {
+        Some(span) => span,
+        None => return,
+    };
+    let fs = mem::take(&mut eval.on_error);
+    for f in &fs {
+        f(span.as_ref(), e, eval)
+    }
+    let added = mem::replace(&mut eval.on_error, fs);
+    assert!(
+        added.is_empty(),
+        "`on_error` cannot be modified during evaluation"
+    );
+}
+
 // There are two requirements to perform a GC:
 //
 // 1. We can't be profiling, since profiling relies on the redundant heap
@@ -689,6 +728,11 @@ impl Compiler<'_, '_, '_> {
                 let st = self.stmt(body, false);
                 StmtsCompiled::for_stmt(span, var, over, st)
             }
+            StmtP::While(cond, box body) => {
+                let cond = self.expr(cond);
+                let st = self.stmt(body, false);
+                StmtsCompiled::while_stmt(span, cond, st)
+            }
             StmtP::Return(None) => StmtsCompiled::one(IrSpanned {
                 node: StmtCompiled::Return(IrSpanned {
                     span,
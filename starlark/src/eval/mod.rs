@@ -25,8 +25,12 @@ pub(crate) use fragment::def::{Def, FrozenDef};
 use gazebo::prelude::*;
 pub use runtime::{
     arguments::{Arguments, ParametersParser, ParametersSpec},
+    coverage::Coverage,
     evaluator::Evaluator,
-    file_loader::{FileLoader, ReturnFileLoader},
+    file_loader::{FileLoader, FilesystemFileLoader, ReturnFileLoader},
+    limits::EvalLimitError,
+    profile_data::ProfileData,
+    profile_mode::ProfileMode,
 };
 
 use crate::{
@@ -56,8 +60,15 @@ impl<'v, 'a> Evaluator<'v, 'a> {
     /// [`Module`](crate::environment::Module) as appropriate.
     pub fn eval_module(&mut self, ast: AstModule, globals: &Globals) -> anyhow::Result<Value<'v>> {
         let start = Instant::now();
+        self.limits
+            .set_heap_limited(self.heap().allocation_limit().is_some());
 
-        let AstModule { codemap, statement } = ast;
+        let AstModule {
+            codemap,
+            statement,
+            enable_recursion,
+            ..
+        } = ast;
 
         let codemap = self
             .module_env
@@ -111,7 +122,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         if unlikely(self.heap_or_flame_profile) {
             self.heap_profile
                 .record_call_enter(Value::new_none(), self.heap());
-            self.flame_profile.record_call_enter(Value::new_none());
+            self.flame_profile.record_call_enter(Value::new_none(), None);
         }
 
         // Evaluation
@@ -123,6 +134,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             constants: Constants::new(),
             has_before_stmt: self.before_stmt.enabled(),
             bc_profile: self.bc_profile.enabled(),
+            enable_recursion,
             eval: self,
         };
 
@@ -149,6 +161,8 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         positional: &[Value<'v>],
         named: &[(&str, Value<'v>)],
     ) -> anyhow::Result<Value<'v>> {
+        self.limits
+            .set_heap_limited(self.heap().allocation_limit().is_some());
         let names = named.map(|(s, _)| (Symbol::new(*s), self.heap().alloc_str(*s)));
         let named = named.map(|x| x.1);
         let params = Arguments {
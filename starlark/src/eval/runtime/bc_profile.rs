@@ -32,7 +32,7 @@ use gazebo::prelude::*;
 
 use crate::eval::{
     bc::opcode::BcOpcode,
-    runtime::{csv::CsvWriter, evaluator::EvaluatorError},
+    runtime::{csv::CsvWriter, evaluator::EvaluatorError, profile_data::ProfileDataError},
 };
 
 // TODO(nga): `Dupe` for `Duration` added in D31723072, need to release gazebo to use it.
@@ -71,13 +71,14 @@ struct BcInstrPairsStat {
     // is not very accurate or helpful, and time for pairs is even less helpful.
 }
 
-struct BcProfileData {
+#[derive(Clone)]
+pub(crate) struct BcProfileData {
     last: Option<(BcOpcode, Instant)>,
     by_instr: [BcInstrStat; BcOpcode::COUNT],
 }
 
-#[derive(Default)]
-struct BcPairsProfileData {
+#[derive(Default, Clone)]
+pub(crate) struct BcPairsProfileData {
     last: Option<BcOpcode>,
     by_instr: HashMap<[BcOpcode; 2], BcInstrPairsStat>,
 }
@@ -103,6 +104,13 @@ impl BcProfileData {
         self.last = Some((opcode, now));
     }
 
+    fn merge(&mut self, other: &BcProfileData) {
+        for (x, y) in self.by_instr.iter_mut().zip(&other.by_instr) {
+            x.count += y.count;
+            x.total_time += y.total_time;
+        }
+    }
+
     fn gen_csv(&self) -> String {
         let mut by_instr: Vec<_> = self
             .by_instr
@@ -142,6 +150,12 @@ impl BcPairsProfileData {
         self.last = Some(opcode);
     }
 
+    fn merge(&mut self, other: &BcPairsProfileData) {
+        for (opcodes, stat) in &other.by_instr {
+            self.by_instr.entry(*opcodes).or_default().count += stat.count;
+        }
+    }
+
     fn gen_csv(&self) -> String {
         let mut by_instr: Vec<_> = self
             .by_instr
@@ -165,12 +179,38 @@ impl BcPairsProfileData {
     }
 }
 
-enum BcProfileDataMode {
+#[derive(Clone)]
+pub(crate) enum BcProfileDataMode {
     Bc(Box<BcProfileData>),
     BcPairs(Box<BcPairsProfileData>),
     Disabled,
 }
 
+impl BcProfileDataMode {
+    fn gen_csv(&self) -> anyhow::Result<String> {
+        match self {
+            BcProfileDataMode::Bc(data) => Ok(data.gen_csv()),
+            BcProfileDataMode::BcPairs(data) => Ok(data.gen_csv()),
+            BcProfileDataMode::Disabled => Err(EvaluatorError::BcProfilingNotEnabled.into()),
+        }
+    }
+
+    pub(crate) fn write_csv(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, self.gen_csv()?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Add the counts of another profile collected in the same mode to this one.
+    pub(crate) fn merge(&mut self, other: &BcProfileDataMode) -> anyhow::Result<()> {
+        match (self, other) {
+            (BcProfileDataMode::Bc(x), BcProfileDataMode::Bc(y)) => x.merge(y),
+            (BcProfileDataMode::BcPairs(x), BcProfileDataMode::BcPairs(y)) => x.merge(y),
+            _ => return Err(ProfileDataError::DifferentModes.into()),
+        }
+        Ok(())
+    }
+}
+
 pub(crate) struct BcProfile {
     data: BcProfileDataMode,
 }
@@ -198,17 +238,20 @@ impl BcProfile {
         }
     }
 
-    fn gen_csv(&self) -> anyhow::Result<String> {
-        match &self.data {
-            BcProfileDataMode::Bc(data) => Ok(data.gen_csv()),
-            BcProfileDataMode::BcPairs(data) => Ok(data.gen_csv()),
-            BcProfileDataMode::Disabled => Err(EvaluatorError::BcProfilingNotEnabled.into()),
+    pub(crate) fn gen(&self) -> anyhow::Result<BcProfileDataMode> {
+        if self.enabled() {
+            Ok(self.data.clone())
+        } else {
+            Err(EvaluatorError::BcProfilingNotEnabled.into())
         }
     }
 
+    fn gen_csv(&self) -> anyhow::Result<String> {
+        self.data.gen_csv()
+    }
+
     pub(crate) fn write_csv(&self, path: &Path) -> anyhow::Result<()> {
-        fs::write(path, self.gen_csv()?.as_bytes())?;
-        Ok(())
+        self.data.write_csv(path)
     }
 
     /// Called from bytecode.
@@ -173,6 +173,20 @@ impl<'v> CallStack<'v> {
         self.count -= 1;
     }
 
+    /// The number of frames on the stack.
+    pub(crate) fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Is the function at the top of the stack also present further down,
+    /// i.e. is it being called recursively.
+    pub(crate) fn top_is_recursive(&self) -> bool {
+        match self.stack[..self.count].split_last() {
+            Some((top, rest)) => rest.iter().any(|x| x.function.ptr_eq(top.function)),
+            None => false,
+        }
+    }
+
     /// The location at the top of the stack. May be `None` if
     /// either there the stack is empty, or the top of the stack lacks location
     /// information (e.g. called from Rust).
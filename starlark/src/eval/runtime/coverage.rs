@@ -0,0 +1,339 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Line coverage, collected using `before_stmt` instrumentation.
+
+use std::{
+    cmp,
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+};
+
+use gazebo::prelude::*;
+
+use crate::{
+    codemap::{CodeMap, FileSpanRef, Span},
+    eval::bc::bytecode::Bc,
+};
+
+// When coverage is not enabled, we want this to be small and cheap
+pub(crate) struct CoverageProfile(Option<Box<CoverageData>>);
+
+/// The number of times each statement was executed, keyed by the `CodeMap` identity,
+/// so recording a statement doesn't need to resolve its line.
+struct CoverageData {
+    files: HashMap<CodeMap, HashMap<Span, usize>>,
+}
+
+impl CoverageProfile {
+    pub(crate) fn new() -> Self {
+        Self(None)
+    }
+
+    pub(crate) fn enable(&mut self) {
+        self.0 = Some(box CoverageData {
+            files: HashMap::new(),
+        })
+    }
+
+    /// Record the statements of newly compiled bytecode as not yet executed, so lines which
+    /// never run are still reported. Only the statements the compiler instruments with
+    /// `before_stmt` are recorded, so those it removes (e.g. docstrings or the body of
+    /// `if False:`) are not. Top-level statements are compiled as the module is evaluated,
+    /// so those after an error are not recorded.
+    pub(crate) fn add_bc(&mut self, bc: &Bc) {
+        if let Some(box data) = &mut self.0 {
+            for span in bc.instrs.before_stmt_spans() {
+                // Usually the CodeMap was seen already, so avoid cloning it
+                if !data.files.contains_key(&*span.file) {
+                    data.files.insert((*span.file).dupe(), HashMap::new());
+                }
+                let stmts = data.files.get_mut(&*span.file).unwrap();
+                stmts.entry(span.span).or_insert(0);
+            }
+        }
+    }
+
+    pub(crate) fn before_stmt(&mut self, span: FileSpanRef) {
+        if let Some(box data) = &mut self.0 {
+            // Usually the CodeMap was seen by add_bc, so avoid cloning it
+            match data.files.get_mut(span.file) {
+                Some(stmts) => *stmts.entry(span.span).or_insert(0) += 1,
+                None => {
+                    data.files
+                        .insert(span.file.dupe(), HashMap::from([(span.span, 1)]));
+                }
+            }
+        }
+    }
+
+    // None = not applicable because not enabled
+    pub(crate) fn gen(&self) -> Option<Coverage> {
+        let data = self.0.as_ref()?;
+        let mut res = Coverage::new();
+        for (file, stmts) in &data.files {
+            let mut lines = BTreeMap::new();
+            for (span, hits) in stmts {
+                // Several statements on one line count as one line, executed as often as any of them
+                let line = lines.entry(file.find_line(span.begin()) + 1).or_insert(0);
+                *line = cmp::max(*line, *hits);
+            }
+            // A file evaluated several times has several CodeMaps, which are added together
+            res.add_file(file.filename(), &lines);
+        }
+        Some(res)
+    }
+}
+
+/// Line coverage of Starlark files, obtained from
+/// [`Evaluator::coverage`](crate::eval::Evaluator::coverage).
+///
+/// Coverage from many evaluations can be combined with [`merge`](Coverage::merge), adding
+/// together the hits of lines in files with the same name. It can be written in the
+/// [lcov](https://github.com/linux-test-project/lcov) tracefile format with
+/// [`to_lcov`](Coverage::to_lcov), or as [Cobertura](https://cobertura.github.io/cobertura/)
+/// XML with [`to_cobertura`](Coverage::to_cobertura).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// For each filename, the number of times each 1-based line with a statement was executed.
+    files: BTreeMap<String, BTreeMap<usize, usize>>,
+}
+
+impl Coverage {
+    /// Coverage of no files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the coverage from `other` into `self`.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (file, lines) in &other.files {
+            self.add_file(file, lines);
+        }
+    }
+
+    fn add_file(&mut self, file: &str, lines: &BTreeMap<usize, usize>) {
+        let res = self.files.entry(file.to_owned()).or_default();
+        for (line, hits) in lines {
+            *res.entry(*line).or_insert(0) += hits;
+        }
+    }
+
+    /// The names of the files with coverage, in sorted order.
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(|x| x.as_str())
+    }
+
+    /// The number of times each 1-based line with a statement was executed, in line order.
+    pub fn lines(&self, file: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.files
+            .get(file)
+            .into_iter()
+            .flat_map(|x| x.iter().map(|(line, hits)| (*line, *hits)))
+    }
+
+    /// The number of lines with a statement, and the number of those lines that were executed.
+    fn count(lines: &BTreeMap<usize, usize>) -> (usize, usize) {
+        (lines.len(), lines.values().filter(|x| **x > 0).count())
+    }
+
+    /// The coverage in the lcov tracefile format, as read by `genhtml`.
+    pub fn to_lcov(&self) -> String {
+        let mut res = String::new();
+        for (file, lines) in &self.files {
+            writeln!(res, "TN:").unwrap();
+            writeln!(res, "SF:{}", file).unwrap();
+            for (line, hits) in lines {
+                writeln!(res, "DA:{},{}", line, hits).unwrap();
+            }
+            let (found, hit) = Self::count(lines);
+            writeln!(res, "LF:{}", found).unwrap();
+            writeln!(res, "LH:{}", hit).unwrap();
+            writeln!(res, "end_of_record").unwrap();
+        }
+        res
+    }
+
+    /// The coverage as Cobertura XML, with a class per file.
+    pub fn to_cobertura(&self) -> String {
+        fn rate((found, hit): (usize, usize)) -> f64 {
+            if found == 0 {
+                1.0
+            } else {
+                hit as f64 / found as f64
+            }
+        }
+
+        fn escape(x: &str) -> String {
+            x.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        }
+
+        let (found, hit) = self
+            .files
+            .values()
+            .map(Self::count)
+            .fold((0, 0), |(f1, h1), (f2, h2)| (f1 + f2, h1 + h2));
+
+        let mut res = String::new();
+        writeln!(res, r#"<?xml version="1.0" ?>"#).unwrap();
+        writeln!(
+            res,
+            r#"<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">"#
+        )
+        .unwrap();
+        writeln!(
+            res,
+            r#"<coverage line-rate="{}" branch-rate="0" lines-covered="{}" lines-valid="{}" branches-covered="0" branches-valid="0" complexity="0" version="0" timestamp="0">"#,
+            rate((found, hit)),
+            hit,
+            found
+        )
+        .unwrap();
+        writeln!(res, "  <sources><source>.</source></sources>").unwrap();
+        writeln!(res, "  <packages>").unwrap();
+        writeln!(
+            res,
+            r#"    <package name="." line-rate="{}" branch-rate="0" complexity="0">"#,
+            rate((found, hit))
+        )
+        .unwrap();
+        writeln!(res, "      <classes>").unwrap();
+        for (file, lines) in &self.files {
+            let file = escape(file);
+            writeln!(
+                res,
+                r#"        <class name="{}" filename="{}" line-rate="{}" branch-rate="0" complexity="0">"#,
+                file,
+                file,
+                rate(Self::count(lines))
+            )
+            .unwrap();
+            writeln!(res, "          <methods/>").unwrap();
+            writeln!(res, "          <lines>").unwrap();
+            for (line, hits) in lines {
+                writeln!(
+                    res,
+                    r#"            <line number="{}" hits="{}"/>"#,
+                    line, hits
+                )
+                .unwrap();
+            }
+            writeln!(res, "          </lines>").unwrap();
+            writeln!(res, "        </class>").unwrap();
+        }
+        writeln!(res, "      </classes>").unwrap();
+        writeln!(res, "    </package>").unwrap();
+        writeln!(res, "  </packages>").unwrap();
+        writeln!(res, "</coverage>").unwrap();
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        environment::{Globals, Module},
+        eval::Evaluator,
+        syntax::{AstModule, Dialect},
+    };
+
+    fn coverage(code: &str, times: usize) -> Coverage {
+        let module = Module::new();
+        let globals = Globals::standard();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_coverage();
+        for _ in 0..times {
+            let ast = AstModule::parse("cover.star", code.to_owned(), &Dialect::Standard);
+            eval.eval_module(ast.unwrap(), &globals).unwrap();
+        }
+        eval.coverage().unwrap()
+    }
+
+    const CODE: &str = r#"
+def f(x):
+    if x:
+        return 1
+    return 2
+f(True); f(True)
+"#;
+
+    #[test]
+    fn test_coverage() {
+        let c = coverage(CODE, 1);
+        assert_eq!(c.files().collect::<Vec<_>>(), vec!["cover.star"]);
+        assert_eq!(
+            c.lines("cover.star").collect::<Vec<_>>(),
+            vec![(2, 1), (3, 2), (4, 2), (5, 0), (6, 1)]
+        );
+
+        // Evaluating again parses a new CodeMap, but the lines are combined by filename
+        let mut c2 = coverage(CODE, 2);
+        assert_eq!(
+            c2.lines("cover.star").collect::<Vec<_>>(),
+            vec![(2, 2), (3, 4), (4, 4), (5, 0), (6, 2)]
+        );
+        c2.merge(&c);
+        assert_eq!(c2.lines("cover.star").nth(1), Some((3, 6)));
+    }
+
+    #[test]
+    fn test_coverage_output() {
+        let c = coverage(CODE, 1);
+        assert_eq!(
+            c.to_lcov(),
+            "TN:\nSF:cover.star\nDA:2,1\nDA:3,2\nDA:4,2\nDA:5,0\nDA:6,1\nLF:5\nLH:4\nend_of_record\n"
+        );
+        let xml = c.to_cobertura();
+        assert!(
+            xml.contains(r#"lines-covered="4" lines-valid="5""#),
+            "{}",
+            xml
+        );
+        assert!(xml.contains(r#"<class name="cover.star" filename="cover.star" line-rate="0.8""#));
+        assert!(xml.contains(r#"<line number="5" hits="0"/>"#));
+    }
+
+    #[test]
+    fn test_coverage_compiled_away() {
+        let c = coverage(
+            r#"
+def f():
+    """A docstring."""
+    if False:
+        fail("never")
+    while False:
+        fail("never")
+    for x in []:
+        fail("never")
+    if True and False:
+        fail("never")
+    return 1
+    fail("never")
+f()
+"#,
+            1,
+        );
+        assert_eq!(
+            c.lines("cover.star").collect::<Vec<_>>(),
+            vec![(2, 1), (12, 1), (14, 1)]
+        );
+    }
+}
@@ -20,6 +20,7 @@ use std::{
     intrinsics::unlikely,
     mem::{self, MaybeUninit},
     path::Path,
+    sync::{atomic::AtomicBool, Arc},
 };
 
 use gazebo::{any::AnyLifetime, cast};
@@ -37,8 +38,12 @@ use crate::{
             bc_profile::BcProfile,
             before_stmt::BeforeStmt,
             call_stack::{CallStack, FrozenFileSpan},
-            flame_profile::FlameProfile,
+            coverage::{Coverage, CoverageProfile},
+            flame_profile::{FlameProfile, FlameProfileFormat},
             heap_profile::{HeapProfile, HeapProfileFormat},
+            limits::EvalLimits,
+            profile_data::{ProfileData, ProfileDataImpl},
+            profile_mode::ProfileMode,
             slots::LocalSlotId,
             stmt_profile::StmtProfile,
         },
@@ -68,11 +73,16 @@ pub(crate) enum EvaluatorError {
     FlameProfilingNotEnabled,
     #[error("Can't call `write_bc_profile` unless you first call `enable_bc_profile`.")]
     BcProfilingNotEnabled,
+    #[error("Can't call `coverage` unless you first call `enable_coverage`.")]
+    CoverageNotEnabled,
 }
 
 /// Number of bytes to allocate between GC's.
 pub(crate) const GC_THRESHOLD: usize = 100000;
 
+/// A function registered with [`Evaluator::on_error`].
+pub(crate) type OnError<'v, 'a> = &'a dyn Fn(FileSpanRef, &anyhow::Error, &mut Evaluator<'v, 'a>);
+
 /// Holds everything about an ongoing evaluation (local variables, globals, module resolution etc).
 pub struct Evaluator<'v, 'a> {
     // The module that is being used for this evaluation
@@ -105,10 +115,16 @@ pub struct Evaluator<'v, 'a> {
     pub(crate) next_gc_level: usize,
     // Extra functions to run on each statement, usually empty
     pub(crate) before_stmt: BeforeStmt<'v, 'a>,
+    // Functions to run when an error is raised, usually empty
+    pub(crate) on_error: Vec<OnError<'v, 'a>>,
     // Used for line profiling
-    stmt_profile: StmtProfile,
+    pub(crate) stmt_profile: StmtProfile,
     // Bytecode profile.
     pub(crate) bc_profile: BcProfile,
+    // Line coverage
+    pub(crate) coverage: CoverageProfile,
+    // Step budget and cancellation.
+    pub(crate) limits: EvalLimits,
     // Used for stack-like allocation
     alloca: Alloca,
     // Another stack-like allocation
@@ -160,9 +176,12 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             heap_profile: HeapProfile::new(),
             stmt_profile: StmtProfile::new(),
             bc_profile: BcProfile::new(),
+            coverage: CoverageProfile::new(),
+            limits: EvalLimits::default(),
             flame_profile: FlameProfile::new(),
             heap_or_flame_profile: false,
             before_stmt: BeforeStmt::default(),
+            on_error: Vec::new(),
             def_info: DefInfo::empty(), // Will be replaced before it is used
             string_pool: StringPool::default(),
             breakpoint_handler: None,
@@ -190,6 +209,30 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.loader = Some(loader);
     }
 
+    /// Limit the number of steps this [`Evaluator`] may take, where a step is
+    /// a bytecode instruction or a function call. The count is shared by all
+    /// evaluations performed with this [`Evaluator`].
+    ///
+    /// Once the limit is exceeded, evaluation fails with
+    /// [`EvalLimitError::StepLimitExceeded`](crate::eval::EvalLimitError::StepLimitExceeded).
+    pub fn set_max_steps(&mut self, max_steps: u64) {
+        self.limits.set_max_steps(max_steps);
+    }
+
+    /// Set a flag which can be used to cancel evaluation, usually from another thread.
+    /// The flag is polled on every step, and once it is `true` evaluation fails with
+    /// [`EvalLimitError::Cancelled`](crate::eval::EvalLimitError::Cancelled).
+    pub fn set_cancellation_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.limits.set_cancellation_flag(flag);
+    }
+
+    /// Number of steps taken so far. Only counted if
+    /// [`set_max_steps`](Evaluator::set_max_steps) or
+    /// [`set_cancellation_flag`](Evaluator::set_cancellation_flag) was called.
+    pub fn steps(&self) -> u64 {
+        self.limits.steps()
+    }
+
     /// Enable profiling, allowing [`Evaluator::write_heap_profile`] to be used.
     /// Has the side effect of disabling garbage-collection.
     ///
@@ -210,6 +253,8 @@ impl<'v, 'a> Evaluator<'v, 'a> {
     /// * The `bc_profile` mode provides information about bytecode instructions.
     /// * The `flame_profile` and the `heap_profile` mode provide input compatible with
     ///   [flamegraph.pl](https://github.com/brendangregg/FlameGraph/blob/master/flamegraph.pl).
+    /// * The `flame_profile` mode can also be written as a timeline with
+    ///   [`write_chrome_trace_profile`](Evaluator::write_chrome_trace_profile).
     pub fn enable_heap_profile(&mut self) {
         self.heap_profile.enable();
         self.heap_or_flame_profile = true;
@@ -225,6 +270,15 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.before_stmt(&|span, eval| eval.stmt_profile.before_stmt(span));
     }
 
+    /// Enable line coverage, allowing [`Evaluator::coverage`] to be used.
+    /// Covers the modules evaluated by this [`Evaluator`], and any code they call which was
+    /// compiled with `before_stmt` instrumentation, see
+    /// [`enable_before_stmt_instrumentation`](Evaluator::enable_before_stmt_instrumentation).
+    pub fn enable_coverage(&mut self) {
+        self.coverage.enable();
+        self.before_stmt(&|span, eval| eval.coverage.before_stmt(span));
+    }
+
     /// Generate instructions to invoke before stmt callbacks when evaluating the module,
     /// even if this module does not use any such callbacks.
     ///
@@ -303,10 +357,87 @@ impl<'v, 'a> Evaluator<'v, 'a> {
     /// See [`Evaluator::enable_heap_profile`] for details about the types of Starlark profiles.
     pub fn write_flame_profile<P: AsRef<Path>>(&self, filename: P) -> anyhow::Result<()> {
         self.flame_profile
-            .write(filename.as_ref())
+            .write(filename.as_ref(), FlameProfileFormat::FlameGraph)
             .unwrap_or_else(|| Err(EvaluatorError::FlameProfilingNotEnabled.into()))
     }
 
+    /// Write a profile to a file in the
+    /// [Chrome Trace Event format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU),
+    /// which can be loaded into [Perfetto](https://ui.perfetto.dev) or `chrome://tracing`.
+    /// Each call is a slice, with the file and line it was called from, and each garbage collection
+    /// is a slice named `GC`.
+    /// Only valid if [`enable_flame_profile`](Evaluator::enable_flame_profile) was called before execution began.
+    pub fn write_chrome_trace_profile<P: AsRef<Path>>(&self, filename: P) -> anyhow::Result<()> {
+        self.flame_profile
+            .write(filename.as_ref(), FlameProfileFormat::ChromeTrace)
+            .unwrap_or_else(|| Err(EvaluatorError::FlameProfilingNotEnabled.into()))
+    }
+
+    /// Enable profiling in the given [`ProfileMode`], allowing
+    /// [`write_profile`](Evaluator::write_profile) to be used with the same mode.
+    pub fn enable_profile(&mut self, mode: &ProfileMode) {
+        match mode {
+            ProfileMode::Heap | ProfileMode::HeapFlame => self.enable_heap_profile(),
+            ProfileMode::Stmt => self.enable_stmt_profile(),
+            ProfileMode::Flame | ProfileMode::ChromeTrace => self.enable_flame_profile(),
+            ProfileMode::Bytecode => self.enable_bytecode_profile(),
+            ProfileMode::BytecodePairs => self.enable_bytecode_pairs_profile(),
+        }
+    }
+
+    /// Write the profile collected in the given [`ProfileMode`] to a file.
+    /// Only valid if [`enable_profile`](Evaluator::enable_profile) was called with the same mode
+    /// before execution began.
+    pub fn write_profile<P: AsRef<Path>>(
+        &self,
+        mode: &ProfileMode,
+        filename: P,
+    ) -> anyhow::Result<()> {
+        self.gen_profile(mode)?.write(filename)
+    }
+
+    /// The profile collected in the given [`ProfileMode`], which can be kept after this
+    /// evaluator and its module are gone, and merged with the profiles of other evaluations.
+    /// Only valid if [`enable_profile`](Evaluator::enable_profile) was called with the same mode
+    /// before execution began.
+    pub fn gen_profile(&self, mode: &ProfileMode) -> anyhow::Result<ProfileData> {
+        let data = match mode {
+            ProfileMode::Heap | ProfileMode::HeapFlame => {
+                let format = match mode {
+                    ProfileMode::Heap => HeapProfileFormat::Summary,
+                    _ => HeapProfileFormat::FlameGraph,
+                };
+                ProfileDataImpl::Heap(
+                    self.heap_profile
+                        .gen(self.heap(), format)
+                        .unwrap_or_else(|| Err(EvaluatorError::HeapProfilingNotEnabled.into()))?,
+                )
+            }
+            ProfileMode::Stmt => ProfileDataImpl::Stmt(
+                self.stmt_profile
+                    .gen()
+                    .ok_or(EvaluatorError::StmtProfilingNotEnabled)?,
+            ),
+            ProfileMode::Flame | ProfileMode::ChromeTrace => ProfileDataImpl::Flame(
+                self.flame_profile
+                    .gen()
+                    .ok_or(EvaluatorError::FlameProfilingNotEnabled)?,
+            ),
+            ProfileMode::Bytecode | ProfileMode::BytecodePairs => {
+                ProfileDataImpl::Bc(self.bc_profile.gen()?)
+            }
+        };
+        Ok(ProfileData::new(*mode, data))
+    }
+
+    /// The line coverage of everything evaluated so far.
+    /// Only valid if [`enable_coverage`](Evaluator::enable_coverage) was called before execution began.
+    pub fn coverage(&self) -> anyhow::Result<Coverage> {
+        self.coverage
+            .gen()
+            .ok_or_else(|| EvaluatorError::CoverageNotEnabled.into())
+    }
+
     /// Enable interactive `breakpoint()`. When enabled, `breakpoint()`
     /// reads commands from stdin and write to stdout.
     /// When disabled (default), `breakpoint()` function results in error.
@@ -319,6 +450,13 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.call_stack.to_diagnostic_frames()
     }
 
+    /// The number of frames on the call-stack, which is `1` for the module itself
+    /// and increases by one for each function call. Cheaper than [`call_stack`](Evaluator::call_stack)
+    /// when only the depth is required, e.g. for stepping in a debugger.
+    pub fn call_stack_count(&self) -> usize {
+        self.call_stack.count()
+    }
+
     /// Obtain the top location on the call-stack. May be [`None`] if the
     /// call happened via native functions.
     pub fn call_stack_top_location(&self) -> Option<FileSpan> {
@@ -334,6 +472,15 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.before_stmt.before_stmt.push(f)
     }
 
+    /// Called when a statement raises an error, with the span where it was raised, the error and
+    /// a reference to the containing [`Evaluator`]. It is called once per error, before the
+    /// function which raised it returns, so its local variables can still be inspected.
+    ///
+    /// Errors raised by native functions are reported in the function which called them.
+    pub fn on_error(&mut self, f: &'a dyn Fn(FileSpanRef, &anyhow::Error, &mut Evaluator<'v, 'a>)) {
+        self.on_error.push(f)
+    }
+
     /// Set the handler invoked when `print` function is used.
     pub fn set_print_handler(&mut self, handler: &'a (dyn PrintHandler + 'a)) {
         self.print_handler = handler;
@@ -366,10 +513,14 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.call_stack.push(function, span)?;
         if unlikely(self.heap_or_flame_profile) {
             self.heap_profile.record_call_enter(function, self.heap());
-            self.flame_profile.record_call_enter(function);
+            self.flame_profile.record_call_enter(function, span);
         }
         // Must always call .pop regardless
-        let res = within(self).map_err(|e| add_diagnostics(e, self));
+        let res = match self.limits.step(self.heap()) {
+            Ok(()) => within(self),
+            Err(e) => Err(e),
+        }
+        .map_err(|e| add_diagnostics(e, self));
         self.call_stack.pop();
         if unlikely(self.heap_or_flame_profile) {
             self.heap_profile.record_call_exit(self.heap());
@@ -551,7 +702,9 @@ impl<'v, 'a> Evaluator<'v, 'a> {
                 self.heap().allocated_bytes()
             );
         }
+        self.flame_profile.record_gc_start();
         self.heap().garbage_collect(|tracer| self.trace(tracer));
+        self.flame_profile.record_gc_end();
         if self.verbose_gc {
             eprintln!(
                 "Starlark: GC complete. Allocated bytes: {}.",
@@ -18,12 +18,36 @@
 //! Define variants of the evaluation function with different support
 //! for the `load(...)` statement.
 
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env, fs,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
 
 use anyhow::anyhow;
 use gazebo::prelude::*;
+use itertools::Itertools;
+use thiserror::Error;
 
-use crate::environment::FrozenModule;
+use crate::{
+    environment::{FrozenModule, Globals, Module},
+    eval::{Coverage, Evaluator},
+    syntax::{AstModule, Dialect},
+};
+
+#[derive(Debug, Error)]
+enum FileLoaderError {
+    #[error("Can't load `{0}`, as no root directory was given for `//` paths")]
+    NoRoot(String),
+    #[error("Can't load `{0}`, as it is outside the root directory `{1}`")]
+    OutsideRoot(String, String),
+    #[error("Can't load `{0}`, the file `{1}` does not exist")]
+    NotFound(String, String),
+    #[error("Load cycle: {}", .0.iter().map(|x| x.display()).join(" -> "))]
+    Cycle(Vec<PathBuf>),
+}
 
 /// A trait for turning a `path` given by a `load()` statement into a [`FrozenModule`].
 pub trait FileLoader {
@@ -51,3 +75,329 @@ impl<'a> FileLoader for ReturnFileLoader<'a> {
         }
     }
 }
+
+/// [`FileLoader`] that reads, parses and evaluates modules from the filesystem.
+///
+/// Paths given to `load()` are resolved as follows:
+///
+/// * `//pkg/sub:file.bzl` is relative to the root directory, giving `<root>/pkg/sub/file.bzl`.
+///   The root is set with [`with_root`](FilesystemFileLoader::with_root).
+/// * `:file.bzl` and `file.bzl` are relative to the directory of the file doing the loading.
+///
+/// If a root is set, paths which resolve to somewhere outside of it (e.g. using `..` or
+/// through a symlink) are rejected.
+///
+/// Each file is evaluated at most once, with the resulting [`FrozenModule`] shared by all the
+/// loaders derived from the same [`new`](FilesystemFileLoader::new). Loads that form a cycle
+/// are reported as errors, listing the files in the cycle.
+///
+/// With [`with_coverage`](FilesystemFileLoader::with_coverage), the loaded modules are
+/// instrumented for line coverage, so an [`Evaluator`] with
+/// [`enable_coverage`](Evaluator::enable_coverage) also covers the functions it calls from them.
+/// Every [`Evaluator`] which calls into those modules must then have coverage enabled.
+#[derive(Debug, Clone)]
+pub struct FilesystemFileLoader {
+    globals: Globals,
+    dialect: Dialect,
+    root: Option<PathBuf>,
+    /// The files currently being loaded, outermost first. The last one is doing the loading.
+    chain: Vec<PathBuf>,
+    cache: Rc<RefCell<HashMap<PathBuf, FrozenModule>>>,
+    /// The coverage of evaluating the loaded modules, if enabled.
+    coverage: Option<Rc<RefCell<Coverage>>>,
+}
+
+/// Remove the `.` and `..` components of a path, without looking at the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut res = PathBuf::new();
+    for x in path.components() {
+        match x {
+            Component::CurDir => {}
+            Component::ParentDir => match res.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    res.pop();
+                }
+                // `/..` is `/`
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => res.push(".."),
+            },
+            x => res.push(x),
+        }
+    }
+    res
+}
+
+/// The absolute version of a path, relative to the current directory.
+fn absolute(path: &Path) -> PathBuf {
+    normalize(&env::current_dir().unwrap_or_default().join(path))
+}
+
+impl FilesystemFileLoader {
+    /// Create a loader which evaluates loaded modules with the given `globals` and `dialect`.
+    /// Relative paths are resolved against the current directory, until a file is given
+    /// with [`for_file`](FilesystemFileLoader::for_file).
+    pub fn new(globals: &Globals, dialect: &Dialect) -> Self {
+        Self {
+            globals: globals.dupe(),
+            dialect: dialect.clone(),
+            root: None,
+            chain: Vec::new(),
+            cache: Rc::new(RefCell::new(HashMap::new())),
+            coverage: None,
+        }
+    }
+
+    /// Set the directory that `//` paths are resolved against.
+    pub fn with_root(self, root: PathBuf) -> Self {
+        Self {
+            root: Some(root),
+            ..self
+        }
+    }
+
+    /// Collect line coverage of the loaded modules, see [`coverage`](FilesystemFileLoader::coverage).
+    pub fn with_coverage(self) -> Self {
+        Self {
+            coverage: Some(Rc::new(RefCell::new(Coverage::new()))),
+            ..self
+        }
+    }
+
+    /// The line coverage of evaluating the loaded modules, shared by all the loaders derived
+    /// from the same [`new`](FilesystemFileLoader::new), or `None` if not enabled with
+    /// [`with_coverage`](FilesystemFileLoader::with_coverage).
+    pub fn coverage(&self) -> Option<Coverage> {
+        self.coverage.as_ref().map(|x| x.borrow().clone())
+    }
+
+    /// A loader for the `load()` statements in the file at `path`, sharing the cache of `self`.
+    pub fn for_file(&self, path: &Path) -> Self {
+        let mut res = self.clone();
+        res.chain
+            .push(fs::canonicalize(path).unwrap_or_else(|_| path.to_owned()));
+        res
+    }
+
+    /// The directory that relative paths are resolved against.
+    fn dir(&self) -> &Path {
+        self.chain
+            .last()
+            .and_then(|x| x.parent())
+            .unwrap_or_else(|| Path::new(""))
+    }
+
+    /// Resolve a path given to `load()` to the file it refers to, which may not exist.
+    pub fn resolve(&self, path: &str) -> anyhow::Result<PathBuf> {
+        let file = if let Some(rest) = path.strip_prefix("//") {
+            let root = match &self.root {
+                Some(root) => root,
+                None => return Err(FileLoaderError::NoRoot(path.to_owned()).into()),
+            };
+            match rest.split_once(':') {
+                Some((package, file)) => root.join(package).join(file),
+                None => root.join(rest),
+            }
+        } else {
+            self.dir().join(path.trim_start_match(':'))
+        };
+        let file = normalize(&file);
+        if let Some(root) = &self.root {
+            // The root may be given through a symlink, while loading files are canonical.
+            let inside = |root: &Path| absolute(&file).starts_with(root);
+            if !inside(&absolute(root)) && !fs::canonicalize(root).map_or(false, |x| inside(&x)) {
+                return Err(FileLoaderError::OutsideRoot(
+                    path.to_owned(),
+                    root.display().to_string(),
+                )
+                .into());
+            }
+        }
+        Ok(file)
+    }
+}
+
+impl FileLoader for FilesystemFileLoader {
+    fn load(&self, path: &str) -> anyhow::Result<FrozenModule> {
+        let file = self.resolve(path)?;
+        let file = fs::canonicalize(&file)
+            .map_err(|_| FileLoaderError::NotFound(path.to_owned(), file.display().to_string()))?;
+        if let Some(root) = &self.root {
+            // `resolve` only looks at the path, but a symlink inside the root may point outside it
+            let canonical_root = fs::canonicalize(root).unwrap_or_else(|_| absolute(root));
+            if !file.starts_with(&canonical_root) {
+                return Err(FileLoaderError::OutsideRoot(
+                    path.to_owned(),
+                    root.display().to_string(),
+                )
+                .into());
+            }
+        }
+        if let Some(module) = self.cache.borrow().get(&file) {
+            return Ok(module.dupe());
+        }
+        if self.chain.contains(&file) {
+            let start = self.chain.iter().position(|x| x == &file).unwrap();
+            let mut cycle = self.chain[start..].to_vec();
+            cycle.push(file);
+            return Err(FileLoaderError::Cycle(cycle).into());
+        }
+
+        let loader = self.for_file(&file);
+        let ast = AstModule::parse_file(&file, &self.dialect)?;
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.set_loader(&loader);
+            if self.coverage.is_some() {
+                eval.enable_coverage();
+            }
+            eval.eval_module(ast, &self.globals)?;
+            if let Some(coverage) = &self.coverage {
+                coverage.borrow_mut().merge(&eval.coverage()?);
+            }
+        }
+        let module = module.freeze()?;
+        self.cache.borrow_mut().insert(file, module.dupe());
+        Ok(module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::runtime::temp_dir::TempDir;
+
+    fn loader(root: &Path) -> FilesystemFileLoader {
+        FilesystemFileLoader::new(&Globals::standard(), &Dialect::Extended)
+            .with_root(root.to_owned())
+    }
+
+    fn load_value(loader: &FilesystemFileLoader, path: &str, name: &str) -> anyhow::Result<String> {
+        let module = loader.load(path)?;
+        Ok(module.get(name).unwrap().value().to_string())
+    }
+
+    #[test]
+    fn test_filesystem_loader() {
+        let dir = TempDir::with_files(&[
+            ("a.bzl", "load('//pkg:b.bzl', 'b')\na = b + 1"),
+            (
+                "pkg/b.bzl",
+                "load(':c.bzl', 'c')\nload('sub/d.bzl', 'd')\nb = c + d",
+            ),
+            ("pkg/c.bzl", "c = 10"),
+            ("pkg/sub/d.bzl", "d = 100"),
+        ]);
+        let root = dir.path();
+        let loader = loader(root).for_file(&root.join("main.bzl"));
+        assert_eq!(load_value(&loader, "a.bzl", "a").unwrap(), "111");
+        assert_eq!(load_value(&loader, "//pkg/sub:d.bzl", "d").unwrap(), "100");
+        // Modules are only evaluated once
+        let b1 = loader.load("//pkg:b.bzl").unwrap();
+        let b2 = loader.load("pkg/b.bzl").unwrap();
+        assert_eq!(b1.get("b").unwrap().value(), b2.get("b").unwrap().value());
+        assert_eq!(loader.cache.borrow().len(), 4);
+
+        let err = loader.load("missing.bzl").unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+        let err = FilesystemFileLoader::new(&Globals::standard(), &Dialect::Extended)
+            .load("//pkg:c.bzl")
+            .unwrap_err();
+        assert!(err.to_string().contains("no root"), "{}", err);
+    }
+
+    #[test]
+    fn test_filesystem_loader_outside_root() {
+        let dir = TempDir::with_files(&[("pkg/a.bzl", "a = 1"), ("b.bzl", "b = 2")]);
+        let root = dir.path();
+        let loader = loader(&root.join("pkg")).for_file(&root.join("pkg/main.bzl"));
+        assert_eq!(load_value(&loader, "./sub/../a.bzl", "a").unwrap(), "1");
+        assert_eq!(load_value(&loader, "//sub/..:a.bzl", "a").unwrap(), "1");
+        for path in [
+            "../b.bzl",
+            "//..:b.bzl",
+            "//../b.bzl",
+            "sub/../../b.bzl",
+            "/etc/passwd",
+        ] {
+            let err = loader.load(path).unwrap_err();
+            assert!(err.to_string().contains("outside the root"), "{}", err);
+        }
+        // Without a root, relative paths can go anywhere
+        let loader = FilesystemFileLoader::new(&Globals::standard(), &Dialect::Extended)
+            .for_file(&root.join("pkg/main.bzl"));
+        assert_eq!(load_value(&loader, "../b.bzl", "b").unwrap(), "2");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_filesystem_loader_symlink_outside_root() {
+        let dir = TempDir::with_files(&[("root/a.bzl", "a = 1"), ("outside/b.bzl", "b = 2")]);
+        let root = dir.path().join("root");
+        std::os::unix::fs::symlink(root.join("a.bzl"), root.join("inside.bzl")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("outside/b.bzl"), root.join("evil.bzl"))
+            .unwrap();
+        std::os::unix::fs::symlink(dir.path().join("outside"), root.join("pkg")).unwrap();
+        let loader = loader(&root);
+        assert_eq!(load_value(&loader, "//:inside.bzl", "a").unwrap(), "1");
+        for path in ["//:evil.bzl", "//pkg:b.bzl"] {
+            let err = loader.load(path).unwrap_err();
+            assert!(err.to_string().contains("outside the root"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("a/./b/../c")), Path::new("a/c"));
+        assert_eq!(normalize(Path::new("../a/..")), Path::new(".."));
+        assert_eq!(normalize(Path::new("/a/../..")), Path::new("/"));
+    }
+
+    #[test]
+    fn test_filesystem_loader_coverage() {
+        let dir = TempDir::with_files(&[(
+            "a.bzl",
+            "def f(x):\n    if x:\n        return 1\n    return 2\n",
+        )]);
+        let root = dir.path();
+        let loader = loader(root).with_coverage();
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_loader(&loader);
+        eval.enable_coverage();
+        let ast = AstModule::parse(
+            "main.bzl",
+            "load('//:a.bzl', 'f')\nf(True)".to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        eval.eval_module(ast, &Globals::standard()).unwrap();
+
+        let mut coverage = loader.coverage().unwrap();
+        coverage.merge(&eval.coverage().unwrap());
+        let file = root.join("a.bzl").canonicalize().unwrap();
+        assert_eq!(
+            coverage.lines(&file.to_string_lossy()).collect::<Vec<_>>(),
+            vec![(1, 1), (2, 1), (3, 1), (4, 0)]
+        );
+        // The `load` isn't a statement that runs
+        assert_eq!(coverage.lines("main.bzl").collect::<Vec<_>>(), vec![(2, 1)]);
+    }
+
+    #[test]
+    fn test_filesystem_loader_cycle() {
+        let dir = TempDir::with_files(&[
+            ("a.bzl", "load('b.bzl', 'b')\na = 1"),
+            ("b.bzl", "load('c.bzl', 'c')\nb = 1"),
+            ("c.bzl", "load('b.bzl', 'a')\nc = 1"),
+        ]);
+        let root = dir.path();
+        let err = loader(root).load("//:a.bzl").unwrap_err();
+        let err = format!("{:#}", err);
+        assert!(
+            err.contains("Load cycle: ") && err.contains("b.bzl -> ") && err.contains("c.bzl -> "),
+            "{}",
+            err
+        );
+    }
+}
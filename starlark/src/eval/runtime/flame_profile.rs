@@ -27,9 +27,13 @@ use std::{
 
 use anyhow::Context;
 use gazebo::prelude::*;
+use serde::Serialize;
 
 use crate as starlark;
-use crate::values::{Trace, Tracer, Value};
+use crate::{
+    eval::runtime::call_stack::FrozenFileSpan,
+    values::{FrozenRef, Trace, Tracer, Value},
+};
 
 /// Index into FlameData.values
 #[derive(Hash, PartialEq, Eq, Clone, Copy, Dupe)]
@@ -51,9 +55,19 @@ impl ValuePtr {
     }
 }
 
+#[derive(Clone, Copy)]
 enum Frame {
-    Push(ValueIndex),
+    /// Entering a function, called from the given location, if known.
+    Push(ValueIndex, Option<FrozenRef<'static, FrozenFileSpan>>),
     Pop,
+    GcStart,
+    GcEnd,
+}
+
+#[derive(Copy, Clone, Dupe, Debug)]
+pub(crate) enum FlameProfileFormat {
+    FlameGraph,
+    ChromeTrace,
 }
 
 #[derive(Trace)]
@@ -80,6 +94,76 @@ unsafe impl<'v> Trace<'v> for FlameData<'v> {
     }
 }
 
+/// The frames of a profile with the functions replaced by their names, so it no longer
+/// refers to the heap, see [`FlameProfile::gen`].
+pub(crate) struct FlameProfileData {
+    names: Vec<String>,
+    frames: Vec<(Frame, Instant)>,
+}
+
+impl FlameProfileData {
+    /// Append the frames of a later profile, matching functions by name.
+    pub(crate) fn merge(&mut self, other: &FlameProfileData) {
+        let mut ids: HashMap<String, ValueIndex> = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (x.clone(), ValueIndex(i)))
+            .collect();
+        let other_ids: Vec<ValueIndex> = other
+            .names
+            .iter()
+            .map(|x| match ids.entry(x.clone()) {
+                Entry::Occupied(e) => *e.get(),
+                Entry::Vacant(e) => {
+                    let res = ValueIndex(self.names.len());
+                    self.names.push(x.clone());
+                    *e.insert(res)
+                }
+            })
+            .collect();
+        self.frames
+            .extend(other.frames.iter().map(|(frame, time)| match frame {
+                Frame::Push(i, span) => (Frame::Push(other_ids[i.0], *span), *time),
+                frame => (*frame, *time),
+            }));
+    }
+
+    pub(crate) fn write(
+        &self,
+        filename: &Path,
+        format: FlameProfileFormat,
+    ) -> anyhow::Result<()> {
+        let file = File::create(filename).with_context(|| {
+            format!("When creating profile output file `{}`", filename.display())
+        })?;
+        match format {
+            FlameProfileFormat::FlameGraph => {
+                self.write_profile_to(file).map_err(anyhow::Error::from)
+            }
+            FlameProfileFormat::ChromeTrace => self.write_chrome_trace_to(file),
+        }
+        .with_context(|| {
+            format!(
+                "When writing to profile output file `{}`",
+                filename.display()
+            )
+        })
+    }
+
+    fn write_profile_to(&self, file: impl Write) -> io::Result<()> {
+        // Need to write out lines which look like:
+        // root;calls1;calls2 1
+        // All the numbers at the end must be whole numbers (we use milliseconds)
+        Stacks::new(&self.names, &self.frames).render(file)
+    }
+
+    fn write_chrome_trace_to(&self, file: impl Write) -> anyhow::Result<()> {
+        serde_json::to_writer(file, &ChromeTrace::new(&self.names, &self.frames))?;
+        Ok(())
+    }
+}
+
 struct Stacks<'a> {
     name: &'a str,
     time: Duration,
@@ -113,12 +197,14 @@ impl<'a> Stacks<'a> {
             *last_time = *time;
             match frame {
                 Frame::Pop => return,
-                Frame::Push(i) => match self.children.entry(*i) {
+                Frame::Push(i, _) => match self.children.entry(*i) {
                     Entry::Occupied(mut e) => e.get_mut().add(names, frames, last_time),
                     Entry::Vacant(e) => e
                         .insert(Stacks::blank(i.lookup(names).as_str()))
                         .add(names, frames, last_time),
                 },
+                // Time spent in GC is attributed to the function that triggered it
+                Frame::GcStart | Frame::GcEnd => {}
             }
         }
     }
@@ -147,6 +233,79 @@ impl<'a> Stacks<'a> {
     }
 }
 
+/// A file in the [Chrome Trace Event format](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU).
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChromeTrace<'a> {
+    trace_events: Vec<TraceEvent<'a>>,
+    display_time_unit: &'static str,
+}
+
+#[derive(Serialize)]
+struct TraceEvent<'a> {
+    name: &'a str,
+    cat: &'static str,
+    /// `B` to begin a slice, `E` to end the most recently begun one.
+    ph: &'static str,
+    /// Microseconds since the first event.
+    ts: f64,
+    pid: u32,
+    tid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<TraceArgs>,
+}
+
+#[derive(Serialize)]
+struct TraceArgs {
+    file: &'static str,
+    line: usize,
+}
+
+impl<'a> ChromeTrace<'a> {
+    fn new(names: &'a [String], frames: &[(Frame, Instant)]) -> Self {
+        let start = frames.first().map_or_else(Instant::now, |x| x.1);
+        // The slices which have begun but not ended, so we can name the end events
+        let mut open = Vec::new();
+        let mut trace_events = Vec::with_capacity(frames.len());
+        for (frame, time) in frames {
+            let (ph, (name, cat), args) = match frame {
+                Frame::Push(i, span) => {
+                    let args = span.map(|x| {
+                        let x = x.as_ref();
+                        TraceArgs {
+                            file: x.file.as_ref().filename(),
+                            line: x.file.find_line(x.span.begin()) + 1,
+                        }
+                    });
+                    open.push((i.lookup(names).as_str(), "call"));
+                    ("B", open[open.len() - 1], args)
+                }
+                Frame::GcStart => {
+                    open.push(("GC", "gc"));
+                    ("B", open[open.len() - 1], None)
+                }
+                Frame::Pop | Frame::GcEnd => match open.pop() {
+                    Some(x) => ("E", x, None),
+                    None => continue,
+                },
+            };
+            trace_events.push(TraceEvent {
+                name,
+                cat,
+                ph,
+                ts: time.duration_since(start).as_secs_f64() * 1e6,
+                pid: 1,
+                tid: 1,
+                args,
+            });
+        }
+        ChromeTrace {
+            trace_events,
+            display_time_unit: "ms",
+        }
+    }
+}
+
 impl<'v> FlameProfile<'v> {
     pub(crate) fn new() -> Self {
         Self(None)
@@ -158,7 +317,11 @@ impl<'v> FlameProfile<'v> {
 
     #[cold]
     #[inline(never)]
-    pub fn record_call_enter(&mut self, function: Value<'v>) {
+    pub fn record_call_enter(
+        &mut self,
+        function: Value<'v>,
+        span: Option<FrozenRef<'static, FrozenFileSpan>>,
+    ) {
         if let Some(box x) = &mut self.0 {
             let ind = match x.map.entry(ValuePtr::new(function)) {
                 Entry::Occupied(e) => *e.get(),
@@ -169,7 +332,7 @@ impl<'v> FlameProfile<'v> {
                     res
                 }
             };
-            x.frames.push((Frame::Push(ind), Instant::now()))
+            x.frames.push((Frame::Push(ind, span), Instant::now()))
         }
     }
 
@@ -181,30 +344,78 @@ impl<'v> FlameProfile<'v> {
         }
     }
 
-    // We could expose profile on the Heap, but it's an implementation detail that it works here.
-    pub(crate) fn write(&self, filename: &Path) -> Option<anyhow::Result<()>> {
-        self.0
-            .as_ref()
-            .map(|box x| Self::write_enabled(x, filename))
+    #[cold]
+    #[inline(never)]
+    pub fn record_gc_start(&mut self) {
+        if let Some(box x) = &mut self.0 {
+            x.frames.push((Frame::GcStart, Instant::now()))
+        }
     }
 
-    fn write_enabled(x: &FlameData, filename: &Path) -> anyhow::Result<()> {
-        let file = File::create(filename).with_context(|| {
-            format!("When creating profile output file `{}`", filename.display())
-        })?;
-        Self::write_profile_to(x, file).with_context(|| {
-            format!(
-                "When writing to profile output file `{}`",
-                filename.display()
-            )
+    #[cold]
+    #[inline(never)]
+    pub fn record_gc_end(&mut self) {
+        if let Some(box x) = &mut self.0 {
+            x.frames.push((Frame::GcEnd, Instant::now()))
+        }
+    }
+
+    // None = not applicable because not enabled
+    pub(crate) fn gen(&self) -> Option<FlameProfileData> {
+        self.0.as_ref().map(|box x| FlameProfileData {
+            names: x.values.map(|x| x.to_repr()),
+            frames: x.frames.clone(),
         })
     }
 
-    fn write_profile_to(x: &FlameData, file: impl Write) -> io::Result<()> {
-        // Need to write out lines which look like:
-        // root;calls1;calls2 1
-        // All the numbers at the end must be whole numbers (we use milliseconds)
-        let names = x.values.map(|x| x.to_repr());
-        Stacks::new(&names, &x.frames).render(file)
+    // We could expose profile on the Heap, but it's an implementation detail that it works here.
+    pub(crate) fn write(
+        &self,
+        filename: &Path,
+        format: FlameProfileFormat,
+    ) -> Option<anyhow::Result<()>> {
+        self.gen().map(|x| x.write(filename, format))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        environment::{Globals, Module},
+        eval::Evaluator,
+        syntax::{AstModule, Dialect},
+    };
+
+    #[test]
+    fn test_chrome_trace() {
+        let module = Module::new();
+        let globals = Globals::standard();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_flame_profile();
+        let code = r#"
+def f(x):
+    return str(x)
+f(1)
+"#;
+        let ast = AstModule::parse("trace.star", code.to_owned(), &Dialect::Standard).unwrap();
+        eval.eval_module(ast, &globals).unwrap();
+
+        let mut json = Vec::new();
+        eval.flame_profile
+            .gen()
+            .unwrap()
+            .write_chrome_trace_to(&mut json)
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let events = json["traceEvents"].as_array().unwrap();
+        let phases = events.map(|x| x["ph"].as_str().unwrap());
+        assert_eq!(phases, vec!["B", "B", "B", "E", "E", "E"]);
+        // The module itself, then `f` and `str`, with the lines they are called from
+        assert_eq!(events[0].get("args"), None);
+        assert_eq!(events[1]["args"], json!({"file": "trace.star", "line": 4}));
+        assert_eq!(events[2]["args"], json!({"file": "trace.star", "line": 3}));
     }
 }
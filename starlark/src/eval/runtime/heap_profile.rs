@@ -33,7 +33,7 @@ use gazebo::{any::AnyLifetime, prelude::*};
 
 use crate as starlark;
 use crate::{
-    eval::runtime::csv::CsvWriter,
+    eval::runtime::{csv::CsvWriter, profile_data::ProfileDataError},
     values::{Freeze, Freezer, Heap, NoSimpleValue, StarlarkValue, Trace, Value, ValueLike},
 };
 
@@ -47,6 +47,53 @@ pub(crate) struct HeapProfile {
     enabled: bool,
 }
 
+/// A heap profile summarized from the heap, so it no longer refers to it, see [`HeapProfile::gen`].
+pub(crate) struct HeapProfileData(HeapProfileDataImpl);
+
+enum HeapProfileDataImpl {
+    Summary(summary::Summary),
+    /// The bytes allocated by each stack, keyed by lines in the format flamegraph.pl
+    /// understands (`func1;func2;func3;type`).
+    FlameGraph(HashMap<String, usize>),
+}
+
+impl HeapProfileData {
+    /// Add another heap profile in the same format to this one, matching functions by name.
+    pub(crate) fn merge(&mut self, other: &HeapProfileData) -> anyhow::Result<()> {
+        match (&mut self.0, &other.0) {
+            (HeapProfileDataImpl::Summary(x), HeapProfileDataImpl::Summary(y)) => x.merge(y),
+            (HeapProfileDataImpl::FlameGraph(x), HeapProfileDataImpl::FlameGraph(y)) => {
+                for (stack, bytes) in y {
+                    *x.entry(stack.clone()).or_insert(0) += bytes;
+                }
+            }
+            _ => return Err(ProfileDataError::DifferentModes.into()),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn write(&self, filename: &Path) -> anyhow::Result<()> {
+        let file = File::create(filename).with_context(|| {
+            format!("When creating profile output file `{}`", filename.display())
+        })?;
+        self.write_to(file).with_context(|| {
+            format!(
+                "When writing to profile output file `{}`",
+                filename.display()
+            )
+        })
+    }
+
+    fn write_to(&self, file: impl Write) -> anyhow::Result<()> {
+        match &self.0 {
+            HeapProfileDataImpl::Summary(x) => {
+                HeapProfile::write_summarized_heap_profile_to(file, x)
+            }
+            HeapProfileDataImpl::FlameGraph(x) => HeapProfile::write_flame_heap_profile_to(file, x),
+        }
+    }
+}
+
 /// A type which is either drop or non-drop.
 trait MaybeDrop: Debug + Sync + Send + 'static {}
 
@@ -110,7 +157,7 @@ impl<'v, D: MaybeDrop + AnyLifetime<'static>> StarlarkValue<'v> for CallExit<D>
 struct FunctionId(usize);
 
 /// A mapping from function Value to FunctionId, which must be continuous
-#[derive(Default)]
+#[derive(Default, Clone)]
 struct FunctionIds {
     // The usize is the result of ptr_value()
     values: HashMap<usize, FunctionId>,
@@ -207,52 +254,60 @@ impl HeapProfile {
         }
     }
 
-    // We could expose profile on the Heap, but it's an implementation detail that it works here.
-    pub(crate) fn write(
+    // None = not applicable because not enabled
+    pub(crate) fn gen(
         &self,
-        filename: &Path,
         heap: &Heap,
         format: HeapProfileFormat,
-    ) -> Option<anyhow::Result<()>> {
+    ) -> Option<anyhow::Result<HeapProfileData>> {
         if !self.enabled {
             None
         } else {
-            Some(Self::write_enabled(filename, heap, format))
+            Some(Self::gen_enabled(heap, format))
         }
     }
 
-    pub(crate) fn write_enabled(
+    // We could expose profile on the Heap, but it's an implementation detail that it works here.
+    fn gen_enabled(heap: &Heap, format: HeapProfileFormat) -> anyhow::Result<HeapProfileData> {
+        Ok(HeapProfileData(match format {
+            HeapProfileFormat::Summary => {
+                HeapProfileDataImpl::Summary(Self::gen_summarized_heap_profile(heap))
+            }
+            HeapProfileFormat::FlameGraph => {
+                HeapProfileDataImpl::FlameGraph(Self::gen_flame_heap_profile(heap)?)
+            }
+        }))
+    }
+
+    pub(crate) fn write(
+        &self,
         filename: &Path,
         heap: &Heap,
         format: HeapProfileFormat,
-    ) -> anyhow::Result<()> {
-        let file = File::create(filename).with_context(|| {
-            format!("When creating profile output file `{}`", filename.display())
-        })?;
-
-        match format {
-            HeapProfileFormat::Summary => Self::write_summarized_heap_profile_to(file, heap),
-            HeapProfileFormat::FlameGraph => Self::write_flame_heap_profile_to(file, heap),
-        }
-        .with_context(|| {
-            format!(
-                "When writing to profile output file `{}`",
-                filename.display()
-            )
-        })
+    ) -> Option<anyhow::Result<()>> {
+        self.gen(heap, format).map(|x| x?.write(filename))
     }
 
-    fn write_flame_heap_profile_to(mut file: impl Write, heap: &Heap) -> anyhow::Result<()> {
+    fn gen_flame_heap_profile(heap: &Heap) -> anyhow::Result<HashMap<String, usize>> {
         let mut collector = flame::StackCollector::new();
         unsafe {
             heap.for_each_ordered(|x| collector.process(x));
         }
-        collector.write_to(&mut file)?;
+        collector.collect()
+    }
+
+    fn write_flame_heap_profile_to(
+        mut file: impl Write,
+        stacks: &HashMap<String, usize>,
+    ) -> anyhow::Result<()> {
+        for (stack, bytes) in stacks {
+            writeln!(file, "{} {}", stack, bytes)?;
+        }
         Ok(())
     }
 
-    fn write_summarized_heap_profile_to(mut file: impl Write, heap: &Heap) -> anyhow::Result<()> {
-        use summary::{FuncInfo, Info};
+    fn gen_summarized_heap_profile(heap: &Heap) -> summary::Summary {
+        use summary::Info;
 
         let mut ids = FunctionIds::default();
         let root = ids.get_string("(root)".to_owned());
@@ -270,12 +325,25 @@ impl HeapProfile {
         // Just has root left on it
         assert!(info.call_stack.len() == 1);
 
+        let Info { mut ids, info, .. } = info;
+        // The values are pointers into the heap, which mean nothing without it
+        ids.values.clear();
+        summary::Summary { ids, info }
+    }
+
+    fn write_summarized_heap_profile_to(
+        mut file: impl Write,
+        summary: &summary::Summary,
+    ) -> anyhow::Result<()> {
+        use summary::FuncInfo;
+
+        let summary::Summary { mut ids, mut info } = summary.clone();
+
         // Add a totals column
-        let total_id = info.ids.get_string("TOTALS".to_owned());
-        info.ensure(total_id);
-        let Info {
-            mut info, mut ids, ..
-        } = info;
+        let total_id = ids.get_string("TOTALS".to_owned());
+        if info.len() <= total_id.0 {
+            info.resize(total_id.0 + 1, FuncInfo::default());
+        }
         let totals = FuncInfo::merge(info.iter());
         let mut columns: Vec<(&'static str, usize)> =
             totals.allocs.iter().map(|(k, v)| (*k, *v)).collect();
@@ -367,6 +435,42 @@ mod summary {
         }
     }
 
+    /// The information about all functions, once the heap has been processed.
+    #[derive(Clone)]
+    pub(super) struct Summary {
+        pub ids: FunctionIds,
+        pub info: Vec<FuncInfo>,
+    }
+
+    impl Summary {
+        /// Add the information from another summary, matching functions by name.
+        pub fn merge(&mut self, other: &Summary) {
+            let ids = other
+                .ids
+                .invert()
+                .iter()
+                .map(|x| self.ids.get_string((*x).to_owned()))
+                .collect::<Vec<_>>();
+            if let Some(max) = ids.iter().map(|x| x.0 + 1).max() {
+                if self.info.len() < max {
+                    self.info.resize(max, FuncInfo::default());
+                }
+            }
+            for (id, x) in ids.iter().zip(&other.info) {
+                let me = &mut self.info[id.0];
+                me.calls += x.calls;
+                me.time += x.time;
+                me.time_rec += x.time_rec;
+                for (caller, n) in &x.callers {
+                    *me.callers.entry(ids[caller.0]).or_insert(0) += n;
+                }
+                for (k, v) in &x.allocs {
+                    *me.allocs.entry(k).or_insert(0) += v;
+                }
+            }
+        }
+    }
+
     /// We morally have two pieces of information:
     /// 1. Information about each function.
     /// 2. The call stack.
@@ -495,30 +599,31 @@ mod flame {
             this.caller.as_ref().duped()
         }
 
-        /// Write this stack frame's data to a file in a format flamegraph.pl understands
-        /// (each line is: `func1:func2:func3 BYTES`).
-        fn write<'a>(
+        /// Collect this stack frame's data in a format flamegraph.pl understands
+        /// (each key is: `func1;func2;func3;type`, with the bytes allocated as the value).
+        fn collect<'a>(
             &self,
-            file: &mut impl Write,
+            res: &mut HashMap<String, usize>,
             stack: &'_ mut Vec<&'a str>,
             ids: &[&'a str],
-        ) -> anyhow::Result<()> {
+        ) {
             let this = self.0.borrow();
 
             for (k, v) in this.allocs.iter() {
-                for e in stack.iter().chain(std::iter::once(k)).intersperse(&";") {
-                    write!(file, "{}", e)?;
-                }
-                writeln!(file, " {}", v.bytes)?;
+                let key = stack
+                    .iter()
+                    .chain(std::iter::once(k))
+                    .intersperse(&";")
+                    .copied()
+                    .collect::<String>();
+                *res.entry(key).or_insert(0) += v.bytes;
             }
 
             for (id, frame) in this.callees.iter() {
                 stack.push(ids[id.0]);
-                frame.write(file, stack, ids)?;
+                frame.collect(res, stack, ids);
                 stack.pop();
             }
-
-            Ok(())
         }
     }
 
@@ -567,12 +672,11 @@ mod flame {
         }
 
         /// Write this our recursively to a file.
-        pub fn write_to(&self, file: &mut impl Write) -> anyhow::Result<()> {
+        pub fn collect(&self) -> anyhow::Result<HashMap<String, usize>> {
             let current = self.current.as_ref().context("Popped the root frame")?;
-            current
-                .write(file, &mut vec![], &self.ids.invert())
-                .context("Writing failed")?;
-            Ok(())
+            let mut res = HashMap::new();
+            current.collect(&mut res, &mut vec![], &self.ids.invert());
+            Ok(res)
         }
     }
 
@@ -629,16 +733,20 @@ f
         eval.enable_heap_profile();
         let f = eval.eval_module(ast, &globals)?;
         // first check module profiling works
-        HeapProfile::write_summarized_heap_profile_to(&mut Vec::new(), module.heap())?;
-        HeapProfile::write_flame_heap_profile_to(&mut Vec::new(), module.heap())?;
+        HeapProfile::gen_enabled(module.heap(), HeapProfileFormat::Summary)?
+            .write_to(&mut Vec::new())?;
+        HeapProfile::gen_enabled(module.heap(), HeapProfileFormat::FlameGraph)?
+            .write_to(&mut Vec::new())?;
 
         // second check function profiling works
         let module = Module::new();
         let mut eval = Evaluator::new(&module);
         eval.enable_heap_profile();
         eval.eval_function(f, &[Value::new_int(100)], &[])?;
-        HeapProfile::write_summarized_heap_profile_to(&mut Vec::new(), module.heap())?;
-        HeapProfile::write_flame_heap_profile_to(&mut Vec::new(), module.heap())?;
+        HeapProfile::gen_enabled(module.heap(), HeapProfileFormat::Summary)?
+            .write_to(&mut Vec::new())?;
+        HeapProfile::gen_enabled(module.heap(), HeapProfileFormat::FlameGraph)?
+            .write_to(&mut Vec::new())?;
 
         // finally, check a user can add values into the heap before/after
         let module = Module::new();
@@ -647,8 +755,10 @@ f
         eval.enable_heap_profile();
         eval.eval_function(f, &[Value::new_int(100)], &[])?;
         module.heap().alloc("Thing that goes after");
-        HeapProfile::write_summarized_heap_profile_to(&mut Vec::new(), module.heap())?;
-        HeapProfile::write_flame_heap_profile_to(&mut Vec::new(), module.heap())?;
+        HeapProfile::gen_enabled(module.heap(), HeapProfileFormat::Summary)?
+            .write_to(&mut Vec::new())?;
+        HeapProfile::gen_enabled(module.heap(), HeapProfileFormat::FlameGraph)?
+            .write_to(&mut Vec::new())?;
 
         Ok(())
     }
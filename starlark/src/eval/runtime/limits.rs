@@ -0,0 +1,130 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Limits on how much work an [`Evaluator`](crate::eval::Evaluator) is allowed to do.
+
+use std::{
+    intrinsics::unlikely,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use thiserror::Error;
+
+use crate::values::Heap;
+
+/// Error raised when evaluation is stopped because of a limit set on the
+/// [`Evaluator`](crate::eval::Evaluator).
+///
+/// The error is reported as the [`message`](crate::errors::Diagnostic::message)
+/// of a [`Diagnostic`](crate::errors::Diagnostic), so can be detected with
+/// [`EvalLimitError::from_error`].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalLimitError {
+    /// More steps were taken than allowed by
+    /// [`set_max_steps`](crate::eval::Evaluator::set_max_steps).
+    #[error("Evaluation exceeded the limit of {0} steps")]
+    StepLimitExceeded(u64),
+    /// The flag passed to
+    /// [`set_cancellation_flag`](crate::eval::Evaluator::set_cancellation_flag) was set.
+    #[error("Evaluation was cancelled")]
+    Cancelled,
+    /// More memory was allocated than allowed by
+    /// [`Heap::set_allocation_limit`](crate::values::Heap::set_allocation_limit).
+    #[error("Evaluation exceeded the memory limit of {0} bytes")]
+    HeapLimitExceeded(usize),
+}
+
+impl EvalLimitError {
+    /// Find the [`EvalLimitError`] an error was caused by, if any.
+    pub fn from_error(err: &anyhow::Error) -> Option<Self> {
+        match err.downcast_ref::<crate::errors::Diagnostic>() {
+            Some(d) => d.message.downcast_ref::<Self>().copied(),
+            None => err.downcast_ref::<Self>().copied(),
+        }
+    }
+}
+
+/// Step budget and cancellation state of an evaluator.
+#[derive(Default)]
+pub(crate) struct EvalLimits {
+    /// Is any limit set. Checked before doing anything else, so evaluation
+    /// without limits pays only for a single branch per step.
+    enabled: bool,
+    /// Maximum number of steps, if limited.
+    max_steps: Option<u64>,
+    /// Number of steps taken so far.
+    steps: u64,
+    /// When set to `true` by someone else, evaluation stops.
+    cancelled: Option<Arc<AtomicBool>>,
+    /// Is there an allocation limit on the heap.
+    heap_limited: bool,
+}
+
+impl EvalLimits {
+    pub(crate) fn set_max_steps(&mut self, max_steps: u64) {
+        self.max_steps = Some(max_steps);
+        self.enabled = true;
+    }
+
+    pub(crate) fn set_cancellation_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancelled = Some(flag);
+        self.enabled = true;
+    }
+
+    /// Called when evaluation starts, since the limit is set on the heap itself.
+    pub(crate) fn set_heap_limited(&mut self, heap_limited: bool) {
+        self.heap_limited = heap_limited;
+        self.enabled = self.max_steps.is_some() || self.cancelled.is_some() || heap_limited;
+    }
+
+    pub(crate) fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Record a step (a bytecode instruction or a function call),
+    /// failing if a limit has been reached.
+    #[inline(always)]
+    pub(crate) fn step(&mut self, heap: &Heap) -> anyhow::Result<()> {
+        if unlikely(self.enabled) {
+            self.step_slow(heap)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline(never)]
+    fn step_slow(&mut self, heap: &Heap) -> anyhow::Result<()> {
+        self.steps += 1;
+        if let Some(max_steps) = self.max_steps {
+            if self.steps > max_steps {
+                return Err(EvalLimitError::StepLimitExceeded(max_steps).into());
+            }
+        }
+        if let Some(cancelled) = &self.cancelled {
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(EvalLimitError::Cancelled.into());
+            }
+        }
+        if self.heap_limited {
+            heap.check_allocation(0)?;
+        }
+        Ok(())
+    }
+}
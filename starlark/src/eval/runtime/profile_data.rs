@@ -0,0 +1,155 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::eval::runtime::{
+    bc_profile::BcProfileDataMode,
+    flame_profile::{FlameProfileData, FlameProfileFormat},
+    heap_profile::HeapProfileData,
+    profile_mode::ProfileMode,
+    stmt_profile::StmtProfileData,
+};
+
+#[derive(Debug, Error)]
+pub(crate) enum ProfileDataError {
+    #[error("Can't merge profiles collected in different modes")]
+    DifferentModes,
+}
+
+pub(crate) enum ProfileDataImpl {
+    Heap(HeapProfileData),
+    Stmt(StmtProfileData),
+    Flame(FlameProfileData),
+    Bc(BcProfileDataMode),
+}
+
+/// A profile collected by an [`Evaluator`](crate::eval::Evaluator), see
+/// [`gen_profile`](crate::eval::Evaluator::gen_profile).
+///
+/// Unlike the evaluator, it doesn't refer to the heap, so it can outlive the module it was
+/// collected in, and the profiles of several evaluations can be [`merge`](ProfileData::merge)d
+/// and written as one.
+pub struct ProfileData {
+    mode: ProfileMode,
+    data: ProfileDataImpl,
+}
+
+impl ProfileData {
+    pub(crate) fn new(mode: ProfileMode, data: ProfileDataImpl) -> Self {
+        Self { mode, data }
+    }
+
+    /// The mode the profile was collected in.
+    pub fn mode(&self) -> ProfileMode {
+        self.mode
+    }
+
+    /// Add a profile collected in the same mode to this one. Functions and statements
+    /// are matched by name and file, so evaluating the same file in several modules
+    /// gives the same profile as evaluating it repeatedly in one.
+    pub fn merge(&mut self, other: &ProfileData) -> anyhow::Result<()> {
+        if self.mode != other.mode {
+            return Err(ProfileDataError::DifferentModes.into());
+        }
+        match (&mut self.data, &other.data) {
+            (ProfileDataImpl::Heap(x), ProfileDataImpl::Heap(y)) => x.merge(y)?,
+            (ProfileDataImpl::Stmt(x), ProfileDataImpl::Stmt(y)) => x.merge(y),
+            (ProfileDataImpl::Flame(x), ProfileDataImpl::Flame(y)) => x.merge(y),
+            (ProfileDataImpl::Bc(x), ProfileDataImpl::Bc(y)) => x.merge(y)?,
+            _ => return Err(ProfileDataError::DifferentModes.into()),
+        }
+        Ok(())
+    }
+
+    /// Write the profile to a file, in the format of its [`ProfileMode`].
+    pub fn write<P: AsRef<Path>>(&self, filename: P) -> anyhow::Result<()> {
+        let filename = filename.as_ref();
+        match &self.data {
+            ProfileDataImpl::Heap(x) => x.write(filename),
+            ProfileDataImpl::Stmt(x) => x.write(filename),
+            ProfileDataImpl::Flame(x) => {
+                let format = match self.mode {
+                    ProfileMode::ChromeTrace => FlameProfileFormat::ChromeTrace,
+                    _ => FlameProfileFormat::FlameGraph,
+                };
+                x.write(filename, format)
+            }
+            ProfileDataImpl::Bc(x) => x.write_csv(filename),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::{
+        environment::{Globals, Module},
+        eval::{runtime::temp_dir::TempDir, Evaluator, ProfileData, ProfileMode},
+        syntax::{AstModule, Dialect},
+    };
+
+    fn profile(mode: ProfileMode, code: &str) -> ProfileData {
+        let module = Module::new();
+        let globals = Globals::standard();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_profile(&mode);
+        let ast = AstModule::parse("profile.star", code.to_owned(), &Dialect::Standard).unwrap();
+        eval.eval_module(ast, &globals).unwrap();
+        eval.gen_profile(&mode).unwrap()
+    }
+
+    #[test]
+    fn test_profile_data_merge() {
+        let code = r#"
+def f(x):
+    return [x] * 2
+f(1)
+"#;
+        let dir = TempDir::new();
+        for mode in ProfileMode::ALL {
+            let mut data = profile(mode, code);
+            data.merge(&profile(mode, code)).unwrap();
+            data.write(dir.path().join(mode.to_string())).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_profile_data_merge_stmt() {
+        let dir = TempDir::new();
+        let mut data = profile(ProfileMode::Stmt, "x = 1");
+        data.merge(&profile(ProfileMode::Stmt, "x = 1")).unwrap();
+        let file = dir.path().join("stmt.csv");
+        data.write(&file).unwrap();
+        let csv = fs::read_to_string(file).unwrap();
+        let rows = csv
+            .lines()
+            .filter(|x| x.starts_with("\"profile.star\","))
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 1, "{}", csv);
+        assert!(rows[0].ends_with(",2"), "{}", csv);
+    }
+
+    #[test]
+    fn test_profile_data_merge_different_modes() {
+        let mut data = profile(ProfileMode::Stmt, "x = 1");
+        assert!(data.merge(&profile(ProfileMode::Flame, "x = 1")).is_err());
+    }
+}
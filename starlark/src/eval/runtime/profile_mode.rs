@@ -0,0 +1,142 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{fmt, fmt::Display, str::FromStr};
+
+use gazebo::prelude::*;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum ProfileModeError {
+    #[error(
+        "Unknown profile mode `{0}`, expected one of `heap`, `heap-flame`, `stmt`, `flame`, `chrome-trace`, `bytecode` or `bytecode-pairs`"
+    )]
+    Unknown(String),
+}
+
+/// The kinds of profile an [`Evaluator`](crate::eval::Evaluator) can collect, see
+/// [`enable_profile`](crate::eval::Evaluator::enable_profile) and
+/// [`write_profile`](crate::eval::Evaluator::write_profile).
+///
+/// Converts to and from the names `heap`, `heap-flame`, `stmt`, `flame`, `chrome-trace`,
+/// `bytecode` and `bytecode-pairs`.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Hash)]
+pub enum ProfileMode {
+    /// Time and allocations per function, written as a `.csv` file,
+    /// see [`write_heap_profile`](crate::eval::Evaluator::write_heap_profile).
+    Heap,
+    /// Allocations per call stack, written as a flamegraph,
+    /// see [`write_heap_flame_profile`](crate::eval::Evaluator::write_heap_flame_profile).
+    HeapFlame,
+    /// Time per statement, written as a `.csv` file,
+    /// see [`write_stmt_profile`](crate::eval::Evaluator::write_stmt_profile).
+    Stmt,
+    /// Time per call stack, written as a flamegraph,
+    /// see [`write_flame_profile`](crate::eval::Evaluator::write_flame_profile).
+    Flame,
+    /// Calls and garbage collections over time, written in the Chrome Trace Event format,
+    /// see [`write_chrome_trace_profile`](crate::eval::Evaluator::write_chrome_trace_profile).
+    ChromeTrace,
+    /// Bytecode instructions executed, written as a `.csv` file,
+    /// see [`write_bytecode_profile`](crate::eval::Evaluator::write_bytecode_profile).
+    Bytecode,
+    /// Pairs of consecutive bytecode instructions executed, written as a `.csv` file.
+    BytecodePairs,
+}
+
+impl ProfileMode {
+    /// Every profile mode.
+    pub const ALL: [ProfileMode; 7] = [
+        ProfileMode::Heap,
+        ProfileMode::HeapFlame,
+        ProfileMode::Stmt,
+        ProfileMode::Flame,
+        ProfileMode::ChromeTrace,
+        ProfileMode::Bytecode,
+        ProfileMode::BytecodePairs,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ProfileMode::Heap => "heap",
+            ProfileMode::HeapFlame => "heap-flame",
+            ProfileMode::Stmt => "stmt",
+            ProfileMode::Flame => "flame",
+            ProfileMode::ChromeTrace => "chrome-trace",
+            ProfileMode::Bytecode => "bytecode",
+            ProfileMode::BytecodePairs => "bytecode-pairs",
+        }
+    }
+}
+
+impl Display for ProfileMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ProfileMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match Self::ALL.iter().find(|x| x.name() == s) {
+            Some(x) => Ok(*x),
+            None => Err(ProfileModeError::Unknown(s.to_owned()).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        environment::{Globals, Module},
+        eval::{runtime::temp_dir::TempDir, Evaluator},
+        syntax::{AstModule, Dialect},
+    };
+
+    #[test]
+    fn test_profile_mode_names() {
+        for mode in ProfileMode::ALL {
+            assert_eq!(mode, mode.to_string().parse().unwrap());
+        }
+        assert!("heap_flame".parse::<ProfileMode>().is_err());
+    }
+
+    #[test]
+    fn test_profile_mode_write() {
+        let code = r#"
+def f(x):
+    return [x] * 2
+f(1)
+"#;
+        let dir = TempDir::new();
+        for mode in ProfileMode::ALL {
+            let module = Module::new();
+            let globals = Globals::standard();
+            let mut eval = Evaluator::new(&module);
+            eval.enable_profile(&mode);
+            // Evaluating twice accumulates into the same profile
+            for _ in 0..2 {
+                let ast = AstModule::parse("profile.star", code.to_owned(), &Dialect::Standard);
+                eval.eval_module(ast.unwrap(), &globals).unwrap();
+            }
+            eval.write_profile(&mode, dir.path().join(mode.to_string()))
+                .unwrap();
+        }
+    }
+}
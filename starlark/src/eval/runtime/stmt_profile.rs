@@ -53,7 +53,7 @@ impl FileId {
 // So we don't need a special case for the first time around,
 // we have a special FileId of empty that we ignore when printing
 #[derive(Clone)]
-struct StmtProfileData {
+pub(crate) struct StmtProfileData {
     files: HashMap<FileId, CodeMap>,
     stmts: HashMap<(FileId, Span), (usize, Duration)>,
     next_file: FileId,
@@ -110,14 +110,38 @@ impl StmtProfileData {
         }
     }
 
-    fn write(&self, filename: &Path, now: Instant) -> anyhow::Result<()> {
+    // The statement that was running last won't have been properly updated.
+    // However, at this point, we have probably run some post-execution code,
+    // so it probably wouldn't have a "fair" timing anyway.
+    // We do our best though, and give it a time of now.
+    // Clone first, since we don't want to impact the real timing with our odd
+    // final execution finish.
+    fn finish(&self, now: Instant) -> Self {
+        let mut data = self.clone();
+        data.add_last(now);
+        data
+    }
+
+    /// Add the statements of another finished profile to this one.
+    pub(crate) fn merge(&mut self, other: &StmtProfileData) {
+        for (file, codemap) in &other.files {
+            self.files.entry(*file).or_insert_with(|| codemap.dupe());
+        }
+        for (stmt, (count, time)) in &other.stmts {
+            let x = self.stmts.entry(*stmt).or_default();
+            x.0 += *count;
+            x.1 += *time;
+        }
+    }
+
+    pub(crate) fn write(&self, filename: &Path) -> anyhow::Result<()> {
         let file = File::create(filename).with_context(|| {
             format!(
                 "When creating line profile output file `{}`",
                 filename.display()
             )
         })?;
-        self.write_to(file, now).with_context(|| {
+        self.write_to(file).with_context(|| {
             format!(
                 "When writing to line profile output file `{}`",
                 filename.display()
@@ -125,34 +149,40 @@ impl StmtProfileData {
         })
     }
 
-    fn write_to(&self, mut file: impl Write, now: Instant) -> anyhow::Result<()> {
-        // The statement that was running last won't have been properly updated.
-        // However, at this point, we have probably run some post-execution code,
-        // so it probably wouldn't have a "fair" timing anyway.
-        // We do our best though, and give it a time of now.
-        // Clone first, since we don't want to impact the real timing with our odd
-        // final execution finish.
-        let mut data = self.clone();
-        data.add_last(now);
-
+    fn write_to(&self, mut file: impl Write) -> anyhow::Result<()> {
         struct Item {
             span: FileSpan,
             time: Duration,
             count: usize,
         }
-        // There should be one EMPTY span entry
-        let mut items = Vec::with_capacity(data.stmts.len() - 1);
+        // A file evaluated several times is parsed each time, giving a different FileId,
+        // so merge the entries by filename. The same goes for merged profiles.
+        let mut items: HashMap<(&str, Span), Item> = HashMap::with_capacity(self.stmts.len());
         let mut total_time = Duration::default();
         let mut total_count = 0;
-        for ((file, span), (count, time)) in data.stmts {
+        for ((file, span), (count, time)) in &self.stmts {
             // EMPTY represents the first time special-case
-            if file != FileId::EMPTY {
-                let span = data.files[&file].file_span(span);
-                total_time += time;
-                total_count += count;
-                items.push(Item { span, time, count })
+            if *file != FileId::EMPTY {
+                let codemap = &self.files[file];
+                total_time += *time;
+                total_count += *count;
+                match items.entry((codemap.filename(), *span)) {
+                    Entry::Occupied(mut x) => {
+                        let x = x.get_mut();
+                        x.time += *time;
+                        x.count += *count;
+                    }
+                    Entry::Vacant(x) => {
+                        x.insert(Item {
+                            span: codemap.file_span(*span),
+                            time: *time,
+                            count: *count,
+                        });
+                    }
+                }
             }
         }
+        let mut items = items.into_values().collect::<Vec<_>>();
         items.sort_by_key(|x| -(x.time.as_nanos() as i128));
 
         let mut csv = CsvWriter::new(["File", "Span", "Duration(s)", "Count"]);
@@ -192,8 +222,44 @@ impl StmtProfile {
     }
 
     // None = not applicable because not enabled
-    pub fn write(&self, filename: &Path) -> Option<anyhow::Result<()>> {
+    pub fn gen(&self) -> Option<StmtProfileData> {
         let now = Instant::now();
-        self.0.as_ref().map(|data| data.write(filename, now))
+        self.0.as_ref().map(|data| data.finish(now))
+    }
+
+    // None = not applicable because not enabled
+    pub fn write(&self, filename: &Path) -> Option<anyhow::Result<()>> {
+        self.gen().map(|data| data.write(filename))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        environment::{Globals, Module},
+        eval::Evaluator,
+        syntax::{AstModule, Dialect},
+    };
+
+    #[test]
+    fn test_stmt_profile_merges_files() {
+        let module = Module::new();
+        let globals = Globals::standard();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_stmt_profile();
+        for _ in 0..3 {
+            let ast = AstModule::parse("stmt.star", "x = 1".to_owned(), &Dialect::Standard);
+            eval.eval_module(ast.unwrap(), &globals).unwrap();
+        }
+        let mut csv = Vec::new();
+        let data = eval.stmt_profile.gen().unwrap();
+        data.write_to(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let rows = csv
+            .lines()
+            .filter(|x| x.starts_with("\"stmt.star\","))
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 1, "{}", csv);
+        assert!(rows[0].ends_with(",3"), "{}", csv);
     }
 }
@@ -44,6 +44,19 @@ fn test_format_one() {
     )
 }
 
+#[test]
+fn test_fstring() {
+    bc::test_instrs(
+        &[BcOpcode::LoadLocal, BcOpcode::FString, BcOpcode::Return],
+        "def test(x): return f'(({x!r}))'",
+    );
+    // An f-string with a single plain expression is the same as `format` of it.
+    bc::test_instrs(
+        &[BcOpcode::LoadLocal, BcOpcode::FormatOne, BcOpcode::Return],
+        "def test(x): return f'(({x}))'",
+    );
+}
+
 #[test]
 fn test_percent_s_one_format_one_eval() {
     assert::pass(
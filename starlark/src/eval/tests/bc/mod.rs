@@ -21,6 +21,7 @@ mod and_or;
 mod compr;
 mod expr;
 mod if_stmt;
+mod while_stmt;
 
 use crate::{
     assert::Assert,
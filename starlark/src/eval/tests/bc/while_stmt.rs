@@ -0,0 +1,60 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Test compilation of `while` loops.
+
+use crate::eval::{bc::opcode::BcOpcode, tests::bc::test_instrs};
+
+#[test]
+fn test_while_x() {
+    test_instrs(
+        &[
+            BcOpcode::WhileLoop,
+            BcOpcode::LoadLocal,
+            BcOpcode::IfBr,
+            BcOpcode::Break,
+            BcOpcode::CallFrozenNativePos,
+            BcOpcode::Pop,
+            BcOpcode::Continue,
+            BcOpcode::ReturnConst,
+        ],
+        "def test(x):\n  while x: print()",
+    )
+}
+
+#[test]
+fn test_while_true() {
+    test_instrs(
+        &[
+            BcOpcode::WhileLoop,
+            BcOpcode::CallFrozenNativePos,
+            BcOpcode::Pop,
+            BcOpcode::Break,
+            BcOpcode::Continue,
+            BcOpcode::ReturnConst,
+        ],
+        "def test():\n  while True:\n    print()\n    break",
+    )
+}
+
+#[test]
+fn test_while_false() {
+    test_instrs(
+        &[BcOpcode::ReturnConst],
+        "def test():\n  while False: print()",
+    )
+}
@@ -0,0 +1,98 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::assert::{self, Assert};
+
+#[test]
+fn test_fstring() {
+    assert::pass(
+        r#"
+x = 1
+s = "a"
+assert_eq(f"", "")
+assert_eq(f"plain", "plain")
+assert_eq(f"{x}", "1")
+assert_eq(f'{s}', "a")
+assert_eq(f"<{x}, {s}>", "<1, a>")
+assert_eq(f"{s!r}", '"a"')
+assert_eq(f"{s!s}", "a")
+assert_eq(f"{x + 1} {[x, s]}", '2 [1, "a"]')
+assert_eq(f"{ {'k': x}['k'] }", "1")
+assert_eq(f"{x != 2}", "True")
+assert_eq(f"{{}} {{{x}}}", "{} {1}")
+assert_eq(f"\n\t{x}\x41", "\n\t1A")
+assert_eq(rf"\n{x}", "\\n1")
+assert_eq(f"""{x}
+{s}""", "1\na")
+assert_eq(f"{f'{x}'}", "1")
+"#,
+    );
+}
+
+#[test]
+fn test_fstring_spec() {
+    assert::pass(
+        r#"
+x = 42
+s = "ab"
+assert_eq(f"[{s:5}]", "[ab   ]")
+assert_eq(f"[{x:5}]", "[   42]")
+assert_eq(f"[{s:>5}]", "[   ab]")
+assert_eq(f"[{x:<5}]", "[42   ]")
+assert_eq(f"[{s:^6}]", "[  ab  ]")
+assert_eq(f"[{s:*^5}]", "[*ab**]")
+assert_eq(f"[{s!r:6}]", '["ab"  ]')
+assert_eq(f"[{s:1}]", "[ab]")
+"#,
+    );
+}
+
+#[test]
+fn test_fstring_in_def() {
+    assert::eq(
+        "def f(a, b): return f'{a}-{b}-{a!r}'\nf('x', 2)",
+        "'x-2-\"x\"'",
+    );
+}
+
+#[test]
+fn test_fstring_fail() {
+    assert::fail("f'{no}'", "not found");
+    assert::fail("f'{}'", "empty expression");
+    assert::fail("f'a}'", "single `}`");
+    assert::fail("x = 1\nf'{x!a}'", "invalid f-string conversion");
+    assert::fail("x = 1\nf'{x:<<<}'", "invalid f-string format spec");
+    assert::eq("len(f'{1:10000}')", "10000");
+    assert::fail("x = 1\nf'{x:10001}'", "width 10001 is too large");
+    assert::fail(
+        "x = 1\nf'{x:99999999999999}'",
+        "width 99999999999999 is too large",
+    );
+    assert::fail(
+        "x = 1\nf'{x:>99999999999999999999999}'",
+        "invalid f-string format spec",
+    );
+    assert::fail("x = 1\nf'{x'", "unfinished f-string expression");
+    assert::fail("x = 1\nf'{x +}'", "unexpected");
+}
+
+#[test]
+fn test_fstring_dialect() {
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_f_strings = false);
+    a.fail("x = 1\nf'{x}'", "f-strings are not allowed");
+}
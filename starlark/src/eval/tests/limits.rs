@@ -0,0 +1,249 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{
+    environment::{Globals, LibraryExtension, Module},
+    errors::Diagnostic,
+    eval::{EvalLimitError, Evaluator},
+    syntax::{AstModule, Dialect},
+};
+
+fn eval_with(program: &str, configure: impl FnOnce(&mut Evaluator)) -> anyhow::Result<()> {
+    let module = Module::new();
+    let globals = Globals::standard();
+    let mut eval = Evaluator::new(&module);
+    configure(&mut eval);
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended)?;
+    eval.eval_module(ast, &globals)?;
+    Ok(())
+}
+
+#[test]
+fn test_max_steps_loop() {
+    let err = eval_with("for x in range(1000000000):\n  pass", |eval| {
+        eval.set_max_steps(1000)
+    })
+    .unwrap_err();
+    assert_eq!(
+        EvalLimitError::from_error(&err),
+        Some(EvalLimitError::StepLimitExceeded(1000))
+    );
+}
+
+#[test]
+fn test_max_steps_while() {
+    let err = eval_with("while True:\n  pass", |eval| eval.set_max_steps(1000)).unwrap_err();
+    assert_eq!(
+        EvalLimitError::from_error(&err),
+        Some(EvalLimitError::StepLimitExceeded(1000))
+    );
+}
+
+#[test]
+fn test_max_steps_call_stack() {
+    let program = r#"
+def f(n):
+    return f(n + 1)
+f(0)
+"#;
+    let err = eval_with(program, |eval| eval.set_max_steps(100)).unwrap_err();
+    assert_eq!(
+        EvalLimitError::from_error(&err),
+        Some(EvalLimitError::StepLimitExceeded(100))
+    );
+    let diag = err.downcast_ref::<Diagnostic>().unwrap();
+    assert!(diag.call_stack.len() > 1);
+    assert!(diag.call_stack.iter().all(|x| x.name == "f"));
+}
+
+#[test]
+fn test_max_steps_assert_fails() {
+    // `assert.fails` must not catch the limit, or evaluation would carry on
+    let program = r#"
+def f():
+    for x in range(1000000000):
+        pass
+assert.fails(f, "")
+"#;
+    let module = Module::new();
+    let globals = Globals::extended_by(&[LibraryExtension::Assert]);
+    let mut eval = Evaluator::new(&module);
+    eval.set_max_steps(1000);
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+    let err = eval.eval_module(ast, &globals).unwrap_err();
+    assert_eq!(
+        EvalLimitError::from_error(&err),
+        Some(EvalLimitError::StepLimitExceeded(1000))
+    );
+}
+
+#[test]
+fn test_max_steps_enough() {
+    eval_with("x = [i for i in range(10)]", |eval| {
+        eval.set_max_steps(1000)
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_steps() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_max_steps(u64::MAX);
+    let ast = AstModule::parse("a.star", "x = 1 + 2".to_owned(), &Dialect::Extended).unwrap();
+    eval.eval_module(ast, &Globals::standard()).unwrap();
+    assert!(eval.steps() > 0);
+}
+
+#[test]
+fn test_cancelled() {
+    let flag = Arc::new(AtomicBool::new(false));
+    let err = eval_with("x = 1", |eval| {
+        flag.store(true, Ordering::Relaxed);
+        eval.set_cancellation_flag(flag.clone())
+    })
+    .unwrap_err();
+    assert_eq!(
+        EvalLimitError::from_error(&err),
+        Some(EvalLimitError::Cancelled)
+    );
+}
+
+#[test]
+fn test_cancelled_from_thread() {
+    let flag = Arc::new(AtomicBool::new(false));
+    let canceller = {
+        let flag = flag.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            flag.store(true, Ordering::Relaxed);
+        })
+    };
+    let err = eval_with(
+        "def f():\n  for x in range(1000000000):\n    pass\nf()",
+        |eval| eval.set_cancellation_flag(flag.clone()),
+    )
+    .unwrap_err();
+    canceller.join().unwrap();
+    assert_eq!(
+        EvalLimitError::from_error(&err),
+        Some(EvalLimitError::Cancelled)
+    );
+}
+
+fn eval_with_heap_limit(program: &str, limit: usize) -> anyhow::Result<()> {
+    let module = Module::new();
+    module.heap().set_allocation_limit(Some(limit));
+    let mut eval = Evaluator::new(&module);
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended)?;
+    eval.eval_module(ast, &Globals::extended_by(&[LibraryExtension::JsonModule]))?;
+    Ok(())
+}
+
+#[test]
+fn test_heap_limit_repeat() {
+    for program in ["'x' * 100000000", "[1] * 100000000", "(1,) * 100000000"] {
+        let err = eval_with_heap_limit(program, 1000000).unwrap_err();
+        assert_eq!(
+            EvalLimitError::from_error(&err),
+            Some(EvalLimitError::HeapLimitExceeded(1000000)),
+            "{}",
+            program
+        );
+    }
+}
+
+#[test]
+fn test_heap_limit_loop() {
+    let program = r#"
+xs = []
+for x in range(100000000):
+    xs.append(str(x))
+"#;
+    let err = eval_with_heap_limit(program, 1000000).unwrap_err();
+    assert_eq!(
+        EvalLimitError::from_error(&err),
+        Some(EvalLimitError::HeapLimitExceeded(1000000))
+    );
+}
+
+#[test]
+fn test_heap_limit_growth() {
+    // Ints aren't allocated on the heap, so only the growing containers count towards
+    // the limit, and for dicts their entries are held outside the heap.
+    for program in [
+        "xs = []\nfor i in range(1000000000):\n    xs.append(i)",
+        "d = {}\nfor i in range(1000000000):\n    d[i] = i",
+        "d = {}\nfor i in range(1000000000):\n    d.setdefault(i, i)",
+    ] {
+        let err = eval_with_heap_limit(program, 1000000).unwrap_err();
+        assert_eq!(
+            EvalLimitError::from_error(&err),
+            Some(EvalLimitError::HeapLimitExceeded(1000000)),
+            "{}",
+            program
+        );
+    }
+}
+
+#[test]
+fn test_heap_limit_single_call() {
+    // Each of these would allocate far more than the limit in one call, so must fail
+    // before or while allocating, not at the next step.
+    for program in [
+        "list(range(2147483647))",
+        "tuple(range(2147483647))",
+        "sorted(range(2147483647))",
+        "','.join(['x' * 1000] * 2000)",
+        "('x' * 1000).replace('x', 'y' * 10000)",
+        "json.decode('[%s0]' % ('0,' * 500000))",
+    ] {
+        let err = eval_with_heap_limit(program, 3000000).unwrap_err();
+        assert_eq!(
+            EvalLimitError::from_error(&err),
+            Some(EvalLimitError::HeapLimitExceeded(3000000)),
+            "{}",
+            program
+        );
+    }
+}
+
+#[test]
+fn test_heap_limit_enough() {
+    eval_with_heap_limit("x = 'x' * 1000", 1000000).unwrap();
+}
+
+#[test]
+fn test_frozen_heap_limit() {
+    let module = Module::new();
+    module.frozen_heap().set_allocation_limit(Some(0));
+    {
+        let mut eval = Evaluator::new(&module);
+        let ast = AstModule::parse("a.star", "x = [1]".to_owned(), &Dialect::Extended).unwrap();
+        eval.eval_module(ast, &Globals::standard()).unwrap();
+    }
+    let err = module.freeze().unwrap_err();
+    assert_eq!(
+        EvalLimitError::from_error(&err),
+        Some(EvalLimitError::HeapLimitExceeded(0))
+    );
+}
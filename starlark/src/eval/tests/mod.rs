@@ -38,11 +38,15 @@ mod call;
 mod comprehension;
 mod def;
 mod docstring;
+mod fstring;
 mod go;
 mod interop;
+mod limits;
+mod on_error;
 mod opt;
 mod runtime;
 mod type_is;
+mod while_stmt;
 
 #[test]
 fn alias_test() {
@@ -0,0 +1,79 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::RefCell;
+
+use crate::{
+    codemap::FileSpanRef,
+    environment::{Globals, Module},
+    eval::Evaluator,
+    syntax::{AstModule, Dialect},
+};
+
+/// The source of the span and the local variables at each error raised by the program.
+fn errors(program: &str) -> Vec<(String, Vec<String>)> {
+    let module = Module::new();
+    let globals = Globals::standard();
+    let mut evaluator = Evaluator::new(&module);
+    let res = RefCell::new(Vec::new());
+    let on_error = |span: FileSpanRef, _e: &anyhow::Error, eval: &mut Evaluator<'_, '_>| {
+        let locals = eval.local_variables().keys().cloned().collect();
+        res.borrow_mut()
+            .push((span.to_file_span().source_span().to_owned(), locals));
+    };
+    evaluator.on_error(&on_error);
+
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+    assert!(evaluator.eval_module(ast, &globals).is_err());
+    res.into_inner()
+}
+
+#[test]
+fn on_error_top_level() {
+    assert_eq!(
+        vec![("y + \"a\"".to_owned(), vec!["y".to_owned()])],
+        errors("y = 1\nx = y + \"a\"\n")
+    );
+}
+
+#[test]
+fn on_error_reported_once_where_raised() {
+    let program = "\
+def g(y):
+    for z in [1]:
+        fail(y)
+def f(x):
+    return g(x + 1)
+f(1)
+";
+    assert_eq!(
+        vec![("fail(y)".to_owned(), vec!["y".to_owned(), "z".to_owned()])],
+        errors(program)
+    );
+}
+
+#[test]
+fn on_error_spanned_by_instruction() {
+    let program = "\
+def f():
+    if False:
+        x = 1
+    return x
+f()
+";
+    assert_eq!(vec![("x".to_owned(), Vec::new())], errors(program));
+}
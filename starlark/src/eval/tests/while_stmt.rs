@@ -0,0 +1,125 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::assert::{self, Assert};
+
+#[test]
+fn test_while() {
+    assert::pass(
+        r#"
+def count(n):
+    i = 0
+    xs = []
+    while i < n:
+        xs.append(i)
+        i += 1
+    return xs
+assert_eq(count(0), [])
+assert_eq(count(3), [0, 1, 2])
+"#,
+    );
+}
+
+#[test]
+fn test_while_break_continue() {
+    assert::pass(
+        r#"
+def f():
+    i = 0
+    xs = []
+    while True:
+        i += 1
+        if i > 6:
+            break
+        if i % 2 == 0:
+            continue
+        xs.append(i)
+    return xs
+assert_eq(f(), [1, 3, 5])
+"#,
+    );
+}
+
+#[test]
+fn test_while_nested() {
+    assert::pass(
+        r#"
+def f():
+    res = []
+    i = 0
+    while i < 3:
+        for j in range(10):
+            if j == i:
+                break
+            res.append((i, j))
+        i += 1
+    return res
+assert_eq(f(), [(1, 0), (2, 0), (2, 1)])
+"#,
+    );
+}
+
+#[test]
+fn test_while_return() {
+    assert::eq("def f(x):\n  while True:\n    return x\nf(7)", "7");
+}
+
+#[test]
+fn test_while_top_level() {
+    assert::pass(
+        r#"
+i = 0
+while i < 5:
+    i += 1
+assert_eq(i, 5)
+"#,
+    );
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_top_level_stmt = false);
+    a.fail(
+        "i = 0\nwhile i < 5:\n  i += 1",
+        "cannot be used outside `def`",
+    );
+}
+
+#[test]
+fn test_while_dialect() {
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_while = false);
+    a.fail(
+        "def f():\n  while True:\n    pass",
+        "`while` is not allowed",
+    );
+}
+
+#[test]
+fn test_recursion_dialect() {
+    let program = r#"
+def fact(n):
+    return 1 if n <= 1 else n * fact(n - 1)
+fact(5)
+"#;
+    assert::eq(program, "120");
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_recursion = false);
+    a.fail(program, "called recursively");
+    a.fail(
+        "def f(n): return g(n)\ndef g(n): return f(n) if n else 0\nf(1)",
+        "called recursively",
+    );
+    a.eq("def f(): return 1\ndef g(): return f() + f()\ng()", "2");
+}
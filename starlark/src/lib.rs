@@ -403,6 +403,7 @@ extern crate maplit;
 mod macros;
 
 pub use starlark_derive::starlark_module;
+pub use stdlib::PrintHandler;
 
 pub(crate) mod analysis;
 pub mod assert;
@@ -0,0 +1,153 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `assert` module, for writing tests in Starlark, with `eq`, `ne`, `contains`, `true`
+//! and `fails`, modelled on [`Assert`](crate::assert::Assert).
+
+use regex::Regex;
+use thiserror::Error;
+
+use crate::{
+    self as starlark,
+    environment::GlobalsBuilder,
+    eval::EvalLimitError,
+    values::{none::NoneType, Value},
+};
+
+#[derive(Debug, Error)]
+enum AssertError {
+    #[error("assert.eq: expected {0}, got {1}")]
+    Eq(String, String),
+    #[error("assert.ne: but {0} == {1}")]
+    Ne(String, String),
+    #[error("assert.contains: expected {0} to be in {1}")]
+    Contains(String, String),
+    #[error("assert.true: got {0}")]
+    True(String),
+    #[error("assert.fails: didn't fail")]
+    DidNotFail,
+    #[error("assert.fails: expected an error matching `{0}`, got: {1}")]
+    WrongError(String, String),
+}
+
+#[starlark_module]
+fn assert_members(builder: &mut GlobalsBuilder) {
+    /// Fail unless `a == b`, where `b` is the expected value.
+    ///
+    /// ```
+    /// # starlark::assert::pass(r#"
+    /// assert.eq(1 + 2, 3)
+    /// # "#);
+    /// ```
+    fn eq(ref a: Value, ref b: Value) -> anyhow::Result<NoneType> {
+        if a.equals(b)? {
+            Ok(NoneType)
+        } else {
+            Err(AssertError::Eq(b.to_repr(), a.to_repr()).into())
+        }
+    }
+
+    /// Fail if `a == b`.
+    fn ne(ref a: Value, ref b: Value) -> anyhow::Result<NoneType> {
+        if a.equals(b)? {
+            Err(AssertError::Ne(a.to_repr(), b.to_repr()).into())
+        } else {
+            Ok(NoneType)
+        }
+    }
+
+    /// Fail unless `x in xs`.
+    ///
+    /// ```
+    /// # starlark::assert::pass(r#"
+    /// assert.contains([1, 2], 2)
+    /// # "#);
+    /// ```
+    fn contains(ref xs: Value, ref x: Value) -> anyhow::Result<NoneType> {
+        if xs.is_in(x)? {
+            Ok(NoneType)
+        } else {
+            Err(AssertError::Contains(x.to_repr(), xs.to_repr()).into())
+        }
+    }
+
+    /// Fail unless `x` is truthy.
+    fn r#true(ref x: Value) -> anyhow::Result<NoneType> {
+        if x.to_bool() {
+            Ok(NoneType)
+        } else {
+            Err(AssertError::True(x.to_repr()).into())
+        }
+    }
+
+    /// Call `f` with no arguments, and fail unless it fails with an error whose message
+    /// matches the regular expression `pattern` somewhere. Exceeding an evaluation limit,
+    /// e.g. the step limit, is never caught.
+    ///
+    /// ```
+    /// # starlark::assert::pass(r#"
+    /// assert.fails(lambda: fail("it broke"), "it b.*e")
+    /// # "#);
+    /// ```
+    fn fails(ref f: Value, ref pattern: &str) -> anyhow::Result<NoneType> {
+        let regex = Regex::new(pattern)?;
+        match f.invoke_pos(&[], eval) {
+            Ok(_) => Err(AssertError::DidNotFail.into()),
+            Err(e) if EvalLimitError::from_error(&e).is_some() => Err(e),
+            Err(e) => {
+                let message = e.to_string();
+                if regex.is_match(&message) {
+                    Ok(NoneType)
+                } else {
+                    Err(AssertError::WrongError(pattern.to_owned(), message).into())
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn global(builder: &mut GlobalsBuilder) {
+    builder.struct_("assert", assert_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_assert() {
+        assert::pass(
+            r#"
+assert.eq([1, 2], [1, 2])
+assert.ne(1, 2)
+assert.contains({"a": 1}, "a")
+assert.true("x")
+assert.fails(lambda: 1 // 0, "[Dd]ivide by zero")
+"#,
+        );
+        assert::fail("assert.eq(1 + 1, 3)", "expected 3, got 2");
+        assert::fail("assert.ne(1, 1)", "1 == 1");
+        assert::fail("assert.contains([1], 2)", "expected 2 to be in [1]");
+        assert::fail("assert.true([])", "got []");
+        assert::fail("assert.fails(lambda: 1, 'x')", "didn't fail");
+        assert::fail(
+            "assert.fails(lambda: fail('oops'), 'ok')",
+            "expected an error matching `ok`",
+        );
+        assert::fail("assert.fails(lambda: fail('oops'), '(')", "regex");
+    }
+}
@@ -0,0 +1,136 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `bytes()` constructor and methods for the `bytes` type.
+
+use thiserror::Error;
+
+use crate as starlark;
+use crate::{
+    environment::{GlobalsBuilder, MethodsBuilder},
+    values::{bytes::Bytes, Heap, Value},
+};
+
+#[derive(Debug, Error)]
+enum BytesError {
+    #[error("bytes: `{0}` is not a byte value (0 to 255)")]
+    NotAByte(i32),
+    #[error("bytes.decode: the bytes are not valid UTF-8: {0}")]
+    InvalidUtf8(std::str::Utf8Error),
+}
+
+/// Convert a string (as UTF-8), bytes or an iterable of ints into [`Bytes`].
+fn to_bytes<'v>(x: Value<'v>, heap: &'v Heap) -> anyhow::Result<Bytes> {
+    if let Some(x) = x.unpack_str() {
+        return Ok(Bytes::new(x.as_bytes().to_vec()));
+    }
+    if let Some(x) = Bytes::from_value(x) {
+        return Ok(x.clone());
+    }
+    x.with_iterator(heap, |it| {
+        it.map(|x| {
+            let x = x.to_int()?;
+            u8::try_from(x).map_err(|_| BytesError::NotAByte(x).into())
+        })
+        .collect::<anyhow::Result<Vec<u8>>>()
+    })?
+    .map(Bytes::new)
+}
+
+#[starlark_module]
+pub fn global(builder: &mut GlobalsBuilder) {
+    /// Create bytes from a string (using its UTF-8 encoding), bytes, or an iterable of
+    /// ints between 0 and 255.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// # (
+    /// bytes("hé") == b"h\xc3\xa9"
+    /// # and
+    /// bytes([104, 105]) == b"hi"
+    /// # )"#);
+    /// ```
+    #[starlark(type(Bytes::TYPE))]
+    #[starlark(speculative_exec_safe)]
+    fn bytes(ref x: Value) -> anyhow::Result<Bytes> {
+        to_bytes(x, heap)
+    }
+}
+
+#[starlark_module]
+pub(crate) fn bytes_methods(registry: &mut MethodsBuilder) {
+    /// `B.decode()` returns the string which `B` is the UTF-8 encoding of, failing if
+    /// `B` is not valid UTF-8.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// b"h\xc3\xa9".decode() == "hé"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn decode(this: &Bytes) -> anyhow::Result<String> {
+        match std::str::from_utf8(this.as_bytes()) {
+            Ok(x) => Ok(x.to_owned()),
+            Err(e) => Err(BytesError::InvalidUtf8(e).into()),
+        }
+    }
+
+    /// `B.elems()` returns a list of the bytes of `B`, as ints.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// b"hi".elems() == [104, 105]
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn elems(this: &Bytes) -> anyhow::Result<Vec<i32>> {
+        Ok(this.as_bytes().iter().map(|x| *x as i32).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_bytes_constructor() {
+        assert::all_true(
+            r#"
+bytes("") == b""
+bytes("a€") == b"a\xe2\x82\xac"
+bytes(b"x") == b"x"
+bytes(range(3)) == b"\x00\x01\x02"
+bytes((255,)) == b"\xff"
+"#,
+        );
+        assert::fail("bytes([256])", "not a byte value");
+        assert::fail("bytes(1)", "(iter)");
+    }
+
+    #[test]
+    fn test_bytes_methods() {
+        assert::all_true(
+            r#"
+"a€".encode() == b"a\xe2\x82\xac"
+"a€".encode().decode() == "a€"
+b"".elems() == []
+list(b"ab".elems()) == [97, 98]
+"#,
+        );
+        assert::fail("b'\\xff'.decode()", "not valid UTF-8");
+    }
+}
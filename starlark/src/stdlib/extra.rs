@@ -16,6 +16,7 @@
  */
 
 use std::{
+    cmp::Ordering,
     collections::HashSet,
     fmt::{self, Display},
 };
@@ -34,9 +35,9 @@ use crate::{
     environment::GlobalsBuilder,
     eval::{Arguments, Evaluator},
     values::{
-        dict::Dict, function::FUNCTION_TYPE, none::NoneType, tuple::Tuple, Freeze, Freezer,
-        FrozenStringValue, FrozenValue, StarlarkValue, StringValue, StringValueLike, Trace, Value,
-        ValueLike,
+        bigint::StarlarkIntRef, dict::Dict, function::FUNCTION_TYPE, none::NoneType, tuple::Tuple,
+        Freeze, Freezer, FrozenStringValue, FrozenValue, StarlarkValue, StringValue,
+        StringValueLike, Trace, Value, ValueError, ValueLike,
     },
 };
 
@@ -184,8 +185,17 @@ pub fn json(builder: &mut GlobalsBuilder) {
 
 #[starlark_module]
 pub fn abs(builder: &mut GlobalsBuilder) {
-    fn abs(ref x: i32) -> anyhow::Result<i32> {
-        Ok(x.abs())
+    fn abs(ref x: Value) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(x) {
+            Some(i) if i.cmp(StarlarkIntRef::Small(0)) == Ordering::Less => Ok(i.minus(heap)),
+            Some(_) => Ok(x),
+            None => Err(ValueError::IncorrectParameterTypeNamedWithExpected(
+                "x".to_owned(),
+                "int".to_owned(),
+                x.get_type().to_owned(),
+            )
+            .into()),
+        }
     }
 }
 
@@ -287,6 +297,22 @@ mod tests {
 
     use crate::{assert, assert::Assert, stdlib::PrintHandler};
 
+    #[test]
+    fn test_abs() {
+        let a = Assert::new();
+        a.all_true(
+            r#"
+abs(0) == 0
+abs(3) == 3
+abs(-3) == 3
+abs(-2147483648) == 2147483648
+abs(2**40) == 2**40
+abs(-2**40) == 2**40
+"#,
+        );
+        a.fail("abs('x')", "doesn't match");
+    }
+
     #[test]
     fn test_filter() {
         assert::pass(
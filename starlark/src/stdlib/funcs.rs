@@ -18,9 +18,14 @@
 //! A module with the standard function and constants that are by default in all
 //! dialect of Starlark
 
-use std::{cmp::Ordering, num::NonZeroI32};
+use std::{
+    cmp::Ordering,
+    num::{IntErrorKind, NonZeroI32},
+};
 
 use anyhow::anyhow;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
 
 use crate::{
     self as starlark,
@@ -28,9 +33,18 @@ use crate::{
     environment::GlobalsBuilder,
     eval::Arguments,
     values::{
-        bool::BOOL_TYPE, dict::Dict, float::StarlarkFloat, int::INT_TYPE, list::List,
-        none::NoneType, num::Num, range::Range, string::STRING_TYPE, tuple::Tuple, Heap,
-        StringValue, Value, ValueError, ValueLike,
+        bigint::{bigint_from_f64_exact, StarlarkBigInt, StarlarkIntRef},
+        bool::BOOL_TYPE,
+        dict::Dict,
+        float::StarlarkFloat,
+        int::INT_TYPE,
+        list::List,
+        none::NoneType,
+        num::Num,
+        range::Range,
+        string::STRING_TYPE,
+        tuple::Tuple,
+        Heap, StringValue, Value, ValueError, ValueLike,
     },
 };
 
@@ -50,6 +64,17 @@ fn unpack_pair<'v>(pair: Value<'v>, heap: &'v Heap) -> anyhow::Result<(Value<'v>
     })?
 }
 
+/// Fail if storing the elements of `x` would go over the allocation limit of the heap,
+/// for iterables whose length is known before iterating over them.
+fn check_iterable_allocation(x: Value, heap: &Heap) -> anyhow::Result<()> {
+    if heap.allocation_limit().is_some() {
+        if let Ok(len) = x.length() {
+            heap.check_allocation_values(len as usize)?;
+        }
+    }
+    Ok(())
+}
+
 #[starlark_module]
 pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     const None: NoneType = NoneType;
@@ -191,8 +216,11 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// ```
     #[starlark(speculative_exec_safe)]
     fn chr(ref i: Value) -> anyhow::Result<String> {
-        let cp = i.to_int()? as u32;
-        match std::char::from_u32(cp) {
+        let cp = match StarlarkIntRef::unpack(i) {
+            Some(i) => i.to_bigint(),
+            None => BigInt::from(i.to_int()?),
+        };
+        match cp.to_u32().and_then(std::char::from_u32) {
             Some(x) => Ok(x.to_string()),
             None => Err(anyhow!(
                 "chr() parameter value is 0x{:x} which is not a valid UTF-8 codepoint",
@@ -312,6 +340,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// ```
     #[starlark(speculative_exec_safe)]
     fn enumerate(ref it: Value, start @ 0: i32) -> anyhow::Result<Value<'v>> {
+        check_iterable_allocation(it, heap)?;
         let v = it
             .iterate(heap)?
             .enumerate()
@@ -355,6 +384,13 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
         let a = a.unwrap();
         if let Some(f) = a.unpack_num().map(|n| n.as_float()) {
             Ok(f)
+        } else if let Some(i) = StarlarkBigInt::from_value(a) {
+            let f = i.to_f64();
+            if f.is_finite() {
+                Ok(f)
+            } else {
+                Err(anyhow!("float() int too large to convert to float: {}", i))
+            }
         } else if let Some(s) = a.unpack_str() {
             match s.parse::<f64>() {
                 Ok(f) => {
@@ -473,8 +509,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     ///
     /// If x is a string, it is interpreted like a string literal;
     /// an optional base prefix (`0`, `0b`, `0B`, `0x`, `0X`) determines which
-    /// base to use. The string may specify an arbitrarily large integer,
-    /// whereas true integer literals are restricted to 64 bits.
+    /// base to use. The string may specify an arbitrarily large integer.
     /// If a non-zero `base` argument is provided, the string is interpreted
     /// in that base and no base prefix is permitted; the base argument may
     /// specified by name.
@@ -496,14 +531,14 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// int(3.14) == 3
     /// int(-12345.6789) == -12345
     /// int(2e9) == 2000000000
+    /// int(1e12) == 1000000000000
+    /// int('1000000000000') == 1000000000000
+    /// int('-0x10000000000') == -1099511627776
     /// # "#);
     /// # starlark::assert::fail(r#"
     /// int("hello")   # error: not a valid number
     /// # "#, "not a valid number");
     /// # starlark::assert::fail(r#"
-    /// int(1e100)   # error: overflow
-    /// # "#, "cannot convert float to integer");
-    /// # starlark::assert::fail(r#"
     /// int(float("nan"))   # error: cannot convert NaN to int
     /// # "#, "cannot convert float to integer");
     /// # starlark::assert::fail(r#"
@@ -512,9 +547,9 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// ```
     #[starlark(type(INT_TYPE))]
     #[starlark(speculative_exec_safe)]
-    fn int(ref a: Option<Value>, base: Option<Value>) -> anyhow::Result<i32> {
+    fn int(ref a: Option<Value>, base: Option<Value>) -> anyhow::Result<Value<'v>> {
         if a.is_none() {
-            return Ok(0);
+            return Ok(Value::new_int(0));
         }
         let a = a.unwrap();
         if let Some(s) = a.unpack_str() {
@@ -570,13 +605,29 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
                 _ => s,
             };
             match i32::from_str_radix(s, base) {
-                Ok(i) => Ok(sign * i),
-                Err(x) => Err(anyhow!(
-                    "{} is not a valid number in base {}: {}",
-                    a.to_repr(),
-                    base,
-                    x,
-                )),
+                Ok(i) => Ok(Value::new_int(sign * i)),
+                Err(x) => match x.kind() {
+                    // `from_str_radix` reports the overflow before reaching any invalid digit,
+                    // and `parse_bytes` accepts `_` separators, so check the digits first.
+                    IntErrorKind::PosOverflow | IntErrorKind::NegOverflow
+                        if s.chars().all(|c| c.is_digit(base)) =>
+                    {
+                        // Valid number, just too big for the compact representation.
+                        let i = BigInt::parse_bytes(s.as_bytes(), base).unwrap();
+                        Ok(StarlarkBigInt::alloc_bigint(sign * i, heap))
+                    }
+                    IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => Err(anyhow!(
+                        "{} is not a valid number in base {}: invalid digit found in string",
+                        a.to_repr(),
+                        base,
+                    )),
+                    _ => Err(anyhow!(
+                        "{} is not a valid number in base {}: {}",
+                        a.to_repr(),
+                        base,
+                        x,
+                    )),
+                },
             }
         } else if let Some(base) = base {
             Err(anyhow!(
@@ -584,15 +635,17 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
                 base.to_repr()
             ))
         } else if let Some(Num::Float(f)) = a.unpack_num() {
-            match Num::from(f.trunc()).as_int() {
-                Some(i) => Ok(i),
+            match bigint_from_f64_exact(f.trunc()) {
+                Some(i) => Ok(StarlarkBigInt::alloc_bigint(i, heap)),
                 None => Err(anyhow!(
                     "int() cannot convert float to integer: {}",
                     a.to_repr()
                 )),
             }
+        } else if StarlarkBigInt::from_value(a).is_some() {
+            Ok(a)
         } else {
-            Ok(a.to_int()?)
+            Ok(Value::new_int(a.to_int()?))
         }
     }
 
@@ -647,6 +700,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
             if let Some(xs) = List::from_value(a) {
                 heap.alloc_list(xs.content())
             } else {
+                check_iterable_allocation(a, heap)?;
                 a.with_iterator(heap, |it| heap.alloc_list_iter(it))?
             }
         } else {
@@ -889,6 +943,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// ```
     #[starlark(speculative_exec_safe)]
     fn reversed(ref a: Value) -> anyhow::Result<Value<'v>> {
+        check_iterable_allocation(a, heap)?;
         let mut v: Vec<Value> = a.iterate(heap)?.collect();
         v.reverse();
         Ok(heap.alloc_list(&v))
@@ -923,6 +978,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
         key: Option<Value>,
         reverse: Option<Value>,
     ) -> anyhow::Result<Value<'v>> {
+        check_iterable_allocation(x, heap)?;
         let it = x.iterate(heap)?;
         let mut it = match key {
             None => it.map(|x| (x, x)).collect(),
@@ -1005,6 +1061,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     fn tuple(ref a: Option<Value>) -> anyhow::Result<Value<'v>> {
         let mut l = Vec::new();
         if let Some(a) = a {
+            check_iterable_allocation(a, heap)?;
             a.with_iterator(heap, |it| {
                 l.extend(it);
             })?;
@@ -1053,6 +1110,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
         let mut v = Vec::new();
         let mut first = true;
         for arg in args {
+            check_iterable_allocation(arg, heap)?;
             let mut idx = 0;
             for e in arg.iterate(heap)? {
                 if first {
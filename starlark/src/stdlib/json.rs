@@ -0,0 +1,513 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `json` module, with `encode`, `decode`, `indent` and `encode_indent`,
+//! following the [Starlark Go](https://pkg.go.dev/go.starlark.net/lib/json) module.
+
+use std::{cell::Cell, fmt};
+
+use gazebo::coerce::coerce;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use thiserror::Error;
+
+use crate::{
+    self as starlark,
+    collections::SmallMap,
+    environment::GlobalsBuilder,
+    values::{
+        dict::Dict,
+        float::StarlarkFloat,
+        list::List,
+        recursive_repr_or_json_guard::{json_stack_push, JsonStackReleaseMemoryOnDrop},
+        set::Set,
+        structs::Struct,
+        tuple::Tuple,
+        Heap, Value, ValueLike,
+    },
+};
+
+#[derive(Debug, Error)]
+enum JsonError {
+    #[error("json.encode: cannot encode non-finite float `{0}`")]
+    NonFiniteFloat(String),
+    #[error("json.encode: dictionary keys must be strings, got `{0}`")]
+    NonStringKey(String),
+    #[error("json.encode: cycle in JSON structure")]
+    Cycle,
+    #[error("json.decode: {0}")]
+    Decode(serde_json::Error),
+    #[error("json.indent: {0}")]
+    Indent(serde_json::Error),
+}
+
+/// Builds Starlark values directly on the heap while parsing a JSON document.
+/// Objects become dictionaries, keeping the order of their keys.
+#[derive(Clone, Copy)]
+struct JsonSeed<'a, 'v> {
+    heap: &'v Heap,
+    /// Set if decoding was stopped because the heap went over its allocation limit.
+    limit_error: &'a Cell<Option<anyhow::Error>>,
+}
+
+impl<'de, 'a, 'v> DeserializeSeed<'de> for JsonSeed<'a, 'v> {
+    type Value = Value<'v>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value<'v>, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'a, 'v> JsonSeed<'a, 'v> {
+    /// Stop decoding if storing `len` more values would go over the heap's allocation limit.
+    fn check_allocation<E: de::Error>(&self, len: usize) -> Result<(), E> {
+        self.heap.check_allocation_values(len).map_err(|e| {
+            self.limit_error.set(Some(e));
+            E::custom("allocation limit exceeded")
+        })
+    }
+}
+
+impl<'de, 'a, 'v> Visitor<'de> for JsonSeed<'a, 'v> {
+    type Value = Value<'v>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value<'v>, E> {
+        Ok(Value::new_none())
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value<'v>, E> {
+        Ok(Value::new_bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value<'v>, E> {
+        Ok(self.heap.alloc(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value<'v>, E> {
+        Ok(self.heap.alloc(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value<'v>, E> {
+        Ok(self.heap.alloc(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value<'v>, E> {
+        Ok(self.heap.alloc_str(v).to_value())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value<'v>, A::Error> {
+        let mut res = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(x) = seq.next_element_seed(self)? {
+            res.push(x);
+            self.check_allocation(res.len())?;
+        }
+        Ok(self.heap.alloc_list(&res))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value<'v>, A::Error> {
+        let mut res = SmallMap::with_capacity(map.size_hint().unwrap_or_default());
+        while let Some(k) = map.next_key::<String>()? {
+            let v = map.next_value_seed(self)?;
+            if res
+                .insert_hashed(self.heap.alloc_str(&k).get_hashed(), v)
+                .is_some()
+            {
+                return Err(de::Error::custom(format!("duplicate key {:?}", k)));
+            }
+            self.check_allocation(res.len() * 2)?;
+        }
+        Ok(self.heap.alloc(Dict::new(coerce(res))))
+    }
+}
+
+fn decode_json<'v>(x: &str, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+    let mut deserializer = serde_json::Deserializer::from_str(x);
+    let limit_error = Cell::new(None);
+    let res = JsonSeed {
+        heap,
+        limit_error: &limit_error,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|res| deserializer.end().map(|()| res));
+    match (res, limit_error.take()) {
+        (_, Some(e)) => Err(e),
+        (res, None) => Ok(res.map_err(JsonError::Decode)?),
+    }
+}
+
+/// Remove the whitespace outside of strings from the output of `to_json()`, which puts
+/// spaces after separators.
+fn compact_json(json: &str, res: &mut String) {
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in json.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c.is_ascii_whitespace() {
+            continue;
+        }
+        res.push(c);
+    }
+}
+
+fn encode_items<'v>(
+    items: impl Iterator<Item = Value<'v>>,
+    res: &mut String,
+) -> anyhow::Result<()> {
+    res.push('[');
+    for (i, x) in items.enumerate() {
+        if i != 0 {
+            res.push(',');
+        }
+        encode_value(x, res)?;
+    }
+    res.push(']');
+    Ok(())
+}
+
+/// Keys must already have been checked to be strings.
+fn encode_fields<'v>(
+    fields: impl Iterator<Item = (Value<'v>, Value<'v>)>,
+    res: &mut String,
+) -> anyhow::Result<()> {
+    res.push('{');
+    for (i, (k, v)) in fields.enumerate() {
+        if i != 0 {
+            res.push(',');
+        }
+        k.collect_json(res)?;
+        res.push(':');
+        encode_value(v, res)?;
+    }
+    res.push('}');
+    Ok(())
+}
+
+fn encode_value(x: Value, res: &mut String) -> anyhow::Result<()> {
+    // Only the containers encoded here are on the stack, as `collect_json` pushes the rest.
+    let push = || json_stack_push(x).map_err(|_| JsonError::Cycle);
+    if let Some(StarlarkFloat(f)) = x.downcast_ref::<StarlarkFloat>() {
+        if !f.is_finite() {
+            return Err(JsonError::NonFiniteFloat(x.to_repr()).into());
+        }
+        x.collect_json(res)
+    } else if let Some(xs) = List::from_value(x) {
+        let _guard = push()?;
+        encode_items(xs.iter(), res)
+    } else if let Some(xs) = Tuple::from_value(x) {
+        let _guard = push()?;
+        encode_items(xs.iter(), res)
+    } else if let Some(xs) = Set::from_value(x) {
+        let _guard = push()?;
+        encode_items(xs.iter(), res)
+    } else if let Some(xs) = Dict::from_value(x) {
+        let _guard = push()?;
+        // Keys are sorted, as in Go Starlark, so the output doesn't depend on insertion order
+        let mut fields = xs
+            .iter()
+            .map(|(k, v)| match k.unpack_str() {
+                Some(s) => Ok((s, k, v)),
+                None => Err(JsonError::NonStringKey(k.to_repr())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        encode_fields(fields.into_iter().map(|(_, k, v)| (k, v)), res)
+    } else if let Some(xs) = Struct::from_value(x) {
+        let _guard = push()?;
+        encode_fields(xs.fields.iter().map(|(k, v)| (k.to_value(), *v)), res)
+    } else {
+        // Scalars and other types, whose JSON is valid but may contain whitespace
+        let mut json = String::new();
+        x.collect_json(&mut json)?;
+        compact_json(&json, res);
+        Ok(())
+    }
+}
+
+/// Encode a value as JSON without any whitespace, matching Go's `json.encode`.
+/// Floats must be finite and dictionary keys must be strings.
+fn encode_json(x: Value) -> anyhow::Result<String> {
+    let _release_memory = JsonStackReleaseMemoryOnDrop;
+    let mut res = String::new();
+    encode_value(x, &mut res)?;
+    Ok(res)
+}
+
+fn newline(res: &mut String, prefix: &str, indent: &str, depth: usize) {
+    res.push('\n');
+    res.push_str(prefix);
+    for _ in 0..depth {
+        res.push_str(indent);
+    }
+}
+
+/// Reformat a valid JSON document with one element per line, in the same layout as
+/// Go's `json.Indent`. Every line but the first starts with `prefix`, followed by
+/// one copy of `indent` per level of nesting.
+fn indent_json(x: &str, prefix: &str, indent: &str) -> anyhow::Result<String> {
+    serde_json::from_str::<IgnoredAny>(x).map_err(JsonError::Indent)?;
+
+    let mut res = String::with_capacity(x.len());
+    let mut depth = 0;
+    // Set after an opening bracket, so that empty objects and arrays stay on one line.
+    let mut need_indent = false;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in x.chars() {
+        if in_string {
+            res.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        if c.is_ascii_whitespace() {
+            continue;
+        }
+        if need_indent && c != '}' && c != ']' {
+            need_indent = false;
+            newline(&mut res, prefix, indent, depth);
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                res.push(c);
+            }
+            '{' | '[' => {
+                res.push(c);
+                depth += 1;
+                need_indent = true;
+            }
+            ',' => {
+                res.push(c);
+                newline(&mut res, prefix, indent, depth);
+            }
+            ':' => res.push_str(": "),
+            '}' | ']' => {
+                depth -= 1;
+                if need_indent {
+                    need_indent = false;
+                } else {
+                    newline(&mut res, prefix, indent, depth);
+                }
+                res.push(c);
+            }
+            _ => res.push(c),
+        }
+    }
+    Ok(res)
+}
+
+#[starlark_module]
+fn json_members(builder: &mut GlobalsBuilder) {
+    /// Encode a value as compact JSON. Dictionaries must have string keys, which are sorted.
+    ///
+    /// ```
+    /// # let mut a = starlark::assert::Assert::new();
+    /// # a.globals_add(|x| starlark::environment::LibraryExtension::JsonModule.add(x));
+    /// # a.is_true(r#"
+    /// json.encode({"a": [1, 2.5, None, True]}) == '{"a":[1,2.5,null,true]}'
+    /// # "#);
+    /// ```
+    fn encode(ref x: Value) -> anyhow::Result<String> {
+        encode_json(x)
+    }
+
+    /// Decode a JSON string. Objects become dictionaries (in the order of their keys),
+    /// arrays become lists, and numbers become integers unless they have a fraction
+    /// or exponent, in which case they become floats.
+    ///
+    /// ```
+    /// # let mut a = starlark::assert::Assert::new();
+    /// # a.globals_add(|x| starlark::environment::LibraryExtension::JsonModule.add(x));
+    /// # a.is_true(r#"
+    /// json.decode('{"a": [1, 2.5, null, true]}') == {"a": [1, 2.5, None, True]}
+    /// # "#);
+    /// ```
+    fn decode(ref x: &str) -> anyhow::Result<Value<'v>> {
+        decode_json(x, heap)
+    }
+
+    /// Reformat a JSON string with one element per line. Every line but the first
+    /// starts with `prefix` (by default empty) followed by `indent` (by default a tab)
+    /// for each level of nesting.
+    ///
+    /// ```
+    /// # let mut a = starlark::assert::Assert::new();
+    /// # a.globals_add(|x| starlark::environment::LibraryExtension::JsonModule.add(x));
+    /// # a.is_true(r#"
+    /// json.indent('{"a": [1, 2], "b": {}}', indent = "  ") == '{\n  "a": [\n    1,\n    2\n  ],\n  "b": {}\n}'
+    /// # "#);
+    /// ```
+    fn indent(
+        ref x: &str,
+        ref prefix: Option<&str>,
+        ref indent: Option<&str>,
+    ) -> anyhow::Result<String> {
+        indent_json(x, prefix.unwrap_or(""), indent.unwrap_or("\t"))
+    }
+
+    /// Equivalent to `json.indent(json.encode(x), prefix, indent)`.
+    ///
+    /// ```
+    /// # let mut a = starlark::assert::Assert::new();
+    /// # a.globals_add(|x| starlark::environment::LibraryExtension::JsonModule.add(x));
+    /// # a.is_true(r#"
+    /// json.encode_indent([1], indent = " ") == '[\n 1\n]'
+    /// # "#);
+    /// ```
+    fn encode_indent(
+        ref x: Value,
+        ref prefix: Option<&str>,
+        ref indent: Option<&str>,
+    ) -> anyhow::Result<String> {
+        indent_json(
+            &encode_json(x)?,
+            prefix.unwrap_or(""),
+            indent.unwrap_or("\t"),
+        )
+    }
+}
+
+pub(crate) fn global(builder: &mut GlobalsBuilder) {
+    builder.struct_("json", json_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert::Assert;
+
+    fn assert() -> Assert<'static> {
+        let mut a = Assert::new();
+        a.globals_add(global);
+        a
+    }
+
+    #[test]
+    fn test_encode() {
+        let a = assert();
+        a.eq(
+            r#"json.encode({"a": 1, "b": [True, None], "c": {"d": "x\"y"}})"#,
+            r#"'{"a":1,"b":[true,null],"c":{"d":"x\\"y"}}'"#,
+        );
+        a.eq("json.encode(struct(x = (1, 2)))", r#"'{"x":[1,2]}'"#);
+        a.eq("json.encode({})", "'{}'");
+        a.eq(
+            "json.encode({'b': 1, 'c': {'z': 2, 'y': 3}, 'a': 4})",
+            r#"'{"a":4,"b":1,"c":{"y":3,"z":2}}'"#,
+        );
+        a.eq(
+            "json.encode({'a': 1, 'b': 'x: y, z'})",
+            r#"'{"a":1,"b":"x: y, z"}'"#,
+        );
+        a.eq(
+            "json.encode({'a': set([1]), 'b': struct(c = {})})",
+            r#"'{"a":[1],"b":{"c":{}}}'"#,
+        );
+        a.fail("json.encode({1: 2})", "keys must be strings, got `1`");
+        a.fail(
+            "json.encode([{'a': 1, None: 2}])",
+            "keys must be strings, got `None`",
+        );
+        a.fail("json.encode(float('nan'))", "non-finite float `nan`");
+        a.fail(
+            "json.encode({'a': [float('-inf')]})",
+            "non-finite float `-inf`",
+        );
+        a.fail("x = []\nx.append(x)\njson.encode(x)", "cycle");
+        a.fail("json.encode(len)", "collect_json");
+    }
+
+    #[test]
+    fn test_json_function() {
+        // The `json()` function of `LibraryExtension::Json` is unchanged by the module.
+        crate::assert::eq("json({1: 'a', 'b': [1, 2]})", r#"'{1: "a", "b": [1,2]}'"#);
+    }
+
+    #[test]
+    fn test_decode() {
+        let a = assert();
+        a.all_true(
+            r#"
+json.decode("null") == None
+json.decode(" true ") == True
+json.decode("-12") == -12
+json.decode("12345678901234") == 12345678901234
+json.decode("1.5e3") == 1500.0
+type(json.decode("1.0")) == "float"
+json.decode('"\\u00e9\\n"') == "é\n"
+json.decode('[1, [], {}]') == [1, [], {}]
+list(json.decode('{"b": 1, "a": 2}').keys()) == ["b", "a"]
+"#,
+        );
+        a.fail("json.decode('[1,')", "json.decode");
+        a.fail("json.decode('1 2')", "json.decode");
+        a.fail("json.decode('{\"a\": 1, \"a\": 2}')", "duplicate key");
+    }
+
+    #[test]
+    fn test_decode_mutable() {
+        let a = assert();
+        a.is_true(
+            r#"
+x = json.decode('{"a": []}')
+x["a"].append(1)
+x["b"] = 2
+x == {"a": [1], "b": 2}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let a = assert();
+        a.is_true(
+            r#"
+x = {"a": [1, -2, 3.5, "s", None, False], "b": {"c": {}}}
+json.decode(json.encode(x)) == x
+"#,
+        );
+    }
+
+    #[test]
+    fn test_indent() {
+        let a = assert();
+        a.eq(r#"json.indent(' [ ] ')"#, "'[]'");
+        a.eq(
+            r#"json.indent('{"a":[1,{}],"b":"x, {y}: [z]"}', prefix = "> ", indent = "  ")"#,
+            r#"'{\n>   "a": [\n>     1,\n>     {}\n>   ],\n>   "b": "x, {y}: [z]"\n> }'"#,
+        );
+        a.eq(r#"json.indent('["\\"]"]')"#, r#"'[\n\t"\\"]"\n]'"#);
+        a.fail("json.indent('{')", "json.indent");
+    }
+}
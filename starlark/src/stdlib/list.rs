@@ -24,6 +24,7 @@ use crate::{
     environment::MethodsBuilder,
     stdlib::util::{convert_index, convert_indices},
     values::{
+        bigint::StarlarkIntRef,
         list::{List, ListRef},
         none::{NoneOr, NoneType},
         Value, ValueError,
@@ -215,7 +216,10 @@ pub(crate) fn list_methods(builder: &mut MethodsBuilder) {
     /// ```
     fn pop(this: Value, ref index: Option<Value>) -> anyhow::Result<Value<'v>> {
         let index = match index {
-            Some(index) => Some(index.to_int()?),
+            Some(index) => Some(match StarlarkIntRef::unpack(index) {
+                Some(index) => index.to_index()?,
+                None => index.to_int()?,
+            }),
             None => None,
         };
 
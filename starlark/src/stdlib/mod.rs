@@ -20,14 +20,18 @@
 
 use crate::environment::GlobalsBuilder;
 
+pub(crate) mod assert;
 pub(crate) mod breakpoint;
+pub(crate) mod bytes;
 pub(crate) mod dict;
 pub(crate) mod enumeration;
 pub(crate) mod extra;
 mod funcs;
 use gazebo::prelude::*;
+pub(crate) mod json;
 pub(crate) mod list;
 pub(crate) mod record;
+pub(crate) mod set;
 pub(crate) mod string;
 pub(crate) mod structs;
 pub(crate) mod util;
@@ -52,6 +56,10 @@ pub enum LibraryExtension {
     RecordType,
     /// Definitions to support the `enum` type, the `enum()` constructor.
     EnumType,
+    /// Definitions to support the `set` type, the `set()` constructor.
+    SetType,
+    /// A function `bytes(x)` to construct bytes from a string or an iterable of ints.
+    Bytes,
     /// A function `map(f, xs)` which applies `f` to each element of `xs` and returns the result.
     Map,
     /// A function `filter(f, xs)` which applies `f` to each element of `xs` and returns those for which `f` returns `True`.
@@ -75,7 +83,18 @@ pub enum LibraryExtension {
     Json,
     /// Add a function `abs()` which will take the absolute value of an int.
     Abs,
-    // Make sure if you add anything new, you add it to `all` below.
+    /// Add an `assert` module for writing tests, with `assert.eq(a, b)`, `assert.ne(a, b)`,
+    /// `assert.contains(xs, x)`, `assert.true(x)` and `assert.fails(f, pattern)`.
+    /// Not included in [`all`](LibraryExtension::all), as it is only meant for tests.
+    Assert,
+    /// Add a `json` module, with `json.encode(x)`, `json.decode(s)`, `json.indent(s)` and
+    /// `json.encode_indent(x)`, following the Go Starlark `json` module.
+    /// Not included in [`all`](LibraryExtension::all), as it replaces the `json()` function
+    /// added by [`Json`](LibraryExtension::Json) when added after it. The `starlark` binary
+    /// adds it after [`all`](LibraryExtension::all) when given `--json-module`.
+    JsonModule,
+    // Make sure if you add anything new, you add it to `all` below, unless it conflicts
+    // or is only meant for tests.
 }
 
 impl LibraryExtension {
@@ -83,8 +102,8 @@ impl LibraryExtension {
     pub fn all() -> &'static [Self] {
         use LibraryExtension::*;
         &[
-            StructType, RecordType, EnumType, Map, Filter, Partial, Dedupe, Debug, Print, Pprint,
-            Breakpoint, Json, Abs,
+            StructType, RecordType, EnumType, SetType, Bytes, Map, Filter, Partial, Dedupe, Debug,
+            Print, Pprint, Breakpoint, Json, Abs,
         ]
     }
 
@@ -95,6 +114,8 @@ impl LibraryExtension {
             StructType => structs::global(builder),
             RecordType => record::global(builder),
             EnumType => enumeration::global(builder),
+            SetType => set::global(builder),
+            Bytes => bytes::global(builder),
             Map => extra::map(builder),
             Filter => extra::filter(builder),
             Partial => extra::partial(builder),
@@ -105,6 +126,8 @@ impl LibraryExtension {
             Breakpoint => breakpoint::global(builder),
             Json => extra::json(builder),
             Abs => extra::abs(builder),
+            Assert => assert::global(builder),
+            JsonModule => json::global(builder),
         }
     }
 }
@@ -0,0 +1,205 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `set()` constructor and methods for the `set` type.
+
+use gazebo::cell::ARef;
+
+use crate as starlark;
+use crate::{
+    environment::{GlobalsBuilder, MethodsBuilder},
+    values::{none::NoneType, set::Set, Heap, Value, ValueError},
+};
+
+/// Collect the values of an iterable into a [`Set`].
+fn to_set<'v>(values: Value<'v>, heap: &'v Heap) -> anyhow::Result<Set<'v>> {
+    if let Some(values) = Set::from_value(values) {
+        return Ok((*values).clone());
+    }
+    values.with_iterator(heap, |it| {
+        let mut res = Set::default();
+        for x in it {
+            res.insert_hashed(x.get_hashed()?);
+        }
+        Ok(res)
+    })?
+}
+
+#[starlark_module]
+pub fn global(builder: &mut GlobalsBuilder) {
+    /// Create a set containing the values of an iterable, or an empty set.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2, 1]) == set([2, 1])
+    /// # "#);
+    /// ```
+    #[starlark(type(Set::TYPE))]
+    #[starlark(speculative_exec_safe)]
+    fn set(ref a: Option<Value>) -> anyhow::Result<Set<'v>> {
+        match a {
+            None => Ok(Set::default()),
+            Some(a) => to_set(a, heap),
+        }
+    }
+}
+
+#[starlark_module]
+pub(crate) fn set_methods(registry: &mut MethodsBuilder) {
+    /// `S.add(x)` adds `x` to the set `S`, doing nothing if it is already present.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1])
+    /// x.add(2)
+    /// x.add(1)
+    /// x == set([1, 2])
+    /// # "#);
+    /// ```
+    fn add(this: Value, ref value: Value) -> anyhow::Result<NoneType> {
+        let mut this = Set::from_value_mut(this)?.unwrap();
+        this.insert_hashed(value.get_hashed()?);
+        Ok(NoneType)
+    }
+
+    /// `S.clear()` removes all the values of the set `S`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.clear()
+    /// x == set()
+    /// # "#);
+    /// ```
+    fn clear(this: Value) -> anyhow::Result<NoneType> {
+        let mut this = Set::from_value_mut(this)?.unwrap();
+        this.clear();
+        Ok(NoneType)
+    }
+
+    /// `S.remove(x)` removes `x` from the set `S`, failing if it is not present.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.remove(1)
+    /// x == set([2])
+    /// # "#);
+    /// ```
+    fn remove(this: Value, ref value: Value) -> anyhow::Result<NoneType> {
+        let mut this = Set::from_value_mut(this)?.unwrap();
+        if this.remove_hashed(value.get_hashed()?) {
+            Ok(NoneType)
+        } else {
+            Err(ValueError::KeyNotFound(value.to_repr()).into())
+        }
+    }
+
+    /// `S.discard(x)` removes `x` from the set `S`, doing nothing if it is not present.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.discard(1)
+    /// x.discard(3)
+    /// x == set([2])
+    /// # "#);
+    /// ```
+    fn discard(this: Value, ref value: Value) -> anyhow::Result<NoneType> {
+        let mut this = Set::from_value_mut(this)?.unwrap();
+        this.remove_hashed(value.get_hashed()?);
+        Ok(NoneType)
+    }
+
+    /// `S.union(xs)` returns a new set with the values of both `S` and the iterable `xs`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).union([2, 3]) == set([1, 2, 3])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn union(this: ARef<Set>, ref other: Value) -> anyhow::Result<Set<'v>> {
+        Ok(this.union(&to_set(other, heap)?))
+    }
+
+    /// `S.intersection(xs)` returns a new set with the values of `S` which are also in
+    /// the iterable `xs`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).intersection([2, 3]) == set([2])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn intersection(this: ARef<Set>, ref other: Value) -> anyhow::Result<Set<'v>> {
+        Ok(this.intersection(&to_set(other, heap)?))
+    }
+
+    /// `S.difference(xs)` returns a new set with the values of `S` which are not in
+    /// the iterable `xs`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).difference([2, 3]) == set([1])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn difference(this: ARef<Set>, ref other: Value) -> anyhow::Result<Set<'v>> {
+        Ok(this.difference(&to_set(other, heap)?))
+    }
+
+    /// `S.symmetric_difference(xs)` returns a new set with the values which are in
+    /// exactly one of `S` and the iterable `xs`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).symmetric_difference([2, 3]) == set([1, 3])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn symmetric_difference(this: ARef<Set>, ref other: Value) -> anyhow::Result<Set<'v>> {
+        Ok(this.symmetric_difference(&to_set(other, heap)?))
+    }
+
+    /// `S.issubset(xs)` returns `True` if every value of `S` is in the iterable `xs`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// # (
+    /// set([1]).issubset([1, 2])
+    /// # and
+    /// not set([1, 3]).issubset(set([1, 2]))
+    /// # )"#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn issubset(this: ARef<Set>, ref other: Value) -> anyhow::Result<bool> {
+        Ok(this.is_subset(&to_set(other, heap)?))
+    }
+
+    /// `S.issuperset(xs)` returns `True` if every value of the iterable `xs` is in `S`.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).issuperset([1])
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn issuperset(this: ARef<Set>, ref other: Value) -> anyhow::Result<bool> {
+        Ok(to_set(other, heap)?.is_subset(&this))
+    }
+}
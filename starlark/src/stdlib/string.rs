@@ -28,6 +28,7 @@ use crate::{
     eval::Arguments,
     stdlib::string::fast_string::convert_str_indices,
     values::{
+        bytes::Bytes,
         none::NoneOr,
         string::{fast_string, interpolation},
         tuple::Tuple,
@@ -246,6 +247,18 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         }
     }
 
+    /// `S.encode()` returns the UTF-8 encoding of the string `S`, as bytes.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// "hé".encode() == b"h\xc3\xa9"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn encode(this: &str) -> anyhow::Result<Bytes> {
+        Ok(Bytes::new(this.as_bytes().to_vec()))
+    }
+
     /// [string.endswith](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·endswith
     /// ): determine if a string ends with a given suffix.
@@ -682,8 +695,13 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
                             // guess towards the upper bound, since we throw away over-allocations quickly
                             // include a buffer (20 bytes)
                             let n = it.size_hint().0 + 2;
-                            let guess =
-                                (cmp::max(s1.len(), s2.len()) * n) + (this.len() * (n - 1)) + 20;
+                            let guess = (cmp::max(s1.len(), s2.len()).saturating_mul(n))
+                                .saturating_add(this.len().saturating_mul(n - 1))
+                                .saturating_add(20);
+                            // The limit is checked as the string grows, so don't reserve beyond it
+                            let guess = heap
+                                .allocation_limit()
+                                .map_or(guess, |x| cmp::min(guess, x));
                             let mut r = String::with_capacity(guess);
                             r.push_str(s1);
                             r.push_str(this);
@@ -691,6 +709,7 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
                             for x in it {
                                 r.push_str(this);
                                 r.push_str(as_str(x)?);
+                                heap.check_allocation(r.len())?;
                             }
                             Ok(heap.alloc(r))
                         }
@@ -790,11 +809,16 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         ref new: &str,
         ref count: Option<i32>,
     ) -> anyhow::Result<String> {
-        match count {
-            Some(count) if count >= 0 => Ok(this.replacen(old, new, count as usize)),
-            Some(count) => Err(anyhow!("Replace final argument was negative '{}'", count)),
-            None => Ok(this.replace(old, new)),
+        let count = match count {
+            Some(count) if count >= 0 => count as usize,
+            Some(count) => return Err(anyhow!("Replace final argument was negative '{}'", count)),
+            None => usize::MAX,
+        };
+        if heap.allocation_limit().is_some() && new.len() > old.len() {
+            let matches = this.matches(old).take(count).count();
+            heap.check_allocation(this.len() + matches * (new.len() - old.len()))?;
         }
+        Ok(this.replacen(old, new, count))
     }
 
     /// [string.rfind](
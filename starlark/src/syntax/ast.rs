@@ -24,9 +24,15 @@ use std::{
 
 use derivative::Derivative;
 use gazebo::prelude::*;
+use once_cell::sync::OnceCell;
 use static_assertions::assert_eq_size;
 
-use crate::codemap::{CodeMap, Pos, Span, Spanned};
+use crate::{
+    analysis::Suppression,
+    codemap::{CodeMap, Pos, Span, Spanned},
+    syntax::lexer::TokenInt,
+    values::bytes::write_bytes_literal,
+};
 
 /// Payload types attached to AST nodes.
 pub trait AstPayload: Debug {
@@ -46,6 +52,7 @@ impl AstPayload for AstNoPayload {
 }
 
 pub type Expr = ExprP<AstNoPayload>;
+pub type FStringPart = FStringPartP<AstNoPayload>;
 pub type Assign = AssignP<AstNoPayload>;
 pub type AssignIdent = AssignIdentP<AstNoPayload>;
 pub type Clause = ClauseP<AstNoPayload>;
@@ -70,8 +77,9 @@ pub type AstAssign = AstAssignP<AstNoPayload>;
 pub type AstAssignIdent = AstAssignIdentP<AstNoPayload>;
 pub type AstArgument = AstArgumentP<AstNoPayload>;
 pub type AstString = Spanned<String>;
+pub type AstBytes = Spanned<Vec<u8>>;
 pub type AstParameter = AstParameterP<AstNoPayload>;
-pub type AstInt = Spanned<i32>;
+pub type AstInt = Spanned<TokenInt>;
 pub type AstFloat = Spanned<f64>;
 pub type AstLoad = AstLoadP<AstNoPayload>;
 pub type AstStmt = AstStmtP<AstNoPayload>;
@@ -95,6 +103,12 @@ pub struct AstModule {
     #[derivative(Debug = "ignore")]
     pub(crate) codemap: CodeMap,
     pub(crate) statement: AstStmt,
+    /// Taken from [`Dialect::enable_recursion`](crate::syntax::Dialect::enable_recursion),
+    /// since recursion can only be detected at runtime.
+    pub(crate) enable_recursion: bool,
+    /// The `# starlark: disable=` comments, found the first time the module is linted.
+    #[derivative(Debug = "ignore")]
+    pub(crate) suppressions: OnceCell<Vec<Suppression>>,
 }
 
 // A trait rather than a function to allow .ast() chaining in the parser.
@@ -135,6 +149,7 @@ pub enum AstLiteral {
     Int(AstInt),
     Float(AstFloat),
     String(AstString),
+    Bytes(AstBytes),
 }
 
 #[derive(Debug)]
@@ -166,6 +181,77 @@ pub enum ExprP<P: AstPayload> {
         Box<ForClauseP<P>>,
         Vec<ClauseP<P>>,
     ),
+    FString(Vec<FStringPartP<P>>),
+}
+
+/// A piece of an `f"..."` string literal.
+#[derive(Debug)]
+pub enum FStringPartP<P: AstPayload> {
+    /// Literal text, with escapes and doubled braces already resolved.
+    Text(AstString),
+    /// An interpolated `{expr}`, and how to format it.
+    Expr(AstExprP<P>, FStringFormat),
+}
+
+/// Alignment of an interpolated value within its width in an f-string.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum FStringAlign {
+    /// `<`
+    Left,
+    /// `>`
+    Right,
+    /// `^`
+    Center,
+}
+
+/// How an interpolated value in an f-string is turned into text, as given by an
+/// optional `!s` or `!r` conversion followed by an optional `:[[fill]align][width]` spec.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub struct FStringFormat {
+    /// Use `repr` rather than `str`, written `!r`.
+    pub repr: bool,
+    /// The character used for padding, a space by default.
+    pub fill: char,
+    /// If `None`, numbers are aligned right and everything else left.
+    pub align: Option<FStringAlign>,
+    /// Minimum width in characters.
+    pub width: usize,
+}
+
+impl Default for FStringFormat {
+    fn default() -> Self {
+        Self {
+            repr: false,
+            fill: ' ',
+            align: None,
+            width: 0,
+        }
+    }
+}
+
+impl Display for FStringFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.repr {
+            f.write_str("!r")?;
+        }
+        if self.align.is_some() || self.width != 0 {
+            f.write_str(":")?;
+            if let Some(align) = self.align {
+                if self.fill != ' ' {
+                    write!(f, "{}", self.fill)?;
+                }
+                f.write_str(match align {
+                    FStringAlign::Left => "<",
+                    FStringAlign::Right => ">",
+                    FStringAlign::Center => "^",
+                })?;
+            }
+            if self.width != 0 {
+                write!(f, "{}", self.width)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 /// In some places e.g. AssignModify, the Tuple case is not allowed.
@@ -262,6 +348,7 @@ pub enum StmtP<P: AstPayload> {
     If(AstExprP<P>, Box<AstStmtP<P>>),
     IfElse(AstExprP<P>, Box<(AstStmtP<P>, AstStmtP<P>)>),
     For(AstAssignP<P>, Box<(AstExprP<P>, AstStmtP<P>)>),
+    While(AstExprP<P>, Box<AstStmtP<P>>),
     Def(
         AstAssignIdentP<P>,
         Vec<AstParameterP<P>>,
@@ -338,7 +425,7 @@ impl Display for AssignOp {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match *self {
             AssignOp::Add => f.write_str(" += "),
-            AssignOp::Subtract => f.write_str(" += "),
+            AssignOp::Subtract => f.write_str(" -= "),
             AssignOp::Multiply => f.write_str(" *= "),
             AssignOp::Divide => f.write_str(" /= "),
             AssignOp::FloorDivide => f.write_str(" //= "),
@@ -373,8 +460,15 @@ where
 
 fn fmt_string_literal(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
     f.write_str("\"")?;
+    fmt_string_contents(f, s, false)?;
+    f.write_str("\"")
+}
+
+/// Write the characters of a string literal, escaped, doubling braces if `fstring`.
+fn fmt_string_contents(f: &mut Formatter<'_>, s: &str, fstring: bool) -> fmt::Result {
     for c in s.chars() {
         match c {
+            '{' | '}' if fstring => write!(f, "{}{}", c, c)?,
             '\n' => f.write_str("\\n")?,
             '\t' => f.write_str("\\t")?,
             '\r' => f.write_str("\\r")?,
@@ -384,7 +478,7 @@ fn fmt_string_literal(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
             x => f.write_str(&x.to_string())?,
         }
     }
-    f.write_str("\"")
+    Ok(())
 }
 
 impl Display for AstLiteral {
@@ -393,6 +487,7 @@ impl Display for AstLiteral {
             AstLiteral::Int(i) => write!(f, "{}", &i.node),
             AstLiteral::Float(n) => write!(f, "{}", &n.node),
             AstLiteral::String(s) => fmt_string_literal(f, &s.node),
+            AstLiteral::Bytes(b) => write_bytes_literal(f, &b.node),
         }
     }
 }
@@ -475,6 +570,16 @@ impl Display for Expr {
                 f.write_str("}}")
             }
             Expr::Literal(x) => write!(f, "{}", x),
+            Expr::FString(parts) => {
+                f.write_str("f\"")?;
+                for part in parts {
+                    match part {
+                        FStringPartP::Text(s) => fmt_string_contents(f, &s.node, true)?,
+                        FStringPartP::Expr(e, format) => write!(f, "{{{}{}}}", e.node, format)?,
+                    }
+                }
+                f.write_str("\"")
+            }
         }
     }
 }
@@ -577,6 +682,10 @@ impl Stmt {
                 writeln!(f, "{}for {} in {}:", tab, bind.node, coll.node)?;
                 suite.node.fmt_with_tab(f, tab + "  ")
             }
+            Stmt::While(cond, box suite) => {
+                writeln!(f, "{}while {}:", tab, cond.node)?;
+                suite.node.fmt_with_tab(f, tab + "  ")
+            }
             Stmt::Def(name, params, return_type, suite, _payload) => {
                 write!(f, "{}def {}(", tab, name.node)?;
                 comma_separated_fmt(f, params, |x, f| write!(f, "{}", x.node), false)?;
@@ -35,6 +35,12 @@ enum DialectError {
     KeywordOnlyArguments,
     #[error("type annotations are not allowed in this dialect")]
     Types,
+    #[error("bytes literals are not allowed in this dialect")]
+    Bytes,
+    #[error("f-strings are not allowed in this dialect")]
+    FStrings,
+    #[error("`while` is not allowed in this dialect")]
+    While,
 }
 
 /// Starlark language features to enable, e.g. [`Standard`](Dialect::Standard) to follow the Starlark standard.
@@ -65,6 +71,21 @@ pub struct Dialect {
     /// Are `for`, `if` and other statements allowed at the top level.
     /// Only enabled in [`Extended`](Dialect::Extended).
     pub enable_top_level_stmt: bool,
+    /// Are `b"..."` bytes literals permitted.
+    /// Only enabled in [`Extended`](Dialect::Extended).
+    pub enable_bytes: bool,
+    /// Are `f"..."` string literals with `{expr}` interpolation permitted.
+    /// Only enabled in [`Extended`](Dialect::Extended).
+    pub enable_f_strings: bool,
+    /// Are `while` loops permitted.
+    /// Only enabled in [`Extended`](Dialect::Extended).
+    pub enable_while: bool,
+    /// Can a `def` call itself, directly or indirectly.
+    /// Recursion is detected when the function is invoked, by looking for it on the call stack,
+    /// and reported as an error if this flag is disabled.
+    /// Enabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended),
+    /// although the Starlark standard forbids recursion.
+    pub enable_recursion: bool,
 }
 
 // These are morally enumerations, so give them enumeration-like names
@@ -81,6 +102,10 @@ impl Dialect {
         enable_tabs: true,
         enable_load_reexport: true, // But they plan to change it
         enable_top_level_stmt: false,
+        enable_bytes: false,
+        enable_f_strings: false,
+        enable_while: false,
+        enable_recursion: true,
     };
 
     /// A superset of [`Standard`](Dialect::Standard), including extra features (types, top-level statements etc).
@@ -93,6 +118,10 @@ impl Dialect {
         enable_tabs: true,
         enable_load_reexport: true,
         enable_top_level_stmt: true,
+        enable_bytes: true,
+        enable_f_strings: true,
+        enable_while: true,
+        enable_recursion: true,
     };
 }
 
@@ -164,6 +193,42 @@ impl Dialect {
         }
     }
 
+    pub(crate) fn check_bytes<T>(
+        &self,
+        codemap: &CodeMap,
+        x: Spanned<T>,
+    ) -> anyhow::Result<Spanned<T>> {
+        if self.enable_bytes {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::Bytes)
+        }
+    }
+
+    pub(crate) fn check_f_strings<T>(
+        &self,
+        codemap: &CodeMap,
+        x: Spanned<T>,
+    ) -> anyhow::Result<Spanned<T>> {
+        if self.enable_f_strings {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::FStrings)
+        }
+    }
+
+    pub(crate) fn check_while<T>(
+        &self,
+        codemap: &CodeMap,
+        x: Spanned<T>,
+    ) -> anyhow::Result<Spanned<T>> {
+        if self.enable_while {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::While)
+        }
+    }
+
     pub(crate) fn load_visibility(&self) -> Visibility {
         if self.enable_load_reexport {
             Visibility::Public
@@ -0,0 +1,1015 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A formatter for Starlark source code, in the style of
+//! [buildifier](https://github.com/bazelbuild/buildtools/tree/master/buildifier).
+//!
+//! The output is produced from the AST, so the layout is canonical, while comments are
+//! recovered from the lexer and reattached to the statements and collection elements they
+//! are next to.
+
+use gazebo::prelude::*;
+
+use crate::{
+    codemap::{CodeMap, Pos, Span},
+    syntax::{
+        ast::{
+            Argument, Assign, AstArgument, AstAssign, AstExpr, AstLiteral, AstLoad, AstParameter,
+            AstStmt, BinOp, Clause, Expr, ForClause, Parameter, Stmt,
+        },
+        lexer::{Lexer, Token},
+        AstModule, Dialect,
+    },
+};
+
+const INDENT: &str = "    ";
+
+// Operator precedence, from loosest to tightest binding, following the grammar.
+const PREC_LAMBDA: u8 = 0;
+const PREC_IF: u8 = 1;
+const PREC_OR: u8 = 2;
+const PREC_AND: u8 = 3;
+const PREC_NOT: u8 = 4;
+const PREC_COMPARE: u8 = 5;
+const PREC_BIT_OR: u8 = 6;
+const PREC_BIT_XOR: u8 = 7;
+const PREC_BIT_AND: u8 = 8;
+const PREC_SHIFT: u8 = 9;
+const PREC_ARITH: u8 = 10;
+const PREC_PRODUCT: u8 = 11;
+const PREC_UNARY: u8 = 12;
+const PREC_PRIMARY: u8 = 13;
+
+fn bin_op_prec(op: BinOp) -> u8 {
+    match op {
+        BinOp::Or => PREC_OR,
+        BinOp::And => PREC_AND,
+        BinOp::Equal
+        | BinOp::NotEqual
+        | BinOp::Less
+        | BinOp::Greater
+        | BinOp::LessOrEqual
+        | BinOp::GreaterOrEqual
+        | BinOp::In
+        | BinOp::NotIn => PREC_COMPARE,
+        BinOp::BitOr => PREC_BIT_OR,
+        BinOp::BitXor => PREC_BIT_XOR,
+        BinOp::BitAnd => PREC_BIT_AND,
+        BinOp::LeftShift | BinOp::RightShift => PREC_SHIFT,
+        BinOp::Add | BinOp::Subtract => PREC_ARITH,
+        BinOp::Multiply | BinOp::Percent | BinOp::Divide | BinOp::FloorDivide => PREC_PRODUCT,
+    }
+}
+
+fn expr_prec(x: &Expr) -> u8 {
+    match x {
+        Expr::Lambda(..) => PREC_LAMBDA,
+        Expr::If(..) => PREC_IF,
+        Expr::Not(..) => PREC_NOT,
+        Expr::Op(_, op, _) => bin_op_prec(*op),
+        Expr::Minus(..) | Expr::Plus(..) | Expr::BitNot(..) => PREC_UNARY,
+        _ => PREC_PRIMARY,
+    }
+}
+
+/// Prefer double quotes for a string literal, unless the contents contain a double quote,
+/// which would then need escaping. Since `\'` means `'` in both quote styles, the contents
+/// can be kept exactly as written.
+fn normalize_string(literal: &str) -> String {
+    let (prefix, rest) = match literal.strip_prefix('r') {
+        Some(rest) => ("r", rest),
+        None => ("", literal),
+    };
+    let quote = if rest.starts_with("'''") {
+        "'''"
+    } else if rest.starts_with('\'') {
+        "'"
+    } else {
+        return literal.to_owned();
+    };
+    let contents = &rest[quote.len()..rest.len() - quote.len()];
+    if contents.contains('"') {
+        return literal.to_owned();
+    }
+    let quote = if quote.len() == 3 { "\"\"\"" } else { "\"" };
+    format!("{}{}{}{}", prefix, quote, contents, quote)
+}
+
+fn flatten<'a>(x: &'a AstStmt, res: &mut Vec<&'a AstStmt>) {
+    match &x.node {
+        Stmt::Statements(xs) => {
+            for x in xs {
+                flatten(x, res);
+            }
+        }
+        _ => res.push(x),
+    }
+}
+
+fn indent(depth: usize) -> String {
+    INDENT.repeat(depth)
+}
+
+struct Comment {
+    begin: Pos,
+    line: usize,
+    column: u32,
+    /// The text of the comment, including the `#`.
+    text: String,
+    /// Is the comment the only thing on its line, rather than following some code.
+    own_line: bool,
+    emitted: bool,
+}
+
+struct Printer<'a> {
+    codemap: &'a CodeMap,
+    /// All the comments in the file, in order.
+    comments: Vec<Comment>,
+    /// The index of the first comment which has not been emitted.
+    next: usize,
+    out: String,
+    /// The source line of the last statement or comment written, so blank lines
+    /// between statements can be preserved (but collapsed to one).
+    last_line: Option<usize>,
+}
+
+impl<'a> Printer<'a> {
+    fn new(codemap: &'a CodeMap) -> Self {
+        let source = codemap.source();
+        // The module has already been parsed, so accept anything the lexer might see.
+        let comments = Lexer::new_with_comments(source, &Dialect::Extended, codemap.dupe())
+            .filter_map(|x| match x {
+                Ok((begin, Token::Comment, end)) => Some((begin, &source[begin + 1..end])),
+                _ => None,
+            })
+            .map(|(begin, text)| {
+                let begin = Pos::new(begin as u32);
+                let line = codemap.find_line(begin);
+                let line_begin = codemap.line_span(line).begin();
+                let before = &source[line_begin.get() as usize..begin.get() as usize];
+                Comment {
+                    begin,
+                    line,
+                    column: begin.get() - line_begin.get(),
+                    text: format!("#{}", text.trim_end()),
+                    own_line: before.trim().is_empty(),
+                    emitted: false,
+                }
+            })
+            .collect();
+        Self {
+            codemap,
+            comments,
+            next: 0,
+            out: String::new(),
+            last_line: None,
+        }
+    }
+
+    fn line(&self, pos: Pos) -> usize {
+        self.codemap.find_line(pos)
+    }
+
+    fn column(&self, pos: Pos) -> u32 {
+        pos.get() - self.codemap.line_span(self.line(pos)).begin().get()
+    }
+
+    /// The position of the next token at or after `pos`, skipping whitespace, comments
+    /// and separators.
+    fn next_token(&self, pos: Pos) -> Pos {
+        let source = self.codemap.source().as_bytes();
+        let mut i = pos.get() as usize;
+        while i < source.len() {
+            match source[i] {
+                b' ' | b'\t' | b'\r' | b'\n' | b'\\' | b',' | b';' => i += 1,
+                b'#' => {
+                    while i < source.len() && source[i] != b'\n' {
+                        i += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        Pos::new(i as u32)
+    }
+
+    /// The position of the next `c` at or after `pos`, skipping over comments.
+    fn find_char(&self, pos: Pos, c: u8) -> Pos {
+        let source = self.codemap.source().as_bytes();
+        let mut i = pos.get() as usize;
+        while i < source.len() && source[i] != c {
+            if source[i] == b'#' {
+                while i < source.len() && source[i] != b'\n' {
+                    i += 1;
+                }
+            } else {
+                i += 1;
+            }
+        }
+        Pos::new(i as u32)
+    }
+
+    /// The positions of the brackets around a tuple, if it was written with them.
+    /// The span of a tuple in the AST does not include the brackets.
+    fn tuple_brackets(&self, span: Span) -> Option<(Pos, Pos)> {
+        let source = self.codemap.source().as_bytes();
+        let mut open = span.begin().get() as usize;
+        while open > 0 && source[open - 1].is_ascii_whitespace() {
+            open -= 1;
+        }
+        if open == 0 || source[open - 1] != b'(' {
+            return None;
+        }
+        let close = self.next_token(span.end());
+        if source.get(close.get() as usize) != Some(&b')') {
+            return None;
+        }
+        Some((Pos::new(open as u32 - 1), close))
+    }
+
+    fn advance(&mut self) {
+        while self.next < self.comments.len() && self.comments[self.next].emitted {
+            self.next += 1;
+        }
+    }
+
+    /// Mark as emitted, and return the indices of, all the comments before `pos`
+    /// which have not yet been emitted. If `own_line` is false, only take the comments
+    /// which follow some code on the same line.
+    fn take_comments(&mut self, pos: Pos, own_line: bool) -> Vec<usize> {
+        let mut res = Vec::new();
+        for i in self.next..self.comments.len() {
+            let c = &mut self.comments[i];
+            if c.begin >= pos {
+                break;
+            }
+            if !c.emitted && (own_line || !c.own_line) {
+                c.emitted = true;
+                res.push(i);
+            }
+        }
+        self.advance();
+        res
+    }
+
+    fn has_comments(&self, begin: Pos, end: Pos) -> bool {
+        self.comments[self.next..]
+            .iter()
+            .take_while(|c| c.begin < end)
+            .any(|c| !c.emitted && c.begin >= begin)
+    }
+
+    /// Take the comment which follows the code ending at `end` on the same line,
+    /// provided it comes before `limit`.
+    fn trailing_comment(&mut self, end: Pos, limit: Pos) -> Option<String> {
+        let line = self.line(end);
+        let c = self.comments[self.next..]
+            .iter_mut()
+            .find(|c| !c.emitted && c.begin >= end)?;
+        if c.own_line || c.begin >= limit || c.line != line {
+            return None;
+        }
+        c.emitted = true;
+        let res = c.text.clone();
+        self.advance();
+        Some(res)
+    }
+
+    /// Write a blank line if the source had one before `line`, unless this is the
+    /// first thing in a block.
+    fn blank_line(&mut self, line: usize, first: &mut bool) {
+        if !*first && matches!(self.last_line, Some(last) if line > last + 1) {
+            self.out.push('\n');
+        }
+        *first = false;
+    }
+
+    fn write_comment(&mut self, i: usize, depth: usize, first: &mut bool) {
+        let line = self.comments[i].line;
+        self.blank_line(line, first);
+        self.out.push_str(&indent(depth));
+        self.out.push_str(&self.comments[i].text);
+        self.out.push('\n');
+        self.last_line = Some(line);
+    }
+
+    /// Write all the comments before `pos` on their own lines.
+    fn comments_before(&mut self, pos: Pos, depth: usize, first: &mut bool) {
+        for i in self.take_comments(pos, true) {
+            self.write_comment(i, depth, first);
+        }
+    }
+
+    fn module(&mut self, x: &AstStmt) {
+        let mut stmts = Vec::new();
+        flatten(x, &mut stmts);
+        let mut first = true;
+        for x in stmts {
+            self.stmt(x, 0, &mut first);
+        }
+        self.comments_before(Pos::new(u32::MAX), 0, &mut first);
+    }
+
+    fn suite(&mut self, x: &AstStmt, depth: usize) {
+        let mut stmts = Vec::new();
+        flatten(x, &mut stmts);
+        let mut first = true;
+        for x in &stmts {
+            self.stmt(x, depth, &mut first);
+        }
+        // Comments after the last statement belong to this block if they are indented
+        // at least as far as its statements.
+        if let (Some(head), Some(last)) = (stmts.first(), stmts.last()) {
+            let limit = self.next_token(last.span.end());
+            let column = self.column(head.span.begin());
+            while let Some(c) = self.comments.get(self.next) {
+                if c.begin >= limit || !c.own_line || c.column < column {
+                    break;
+                }
+                self.comments[self.next].emitted = true;
+                self.write_comment(self.next, depth, &mut first);
+                self.advance();
+            }
+        }
+    }
+
+    /// Write the header of a compound statement, e.g. `if x`, followed by `:` and any
+    /// comment on the same line. The comments on the line are those before the body,
+    /// while the comments on their own lines go with the first statement of the body.
+    fn header(&mut self, header: String, body: &AstStmt, depth: usize) {
+        let mut stmts = Vec::new();
+        flatten(body, &mut stmts);
+        let begin = stmts.first().map_or(body.span.begin(), |x| x.span.begin());
+        let mut comments = self.take_comments(begin, false);
+        let trailing = comments.pop();
+        for i in comments {
+            self.write_comment(i, depth, &mut false);
+        }
+        self.out.push_str(&indent(depth));
+        self.out.push_str(&header);
+        self.out.push(':');
+        if let Some(i) = trailing {
+            self.out.push_str("  ");
+            self.out.push_str(&self.comments[i].text);
+        }
+        self.out.push('\n');
+    }
+
+    fn stmt(&mut self, x: &AstStmt, depth: usize, first: &mut bool) {
+        self.comments_before(x.span.begin(), depth, first);
+        let line = self.line(x.span.begin());
+        self.blank_line(line, first);
+        self.last_line = Some(line);
+        match &x.node {
+            Stmt::If(cond, box body) => self.if_stmt("if", cond, body, None, depth),
+            Stmt::IfElse(cond, box (body, orelse)) => {
+                self.if_stmt("if", cond, body, Some(orelse), depth)
+            }
+            Stmt::For(var, box (over, body)) => {
+                let header = format!(
+                    "for {} in {}",
+                    self.assign(var, depth, true),
+                    self.expr(over, depth, PREC_LAMBDA)
+                );
+                self.header(header, body, depth);
+                self.suite(body, depth + 1);
+            }
+            Stmt::While(cond, box body) => self.if_stmt("while", cond, body, None, depth),
+            Stmt::Def(name, params, ret, body, _) => {
+                let close = self.find_char(
+                    params.last().map_or(name.span.end(), |x| x.span.end()),
+                    b')',
+                );
+                let params = self.seq(
+                    depth,
+                    ("(", ")"),
+                    (name.span.end(), close),
+                    params,
+                    |x| x.span,
+                    Self::param,
+                    false,
+                );
+                let mut header = format!("def {}{}", name.node.0, params);
+                if let Some(ret) = ret {
+                    header.push_str(" -> ");
+                    header.push_str(&self.expr(ret, depth, PREC_LAMBDA));
+                }
+                self.header(header, body, depth);
+                self.suite(body, depth + 1);
+            }
+            _ => {
+                let text = self.simple_stmt(x, depth);
+                // Any comments inside the statement that didn't find a home go before it.
+                self.comments_before(x.span.end(), depth, &mut false);
+                self.out.push_str(&indent(depth));
+                self.out.push_str(&text);
+                let limit = self.next_token(x.span.end());
+                if let Some(comment) = self.trailing_comment(x.span.end(), limit) {
+                    self.out.push_str("  ");
+                    self.out.push_str(&comment);
+                }
+                self.out.push('\n');
+                self.last_line = Some(self.line(x.span.end()));
+            }
+        }
+    }
+
+    fn if_stmt(
+        &mut self,
+        keyword: &str,
+        cond: &AstExpr,
+        body: &AstStmt,
+        orelse: Option<&AstStmt>,
+        depth: usize,
+    ) {
+        let header = format!("{} {}", keyword, self.expr(cond, depth, PREC_LAMBDA));
+        self.header(header, body, depth);
+        self.suite(body, depth + 1);
+        if let Some(orelse) = orelse {
+            let keyword = self.next_token(body.span.end());
+            self.comments_before(keyword, depth, &mut false);
+            match &orelse.node {
+                Stmt::If(cond, box body) => self.if_stmt("elif", cond, body, None, depth),
+                Stmt::IfElse(cond, box (body, orelse)) => {
+                    self.if_stmt("elif", cond, body, Some(orelse), depth)
+                }
+                _ => {
+                    self.header("else".to_owned(), orelse, depth);
+                    self.suite(orelse, depth + 1);
+                }
+            }
+        }
+    }
+
+    fn simple_stmt(&mut self, x: &AstStmt, depth: usize) -> String {
+        match &x.node {
+            Stmt::Break => "break".to_owned(),
+            Stmt::Continue => "continue".to_owned(),
+            Stmt::Pass => "pass".to_owned(),
+            Stmt::Return(None) => "return".to_owned(),
+            Stmt::Return(Some(e)) => format!("return {}", self.expr_top(e, depth)),
+            Stmt::Expression(e) => self.expr_top(e, depth),
+            Stmt::Assign(lhs, rhs) => format!(
+                "{} = {}",
+                self.assign(lhs, depth, true),
+                self.expr_top(rhs, depth)
+            ),
+            Stmt::AssignModify(lhs, op, rhs) => format!(
+                "{}{}{}",
+                self.assign(lhs, depth, true),
+                op,
+                self.expr_top(rhs, depth)
+            ),
+            Stmt::Load(load) => self.load(load, depth),
+            Stmt::Statements(_)
+            | Stmt::If(..)
+            | Stmt::IfElse(..)
+            | Stmt::For(..)
+            | Stmt::While(..)
+            | Stmt::Def(..) => unreachable!("not a simple statement"),
+        }
+    }
+
+    /// Load symbols are sorted by the symbol loaded, then by the name it is loaded as, unless
+    /// there are comments inside the `load`, which would then be moved away from the symbol
+    /// they describe.
+    fn load(&mut self, x: &AstLoad, depth: usize) -> String {
+        let span = x.span;
+        let mut items = vec![(
+            x.node.module.span,
+            normalize_string(self.codemap.source_span(x.node.module.span)),
+        )];
+        let mut args: Vec<_> = x.node.args.iter().collect();
+        if !self.has_comments(span.begin(), span.end()) {
+            args.sort_by(|a, b| (&a.1.node, &a.0.node.0).cmp(&(&b.1.node, &b.0.node.0)));
+        }
+        for (local, symbol) in args {
+            let literal = normalize_string(self.codemap.source_span(symbol.span));
+            let item = if local.node.0 == symbol.node {
+                literal
+            } else {
+                format!("{} = {}", local.node.0, literal)
+            };
+            items.push((local.span.merge(symbol.span), item));
+        }
+        let args = self.seq(
+            depth,
+            ("(", ")"),
+            (span.begin() + 4, Pos::new(span.end().get() - 1)),
+            &items,
+            |x| x.0,
+            |_, x, _| x.1.clone(),
+            false,
+        );
+        format!("load{}", args)
+    }
+
+    /// Format a bracketed sequence. If the source had the first element on a different line
+    /// to the opening bracket, the closing bracket on a different line to the last element,
+    /// any two elements on different lines, or comments inside, then write one element per
+    /// line with a trailing comma.
+    /// Otherwise write everything on one line without a trailing comma.
+    #[allow(clippy::too_many_arguments)]
+    fn seq<T>(
+        &mut self,
+        depth: usize,
+        brackets: (&str, &str),
+        positions: (Pos, Pos),
+        xs: &[T],
+        span: impl Fn(&T) -> Span,
+        render: impl Fn(&mut Self, &T, usize) -> String,
+        tuple: bool,
+    ) -> String {
+        let (open, close) = brackets;
+        let (open_pos, close_pos) = positions;
+        let comments = self.has_comments(open_pos, close_pos);
+        let multiline = comments
+            || match (xs.first(), xs.last()) {
+                (Some(head), Some(last)) => {
+                    self.line(open_pos) != self.line(span(head).begin())
+                        || self.line(span(last).end()) != self.line(close_pos)
+                }
+                _ => false,
+            }
+            || xs
+                .windows(2)
+                .any(|w| self.line(span(&w[0]).end()) != self.line(span(&w[1]).begin()));
+
+        if !multiline {
+            let items = xs.map(|x| render(self, x, depth));
+            let trailing = if tuple && items.len() == 1 { "," } else { "" };
+            return format!("{}{}{}{}", open, items.join(", "), trailing, close);
+        }
+
+        let mut res = format!("{}\n", open);
+        for (i, x) in xs.iter().enumerate() {
+            let x_span = span(x);
+            for c in self.take_comments(x_span.begin(), true) {
+                res.push_str(&indent(depth + 1));
+                res.push_str(&self.comments[c].text);
+                res.push('\n');
+            }
+            let item = render(self, x, depth + 1);
+            res.push_str(&indent(depth + 1));
+            res.push_str(&item);
+            res.push(',');
+            let limit = xs.get(i + 1).map_or(close_pos, |x| span(x).begin());
+            if let Some(comment) = self.trailing_comment(x_span.end(), limit) {
+                res.push_str("  ");
+                res.push_str(&comment);
+            }
+            res.push('\n');
+        }
+        for c in self.take_comments(close_pos, true) {
+            res.push_str(&indent(depth + 1));
+            res.push_str(&self.comments[c].text);
+            res.push('\n');
+        }
+        res.push_str(&indent(depth));
+        res.push_str(close);
+        res
+    }
+
+    /// Format a tuple. At the top of a statement (e.g. `return 1, 2`) the brackets are
+    /// kept only if they were written, elsewhere they are always required.
+    fn tuple(&mut self, span: Span, xs: &[AstExpr], depth: usize, top: bool) -> String {
+        if xs.is_empty() {
+            return "()".to_owned();
+        }
+        let positions = match self.tuple_brackets(span) {
+            Some(positions) => positions,
+            None if top => {
+                let items = xs.map(|x| self.expr(x, depth, PREC_LAMBDA));
+                let trailing = if items.len() == 1 { "," } else { "" };
+                return format!("{}{}", items.join(", "), trailing);
+            }
+            None => (span.begin(), span.end()),
+        };
+        self.seq(
+            depth,
+            ("(", ")"),
+            positions,
+            xs,
+            |x| x.span,
+            |p, x, depth| p.expr(x, depth, PREC_LAMBDA),
+            true,
+        )
+    }
+
+    fn expr_top(&mut self, x: &AstExpr, depth: usize) -> String {
+        match &x.node {
+            Expr::Tuple(xs) => self.tuple(x.span, xs, depth, true),
+            _ => self.expr(x, depth, PREC_LAMBDA),
+        }
+    }
+
+    /// Format an expression, adding brackets if it binds looser than `prec`.
+    fn expr(&mut self, x: &AstExpr, depth: usize, prec: u8) -> String {
+        let res = self.expr_unbracketed(x, depth);
+        if expr_prec(&x.node) < prec {
+            format!("({})", res)
+        } else {
+            res
+        }
+    }
+
+    fn expr_unbracketed(&mut self, x: &AstExpr, depth: usize) -> String {
+        match &x.node {
+            Expr::Tuple(xs) => self.tuple(x.span, xs, depth, false),
+            Expr::Dot(e, name) => format!("{}.{}", self.expr(e, depth, PREC_PRIMARY), name.node),
+            Expr::Call(f, args) => {
+                let f_str = self.expr(f, depth, PREC_PRIMARY);
+                let args = self.seq(
+                    depth,
+                    ("(", ")"),
+                    (f.span.end(), Pos::new(x.span.end().get() - 1)),
+                    args,
+                    |x| x.span,
+                    Self::arg,
+                    false,
+                );
+                format!("{}{}", f_str, args)
+            }
+            Expr::ArrayIndirection(box (e, i)) => format!(
+                "{}[{}]",
+                self.expr(e, depth, PREC_PRIMARY),
+                self.expr_top(i, depth)
+            ),
+            Expr::Slice(e, i1, i2, i3) => {
+                let mut res = self.expr(e, depth, PREC_PRIMARY);
+                res.push('[');
+                if let Some(i1) = i1 {
+                    res.push_str(&self.expr(i1, depth, PREC_LAMBDA));
+                }
+                res.push(':');
+                if let Some(i2) = i2 {
+                    res.push_str(&self.expr(i2, depth, PREC_LAMBDA));
+                }
+                if let Some(i3) = i3 {
+                    res.push(':');
+                    res.push_str(&self.expr(i3, depth, PREC_LAMBDA));
+                }
+                res.push(']');
+                res
+            }
+            Expr::Identifier(name, _) => name.node.clone(),
+            Expr::Lambda(params, body, _) => {
+                let params = params.map(|x| self.param(x, depth));
+                let body = self.expr(body, depth, PREC_LAMBDA);
+                if params.is_empty() {
+                    format!("lambda: {}", body)
+                } else {
+                    format!("lambda {}: {}", params.join(", "), body)
+                }
+            }
+            Expr::Literal(AstLiteral::String(s)) => {
+                normalize_string(self.codemap.source_span(s.span))
+            }
+            Expr::Literal(AstLiteral::Int(i)) => self.codemap.source_span(i.span).to_owned(),
+            Expr::Literal(AstLiteral::Float(f)) => self.codemap.source_span(f.span).to_owned(),
+            Expr::Literal(AstLiteral::Bytes(b)) => self.codemap.source_span(b.span).to_owned(),
+            // The quotes used inside the braces depend on the outer quotes, so keep it as written
+            Expr::FString(_) => self.codemap.source_span(x.span).to_owned(),
+            Expr::Not(e) => format!("not {}", self.expr(e, depth, PREC_NOT)),
+            Expr::Minus(e) => format!("-{}", self.expr(e, depth, PREC_UNARY)),
+            Expr::Plus(e) => format!("+{}", self.expr(e, depth, PREC_UNARY)),
+            Expr::BitNot(e) => format!("~{}", self.expr(e, depth, PREC_UNARY)),
+            Expr::Op(lhs, op, rhs) => {
+                let prec = bin_op_prec(*op);
+                // Comparisons don't chain, everything else is left associative.
+                let lhs_prec = if prec == PREC_COMPARE { prec + 1 } else { prec };
+                format!(
+                    "{}{}{}",
+                    self.expr(lhs, depth, lhs_prec),
+                    op,
+                    self.expr(rhs, depth, prec + 1)
+                )
+            }
+            Expr::If(box (cond, then_expr, else_expr)) => format!(
+                "{} if {} else {}",
+                self.expr(then_expr, depth, PREC_OR),
+                self.expr(cond, depth, PREC_OR),
+                self.expr(else_expr, depth, PREC_LAMBDA)
+            ),
+            Expr::List(xs) => self.seq(
+                depth,
+                ("[", "]"),
+                (x.span.begin(), Pos::new(x.span.end().get() - 1)),
+                xs,
+                |x| x.span,
+                |p, x, depth| p.expr(x, depth, PREC_LAMBDA),
+                false,
+            ),
+            Expr::Dict(xs) => self.seq(
+                depth,
+                ("{", "}"),
+                (x.span.begin(), Pos::new(x.span.end().get() - 1)),
+                xs,
+                |(k, v)| k.span.merge(v.span),
+                |p, (k, v), depth| {
+                    format!(
+                        "{}: {}",
+                        p.expr(k, depth, PREC_LAMBDA),
+                        p.expr(v, depth, PREC_LAMBDA)
+                    )
+                },
+                false,
+            ),
+            Expr::ListComprehension(e, for_, clauses) => format!(
+                "[{}{}]",
+                self.expr(e, depth, PREC_LAMBDA),
+                self.clauses(for_, clauses, depth)
+            ),
+            Expr::DictComprehension(box (k, v), for_, clauses) => format!(
+                "{{{}: {}{}}}",
+                self.expr(k, depth, PREC_LAMBDA),
+                self.expr(v, depth, PREC_LAMBDA),
+                self.clauses(for_, clauses, depth)
+            ),
+        }
+    }
+
+    fn for_clause(&mut self, x: &ForClause, depth: usize) -> String {
+        format!(
+            " for {} in {}",
+            self.assign(&x.var, depth, true),
+            self.expr(&x.over, depth, PREC_OR)
+        )
+    }
+
+    fn clauses(&mut self, for_: &ForClause, clauses: &[Clause], depth: usize) -> String {
+        let mut res = self.for_clause(for_, depth);
+        for x in clauses {
+            match x {
+                Clause::For(x) => res.push_str(&self.for_clause(x, depth)),
+                Clause::If(x) => {
+                    res.push_str(" if ");
+                    res.push_str(&self.expr(x, depth, PREC_OR));
+                }
+            }
+        }
+        res
+    }
+
+    fn assign(&mut self, x: &AstAssign, depth: usize, top: bool) -> String {
+        match &x.node {
+            Assign::Tuple(xs) => {
+                let items = xs.map(|x| self.assign(x, depth, false));
+                let trailing = if items.len() == 1 { "," } else { "" };
+                let res = format!("{}{}", items.join(", "), trailing);
+                if top && self.tuple_brackets(x.span).is_none() {
+                    res
+                } else {
+                    format!("({})", res)
+                }
+            }
+            Assign::ArrayIndirection(box (e, i)) => format!(
+                "{}[{}]",
+                self.expr(e, depth, PREC_PRIMARY),
+                self.expr_top(i, depth)
+            ),
+            Assign::Dot(e, name) => format!("{}.{}", self.expr(e, depth, PREC_PRIMARY), name.node),
+            Assign::Identifier(name) => name.node.0.clone(),
+        }
+    }
+
+    fn arg(&mut self, x: &AstArgument, depth: usize) -> String {
+        match &x.node {
+            Argument::Positional(e) => self.expr(e, depth, PREC_LAMBDA),
+            Argument::Named(name, e) => {
+                format!("{} = {}", name.node, self.expr(e, depth, PREC_LAMBDA))
+            }
+            Argument::Args(e) => format!("*{}", self.expr(e, depth, PREC_LAMBDA)),
+            Argument::KwArgs(e) => format!("**{}", self.expr(e, depth, PREC_LAMBDA)),
+        }
+    }
+
+    fn param(&mut self, x: &AstParameter, depth: usize) -> String {
+        let (prefix, name, typ, default) = match &x.node {
+            Parameter::Normal(name, typ) => ("", name, typ, None),
+            Parameter::WithDefaultValue(name, typ, default) => ("", name, typ, Some(default)),
+            Parameter::NoArgs => return "*".to_owned(),
+            Parameter::Args(name, typ) => ("*", name, typ, None),
+            Parameter::KwArgs(name, typ) => ("**", name, typ, None),
+        };
+        let mut res = format!("{}{}", prefix, name.node.0);
+        if let Some(typ) = typ {
+            res.push_str(": ");
+            res.push_str(&self.expr(typ, depth, PREC_LAMBDA));
+        }
+        if let Some(default) = default {
+            res.push_str(" = ");
+            res.push_str(&self.expr(default, depth, PREC_LAMBDA));
+        }
+        res
+    }
+}
+
+impl AstModule {
+    /// Format the module in a canonical layout, similar to that of `buildifier`:
+    /// four space indentation, double quoted strings, brackets only where needed,
+    /// sorted `load` symbols, and collections either on one line or with one element per
+    /// line and a trailing comma, depending on how they were written. Comments are preserved.
+    pub fn format(&self) -> String {
+        let mut printer = Printer::new(&self.codemap);
+        printer.module(&self.statement);
+        printer.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::{AstModule, Dialect};
+
+    fn format(program: &str) -> String {
+        let res = AstModule::parse("test.star", program.to_owned(), &Dialect::Extended)
+            .unwrap()
+            .format();
+        // Formatting should be idempotent.
+        let again = AstModule::parse("test.star", res.clone(), &Dialect::Extended)
+            .unwrap()
+            .format();
+        assert_eq!(res, again, "Formatting is not idempotent");
+        res
+    }
+
+    #[test]
+    fn test_format_layout() {
+        assert_eq!(
+            format(
+                r#"
+def f(x, y=1, *args, **kwargs):
+  if x: return 'a'
+  elif y:
+          pass
+  else:
+    for (a, b) in zip(x,y):
+      print(a , b)
+  return (x + y) * 2, -(x), (y)
+"#
+            ),
+            r#"def f(x, y = 1, *args, **kwargs):
+    if x:
+        return "a"
+    elif y:
+        pass
+    else:
+        for (a, b) in zip(x, y):
+            print(a, b)
+    return (x + y) * 2, -x, y
+"#
+        );
+    }
+
+    #[test]
+    fn test_format_brackets() {
+        assert_eq!(
+            format("x = a + (b + c) - (d * e)\ny = (a - b) - c\nz = not (a and b) or c\n"),
+            "x = a + (b + c) - d * e\ny = a - b - c\nz = not (a and b) or c\n"
+        );
+        assert_eq!(
+            format("x = (1, 2)\ny = 1, 2\nf((1,))\na, b = b, a\n"),
+            "x = (1, 2)\ny = 1, 2\nf((1,))\na, b = b, a\n"
+        );
+        assert_eq!(
+            format("x = (lambda: 1)()\ny = (a if b else c).d\n"),
+            "x = (lambda: 1)()\ny = (a if b else c).d\n"
+        );
+    }
+
+    #[test]
+    fn test_format_strings() {
+        assert_eq!(
+            format(
+                r#"
+a = 'x'
+b = 'it\'s'
+c = 'say "hi"'
+d = r'\d+'
+e = '''doc'''
+"#
+            ),
+            r#"a = "x"
+b = "it\'s"
+c = 'say "hi"'
+d = r"\d+"
+e = """doc"""
+"#
+        );
+    }
+
+    #[test]
+    fn test_format_collections() {
+        assert_eq!(
+            format(
+                r#"
+x = [1, 2,]
+y = [
+  1, 2]
+z = {'a': 1,
+     'b': [3,
+           4]}
+f(a,
+  b = 2)
+"#
+            ),
+            r#"x = [1, 2]
+y = [
+    1,
+    2,
+]
+z = {
+    "a": 1,
+    "b": [
+        3,
+        4,
+    ],
+}
+f(
+    a,
+    b = 2,
+)
+"#
+        );
+    }
+
+    #[test]
+    fn test_format_load() {
+        assert_eq!(
+            format("load('foo.star', 'c', x = 'b', 'a')\nx = 1\n"),
+            "load(\"foo.star\", \"a\", x = \"b\", \"c\")\nx = 1\n"
+        );
+    }
+
+    #[test]
+    fn test_format_load_sorted_by_symbol() {
+        // The local names are in the opposite order to the symbols
+        assert_eq!(
+            format("load('foo.star', a = 'z', y = 'b', z = 'a')\n"),
+            "load(\"foo.star\", z = \"a\", y = \"b\", a = \"z\")\n"
+        );
+        assert_eq!(
+            format("load('foo.star', y = 'a', x = 'a')\n"),
+            "load(\"foo.star\", x = \"a\", y = \"a\")\n"
+        );
+    }
+
+    #[test]
+    fn test_format_comments() {
+        assert_eq!(
+            format(
+                r#"
+#!/usr/bin/env starlark
+# Leading comment
+
+x = 1 # trailing
+def f(): # on def
+    # inside
+    y = [ # on bracket
+        1, # one
+        # before two
+        2,
+    ]
+
+
+    return y
+    # end of f
+
+# end of file
+"#
+            ),
+            r#"#!/usr/bin/env starlark
+# Leading comment
+
+x = 1  # trailing
+def f():  # on def
+    # inside
+    y = [
+        # on bracket
+        1,  # one
+        # before two
+        2,
+    ]
+
+    return y
+    # end of f
+
+# end of file
+"#
+        );
+    }
+}
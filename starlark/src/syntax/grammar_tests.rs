@@ -99,6 +99,7 @@ fn test_top_level_statements() {
     a.dialect_set(|x| x.enable_top_level_stmt = false);
     a.parse_fail("x = 1\n!if x == 1:\n  x = 2\n!x = 3");
     a.parse_fail("x = 1\n!for x in []:\n   pass\n!");
+    a.parse_fail("x = 1\n!while x:\n   pass\n!");
     assert_eq!(a.parse("pass"), "pass\n");
 
     assert_eq!(
@@ -126,6 +127,14 @@ def toto():
     );
 }
 
+#[test]
+fn test_while() {
+    assert_eq!(
+        assert::parse("def d():\n  while x < 3:\n    x += 1\n    continue"),
+        "def d():\n  while (x < 3):\n    x += 1\n    continue\n"
+    );
+}
+
 #[test]
 fn test_ifelse() {
     assert_eq!(
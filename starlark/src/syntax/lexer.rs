@@ -15,19 +15,22 @@
  * limitations under the License.
  */
 
-use std::{char, collections::VecDeque, fmt, fmt::Display};
+use std::{char, collections::VecDeque, fmt, fmt::Display, mem};
 
+use gazebo::dupe::Dupe;
 use logos::Logos;
+use num_bigint::BigInt;
 use thiserror::Error;
 
 use crate::{
     codemap::{CodeMap, Pos, Span},
     errors::Diagnostic,
     syntax::{
+        ast::{FStringAlign, FStringFormat},
         cursors::{CursorBytes, CursorChars},
         dialect::Dialect,
     },
-    values::StarlarkValue,
+    values::{bytes::write_bytes_literal, StarlarkValue},
 };
 
 #[derive(Error, Debug)]
@@ -46,12 +49,44 @@ pub(crate) enum LexemeError {
     ReservedKeyword(String),
     #[error("Parse error: integer cannot have leading 0, got `{0}`")]
     StartsZero(String),
-    #[error("Parse error: integer overflow, must fit in 32 bits, got `{0}`")]
-    IntOverflow(String),
+    #[error("Parse error: unfinished f-string expression, expected `}}`")]
+    UnfinishedFStringExpression,
+    #[error("Parse error: empty expression in f-string")]
+    EmptyFStringExpression,
+    #[error("Parse error: single `}}` in f-string, use `}}}}` for a literal brace")]
+    FStringClosingBrace,
+    #[error("Parse error: invalid f-string conversion, only `!s` and `!r` are allowed")]
+    InvalidFStringConversion,
+    #[error("Parse error: invalid f-string format spec `{0}`, expected `[[fill]align][width]`")]
+    InvalidFStringSpec(String),
+    #[error(
+        "Parse error: f-string width {0} is too large, the maximum is {}",
+        FSTRING_MAX_WIDTH
+    )]
+    FStringWidthTooLarge(usize),
 }
 
+/// The largest width allowed in an f-string format spec, so that padding can't exhaust memory.
+const FSTRING_MAX_WIDTH: usize = 10000;
+
 type Lexeme = anyhow::Result<(usize, Token, usize)>;
 
+/// Integer literal, boxed when it doesn't fit into `i32` to keep the AST small.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenInt {
+    I32(i32),
+    BigInt(Box<BigInt>),
+}
+
+impl Display for TokenInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenInt::I32(i) => write!(f, "{}", i),
+            TokenInt::BigInt(i) => write!(f, "{}", i),
+        }
+    }
+}
+
 pub(crate) struct Lexer<'a> {
     // Information for spans
     codemap: CodeMap,
@@ -63,10 +98,22 @@ pub(crate) struct Lexer<'a> {
     lexer: logos::Lexer<'a, Token>,
     done: bool,
     dialect_allow_tabs: bool,
+    /// Whether to produce [`Token::Comment`] rather than skipping comments.
+    emit_comments: bool,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str, dialect: &Dialect, codemap: CodeMap) -> Self {
+        Self::new_impl(input, dialect, codemap, false)
+    }
+
+    /// Like [`new`](Lexer::new), but also produce a [`Token::Comment`] for every comment.
+    /// The parser does not accept these tokens, but the formatter needs them.
+    pub fn new_with_comments(input: &'a str, dialect: &Dialect, codemap: CodeMap) -> Self {
+        Self::new_impl(input, dialect, codemap, true)
+    }
+
+    fn new_impl(input: &'a str, dialect: &Dialect, codemap: CodeMap, emit_comments: bool) -> Self {
         let lexer = Token::lexer(input);
         let mut lexer2 = Self {
             codemap,
@@ -77,6 +124,7 @@ impl<'a> Lexer<'a> {
             parens: 0,
             done: false,
             dialect_allow_tabs: dialect.enable_tabs,
+            emit_comments,
         };
         if let Err(e) = lexer2.calculate_indent() {
             lexer2.buffer.push_back(Err(e));
@@ -104,6 +152,16 @@ impl<'a> Lexer<'a> {
         )
     }
 
+    /// Record a comment which `calculate_indent` skipped over, from the `#` at `start`
+    /// up to the end of the line at `end`.
+    fn line_comment(&mut self, start: usize, end: usize) {
+        if self.emit_comments {
+            let text = self.lexer.source()[start..end].trim_end_matches('\r');
+            self.buffer
+                .push_back(Ok((start, Token::Comment, start + text.len())));
+        }
+    }
+
     /// We have just seen a newline, read how many indents we have
     /// and then set self.indent properly
     fn calculate_indent(&mut self) -> anyhow::Result<()> {
@@ -139,9 +197,12 @@ impl<'a> Lexer<'a> {
                     // Remove skip now, so we can freely add it on later
                     spaces = 0;
                     tabs = 0;
+                    let base = self.lexer.span().end;
+                    let comment_start = base + it.pos() - 1;
                     loop {
                         match it.next_char() {
                             None => {
+                                self.line_comment(comment_start, base + it.pos());
                                 self.lexer.bump(it.pos());
                                 return Ok(());
                             }
@@ -149,6 +210,7 @@ impl<'a> Lexer<'a> {
                             Some(_) => {}
                         }
                     }
+                    self.line_comment(comment_start, base + it.pos() - 1);
                     indent_start = self.lexer.span().end + it.pos();
                 }
                 _ => break,
@@ -195,9 +257,23 @@ impl<'a> Lexer<'a> {
         Some(Ok((span.start, token, span.end)))
     }
 
+    fn push_char(res: &mut Vec<u8>, c: char) {
+        res.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    // A numeric escape is a single byte in a bytes literal, but a code point in a string
+    fn push_escape(res: &mut Vec<u8>, bytes: bool, value: u32) -> Result<(), ()> {
+        if bytes {
+            res.push(u8::try_from(value).map_err(|_| ())?);
+        } else {
+            Self::push_char(res, char::from_u32(value).ok_or(())?);
+        }
+        Ok(())
+    }
+
     // We've potentially seen one character, now consume between min and max elements of iterator
     // and treat it as an int in base radix
-    fn escape_char(it: &mut CursorChars, min: usize, max: usize, radix: u32) -> Result<char, ()> {
+    fn escape_char(it: &mut CursorChars, min: usize, max: usize, radix: u32) -> Result<u32, ()> {
         let mut value = 0u32;
         let mut count = 0;
         while count < max {
@@ -225,19 +301,19 @@ impl<'a> Lexer<'a> {
                 },
             }
         }
-        char::from_u32(value).ok_or(())
+        Ok(value)
     }
 
     // We have seen a '\' character, now parse what comes next
-    fn escape(it: &mut CursorChars, res: &mut String) -> Result<(), ()> {
+    fn escape(it: &mut CursorChars, bytes: bool, res: &mut Vec<u8>) -> Result<(), ()> {
         match it.next() {
-            Some('n') => res.push('\n'),
-            Some('r') => res.push('\r'),
-            Some('t') => res.push('\t'),
-            Some('a') => res.push('\x07'),
-            Some('b') => res.push('\x08'),
-            Some('f') => res.push('\x0C'),
-            Some('v') => res.push('\x0B'),
+            Some('n') => res.push(b'\n'),
+            Some('r') => res.push(b'\r'),
+            Some('t') => res.push(b'\t'),
+            Some('a') => res.push(b'\x07'),
+            Some('b') => res.push(b'\x08'),
+            Some('f') => res.push(b'\x0C'),
+            Some('v') => res.push(b'\x0B'),
             Some('\n') => {}
             Some('\r') => {
                 // Windows newline incoming, we expect a \n next, which we can ignore
@@ -246,18 +322,18 @@ impl<'a> Lexer<'a> {
                     return Err(());
                 }
             }
-            Some('x') => res.push(Self::escape_char(it, 2, 2, 16)?),
-            Some('u') => res.push(Self::escape_char(it, 4, 4, 16)?),
-            Some('U') => res.push(Self::escape_char(it, 8, 8, 16)?),
+            Some('x') => Self::push_escape(res, bytes, Self::escape_char(it, 2, 2, 16)?)?,
+            Some('u') => Self::push_escape(res, false, Self::escape_char(it, 4, 4, 16)?)?,
+            Some('U') => Self::push_escape(res, false, Self::escape_char(it, 8, 8, 16)?)?,
             Some(c) => match c {
                 '0'..='7' => {
                     it.unnext(c);
-                    res.push(Self::escape_char(it, 1, 3, 8)?)
+                    Self::push_escape(res, bytes, Self::escape_char(it, 1, 3, 8)?)?
                 }
-                '"' | '\'' | '\\' => res.push(c),
+                '"' | '\'' | '\\' => res.push(c as u8),
                 _ => {
-                    res.push('\\');
-                    res.push(c);
+                    res.push(b'\\');
+                    Self::push_char(res, c);
                 }
             },
             None => {
@@ -269,9 +345,16 @@ impl<'a> Lexer<'a> {
 
     // String parsing is a hot-spot, so parameterise by a `stop` function which gets
     // specialised for each variant
-    fn string(&mut self, triple: bool, raw: bool, mut stop: impl FnMut(char) -> bool) -> Lexeme {
+    fn string(
+        &mut self,
+        triple: bool,
+        raw: bool,
+        bytes: bool,
+        mut stop: impl FnMut(char) -> bool,
+    ) -> Lexeme {
         // We have seen an openning quote, which is either ' or "
         // If triple is true, it was a triple quote
+        // If bytes is true, we produce a bytes literal rather than a string
         // stop lets us know when a string ends.
 
         // Before the first quote character
@@ -303,15 +386,18 @@ impl<'a> Lexer<'a> {
                     if stop(c) {
                         let contents_end = it.pos() - if triple { 3 } else { 1 };
                         let contents = &self.lexer.remainder()[contents_start..contents_end];
+                        let token = if bytes {
+                            Token::Bytes(contents.as_bytes().to_vec())
+                        } else {
+                            Token::String(contents.to_owned())
+                        };
                         self.lexer.bump(it.pos());
-                        return Ok((
-                            string_start,
-                            Token::String(contents.to_owned()),
-                            string_end + it.pos(),
-                        ));
+                        return Ok((string_start, token, string_end + it.pos()));
                     } else if c == '\\' || c == '\r' || (c == '\n' && !triple) {
-                        res = String::with_capacity(it.pos() + 10);
-                        res.push_str(&self.lexer.remainder()[contents_start..it.pos() - 1]);
+                        res = Vec::with_capacity(it.pos() + 10);
+                        res.extend_from_slice(
+                            self.lexer.remainder()[contents_start..it.pos() - 1].as_bytes(),
+                        );
                         it2 = CursorChars::new_offset(self.lexer.remainder(), it.pos() - 1);
                         break;
                     }
@@ -328,7 +414,13 @@ impl<'a> Lexer<'a> {
                 if triple {
                     res.truncate(res.len() - 2);
                 }
-                return Ok((string_start, Token::String(res), string_end + it.pos()));
+                let token = if bytes {
+                    Token::Bytes(res)
+                } else {
+                    // We only ever push whole characters into a string
+                    Token::String(String::from_utf8(res).unwrap())
+                };
+                return Ok((string_start, token, string_end + it.pos()));
             }
             match c {
                 '\n' if !triple => {
@@ -345,15 +437,15 @@ impl<'a> Lexer<'a> {
                         match it.next() {
                             Some(c) => {
                                 if c != '\'' && c != '"' {
-                                    res.push('\\');
+                                    res.push(b'\\');
                                 }
-                                res.push(c);
+                                Self::push_char(&mut res, c);
                             }
                             _ => break, // Out of chars
                         }
                     } else {
                         let pos = it.pos();
-                        if Self::escape(&mut it, &mut res).is_err() {
+                        if Self::escape(&mut it, bytes, &mut res).is_err() {
                             return self.err_span(
                                 LexemeError::InvalidEscapeSequence(
                                     self.lexer.remainder()[pos..it.pos()].to_owned(),
@@ -364,7 +456,7 @@ impl<'a> Lexer<'a> {
                         }
                     }
                 }
-                c => res.push(c),
+                c => Self::push_char(&mut res, c),
             }
         }
 
@@ -376,6 +468,274 @@ impl<'a> Lexer<'a> {
         )
     }
 
+    fn double_quote(&mut self, raw: bool, bytes: bool) -> Lexeme {
+        if self.lexer.remainder().starts_with("\"\"") {
+            let mut qs = 0;
+            self.string(true, raw, bytes, |c| {
+                if c == '\"' {
+                    qs += 1;
+                    qs == 3
+                } else {
+                    qs = 0;
+                    false
+                }
+            })
+        } else {
+            self.string(false, raw, bytes, |c| c == '\"')
+        }
+    }
+
+    fn single_quote(&mut self, raw: bool, bytes: bool) -> Lexeme {
+        if self.lexer.remainder().starts_with("''") {
+            let mut qs = 0;
+            self.string(true, raw, bytes, |c| {
+                if c == '\'' {
+                    qs += 1;
+                    qs == 3
+                } else {
+                    qs = 0;
+                    false
+                }
+            })
+        } else {
+            self.string(false, raw, bytes, |c| c == '\'')
+        }
+    }
+
+    /// We have seen the opening quote of an f-string. Push the tokens for its text and for
+    /// each interpolated expression, and return the [`Token::FStringStart`] which precedes them.
+    fn fstring(&mut self, raw: bool, quote: char) -> Lexeme {
+        let triple = self
+            .lexer
+            .remainder()
+            .starts_with(if quote == '"' { "\"\"" } else { "''" });
+        let content_start = self.lexer.span().end + if triple { 2 } else { 0 };
+        // Find the end of the literal. Lex it as raw, since we resolve escapes piece by piece.
+        let (start, _, end) = if quote == '"' {
+            self.double_quote(true, false)?
+        } else {
+            self.single_quote(true, false)?
+        };
+        let content_end = end - if triple { 3 } else { 1 };
+
+        let bytes = self.lexer.source().as_bytes();
+        let mut text = String::new();
+        // Where the text of the next `FStringText` token starts
+        let mut text_start = content_start;
+        // Where the source we have not yet added to `text` starts
+        let mut piece_start = content_start;
+        let mut i = content_start;
+        while i < content_end {
+            match bytes[i] {
+                b'\\' => {
+                    // Skip the escaped character, unless it is a brace which must still be seen
+                    i += 1;
+                    if i < content_end && bytes[i] != b'{' && bytes[i] != b'}' {
+                        i += 1;
+                    }
+                }
+                b'{' | b'}' if bytes[i + 1] == bytes[i] => {
+                    self.fstring_text(&mut text, piece_start, i + 1, raw)?;
+                    i += 2;
+                    piece_start = i;
+                }
+                b'}' => return self.err_span(LexemeError::FStringClosingBrace, i, i + 1),
+                b'{' => {
+                    self.fstring_text(&mut text, piece_start, i, raw)?;
+                    if !text.is_empty() {
+                        let text = mem::take(&mut text);
+                        self.buffer
+                            .push_back(Ok((text_start, Token::FStringText(text), i)));
+                    }
+                    i = self.fstring_expr(i, content_end)?;
+                    text_start = i;
+                    piece_start = i;
+                }
+                _ => i += 1,
+            }
+        }
+        self.fstring_text(&mut text, piece_start, content_end, raw)?;
+        if !text.is_empty() {
+            self.buffer
+                .push_back(Ok((text_start, Token::FStringText(text), content_end)));
+        }
+        self.buffer
+            .push_back(Ok((content_end, Token::FStringEnd, end)));
+        Ok((start, Token::FStringStart, content_start))
+    }
+
+    /// Append the f-string text between `start` and `end` to `text`, resolving escapes.
+    fn fstring_text(
+        &self,
+        text: &mut String,
+        start: usize,
+        end: usize,
+        raw: bool,
+    ) -> anyhow::Result<()> {
+        let source = self.lexer.source();
+        let mut res = Vec::with_capacity(end - start);
+        let mut it = CursorChars::new_offset(&source[..end], start);
+        while let Some(c) = it.next() {
+            match c {
+                '\r' => {}
+                '\\' if raw => match it.next() {
+                    Some(c) => {
+                        if c != '\'' && c != '"' {
+                            res.push(b'\\');
+                        }
+                        Self::push_char(&mut res, c);
+                    }
+                    None => res.push(b'\\'),
+                },
+                '\\' => {
+                    let pos = it.pos();
+                    if Self::escape(&mut it, false, &mut res).is_err() {
+                        return self.err_span(
+                            LexemeError::InvalidEscapeSequence(source[pos..it.pos()].to_owned()),
+                            pos - 1,
+                            it.pos(),
+                        );
+                    }
+                }
+                c => Self::push_char(&mut res, c),
+            }
+        }
+        // We only ever push whole characters
+        text.push_str(std::str::from_utf8(&res).unwrap());
+        Ok(())
+    }
+
+    /// Push the tokens for the f-string expression whose `{` is at `open`, returning the
+    /// position after its closing `}`.
+    fn fstring_expr(&mut self, open: usize, content_end: usize) -> anyhow::Result<usize> {
+        let source = self.lexer.source();
+        let bytes = source.as_bytes();
+
+        // Find the end of the expression, skipping over brackets and string literals
+        let mut depth = 0;
+        let mut i = open + 1;
+        let expr_end = loop {
+            if i >= content_end {
+                return self.err_span(LexemeError::UnfinishedFStringExpression, open, i);
+            }
+            match bytes[i] {
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' => depth -= 1,
+                b'}' if depth > 0 => depth -= 1,
+                b'}' => break i,
+                b'!' if depth == 0 && bytes[i + 1] != b'=' => break i,
+                b':' if depth == 0 => break i,
+                q @ (b'\'' | b'"') => {
+                    i += 1;
+                    while i < content_end && bytes[i] != q {
+                        if bytes[i] == b'\\' {
+                            i += 1;
+                        }
+                        i += 1;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        };
+        if source[open + 1..expr_end].trim().is_empty() {
+            return self.err_span(LexemeError::EmptyFStringExpression, open, expr_end + 1);
+        }
+
+        let mut format = FStringFormat::default();
+        let mut i = expr_end;
+        if bytes[i] == b'!' {
+            match bytes[i + 1] {
+                b's' => {}
+                b'r' => format.repr = true,
+                _ => return self.err_span(LexemeError::InvalidFStringConversion, i, i + 2),
+            }
+            i += 2;
+        }
+        if i < content_end && bytes[i] == b':' {
+            let spec_start = i + 1;
+            while i < content_end && bytes[i] != b'}' {
+                i += 1;
+            }
+            let spec = &source[spec_start..i];
+            if Self::fstring_spec(spec, &mut format).is_err() {
+                return self.err_span(
+                    LexemeError::InvalidFStringSpec(spec.to_owned()),
+                    spec_start,
+                    i,
+                );
+            }
+            if format.width > FSTRING_MAX_WIDTH {
+                return self.err_span(
+                    LexemeError::FStringWidthTooLarge(format.width),
+                    spec_start,
+                    i,
+                );
+            }
+        }
+        if i >= content_end || bytes[i] != b'}' {
+            return self.err_span(LexemeError::UnfinishedFStringExpression, open, i);
+        }
+
+        self.buffer
+            .push_back(Ok((open, Token::FStringExprStart, open + 1)));
+        let mut inner = Lexer {
+            codemap: self.codemap.dupe(),
+            indent_levels: Vec::new(),
+            buffer: VecDeque::new(),
+            lexer: Token::lexer(&source[..expr_end]),
+            // Like inside brackets, newlines are not significant
+            parens: 1,
+            done: false,
+            dialect_allow_tabs: self.dialect_allow_tabs,
+            emit_comments: false,
+        };
+        inner.lexer.bump(open + 1);
+        while let Some(x) = inner.next() {
+            let x = x?;
+            // Skip the newline produced at the end of the expression
+            if !(inner.done && x.1 == Token::Newline) {
+                self.buffer.push_back(Ok(x));
+            }
+        }
+        self.buffer
+            .push_back(Ok((expr_end, Token::FStringExprEnd(format), i + 1)));
+        Ok(i + 1)
+    }
+
+    // Parse the `[[fill]align][width]` spec after the `:` in an f-string expression
+    fn fstring_spec(spec: &str, format: &mut FStringFormat) -> Result<(), ()> {
+        fn align(c: char) -> Option<FStringAlign> {
+            match c {
+                '<' => Some(FStringAlign::Left),
+                '>' => Some(FStringAlign::Right),
+                '^' => Some(FStringAlign::Center),
+                _ => None,
+            }
+        }
+
+        let mut chars = spec.chars();
+        let width = match (chars.next(), chars.next()) {
+            (Some(fill), Some(c)) if align(c).is_some() => {
+                format.fill = fill;
+                format.align = align(c);
+                chars.as_str()
+            }
+            (Some(c), _) if align(c).is_some() => {
+                format.align = align(c);
+                &spec[1..]
+            }
+            _ => spec,
+        };
+        if !width.is_empty() {
+            if !width.bytes().all(|x| x.is_ascii_digit()) {
+                return Err(());
+            }
+            format.width = width.parse().map_err(|_| ())?;
+        }
+        Ok(())
+    }
+
     pub fn next(&mut self) -> Option<Lexeme> {
         loop {
             // Note that this function doesn't always return - a few branches use `continue`
@@ -415,6 +775,13 @@ impl<'a> Lexer<'a> {
                                 continue;
                             }
                         }
+                        Token::Comment => {
+                            if self.emit_comments {
+                                self.wrap(token)
+                            } else {
+                                continue;
+                            }
+                        }
                         Token::Reserved => Some(self.err_now(LexemeError::ReservedKeyword)),
                         Token::Error => Some(self.err_now(LexemeError::InvalidInput)),
                         Token::Int(radix) => {
@@ -427,50 +794,40 @@ impl<'a> Lexer<'a> {
                                 // Skip the 0x prefix
                                 s = &s[2..];
                             }
-                            match i32::from_str_radix(s, radix as u32) {
-                                Ok(i) => {
-                                    let span = self.lexer.span();
-                                    Some(Ok((span.start, Token::Int(i), span.end)))
-                                }
+                            let token = match i32::from_str_radix(s, radix as u32) {
+                                Ok(i) => Token::Int(i),
                                 Err(_) => {
                                     // Because we validated the characters going in, it must have been an overflow
-                                    Some(self.err_now(LexemeError::IntOverflow))
+                                    Token::BigInt(
+                                        BigInt::parse_bytes(s.as_bytes(), radix as u32).unwrap(),
+                                    )
                                 }
-                            }
+                            };
+                            self.wrap(token)
                         }
                         Token::RawDoubleQuote => {
                             let raw = self.lexer.span().len() == 2;
-                            if self.lexer.remainder().starts_with("\"\"") {
-                                let mut qs = 0;
-                                Some(self.string(true, raw, |c| {
-                                    if c == '\"' {
-                                        qs += 1;
-                                        qs == 3
-                                    } else {
-                                        qs = 0;
-                                        false
-                                    }
-                                }))
-                            } else {
-                                Some(self.string(false, raw, |c| c == '\"'))
-                            }
+                            Some(self.double_quote(raw, false))
                         }
                         Token::RawSingleQuote => {
                             let raw = self.lexer.span().len() == 2;
-                            if self.lexer.remainder().starts_with("''") {
-                                let mut qs = 0;
-                                Some(self.string(true, raw, |c| {
-                                    if c == '\'' {
-                                        qs += 1;
-                                        qs == 3
-                                    } else {
-                                        qs = 0;
-                                        false
-                                    }
-                                }))
-                            } else {
-                                Some(self.string(false, raw, |c| c == '\''))
-                            }
+                            Some(self.single_quote(raw, false))
+                        }
+                        Token::BytesDoubleQuote => {
+                            let raw = self.lexer.span().len() == 3;
+                            Some(self.double_quote(raw, true))
+                        }
+                        Token::BytesSingleQuote => {
+                            let raw = self.lexer.span().len() == 3;
+                            Some(self.single_quote(raw, true))
+                        }
+                        Token::FStringDoubleQuote => {
+                            let raw = self.lexer.span().len() == 3;
+                            Some(self.fstring(raw, '"'))
+                        }
+                        Token::FStringSingleQuote => {
+                            let raw = self.lexer.span().len() == 3;
+                            Some(self.fstring(raw, '\''))
                         }
                         Token::OpeningCurly | Token::OpeningRound | Token::OpeningSquare => {
                             self.parens += 1;
@@ -494,10 +851,14 @@ pub enum Token {
     #[regex(" +", logos::skip)] // Whitespace
     #[token("\\\n", logos::skip)] // Escaped newline
     #[token("\\\r\n", logos::skip)] // Escaped newline (Windows line ending)
-    #[regex(r#"#[^\n]*"#, logos::skip)] // Comments
     #[error]
     Error,
 
+    // A comment, only produced on request. Its text is the source of its span, starting
+    // with the `#`, so lexing doesn't allocate for every comment.
+    #[regex(r#"#[^\r\n]*"#)]
+    Comment,
+
     #[regex("\t+")] // Tabs (might be an error)
     Tabs,
 
@@ -515,10 +876,24 @@ pub enum Token {
     #[token("\"")]
     #[token("r\"")]
     RawDoubleQuote,
+    #[token("b'")]
+    #[token("br'")]
+    #[token("rb'")]
+    BytesSingleQuote,
+    #[token("b\"")]
+    #[token("br\"")]
+    #[token("rb\"")]
+    BytesDoubleQuote,
+    #[token("f'")]
+    #[token("fr'")]
+    #[token("rf'")]
+    FStringSingleQuote,
+    #[token("f\"")]
+    #[token("fr\"")]
+    #[token("rf\"")]
+    FStringDoubleQuote,
 
-    #[regex(
-        "as|import|is|class|nonlocal|del|raise|except|try|finally|while|from|with|global|yield"
-    )]
+    #[regex("as|import|is|class|nonlocal|del|raise|except|try|finally|from|with|global|yield")]
     Reserved, // One of the reserved keywords
 
     #[regex(
@@ -531,13 +906,20 @@ pub enum Token {
     #[regex("0[bB][01]+", |_| 2)]
     #[regex("0[oO][0-7]+", |_| 8)]
     Int(i32), // An integer literal (123, 0x1, 0b1011, 0o755, ...)
+    BigInt(BigInt), // An integer literal which doesn't fit into `i32`
 
     #[regex("[0-9]+\\.[0-9]*([eE][-+]?[0-9]+)?", |lex| lex.slice().parse::<f64>())]
     #[regex("[0-9]+[eE][-+]?[0-9]+", |lex| lex.slice().parse::<f64>())]
     #[regex("\\.[0-9]+([eE][-+]?[0-9]+)?", |lex| lex.slice().parse::<f64>())]
     Float(f64), // A float literal (3.14, .3, 1e6, 0.)
 
-    String(String), // A string literal
+    String(String),                // A string literal
+    Bytes(Vec<u8>),                // A bytes literal
+    FStringStart,                  // The start of an f-string literal
+    FStringText(String),           // Literal text in an f-string
+    FStringExprStart,              // The `{` before an expression in an f-string
+    FStringExprEnd(FStringFormat), // The format and `}` after an expression in an f-string
+    FStringEnd,                    // The end of an f-string literal
 
     // Keywords
     #[token("and")]
@@ -570,6 +952,8 @@ pub enum Token {
     Return,
     #[token("lambda")]
     Lambda,
+    #[token("while")]
+    While,
     // Symbols
     #[token(",")]
     Comma,
@@ -666,6 +1050,8 @@ impl Token {
             Token::Indent => "\t".to_owned(),
             Token::Newline => "\n".to_owned(),
             Token::Dedent => "#dedent".to_owned(),
+            Token::FStringStart => "f\"".to_owned(),
+            Token::FStringEnd => "\"".to_owned(),
             Token::String(x) => {
                 // The Rust {:?} is unstable, so changes between versions,
                 // instead use the JSON standard for string escapes.
@@ -712,6 +1098,7 @@ impl Display for Token {
             Token::Elif => write!(f, "keyword 'elif'"),
             Token::Return => write!(f, "keyword 'return'"),
             Token::Lambda => write!(f, "keyword 'lambda'"),
+            Token::While => write!(f, "keyword 'while'"),
             Token::Comma => write!(f, "symbol ','"),
             Token::Semicolon => write!(f, "symbol ';'"),
             Token::Colon => write!(f, "symbol ':'"),
@@ -757,10 +1144,26 @@ impl Display for Token {
             Token::Reserved => write!(f, "reserved keyword"),
             Token::Identifier(s) => write!(f, "identifier '{}'", s),
             Token::Int(i) => write!(f, "integer literal '{}'", i),
+            Token::BigInt(i) => write!(f, "integer literal '{}'", i),
             Token::Float(n) => write!(f, "float literal '{}'", n),
             Token::String(s) => write!(f, "string literal '{}'", s),
+            Token::Bytes(s) => {
+                write!(f, "bytes literal '")?;
+                write_bytes_literal(f, s)?;
+                write!(f, "'")
+            }
+            Token::Comment => write!(f, "comment"),
             Token::RawSingleQuote => write!(f, "starting '"),
             Token::RawDoubleQuote => write!(f, "starting \""),
+            Token::BytesSingleQuote => write!(f, "starting b'"),
+            Token::BytesDoubleQuote => write!(f, "starting b\""),
+            Token::FStringSingleQuote => write!(f, "starting f'"),
+            Token::FStringDoubleQuote => write!(f, "starting f\""),
+            Token::FStringStart => write!(f, "start of f-string"),
+            Token::FStringText(s) => write!(f, "f-string text '{}'", s),
+            Token::FStringExprStart => write!(f, "f-string symbol '{{'"),
+            Token::FStringExprEnd(format) => write!(f, "f-string symbol '{}}}'", format),
+            Token::FStringEnd => write!(f, "end of f-string"),
             Token::Tabs => Ok(()),
         }
     }
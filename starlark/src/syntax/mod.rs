@@ -30,6 +30,7 @@ mod testcases;
 pub(crate) mod ast;
 pub(crate) mod cursors;
 mod dialect;
+mod format;
 pub(crate) mod lexer;
 pub(crate) mod payload_map;
 pub(crate) mod validate;
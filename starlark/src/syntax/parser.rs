@@ -20,12 +20,13 @@ use std::{fs, path::Path};
 use anyhow::anyhow;
 use gazebo::prelude::*;
 use lalrpop_util as lu;
+use once_cell::sync::OnceCell;
 
 use crate::{
     codemap::{CodeMap, FileSpan, Pos, Span},
     errors::Diagnostic,
     syntax::{
-        ast::{AstModule, AstStmt, Stmt},
+        ast::{AstLoad, AstModule, AstStmt, Stmt},
         dialect::Dialect,
         grammar::StarlarkParser,
         lexer::{Lexer, Token},
@@ -101,7 +102,12 @@ impl AstModule {
         dialect: &Dialect,
     ) -> anyhow::Result<AstModule> {
         Stmt::validate(&codemap, &statement, dialect)?;
-        Ok(AstModule { codemap, statement })
+        Ok(AstModule {
+            codemap,
+            statement,
+            enable_recursion: dialect.enable_recursion,
+            suppressions: OnceCell::new(),
+        })
     }
 
     /// Parse a file stored on disk. For details see [`parse`](AstModule::parse).
@@ -133,14 +139,13 @@ impl AstModule {
         }
     }
 
-    /// Return the file names of all the `load` statements in the module.
-    /// If the [`Dialect`] had [`enable_load`](Dialect::enable_load) set to [`false`] this will be an empty list.
-    pub fn loads(&self) -> Vec<&str> {
+    /// All the `load` statements in the module.
+    pub(crate) fn load_statements(&self) -> Vec<&AstLoad> {
         // We know that `load` statements must be at the top-level, so no need to descend inside `if`, `for`, `def` etc.
         // There is a suggestion that `load` statements should be at the top of a file, but we tolerate that not being true.
-        fn f<'a>(ast: &'a AstStmt, vec: &mut Vec<&'a str>) {
+        fn f<'a>(ast: &'a AstStmt, vec: &mut Vec<&'a AstLoad>) {
             match &ast.node {
-                Stmt::Load(load) => vec.push(&load.module.node),
+                Stmt::Load(load) => vec.push(load),
                 Stmt::Statements(stmts) => {
                     for s in stmts {
                         f(s, vec);
@@ -155,6 +160,12 @@ impl AstModule {
         loads
     }
 
+    /// Return the file names of all the `load` statements in the module.
+    /// If the [`Dialect`] had [`enable_load`](Dialect::enable_load) set to [`false`] this will be an empty list.
+    pub fn loads(&self) -> Vec<&str> {
+        self.load_statements().into_map(|load| load.module.node.as_str())
+    }
+
     /// Look up a [`Span`] contained in this module to a [`FileSpan`].
     pub(crate) fn file_span(&self, x: Span) -> FileSpan {
         self.codemap.file_span(x)
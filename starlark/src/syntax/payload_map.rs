@@ -22,8 +22,8 @@ use gazebo::prelude::*;
 use crate::{
     codemap::Spanned,
     syntax::ast::{
-        ArgumentP, AssignIdentP, AssignP, AstPayload, ClauseP, ExprP, ForClauseP, LoadP,
-        ParameterP, StmtP,
+        ArgumentP, AssignIdentP, AssignP, AstPayload, ClauseP, ExprP, FStringPartP, ForClauseP,
+        LoadP, ParameterP, StmtP,
     },
 };
 
@@ -86,6 +86,9 @@ impl<A: AstPayload> StmtP<A> {
                 assign.into_map_payload(f),
                 box (coll.into_map_payload(f), body.into_map_payload(f)),
             ),
+            StmtP::While(cond, box body) => {
+                StmtP::While(cond.into_map_payload(f), box body.into_map_payload(f))
+            }
             StmtP::Def(name, params, ret, body, p) => StmtP::Def(
                 name.into_map_payload(f),
                 params.into_map(|p| p.into_map_payload(f)),
@@ -152,6 +155,19 @@ impl<A: AstPayload> ExprP<A> {
                 box c0.into_map_payload(f),
                 cs.into_map(|c| c.into_map_payload(f)),
             ),
+            ExprP::FString(parts) => ExprP::FString(parts.into_map(|p| p.into_map_payload(f))),
+        }
+    }
+}
+
+impl<A: AstPayload> FStringPartP<A> {
+    pub(crate) fn into_map_payload<B: AstPayload>(
+        self,
+        f: &mut impl AstPayloadFunction<A, B>,
+    ) -> FStringPartP<B> {
+        match self {
+            FStringPartP::Text(text) => FStringPartP::Text(text),
+            FStringPartP::Expr(e, format) => FStringPartP::Expr(e.into_map_payload(f), format),
         }
     }
 }
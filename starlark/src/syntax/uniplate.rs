@@ -22,8 +22,8 @@
 #![allow(clippy::redundant_closure)]
 
 use crate::syntax::ast::{
-    AssignP, AstAssignIdentP, AstExprP, AstPayload, AstStmtP, ClauseP, ExprP, ForClauseP,
-    ParameterP, StmtP,
+    AssignP, AstAssignIdentP, AstExprP, AstPayload, AstStmtP, ClauseP, ExprP, FStringPartP,
+    ForClauseP, ParameterP, StmtP,
 };
 
 pub(crate) enum Visit<'a, P: AstPayload> {
@@ -61,6 +61,10 @@ impl<P: AstPayload> StmtP<P> {
                 f(Visit::Expr(over));
                 f(Visit::Stmt(body));
             }
+            StmtP::While(condition, box body) => {
+                f(Visit::Expr(condition));
+                f(Visit::Stmt(body));
+            }
             // Nothing else contains nested statements
             StmtP::Break => {}
             StmtP::Continue => {}
@@ -105,6 +109,10 @@ impl<P: AstPayload> StmtP<P> {
                 f(VisitMut::Expr(over));
                 f(VisitMut::Stmt(body));
             }
+            StmtP::While(condition, box body) => {
+                f(VisitMut::Expr(condition));
+                f(VisitMut::Stmt(body));
+            }
             // Nothing else contains nested statements
             StmtP::Break => {}
             StmtP::Continue => {}
@@ -286,6 +294,10 @@ impl<P: AstPayload> ExprP<P> {
                 f(&x.0);
                 f(&x.1);
             }
+            ExprP::FString(parts) => parts.iter().for_each(|x| match x {
+                FStringPartP::Text(_) => {}
+                FStringPartP::Expr(x, _) => f(x),
+            }),
         }
     }
 
@@ -342,6 +354,10 @@ impl<P: AstPayload> ExprP<P> {
                 f(&mut x.0);
                 f(&mut x.1);
             }
+            ExprP::FString(parts) => parts.iter_mut().for_each(|x| match x {
+                FStringPartP::Text(_) => {}
+                FStringPartP::Expr(x, _) => f(x),
+            }),
         }
     }
 }
@@ -36,9 +36,9 @@ use crate::{
 
 #[derive(Error, Debug)]
 enum ValidateError {
-    #[error("`break` cannot be used outside of a `for` loop")]
+    #[error("`break` cannot be used outside of a `for` or `while` loop")]
     BreakOutsideLoop,
-    #[error("`continue` cannot be used outside of a `for` loop")]
+    #[error("`continue` cannot be used outside of a `for` or `while` loop")]
     ContinueOutsideLoop,
     #[error("`return` cannot be used outside of a `def` function")]
     ReturnOutsideDef,
@@ -48,6 +48,8 @@ enum ValidateError {
     NoTopLevelIf,
     #[error("`for` cannot be used outside `def` in this dialect")]
     NoTopLevelFor,
+    #[error("`while` cannot be used outside `def` in this dialect")]
+    NoTopLevelWhile,
     #[error("left-hand-side of assignment must take the form `a`, `a.b` or `a[b]`")]
     InvalidLhs,
     #[error("left-hand-side of modifying assignment cannot be a list or tuple")]
@@ -296,10 +298,10 @@ impl Stmt {
 
     /// Validate all statements only occur where they are allowed to.
     pub fn validate(codemap: &CodeMap, stmt: &AstStmt, dialect: &Dialect) -> anyhow::Result<()> {
-        // Inside a for, we allow continue/break, unless we go beneath a def.
+        // Inside a for/while, we allow continue/break, unless we go beneath a def.
         // Inside a def, we allow return.
         // All load's must occur at the top-level.
-        // At the top-level we only allow for/if when the dialect permits it.
+        // At the top-level we only allow for/while/if when the dialect permits it.
         fn f(
             codemap: &CodeMap,
             dialect: &Dialect,
@@ -319,6 +321,13 @@ impl Stmt {
                         f(codemap, dialect, body, false, true, inside_def)
                     }
                 }
+                Stmt::While(_, box body) => {
+                    if top_level && !dialect.enable_top_level_stmt {
+                        err(ValidateError::NoTopLevelWhile)
+                    } else {
+                        f(codemap, dialect, body, false, true, inside_def)
+                    }
+                }
                 Stmt::If(..) | Stmt::IfElse(..) => {
                     if top_level && !dialect.enable_top_level_stmt {
                         err(ValidateError::NoTopLevelIf)
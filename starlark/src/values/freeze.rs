@@ -22,7 +22,7 @@ use gazebo::prelude::*;
 use crate::{
     collections::{
         vec_map::{Bucket, VecMap},
-        SmallMap,
+        SmallMap, SmallSet,
     },
     values::{Freezer, FrozenStringValue, FrozenValue, StringValue, Value},
 };
@@ -87,6 +87,14 @@ impl Freeze for bool {
     }
 }
 
+impl Freeze for () {
+    type Frozen = ();
+
+    fn freeze(self, _freezer: &Freezer) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
 impl<'v, T: 'static> Freeze for marker::PhantomData<&'v T> {
     type Frozen = PhantomData<&'static T>;
 
@@ -161,6 +169,17 @@ where
     }
 }
 
+impl<T> Freeze for SmallSet<T>
+where
+    T: Freeze,
+{
+    type Frozen = SmallSet<T::Frozen>;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<SmallSet<T::Frozen>> {
+        Ok(SmallSet(self.0.freeze(freezer)?))
+    }
+}
+
 impl<'v> Freeze for Value<'v> {
     type Frozen = FrozenValue;
 
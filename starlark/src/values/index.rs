@@ -15,7 +15,16 @@
  * limitations under the License.
  */
 
-use crate::values::{Value, ValueError};
+use crate::values::{bigint::StarlarkIntRef, Value, ValueError};
+
+/// Like [`Value::to_int`], but big integers saturate, since they are beyond either end
+/// of any sequence.
+pub(crate) fn to_int_saturating(v: Value) -> anyhow::Result<i32> {
+    match StarlarkIntRef::unpack(v) {
+        Some(x) => Ok(x.to_i32_saturating()),
+        None => v.to_int(),
+    }
+}
 
 // Helper for convert_slice_indices
 fn convert_index_aux(
@@ -29,7 +38,7 @@ fn convert_index_aux(
         if v.is_none() {
             Ok(default)
         } else {
-            match v.to_int() {
+            match to_int_saturating(v) {
                 Ok(x) => {
                     let i = if x < 0 { len + x } else { x };
                     if i < min {
@@ -58,6 +67,9 @@ fn convert_index_aux(
 /// and len. Raise the correct errors if the value is not numeric or the
 /// index is out of bound.
 pub(crate) fn convert_index(v: Value, len: i32) -> anyhow::Result<i32> {
+    if let Some(x @ StarlarkIntRef::Big(_)) = StarlarkIntRef::unpack(v) {
+        return x.to_index();
+    }
     match v.to_int() {
         Ok(x) => {
             let i = if x < 0 {
@@ -94,7 +106,7 @@ pub(crate) fn convert_slice_indices(
     let stride = match stride {
         None => 1,
         Some(v) if v.is_none() => 1,
-        Some(v) => v.to_int().map_err(|_| {
+        Some(v) => to_int_saturating(v).map_err(|_| {
             ValueError::IncorrectParameterTypeWithExpected(
                 "int or None".to_owned(),
                 v.get_type().to_owned(),
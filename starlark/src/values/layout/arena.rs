@@ -336,17 +336,26 @@ impl Arena {
             .for_each(|chunk| Self::iter_chunk(chunk, &mut f))
     }
 
-    // For each Rust-level type (the String) report how many entries there are in the heap, and how much size they consume
-    pub fn allocated_summary(&self) -> HeapSummary {
-        fn for_each<'a>(bump: &'a Bump, mut f: impl FnMut(&'a AValueHeader)) {
-            // SAFE: We're consuming the iterator immediately and not allocating from the arena during.
-            unsafe {
-                bump.iter_allocated_chunks_raw().for_each(|(data, len)| {
-                    Arena::iter_chunk(slice::from_raw_parts(data as *const _, len), &mut f)
-                })
-            }
+    // Iterate over the values in a bump in any order, without needing `&mut`
+    fn for_each_raw<'a>(bump: &'a Bump, mut f: impl FnMut(&'a AValueHeader)) {
+        // SAFE: We're consuming the iterator immediately and not allocating from the arena during.
+        unsafe {
+            bump.iter_allocated_chunks_raw().for_each(|(data, len)| {
+                Arena::iter_chunk(slice::from_raw_parts(data as *const _, len), &mut f)
+            })
         }
+    }
 
+    // The memory held outside the arena by the values in it.
+    // Values which need no drop can't own any memory, so only look at those which do.
+    pub fn extra_memory(&self) -> usize {
+        let mut res = 0;
+        Self::for_each_raw(&self.drop, |x| res += x.unpack().extra_memory());
+        res
+    }
+
+    // For each Rust-level type (the String) report how many entries there are in the heap, and how much size they consume
+    pub fn allocated_summary(&self) -> HeapSummary {
         // Record how many times each header occurs
         // We deliberately hash by the AValueHeader for higher performance, less type lookup
         let mut entries: HashMap<AValueHeader, (&'static str, (usize, usize))> = HashMap::new();
@@ -358,8 +367,8 @@ impl Arena {
             e.1.0 += 1;
             e.1.1 += v.total_memory()
         };
-        for_each(&self.drop, &mut f);
-        for_each(&self.non_drop, &mut f);
+        Self::for_each_raw(&self.drop, &mut f);
+        Self::for_each_raw(&self.non_drop, &mut f);
 
         // For a given type, the AValueHeader isn't always unique
         // (if they get compiled in different translation units),
@@ -24,7 +24,7 @@ use std::{
     hash::{Hash, Hasher},
     intrinsics::copy_nonoverlapping,
     marker::PhantomData,
-    mem::MaybeUninit,
+    mem::{self, MaybeUninit},
     ops::Deref,
     ptr,
     sync::Arc,
@@ -35,7 +35,7 @@ use either::Either;
 use gazebo::{cast, prelude::*};
 
 use crate::{
-    eval::FrozenDef,
+    eval::{EvalLimitError, FrozenDef},
     values::{
         any::StarlarkAny,
         array::Array,
@@ -61,6 +61,11 @@ use crate::{
 pub struct Heap {
     /// Peak memory seen when a garbage collection takes place (may be lower than currently allocated)
     peak_allocated: Cell<usize>,
+    /// Maximum number of bytes this heap may allocate, if limited.
+    limit: Cell<Option<usize>>,
+    /// Memory held outside the arena by values on this heap (e.g. dict entries or big int digits).
+    /// Only tracked while the heap is limited.
+    extra_allocated: Cell<usize>,
     arena: FastCell<Arena>,
 }
 
@@ -81,6 +86,7 @@ impl Debug for Heap {
 pub struct FrozenHeap {
     arena: Arena,                          // My memory
     refs: RefCell<HashSet<FrozenHeapRef>>, // Memory I depend on
+    limit: Cell<Option<usize>>,            // Maximum number of bytes, if limited
 }
 
 /// `FrozenHeap` when it is no longer modified and can be share between threads.
@@ -174,7 +180,7 @@ impl FrozenHeap {
     /// [`FrozenHeapRef`] which can be [`clone`](Clone::clone)d, shared between threads,
     /// and ensures the underlying values allocated on the [`FrozenHeap`] remain valid.
     pub fn into_ref(self) -> FrozenHeapRef {
-        let FrozenHeap { arena, refs } = self;
+        let FrozenHeap { arena, refs, .. } = self;
         FrozenHeapRef(Arc::new(FrozenFrozenHeap {
             arena,
             refs: refs.into_inner(),
@@ -293,6 +299,34 @@ impl FrozenHeap {
     pub fn allocated_summary(&self) -> HeapSummary {
         self.arena.allocated_summary()
     }
+
+    /// Limit the number of bytes allocated on this heap, as checked by
+    /// [`check_allocation`](FrozenHeap::check_allocation).
+    /// [`Module::freeze`](crate::environment::Module::freeze) fails if the limit
+    /// of the module's frozen heap is exceeded.
+    pub fn set_allocation_limit(&self, limit: Option<usize>) {
+        self.limit.set(limit);
+    }
+
+    /// Fail if allocating `bytes` more bytes would exceed the limit set by
+    /// [`set_allocation_limit`](FrozenHeap::set_allocation_limit).
+    pub fn check_allocation(&self, bytes: usize) -> anyhow::Result<()> {
+        check_limit(self.limit.get(), || self.allocated_bytes(), bytes)
+    }
+}
+
+// Only counts the allocated bytes if there is a limit, so is cheap to call when there isn't.
+fn check_limit(
+    limit: Option<usize>,
+    allocated: impl FnOnce() -> usize,
+    bytes: usize,
+) -> anyhow::Result<()> {
+    match limit {
+        Some(limit) if allocated().saturating_add(bytes) > limit => {
+            Err(EvalLimitError::HeapLimitExceeded(limit).into())
+        }
+        _ => Ok(()),
+    }
 }
 
 /// Used to `freeze` values by [`Freeze::freeze`](crate::values::Freeze::freeze).
@@ -385,6 +419,61 @@ impl Heap {
         self.arena.borrow().available_bytes()
     }
 
+    /// Limit the number of bytes allocated on this heap. Once
+    /// [`allocated_bytes`](Heap::allocated_bytes), plus the memory values hold outside the heap
+    /// (see [`extra_memory`](crate::values::StarlarkValue::extra_memory)), goes over the limit,
+    /// evaluation fails with [`EvalLimitError::HeapLimitExceeded`].
+    ///
+    /// The limit is checked by the [`Evaluator`](crate::eval::Evaluator) between steps,
+    /// so must be set before evaluation starts. Within a step, builtins which build
+    /// strings or collections (e.g. `"x" * n`, `list(range(n))`, `",".join(xs)` or
+    /// `json.decode(s)`) check the limit before or while allocating, so a single call can't
+    /// go far over it, but the limit may still be slightly overshot.
+    pub fn set_allocation_limit(&self, limit: Option<usize>) {
+        self.limit.set(limit);
+    }
+
+    /// The limit set by [`set_allocation_limit`](Heap::set_allocation_limit).
+    pub fn allocation_limit(&self) -> Option<usize> {
+        self.limit.get()
+    }
+
+    /// Fail if allocating `bytes` more bytes would exceed the limit set by
+    /// [`set_allocation_limit`](Heap::set_allocation_limit). Should be called by
+    /// native functions before making allocations proportional to their input.
+    pub fn check_allocation(&self, bytes: usize) -> anyhow::Result<()> {
+        check_limit(
+            self.limit.get(),
+            || self.allocated_bytes() + self.extra_allocated.get(),
+            bytes,
+        )
+    }
+
+    /// Fail if allocating `len` more values (e.g. for the elements of a list) would exceed
+    /// the limit set by [`set_allocation_limit`](Heap::set_allocation_limit).
+    pub(crate) fn check_allocation_values(&self, len: usize) -> anyhow::Result<()> {
+        self.check_allocation(len.saturating_mul(mem::size_of::<Value>()))
+    }
+
+    /// Run `f`, which may grow `value` in place (e.g. `list.append` or `dict[k] = v`),
+    /// counting any change in the memory it holds outside the heap towards the limit.
+    #[inline(always)]
+    pub(crate) fn track_extra_memory<'v, R>(
+        &'v self,
+        value: Value<'v>,
+        f: impl FnOnce() -> R,
+    ) -> R {
+        if self.limit.get().is_none() {
+            return f();
+        }
+        let before = value.get_ref().extra_memory();
+        let res = f();
+        let after = value.get_ref().extra_memory();
+        self.extra_allocated
+            .set((self.extra_allocated.get() + after).saturating_sub(before));
+        res
+    }
+
     fn alloc_raw<'v, 'v2: 'v2>(&'v self, x: impl AValue<'v2, ExtraElem = ()>) -> Value<'v> {
         let arena_ref = self.arena.borrow();
         let arena = &*arena_ref;
@@ -393,10 +482,15 @@ impl Heap {
         // We have an arena inside a RefCell which stores ValueMem<'v>
         // However, we promise not to clear the RefCell other than for GC
         // so we can make the `arena` available longer
-        unsafe {
+        let value = unsafe {
             let value = Value::new_repr(cast::ptr_lifetime(v));
             transmute!(Value, Value, value)
+        };
+        if self.limit.get().is_some() {
+            self.extra_allocated
+                .set(self.extra_allocated.get() + value.get_ref().extra_memory());
         }
+        value
     }
 
     fn alloc_raw_typed<'v, A: AValue<'v, ExtraElem = ()>>(
@@ -576,6 +670,9 @@ impl Heap {
             phantom: PhantomData,
         };
         f(&tracer);
+        if self.limit.get().is_some() {
+            self.extra_allocated.set(tracer.arena.extra_memory());
+        }
         self.arena.set(tracer.arena);
     }
 
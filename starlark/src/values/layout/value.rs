@@ -48,6 +48,7 @@ use crate::{
     collections::{Hashed, StarlarkHashValue, StarlarkHasher},
     eval::{runtime::call_stack::FrozenFileSpan, Arguments, Evaluator, FrozenDef},
     values::{
+        bigint::StarlarkBigInt,
         dict::FrozenDict,
         docs::DocItem,
         enumeration::{EnumType, FrozenEnumValue},
@@ -424,20 +425,23 @@ impl<'v> Value<'v> {
         self.get_ref().floor_div(other, heap)
     }
 
-    pub fn bit_and(self, other: Value<'v>) -> anyhow::Result<Value<'v>> {
-        self.get_ref().bit_and(other)
+    pub fn bit_not(self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_ref().bit_not(heap)
     }
-    pub fn bit_or(self, other: Value<'v>) -> anyhow::Result<Value<'v>> {
-        self.get_ref().bit_or(other)
+    pub fn bit_and(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_ref().bit_and(other, heap)
     }
-    pub fn bit_xor(self, other: Value<'v>) -> anyhow::Result<Value<'v>> {
-        self.get_ref().bit_xor(other)
+    pub fn bit_or(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_ref().bit_or(other, heap)
     }
-    pub fn left_shift(self, other: Value<'v>) -> anyhow::Result<Value<'v>> {
-        self.get_ref().left_shift(other)
+    pub fn bit_xor(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_ref().bit_xor(other, heap)
     }
-    pub fn right_shift(self, other: Value<'v>) -> anyhow::Result<Value<'v>> {
-        self.get_ref().right_shift(other)
+    pub fn left_shift(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_ref().left_shift(other, heap)
+    }
+    pub fn right_shift(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_ref().right_shift(other, heap)
     }
 
     pub(crate) fn invoke_with_loc(
@@ -757,6 +761,7 @@ impl FrozenValue {
             || self.is_str()
             || self.unpack_bool().is_some()
             || self.unpack_int().is_some()
+            || FrozenValueTyped::<StarlarkBigInt>::new(self).is_some()
             || FrozenValueTyped::<StarlarkFloat>::new(self).is_some()
             || FrozenList::from_frozen_value(&self).is_some()
             || FrozenDict::from_frozen_value(&self).is_some()
@@ -23,7 +23,7 @@ use std::{
 use hashbrown::raw::RawTable;
 
 use crate::{
-    collections::SmallMap,
+    collections::{SmallMap, SmallSet},
     values::{FrozenValue, Tracer, Value},
 };
 
@@ -66,6 +66,12 @@ unsafe impl<'v, K: Trace<'v>, V: Trace<'v>> Trace<'v> for SmallMap<K, V> {
     }
 }
 
+unsafe impl<'v, T: Trace<'v>> Trace<'v> for SmallSet<T> {
+    fn trace(&mut self, tracer: &Tracer<'v>) {
+        self.0.trace(tracer)
+    }
+}
+
 unsafe impl<'v, T: Trace<'v>> Trace<'v> for Option<T> {
     fn trace(&mut self, tracer: &Tracer<'v>) {
         if let Some(x) = self {
@@ -109,6 +115,10 @@ unsafe impl<'v> Trace<'v> for FrozenValue {
     fn trace(&mut self, _tracer: &Tracer<'v>) {}
 }
 
+unsafe impl<'v> Trace<'v> for () {
+    fn trace(&mut self, _tracer: &Tracer<'v>) {}
+}
+
 unsafe impl<'v> Trace<'v> for String {
     fn trace(&mut self, _tracer: &Tracer<'v>) {}
 }
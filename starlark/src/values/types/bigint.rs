@@ -0,0 +1,648 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Integers which do not fit into the 32 bit [`PointerI32`] representation.
+//!
+//! Starlark programs never observe the difference between small and big integers:
+//! both have type `int`, and every operation produces the small representation
+//! whenever the result fits. As a consequence, a [`StarlarkBigInt`] value never
+//! holds a number in the `i32` range.
+
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Write},
+    hash::Hasher,
+};
+
+use gazebo::prelude::*;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+use thiserror::Error;
+
+use crate::{
+    collections::StarlarkHasher,
+    values::{
+        float::StarlarkFloat, int::INT_TYPE, num::Num, FrozenHeap, FrozenValue, Heap,
+        StarlarkValue, Value, ValueError,
+    },
+};
+
+/// Shifts by more than this number of bits are rejected,
+/// same as in the Go implementation.
+const MAX_SHIFT: i32 = 512;
+
+#[derive(Debug, Error)]
+enum BigIntError {
+    #[error("Negative shift count: {0}")]
+    NegativeShift(String),
+    #[error("Shift count too large: {0}")]
+    ShiftTooLarge(String),
+    #[error("Index `{0}` is out of bound")]
+    IndexOutOfBound(String),
+}
+
+/// An integer which doesn't fit into `i32`, stored on the heap.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StarlarkBigInt {
+    value: BigInt,
+}
+
+starlark_simple_value!(StarlarkBigInt);
+
+impl Display for StarlarkBigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.value, f)
+    }
+}
+
+impl StarlarkBigInt {
+    /// Allocate an integer, using the compact representation if it fits into `i32`.
+    pub fn alloc_bigint<'v>(value: BigInt, heap: &'v Heap) -> Value<'v> {
+        match value.to_i32() {
+            Some(i) => Value::new_int(i),
+            None => heap.alloc_simple(StarlarkBigInt { value }),
+        }
+    }
+
+    /// Allocate an integer on the frozen heap,
+    /// using the compact representation if it fits into `i32`.
+    pub fn alloc_bigint_frozen(value: BigInt, heap: &FrozenHeap) -> FrozenValue {
+        match value.to_i32() {
+            Some(i) => FrozenValue::new_int(i),
+            None => heap.alloc_simple(StarlarkBigInt { value }),
+        }
+    }
+
+    /// The underlying number.
+    pub fn get(&self) -> &BigInt {
+        &self.value
+    }
+
+    /// Closest float to this integer.
+    pub(crate) fn to_f64(&self) -> f64 {
+        // `BigInt::to_f64` never fails, it returns infinity for too large numbers.
+        self.value.to_f64().unwrap()
+    }
+
+    /// Compare exactly with a float. NaN is greater than any integer.
+    pub(crate) fn cmp_f64(&self, f: f64) -> Ordering {
+        if f.is_nan() {
+            return Ordering::Less;
+        }
+        if f.is_infinite() {
+            return if f > 0.0 {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            };
+        }
+        let floor = f.floor();
+        // `floor` is an integer, so the conversion is exact.
+        match self.value.cmp(&BigInt::from_f64(floor).unwrap()) {
+            Ordering::Equal if f > floor => Ordering::Less,
+            ordering => ordering,
+        }
+    }
+
+    /// The closest float, to apply an operator whose other operand is a number,
+    /// but not an integer handled exactly.
+    fn float_operand<'v>(&self, op: &str, other: Value<'v>) -> anyhow::Result<StarlarkFloat> {
+        if other.unpack_num().is_some() || StarlarkBigInt::from_value(other).is_some() {
+            Ok(StarlarkFloat(self.to_f64()))
+        } else {
+            ValueError::unsupported_with(self, op, other)
+        }
+    }
+
+    /// Hash compatible with the hash of a float with the same value.
+    fn hash_64(&self) -> u64 {
+        let f = self.to_f64();
+        if f.is_finite() && bigint_from_f64_exact(f).as_ref() == Some(&self.value) {
+            Num::Float(f).get_hash_64()
+        } else {
+            let (sign, digits) = self.value.to_u64_digits();
+            digits
+                .iter()
+                .fold(sign as u64, |h, d| h.rotate_left(5) ^ d.wrapping_mul(31))
+        }
+    }
+}
+
+/// Convert a float to `BigInt` if the float is an integer.
+pub(crate) fn bigint_from_f64_exact(f: f64) -> Option<BigInt> {
+    if f.fract() != 0.0 {
+        // Also covers infinities and NaN.
+        return None;
+    }
+    BigInt::from_f64(f)
+}
+
+/// Integer unpacked from a [`Value`], either in compact or heap representation.
+#[derive(Clone, Copy, Dupe, Debug)]
+pub(crate) enum StarlarkIntRef<'v> {
+    Small(i32),
+    Big(&'v StarlarkBigInt),
+}
+
+impl<'v> StarlarkIntRef<'v> {
+    /// Unpack an integer, returns `None` if the value is not an `int`.
+    pub(crate) fn unpack(value: Value<'v>) -> Option<Self> {
+        match value.unpack_int() {
+            Some(i) => Some(StarlarkIntRef::Small(i)),
+            None => StarlarkBigInt::from_value(value).map(StarlarkIntRef::Big),
+        }
+    }
+}
+
+impl StarlarkIntRef<'_> {
+    pub(crate) fn to_bigint(self) -> BigInt {
+        match self {
+            StarlarkIntRef::Small(i) => BigInt::from(i),
+            StarlarkIntRef::Big(b) => b.value.clone(),
+        }
+    }
+
+    pub(crate) fn to_f64(self) -> f64 {
+        match self {
+            StarlarkIntRef::Small(i) => i as f64,
+            StarlarkIntRef::Big(b) => b.to_f64(),
+        }
+    }
+
+    pub(crate) fn to_i64(self) -> Option<i64> {
+        match self {
+            StarlarkIntRef::Small(i) => Some(i as i64),
+            StarlarkIntRef::Big(b) => b.value.to_i64(),
+        }
+    }
+
+    pub(crate) fn to_u64(self) -> Option<u64> {
+        match self {
+            StarlarkIntRef::Small(i) => i.try_into().ok(),
+            StarlarkIntRef::Big(b) => b.value.to_u64(),
+        }
+    }
+
+    /// Convert to an index into a sequence, where every big integer is out of range.
+    pub(crate) fn to_index(self) -> anyhow::Result<i32> {
+        match self {
+            StarlarkIntRef::Small(i) => Ok(i),
+            StarlarkIntRef::Big(b) => Err(BigIntError::IndexOutOfBound(b.to_string()).into()),
+        }
+    }
+
+    /// Convert to `i32`, where big integers saturate at `i32::MAX` or `-i32::MAX`.
+    /// Useful for slice bounds and strides, which are clamped to the sequence length anyway.
+    pub(crate) fn to_i32_saturating(self) -> i32 {
+        match self {
+            StarlarkIntRef::Small(i) => i,
+            StarlarkIntRef::Big(b) if b.value.is_negative() => -i32::MAX,
+            StarlarkIntRef::Big(_) => i32::MAX,
+        }
+    }
+
+    pub(crate) fn minus<'v>(self, heap: &'v Heap) -> Value<'v> {
+        match self {
+            StarlarkIntRef::Small(i) => heap.alloc(-(i as i64)),
+            StarlarkIntRef::Big(b) => StarlarkBigInt::alloc_bigint(-&b.value, heap),
+        }
+    }
+
+    pub(crate) fn bit_not<'v>(self, heap: &'v Heap) -> Value<'v> {
+        match self {
+            StarlarkIntRef::Small(i) => Value::new_int(!i),
+            // `~x == -x - 1` in two's complement.
+            StarlarkIntRef::Big(b) => StarlarkBigInt::alloc_bigint(-&b.value - 1, heap),
+        }
+    }
+
+    pub(crate) fn add<'v>(self, other: StarlarkIntRef, heap: &'v Heap) -> Value<'v> {
+        match (self, other) {
+            (StarlarkIntRef::Small(a), StarlarkIntRef::Small(b)) => match a.checked_add(b) {
+                Some(x) => Value::new_int(x),
+                None => heap.alloc(a as i64 + b as i64),
+            },
+            (a, b) => StarlarkBigInt::alloc_bigint(a.to_bigint() + b.to_bigint(), heap),
+        }
+    }
+
+    pub(crate) fn sub<'v>(self, other: StarlarkIntRef, heap: &'v Heap) -> Value<'v> {
+        match (self, other) {
+            (StarlarkIntRef::Small(a), StarlarkIntRef::Small(b)) => match a.checked_sub(b) {
+                Some(x) => Value::new_int(x),
+                None => heap.alloc(a as i64 - b as i64),
+            },
+            (a, b) => StarlarkBigInt::alloc_bigint(a.to_bigint() - b.to_bigint(), heap),
+        }
+    }
+
+    pub(crate) fn mul<'v>(self, other: StarlarkIntRef, heap: &'v Heap) -> Value<'v> {
+        match (self, other) {
+            (StarlarkIntRef::Small(a), StarlarkIntRef::Small(b)) => match a.checked_mul(b) {
+                Some(x) => Value::new_int(x),
+                None => heap.alloc(a as i64 * b as i64),
+            },
+            (a, b) => StarlarkBigInt::alloc_bigint(a.to_bigint() * b.to_bigint(), heap),
+        }
+    }
+
+    /// Floor division, rounds towards negative infinity.
+    pub(crate) fn floor_div<'v>(
+        self,
+        other: StarlarkIntRef,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        match (self, other) {
+            (_, StarlarkIntRef::Small(0)) => Err(ValueError::DivisionByZero.into()),
+            (StarlarkIntRef::Small(a), StarlarkIntRef::Small(b)) => {
+                // Can only overflow for `i32::MIN // -1`, which fits in `i64`.
+                Ok(heap.alloc(Integer::div_floor(&(a as i64), &(b as i64))))
+            }
+            (a, b) => Ok(StarlarkBigInt::alloc_bigint(
+                a.to_bigint().div_floor(&b.to_bigint()),
+                heap,
+            )),
+        }
+    }
+
+    /// Modulo, the result has the sign of the divisor.
+    pub(crate) fn percent<'v>(
+        self,
+        other: StarlarkIntRef,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        match (self, other) {
+            (_, StarlarkIntRef::Small(0)) => Err(ValueError::DivisionByZero.into()),
+            (StarlarkIntRef::Small(a), StarlarkIntRef::Small(b)) => {
+                // In Rust `i32::MIN % -1` is overflow, but in `i64` it is zero.
+                Ok(heap.alloc(Integer::mod_floor(&(a as i64), &(b as i64))))
+            }
+            (a, b) => Ok(StarlarkBigInt::alloc_bigint(
+                a.to_bigint().mod_floor(&b.to_bigint()),
+                heap,
+            )),
+        }
+    }
+
+    pub(crate) fn bit_and<'v>(self, other: StarlarkIntRef, heap: &'v Heap) -> Value<'v> {
+        match (self, other) {
+            (StarlarkIntRef::Small(a), StarlarkIntRef::Small(b)) => Value::new_int(a & b),
+            (a, b) => StarlarkBigInt::alloc_bigint(a.to_bigint() & b.to_bigint(), heap),
+        }
+    }
+
+    pub(crate) fn bit_or<'v>(self, other: StarlarkIntRef, heap: &'v Heap) -> Value<'v> {
+        match (self, other) {
+            (StarlarkIntRef::Small(a), StarlarkIntRef::Small(b)) => Value::new_int(a | b),
+            (a, b) => StarlarkBigInt::alloc_bigint(a.to_bigint() | b.to_bigint(), heap),
+        }
+    }
+
+    pub(crate) fn bit_xor<'v>(self, other: StarlarkIntRef, heap: &'v Heap) -> Value<'v> {
+        match (self, other) {
+            (StarlarkIntRef::Small(a), StarlarkIntRef::Small(b)) => Value::new_int(a ^ b),
+            (a, b) => StarlarkBigInt::alloc_bigint(a.to_bigint() ^ b.to_bigint(), heap),
+        }
+    }
+
+    fn shift_count(other: StarlarkIntRef) -> anyhow::Result<usize> {
+        match other {
+            StarlarkIntRef::Small(i) if i < 0 => {
+                Err(BigIntError::NegativeShift(i.to_string()).into())
+            }
+            StarlarkIntRef::Small(i) if i < MAX_SHIFT => Ok(i as usize),
+            StarlarkIntRef::Small(i) => Err(BigIntError::ShiftTooLarge(i.to_string()).into()),
+            StarlarkIntRef::Big(b) if b.value.is_negative() => {
+                Err(BigIntError::NegativeShift(b.to_string()).into())
+            }
+            StarlarkIntRef::Big(b) => Err(BigIntError::ShiftTooLarge(b.to_string()).into()),
+        }
+    }
+
+    pub(crate) fn left_shift<'v>(
+        self,
+        other: StarlarkIntRef,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let count = Self::shift_count(other)?;
+        match self {
+            StarlarkIntRef::Small(a) if count < 32 => Ok(heap.alloc((a as i64) << count)),
+            a => Ok(StarlarkBigInt::alloc_bigint(a.to_bigint() << count, heap)),
+        }
+    }
+
+    pub(crate) fn right_shift<'v>(
+        self,
+        other: StarlarkIntRef,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let count = match Self::shift_count(other) {
+            Ok(count) => count,
+            // Shifting right by a huge number is well defined.
+            Err(_) if matches!(other, StarlarkIntRef::Big(b) if b.value.is_positive()) => {
+                usize::MAX
+            }
+            Err(e) => return Err(e),
+        };
+        match self {
+            StarlarkIntRef::Small(a) => Ok(Value::new_int(a >> count.min(31))),
+            StarlarkIntRef::Big(b) if count == usize::MAX => {
+                Ok(Value::new_int(if b.value.is_negative() { -1 } else { 0 }))
+            }
+            // `BigInt` shift rounds towards negative infinity, like arithmetic shift.
+            StarlarkIntRef::Big(b) => Ok(StarlarkBigInt::alloc_bigint(&b.value >> count, heap)),
+        }
+    }
+
+    pub(crate) fn cmp(self, other: StarlarkIntRef) -> Ordering {
+        match (self, other) {
+            (StarlarkIntRef::Small(a), StarlarkIntRef::Small(b)) => a.cmp(&b),
+            (StarlarkIntRef::Big(a), StarlarkIntRef::Big(b)) => a.value.cmp(&b.value),
+            // Big integers are outside of `i32` range, so only the sign matters.
+            (StarlarkIntRef::Small(_), StarlarkIntRef::Big(b)) => {
+                if b.value.is_negative() {
+                    Ordering::Greater
+                } else {
+                    Ordering::Less
+                }
+            }
+            (StarlarkIntRef::Big(a), StarlarkIntRef::Small(_)) => {
+                if a.value.is_negative() {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            }
+        }
+    }
+}
+
+impl<'v> StarlarkValue<'v> for StarlarkBigInt {
+    starlark_type!(INT_TYPE);
+
+    fn equals(&self, other: Value) -> anyhow::Result<bool> {
+        Ok(match StarlarkIntRef::unpack(other) {
+            Some(other) => StarlarkIntRef::Big(self).cmp(other) == Ordering::Equal,
+            None => match other.unpack_num() {
+                Some(Num::Float(f)) => bigint_from_f64_exact(f).as_ref() == Some(&self.value),
+                _ => false,
+            },
+        })
+    }
+
+    fn compare(&self, other: Value) -> anyhow::Result<Ordering> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(StarlarkIntRef::Big(self).cmp(other)),
+            None => match other.unpack_num() {
+                Some(Num::Float(f)) => Ok(self.cmp_f64(f)),
+                _ => ValueError::unsupported_with(self, "==", other),
+            },
+        }
+    }
+
+    fn collect_json(&self, collector: &mut String) -> anyhow::Result<()> {
+        write!(collector, "{}", self.value).unwrap();
+        Ok(())
+    }
+
+    fn to_int(&self) -> anyhow::Result<i32> {
+        Err(ValueError::IntegerOverflow.into())
+    }
+
+    fn to_bool(&self) -> bool {
+        // Zero is always represented as `PointerI32`.
+        debug_assert!(!self.value.is_zero());
+        true
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
+        hasher.write_u64(self.hash_64());
+        Ok(())
+    }
+
+    fn extra_memory(&self) -> usize {
+        self.value.bits() as usize / 8
+    }
+
+    fn plus(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc_simple(self.clone()))
+    }
+
+    fn minus(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(StarlarkIntRef::Big(self).minus(heap))
+    }
+
+    fn bit_not(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(StarlarkIntRef::Big(self).bit_not(heap))
+    }
+
+    fn add(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(StarlarkIntRef::Big(self).add(other, heap)),
+            None => self.float_operand("+", other)?.add(other, heap),
+        }
+    }
+
+    fn sub(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(StarlarkIntRef::Big(self).sub(other, heap)),
+            None => self.float_operand("-", other)?.sub(other, heap),
+        }
+    }
+
+    fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(StarlarkIntRef::Big(self).mul(other, heap)),
+            None if other.unpack_num().is_some() => StarlarkFloat(self.to_f64()).mul(other, heap),
+            // Let sequences handle `int * list` and such.
+            None => other.mul(heap.alloc_simple(self.clone()), heap),
+        }
+    }
+
+    fn div(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.float_operand("/", other)?.div(other, heap)
+    }
+
+    fn percent(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => StarlarkIntRef::Big(self).percent(other, heap),
+            None => self.float_operand("%", other)?.percent(other, heap),
+        }
+    }
+
+    fn floor_div(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => StarlarkIntRef::Big(self).floor_div(other, heap),
+            None => self.float_operand("//", other)?.floor_div(other, heap),
+        }
+    }
+
+    fn bit_and(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(StarlarkIntRef::Big(self).bit_and(other, heap)),
+            None => ValueError::unsupported_with(self, "&", other),
+        }
+    }
+
+    fn bit_or(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(StarlarkIntRef::Big(self).bit_or(other, heap)),
+            None => ValueError::unsupported_with(self, "|", other),
+        }
+    }
+
+    fn bit_xor(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(StarlarkIntRef::Big(self).bit_xor(other, heap)),
+            None => ValueError::unsupported_with(self, "^", other),
+        }
+    }
+
+    fn left_shift(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => StarlarkIntRef::Big(self).left_shift(other, heap),
+            None => ValueError::unsupported_with(self, "<<", other),
+        }
+    }
+
+    fn right_shift(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => StarlarkIntRef::Big(self).right_shift(other, heap),
+            None => ValueError::unsupported_with(self, ">>", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+
+    use crate::{
+        assert,
+        values::{types::bigint::StarlarkBigInt, Heap},
+    };
+
+    #[test]
+    fn test_alloc_normalizes() {
+        let heap = Heap::new();
+        let small = StarlarkBigInt::alloc_bigint(BigInt::from(17), &heap);
+        assert_eq!(Some(17), small.unpack_int());
+        let big = StarlarkBigInt::alloc_bigint(BigInt::from(1i64 << 40), &heap);
+        assert_eq!(None, big.unpack_int());
+        assert_eq!("int", big.get_type());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert::all_true(
+            r#"
+2147483647 + 1 == 2147483648
+-2147483647 - 2 == -2147483649
+(1 << 40) == 1099511627776
+(1 << 40) * (1 << 40) == 1208925819614629174706176
+(1 << 100) // (1 << 98) == 4
+(1 << 100) - (1 << 100) == 0
+type(1 << 100) == "int"
+-(1 << 40) // 3 == -366503875926
+-(1 << 40) % 3 == 2
+(1 << 40) % -3 == -2
+((1 << 40) + 5) & 7 == 5
+(1 << 40) | 1 == 1099511627777
+((1 << 40) ^ (1 << 40)) == 0
+~(1 << 40) == -1099511627777
+(1 << 40) >> 38 == 4
+-(1 << 40) >> 100 == -1
+(1 << 40) > 2147483647
+-(1 << 40) < -2147483648
+(1 << 40) == 1099511627776.0
+(1 << 40) / 2 == 549755813888.0
+(1 << 40) + 0.5 == 1099511627776.5
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hash() {
+        assert::all_true(
+            r#"
+{(1 << 40): 1}[1 << 40] == 1
+{(1 << 40): 1}[1099511627776.0] == 1
+{(1 << 100) + 1: 1}[(1 << 100) + 1] == 1
+"#,
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert::all_true(
+            r#"
+str(1 << 40) == "1099511627776"
+repr(-(1 << 40)) == "-1099511627776"
+int("1099511627776") == 1 << 40
+int("-0x10000000000") == -(1 << 40)
+int(1099511627776.0) == 1 << 40
+float(1 << 40) == 1099511627776.0
+"%d" % (1 << 40) == "1099511627776"
+"%x" % (1 << 40) == "10000000000"
+"#,
+        );
+        assert::fail("int('99999999999x')", "not a valid number in base 10");
+        assert::fail("int('99999999999_9')", "not a valid number in base 10");
+        assert::fail("int('--99999999999')", "not a valid number in base 10");
+        assert::fail("1 << -1", "Negative shift count");
+        assert::fail("1 << 1000", "Shift count too large");
+        assert::fail("(1 << 40) // 0", "Cannot divide by zero");
+        assert::fail("[1][1 << 40]", "Index `1099511627776` is out of bound");
+        assert::fail("[1].pop(-(1 << 40))", "out of bound");
+        assert::all_true(
+            r#"
+[1, 2, 3][1:1 << 40] == [2, 3]
+[1, 2, 3][-(1 << 40):1] == [1]
+[1, 2, 3][::1 << 40] == [1]
+"abc"[1:1 << 40] == "bc"
+"#,
+        );
+        assert::fail("chr(1 << 40)", "not a valid UTF-8 codepoint");
+        assert::fail("chr(-1)", "not a valid UTF-8 codepoint");
+    }
+
+    #[test]
+    fn test_float_comparison() {
+        // 2**53 + 1 is not representable as a float, so comparing via floats is inexact.
+        assert::all_true(
+            r#"
+(1 << 53) + 1 > 9007199254740992.0
+(1 << 53) + 1 != 9007199254740992.0
+9007199254740992.0 < (1 << 53) + 1
+(1 << 40) < 1099511627776.5
+-(1 << 40) > -1099511627776.5
+(1 << 40) < float("inf")
+(1 << 40) > float("-inf")
+(1 << 40) < float("nan")
+sorted([1099511627776.5, 1 << 40]) == [1 << 40, 1099511627776.5]
+"#,
+        );
+    }
+
+    #[test]
+    fn test_unsupported_operand() {
+        assert::fail("(1 << 40) + 'x'", "types `int` and `string`");
+        assert::fail("(1 << 40) / []", "types `int` and `list`");
+        assert::fail("(1 << 40) // None", "types `int` and `NoneType`");
+    }
+}
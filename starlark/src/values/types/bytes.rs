@@ -0,0 +1,211 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The bytes type, an immutable sequence of bytes.
+//! Bytes literals (`b"..."`) are only available with
+//! [`Dialect::enable_bytes`](crate::syntax::Dialect::enable_bytes).
+
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Write},
+    hash::Hasher,
+};
+
+use crate::{
+    collections::StarlarkHasher,
+    environment::{Methods, MethodsStatic},
+    values::{
+        index::{apply_slice, convert_index},
+        Heap, StarlarkValue, UnpackValue, Value, ValueError,
+    },
+};
+
+/// Representation of the bytes type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Bytes(Box<[u8]>);
+
+starlark_simple_value!(Bytes);
+
+/// Write bytes in the same form as a `b"..."` literal, escaping anything other than
+/// printable ASCII.
+pub(crate) fn write_bytes_literal(f: &mut impl Write, xs: &[u8]) -> fmt::Result {
+    f.write_str("b\"")?;
+    for &x in xs {
+        match x {
+            b'\n' => f.write_str("\\n")?,
+            b'\t' => f.write_str("\\t")?,
+            b'\r' => f.write_str("\\r")?,
+            b'"' => f.write_str("\\\"")?,
+            b'\\' => f.write_str("\\\\")?,
+            b' '..=b'~' => f.write_char(x as char)?,
+            _ => write!(f, "\\x{:02x}", x)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_bytes_literal(f, &self.0)
+    }
+}
+
+impl Bytes {
+    /// The result of calling `type()` on bytes.
+    pub const TYPE: &'static str = "bytes";
+
+    /// Create a new [`Bytes`].
+    pub fn new(xs: Vec<u8>) -> Self {
+        Self(xs.into_boxed_slice())
+    }
+
+    /// The underlying bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+pub(crate) fn bytes_methods() -> Option<&'static Methods> {
+    static RES: MethodsStatic = MethodsStatic::new();
+    RES.methods(crate::stdlib::bytes::bytes_methods)
+}
+
+impl<'v> StarlarkValue<'v> for Bytes {
+    starlark_type!(Bytes::TYPE);
+
+    fn get_methods(&self) -> Option<&'static Methods> {
+        bytes_methods()
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    fn extra_memory(&self) -> usize {
+        self.0.len()
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
+        hasher.write(&self.0);
+        Ok(())
+    }
+
+    fn equals(&self, other: Value) -> anyhow::Result<bool> {
+        match Bytes::from_value(other) {
+            Some(other) => Ok(self == other),
+            None => Ok(false),
+        }
+    }
+
+    fn compare(&self, other: Value) -> anyhow::Result<Ordering> {
+        match Bytes::from_value(other) {
+            Some(other) => Ok(self.cmp(other)),
+            None => ValueError::unsupported_with(self, "cmp()", other),
+        }
+    }
+
+    fn at(&self, index: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let i = convert_index(index, self.0.len() as i32)?;
+        Ok(Value::new_int(self.0[i as usize] as i32))
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.0.len() as i32)
+    }
+
+    fn slice(
+        &self,
+        start: Option<Value>,
+        stop: Option<Value>,
+        stride: Option<Value>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc(Bytes::new(apply_slice(&self.0, start, stop, stride)?)))
+    }
+
+    /// Either a byte (as an int) or a subsequence of bytes.
+    fn is_in(&self, other: Value) -> anyhow::Result<bool> {
+        if let Some(needle) = Bytes::from_value(other) {
+            let needle = needle.as_bytes();
+            Ok(needle.is_empty() || self.0.windows(needle.len()).any(|x| x == needle))
+        } else {
+            let x = i32::unpack_param(other)?;
+            match u8::try_from(x) {
+                Ok(x) => Ok(self.0.contains(&x)),
+                Err(_) => Err(ValueError::IntegerOverflow.into()),
+            }
+        }
+    }
+
+    fn add(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match Bytes::from_value(other) {
+            Some(other) => Ok(heap.alloc(Bytes::new([&*self.0, &*other.0].concat()))),
+            None => ValueError::unsupported_with(self, "+", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::{self, Assert};
+
+    #[test]
+    fn test_bytes_literals() {
+        assert::eq("b'abc'", "b\"abc\"");
+        assert::eq("b'\\x00\\xff\\377'", "bytes([0, 255, 255])");
+        assert::eq("rb'\\x00'", "b'\\\\x00'");
+        assert::eq("b'é'", "b'\\xc3\\xa9'");
+        assert::eq("repr(b'a\"\\n\\x80')", "'b\"a\\\\\"\\\\n\\\\x80\"'");
+        assert::eq("type(b'')", "'bytes'");
+        assert::eq("b'\\u0100'", "b'\\xc4\\x80'");
+        assert::fail("b'\\400'", "invalid string escape");
+    }
+
+    #[test]
+    fn test_bytes_dialect() {
+        let mut a = Assert::new();
+        a.dialect_set(|d| d.enable_bytes = false);
+        a.fail("b'x'", "bytes literals are not allowed");
+    }
+
+    #[test]
+    fn test_bytes_operations() {
+        assert::all_true(
+            r#"
+len(b"abc") == 3
+b"abc"[0] == 97
+b"abc"[-1] == 99
+b"abcd"[1:3] == b"bc"
+b"abcd"[::-1] == b"dcba"
+b"ab" + b"cd" == b"abcd"
+97 in b"abc"
+b"bc" in b"abc"
+b"" in b"abc"
+not (b"x" in b"abc")
+b"abc" < b"abd"
+b"ab" < b"abc"
+b"abc" != "abc"
+not b""
+{b"a": 1}[b"a"] == 1
+"#,
+        );
+        assert::fail("b'abc'[3]", "out of bound");
+        assert::fail("256 in b'abc'", "overflow");
+        assert::fail("b'abc' < 'abc'", "not supported");
+        assert::fail("b'abc' + 'abc'", "not supported");
+    }
+}
@@ -28,8 +28,10 @@ use gazebo::{any::AnyLifetime, prelude::*};
 use crate::{
     collections::StarlarkHasher,
     values::{
-        num::Num, AllocFrozenValue, AllocValue, FrozenHeap, FrozenValue, Heap, StarlarkValue,
-        UnpackValue, Value, ValueError, ValueLike,
+        bigint::{bigint_from_f64_exact, StarlarkBigInt},
+        num::Num,
+        AllocFrozenValue, AllocValue, FrozenHeap, FrozenValue, Heap, StarlarkValue, UnpackValue,
+        Value, ValueError, ValueLike,
     },
 };
 
@@ -168,6 +170,14 @@ impl<'v> UnpackValue<'v> for StarlarkFloat {
     }
 }
 
+/// Unpack the other operand of a float operation, converting integers to floats.
+fn unpack_float_operand(value: Value) -> Option<f64> {
+    match value.unpack_num() {
+        Some(n) => Some(n.as_float()),
+        None => StarlarkBigInt::from_value(value).map(|b| b.to_f64()),
+    }
+}
+
 fn f64_arith_bin_op<'v, F>(
     left: f64,
     right: Value,
@@ -178,7 +188,7 @@ fn f64_arith_bin_op<'v, F>(
 where
     F: FnOnce(f64, f64) -> anyhow::Result<f64>,
 {
-    if let Some(right) = right.unpack_num().map(|n| n.as_float()) {
+    if let Some(right) = unpack_float_operand(right) {
         Ok(heap.alloc_float(StarlarkFloat(f(left, right)?)))
     } else {
         ValueError::unsupported_with(&StarlarkFloat(left), op, right)
@@ -202,7 +212,10 @@ impl<'v> StarlarkValue<'v> for StarlarkFloat {
     }
 
     fn equals(&self, other: Value) -> anyhow::Result<bool> {
-        if other.unpack_num().is_some() {
+        if let Some(other) = StarlarkBigInt::from_value(other) {
+            // Compare exactly, as big integers may be not representable as floats.
+            Ok(bigint_from_f64_exact(self.0).as_ref() == Some(other.get()))
+        } else if other.unpack_num().is_some() {
             Ok(self.compare(other)? == Ordering::Equal)
         } else {
             Ok(false)
@@ -289,7 +302,10 @@ impl<'v> StarlarkValue<'v> for StarlarkFloat {
     }
 
     fn compare(&self, other: Value) -> anyhow::Result<Ordering> {
-        if let Some(other_float) = other.unpack_num().map(|n| n.as_float()) {
+        if let Some(other) = StarlarkBigInt::from_value(other) {
+            // Compare exactly, as big integers may be not representable as floats.
+            Ok(other.cmp_f64(self.0).reverse())
+        } else if let Some(other_float) = unpack_float_operand(other) {
             // According to the spec (https://github.com/bazelbuild/starlark/blob/689f54426951638ef5b7c41a14d8fc48e65c5f77/spec.md#floating-point-numbers)
             // All NaN values compare equal to each other, but greater than any non-NaN float value.
             match (self.0.is_nan(), other_float.is_nan()) {
@@ -15,13 +15,13 @@
  * limitations under the License.
  */
 
-//! The integer type. Currently limited to 32 bit.
+//! The integer type.
 //!
 //! Can be created with [`new_int`](Value::new_int) and unwrapped with [`unpack_int`](Value::unpack_int).
-//! Unlike most Starlark values, these aren't actually represented on the [`Heap`], but as special values.
-//! At some point in the future we plan to support arbitrary sized integers (as required by the
-//! [Starlark spec](https://github.com/bazelbuild/starlark/blob/master/spec.md#integers)), and those larger
-//! integer values will be stored on the heap.
+//! Unlike most Starlark values, integers which fit in 32 bits aren't actually represented on the
+//! [`Heap`], but as special values. Integers are of arbitrary size (as required by the
+//! [Starlark spec](https://github.com/bazelbuild/starlark/blob/master/spec.md#integers)), and
+//! larger integer values are stored on the heap as [`StarlarkBigInt`](crate::values::bigint::StarlarkBigInt).
 
 use std::{
     cmp::Ordering,
@@ -32,9 +32,14 @@ use std::{
 use crate::{
     collections::{StarlarkHashValue, StarlarkHasher},
     values::{
-        basic::StarlarkValueBasic, error::ValueError, float::StarlarkFloat, layout::PointerI32,
-        num::Num, AllocFrozenValue, AllocValue, FrozenHeap, FrozenValue, Heap, StarlarkValue,
-        UnpackValue, Value,
+        basic::StarlarkValueBasic,
+        bigint::{StarlarkBigInt, StarlarkIntRef},
+        error::ValueError,
+        float::StarlarkFloat,
+        layout::PointerI32,
+        num::Num,
+        AllocFrozenValue, AllocValue, FrozenHeap, FrozenValue, Heap, StarlarkValue, UnpackValue,
+        Value,
     },
 };
 
@@ -62,18 +67,63 @@ impl UnpackValue<'_> for i32 {
     }
 }
 
-fn i64_arith_bin_op<'v, F>(
-    left: i32,
-    right: Value,
-    op: &'static str,
-    f: F,
-) -> anyhow::Result<Value<'v>>
-where
-    F: FnOnce(i32, i32) -> anyhow::Result<i32>,
-{
-    match right.unpack_int() {
-        Some(right) => Ok(Value::new_int(f(left, right)?)),
-        None => ValueError::unsupported_owned(INT_TYPE, op, Some(INT_TYPE)),
+impl<'v> AllocValue<'v> for i64 {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        match i32::try_from(self) {
+            Ok(x) => Value::new_int(x),
+            Err(_) => StarlarkBigInt::alloc_bigint(self.into(), heap),
+        }
+    }
+}
+impl AllocFrozenValue for i64 {
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        match i32::try_from(self) {
+            Ok(x) => FrozenValue::new_int(x),
+            Err(_) => StarlarkBigInt::alloc_bigint_frozen(self.into(), heap),
+        }
+    }
+}
+
+impl<'v> AllocValue<'v> for u64 {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        match i32::try_from(self) {
+            Ok(x) => Value::new_int(x),
+            Err(_) => StarlarkBigInt::alloc_bigint(self.into(), heap),
+        }
+    }
+}
+impl AllocFrozenValue for u64 {
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        match i32::try_from(self) {
+            Ok(x) => FrozenValue::new_int(x),
+            Err(_) => StarlarkBigInt::alloc_bigint_frozen(self.into(), heap),
+        }
+    }
+}
+
+impl UnpackValue<'_> for i64 {
+    fn expected() -> String {
+        "int".to_owned()
+    }
+
+    fn unpack_value(value: Value) -> Option<Self> {
+        StarlarkIntRef::unpack(value)?.to_i64()
+    }
+}
+
+impl UnpackValue<'_> for u64 {
+    fn expected() -> String {
+        "int".to_owned()
+    }
+
+    fn unpack_value(value: Value) -> Option<Self> {
+        StarlarkIntRef::unpack(value)?.to_u64()
+    }
+}
+
+impl PointerI32 {
+    fn as_int_ref(&self) -> StarlarkIntRef<'static> {
+        StarlarkIntRef::Small(self.get())
     }
 }
 
@@ -95,6 +145,7 @@ impl<'v> StarlarkValue<'v> for PointerI32 {
     }
 
     fn equals(&self, other: Value) -> anyhow::Result<bool> {
+        // Big integers are never in `i32` range, so can't be equal to `self`.
         Ok(match other.unpack_num() {
             Some(Num::Int(other)) => self.get() == other,
             Some(Num::Float(other)) => self.get() as f64 == other,
@@ -120,143 +171,103 @@ impl<'v> StarlarkValue<'v> for PointerI32 {
     fn plus(&self, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         Ok(Value::new_int(self.get()))
     }
-    fn minus(&self, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        self.get()
-            .checked_neg()
-            .map(Value::new_int)
-            .ok_or_else(|| ValueError::IntegerOverflow.into())
+    fn minus(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(self.as_int_ref().minus(heap))
+    }
+    fn bit_not(&self, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(Value::new_int(!self.get()))
     }
     fn add(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        match other.unpack_num() {
-            Some(Num::Int(other)) => self
-                .get()
-                .checked_add(other)
-                .map(Value::new_int)
-                .ok_or_else(|| ValueError::IntegerOverflow.into()),
-            Some(Num::Float(_)) => StarlarkFloat(self.get() as f64).add(other, heap),
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(self.as_int_ref().add(other, heap)),
+            None if other.unpack_num().is_some() => {
+                StarlarkFloat(self.get() as f64).add(other, heap)
+            }
             None => ValueError::unsupported_with(self, "+", other),
         }
     }
     fn sub(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        match other.unpack_num() {
-            Some(Num::Int(other)) => self
-                .get()
-                .checked_sub(other)
-                .map(Value::new_int)
-                .ok_or_else(|| ValueError::IntegerOverflow.into()),
-            Some(Num::Float(_)) => StarlarkFloat(self.get() as f64).sub(other, heap),
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(self.as_int_ref().sub(other, heap)),
+            None if other.unpack_num().is_some() => {
+                StarlarkFloat(self.get() as f64).sub(other, heap)
+            }
             None => ValueError::unsupported_with(self, "-", other),
         }
     }
     fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        if let Some(other) = other.unpack_int() {
-            self.get()
-                .checked_mul(other)
-                .ok_or_else(|| ValueError::IntegerOverflow.into())
-                .map(Value::new_int)
+        if let Some(other) = StarlarkIntRef::unpack(other) {
+            Ok(self.as_int_ref().mul(other, heap))
         } else {
             other.mul(Value::new_int(self.get()), heap)
         }
     }
     fn div(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        if other.unpack_num().is_some() {
+        if other.unpack_num().is_some() || StarlarkIntRef::unpack(other).is_some() {
             StarlarkFloat(self.get() as f64).div(other, heap)
         } else {
             ValueError::unsupported_with(self, "/", other)
         }
     }
     fn percent(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        if let Some(Num::Float(_)) = other.unpack_num() {
-            return StarlarkFloat(self.get() as f64).percent(other, heap);
-        }
-        i64_arith_bin_op(self.get(), other, "%", |a, b| {
-            if b == 0 {
-                return Err(ValueError::DivisionByZero.into());
-            }
-            // In Rust `i32::min_value() % -1` is overflow, but we should eval it to zero.
-            if self.get() == i32::min_value() && b == -1 {
-                return Ok(0);
-            }
-            let r = a % b;
-            if r == 0 {
-                Ok(0)
-            } else {
-                Ok(if b.signum() != r.signum() { r + b } else { r })
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => self.as_int_ref().percent(other, heap),
+            None if other.unpack_num().is_some() => {
+                StarlarkFloat(self.get() as f64).percent(other, heap)
             }
-        })
+            None => ValueError::unsupported_with(self, "%", other),
+        }
     }
     fn floor_div(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        if let Some(Num::Float(_)) = other.unpack_num() {
-            return StarlarkFloat(self.get() as f64).floor_div(other, heap);
-        }
-        i64_arith_bin_op(self.get(), other, "//", |a, b| {
-            if b == 0 {
-                return Err(ValueError::DivisionByZero.into());
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => self.as_int_ref().floor_div(other, heap),
+            None if other.unpack_num().is_some() => {
+                StarlarkFloat(self.get() as f64).floor_div(other, heap)
             }
-            let sig = b.signum() * a.signum();
-            let offset = if sig < 0 && a % b != 0 { 1 } else { 0 };
-            match a.checked_div(b) {
-                Some(div) => Ok(div - offset),
-                None => Err(ValueError::IntegerOverflow.into()),
-            }
-        })
+            None => ValueError::unsupported_with(self, "//", other),
+        }
     }
 
     fn compare(&self, other: Value) -> anyhow::Result<Ordering> {
-        match other.unpack_num() {
-            Some(Num::Int(other)) => Ok(self.get().cmp(&other)),
-            Some(Num::Float(_)) => StarlarkFloat(self.get() as f64).compare(other),
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(self.as_int_ref().cmp(other)),
+            None if other.unpack_num().is_some() => StarlarkFloat(self.get() as f64).compare(other),
             None => ValueError::unsupported_with(self, "==", other),
         }
     }
 
-    fn bit_and(&self, other: Value) -> anyhow::Result<Value<'v>> {
-        if let Some(other) = other.unpack_int() {
-            Ok(Value::new_int(self.get() & other))
-        } else {
-            ValueError::unsupported_with(self, "&", other)
+    fn bit_and(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(self.as_int_ref().bit_and(other, heap)),
+            None => ValueError::unsupported_with(self, "&", other),
         }
     }
 
-    fn bit_or(&self, other: Value) -> anyhow::Result<Value<'v>> {
-        if let Some(other) = other.unpack_int() {
-            Ok(Value::new_int(self.get() | other))
-        } else {
-            ValueError::unsupported_with(self, "|", other)
+    fn bit_or(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(self.as_int_ref().bit_or(other, heap)),
+            None => ValueError::unsupported_with(self, "|", other),
         }
     }
 
-    fn bit_xor(&self, other: Value) -> anyhow::Result<Value<'v>> {
-        if let Some(other) = other.unpack_int() {
-            Ok(Value::new_int(self.get() ^ other))
-        } else {
-            ValueError::unsupported_with(self, "^", other)
+    fn bit_xor(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => Ok(self.as_int_ref().bit_xor(other, heap)),
+            None => ValueError::unsupported_with(self, "^", other),
         }
     }
 
-    fn left_shift(&self, other: Value) -> anyhow::Result<Value<'v>> {
-        if let Some(other) = other.unpack_int() {
-            other
-                .try_into()
-                .ok()
-                .and_then(|unsigned_other| self.get().checked_shl(unsigned_other))
-                .map(Value::new_int)
-                .ok_or_else(|| ValueError::IntegerOverflow.into())
-        } else {
-            ValueError::unsupported_with(self, "<<", other)
+    fn left_shift(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => self.as_int_ref().left_shift(other, heap),
+            None => ValueError::unsupported_with(self, "<<", other),
         }
     }
 
-    fn right_shift(&self, other: Value) -> anyhow::Result<Value<'v>> {
-        if let Some(other) = other.unpack_int() {
-            other
-                .try_into()
-                .ok()
-                .and_then(|unsigned_other| self.get().checked_shr(unsigned_other))
-                .map(Value::new_int)
-                .ok_or_else(|| ValueError::IntegerOverflow.into())
-        } else {
-            ValueError::unsupported_with(self, ">>", other)
+    fn right_shift(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkIntRef::unpack(other) {
+            Some(other) => self.as_int_ref().right_shift(other, heap),
+            None => ValueError::unsupported_with(self, ">>", other),
         }
     }
 }
@@ -269,7 +280,10 @@ impl<'v> StarlarkValueBasic<'v> for PointerI32 {
 
 #[cfg(test)]
 mod tests {
-    use crate::assert;
+    use crate::{
+        assert,
+        values::{Heap, UnpackValue},
+    };
 
     #[test]
     fn test_arithmetic_operators() {
@@ -286,7 +300,26 @@ mod tests {
 4 / 2 == 2.0
 5 % 3 == 2
 4 // 2 == 2
+2147483647 + 1 == 2147483648
+-2147483648 - 1 == -2147483649
+-(-2147483648) == 2147483648
+65536 * 65536 == 4294967296
+-2147483648 // -1 == 2147483648
+-2147483648 % -1 == 0
 "#,
         );
     }
+
+    #[test]
+    fn test_i64_u64_roundtrip() {
+        let heap = Heap::new();
+        for x in [0i64, 1, -1, i32::MAX as i64 + 1, i64::MAX, i64::MIN] {
+            assert_eq!(Some(x), i64::unpack_value(heap.alloc(x)));
+        }
+        for x in [0u64, u32::MAX as u64, u64::MAX] {
+            assert_eq!(Some(x), u64::unpack_value(heap.alloc(x)));
+        }
+        assert_eq!(None, u64::unpack_value(heap.alloc(-1i64)));
+        assert_eq!(None, i64::unpack_value(heap.alloc(u64::MAX)));
+    }
 }
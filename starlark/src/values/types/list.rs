@@ -562,7 +562,13 @@ where
 
     fn mul(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         let l = i32::unpack_param(other)?;
-        let mut result = Vec::with_capacity(self.0.content().len() * cmp::max(0, l) as usize);
+        let len = self
+            .0
+            .content()
+            .len()
+            .saturating_mul(cmp::max(0, l) as usize);
+        heap.check_allocation_values(len)?;
+        let mut result = Vec::with_capacity(len);
         for _ in 0..l {
             result.extend(self.0.content().iter());
         }
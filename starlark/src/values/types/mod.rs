@@ -17,7 +17,9 @@
 
 pub mod any;
 pub mod array;
+pub mod bigint;
 pub mod bool;
+pub mod bytes;
 pub mod dict;
 pub mod enumeration;
 pub mod float;
@@ -28,6 +30,7 @@ pub mod list;
 pub mod none;
 pub mod range;
 pub mod record;
+pub mod set;
 pub mod string;
 pub mod structs;
 pub mod tuple;
@@ -0,0 +1,488 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The set type, a mutable collection of unique values, which iterates in insertion order.
+//! Only available with [`LibraryExtension::SetType`](crate::environment::LibraryExtension::SetType).
+
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    fmt,
+    fmt::{Debug, Display},
+    hash::Hasher,
+    intrinsics::unlikely,
+};
+
+use gazebo::{
+    any::AnyLifetime,
+    cell::ARef,
+    coerce::{coerce_ref, Coerce},
+};
+
+use crate::{
+    collections::{Hashed, SmallSet, StarlarkHasher},
+    environment::{Methods, MethodsStatic},
+    values::{
+        display::display_container,
+        error::{ControlError, ValueError},
+        iter::ARefIterator,
+        AllocFrozenValue, AllocValue, Freeze, Freezer, FrozenHeap, FrozenStringValue, FrozenValue,
+        Heap, StarlarkValue, Trace, UnpackValue, Value,
+    },
+};
+
+#[derive(Clone, Default, Trace, Debug)]
+struct SetGen<T>(T);
+
+impl<'v, T: SetLike<'v>> Display for SetGen<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let content = self.0.content();
+        if content.is_empty() {
+            f.write_str("set()")
+        } else {
+            display_container(f, "set([", "])", content.iter())
+        }
+    }
+}
+
+/// Define the set type. See [`Set`] and [`FrozenSet`] as the two possible representations.
+#[derive(Clone, Default, Trace, Debug)]
+#[repr(transparent)]
+pub struct Set<'v> {
+    /// The data stored by the set. The values must all be hashable.
+    content: SmallSet<Value<'v>>,
+}
+
+/// Define the set type. See [`Set`] and [`FrozenSet`] as the two possible representations.
+#[derive(Clone, Default, Debug, AnyLifetime)]
+#[repr(transparent)]
+pub struct FrozenSet {
+    /// The data stored by the set. The values must all be hashable.
+    content: SmallSet<FrozenValue>,
+}
+
+unsafe impl<'v> AnyLifetime<'v> for SetGen<RefCell<Set<'v>>> {
+    any_lifetime_body!(SetGen<RefCell<Set<'static>>>);
+}
+any_lifetime!(SetGen<FrozenSet>);
+
+unsafe impl<'v> Coerce<Set<'v>> for FrozenSet {}
+
+impl<'v> AllocValue<'v> for Set<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex(SetGen(RefCell::new(self)))
+    }
+}
+
+impl AllocFrozenValue for FrozenSet {
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        heap.alloc_simple(SetGen(self))
+    }
+}
+
+impl<'v> Set<'v> {
+    /// The result of calling `type()` on sets.
+    pub const TYPE: &'static str = "set";
+
+    pub fn get_type_value_static() -> FrozenStringValue {
+        SetGen::<FrozenSet>::get_type_value_static()
+    }
+
+    /// Create a new [`Set`].
+    pub fn new(content: SmallSet<Value<'v>>) -> Self {
+        Self { content }
+    }
+
+    pub fn from_value(x: Value<'v>) -> Option<ARef<'v, Self>> {
+        if x.unpack_frozen().is_some() {
+            x.downcast_ref::<SetGen<FrozenSet>>()
+                .map(|x| ARef::new_ptr(coerce_ref(&x.0)))
+        } else {
+            let ptr = x.downcast_ref::<SetGen<RefCell<Set<'v>>>>()?;
+            Some(ARef::new_ref(ptr.0.borrow()))
+        }
+    }
+
+    pub fn from_value_mut(x: Value<'v>) -> anyhow::Result<Option<RefMut<'v, Self>>> {
+        if unlikely(x.unpack_frozen().is_some()) {
+            return Err(ValueError::CannotMutateImmutableValue.into());
+        }
+        let ptr = x.downcast_ref::<SetGen<RefCell<Set<'v>>>>();
+        match ptr {
+            None => Ok(None),
+            Some(ptr) => match ptr.0.try_borrow_mut() {
+                Ok(x) => Ok(Some(x)),
+                Err(_) => Err(ValueError::MutationDuringIteration.into()),
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    /// Iterate through the values in the set.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = Value<'v>> + 'a {
+        self.content.iter().copied()
+    }
+
+    /// Iterate through the values in the set, retaining their hashes.
+    pub fn iter_hashed<'a>(&'a self) -> impl Iterator<Item = Hashed<Value<'v>>> + 'a
+    where
+        'v: 'a,
+    {
+        self.content.iter_hashed().map(|x| x.unborrow_copy())
+    }
+
+    /// Is the value in the set. Will be [`Err`] if the value is not hashable.
+    pub fn contains(&self, value: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self.contains_hashed(value.get_hashed()?))
+    }
+
+    pub fn contains_hashed(&self, value: Hashed<Value<'v>>) -> bool {
+        self.content.contains_hashed(value.borrow())
+    }
+
+    /// Add a value, returning `true` if it was not already present.
+    pub fn insert_hashed(&mut self, value: Hashed<Value<'v>>) -> bool {
+        self.content.insert_hashed(value)
+    }
+
+    /// Remove a value, returning `true` if it was present.
+    pub fn remove_hashed(&mut self, value: Hashed<Value<'v>>) -> bool {
+        self.content.remove_hashed(value.borrow())
+    }
+
+    pub fn clear(&mut self) {
+        self.content.clear();
+    }
+
+    /// Is every value of this set also in `other`.
+    pub fn is_subset(&self, other: &Set<'v>) -> bool {
+        self.len() <= other.len() && self.iter_hashed().all(|x| other.contains_hashed(x))
+    }
+
+    /// Values in either set.
+    pub fn union(&self, other: &Set<'v>) -> Set<'v> {
+        let mut res = self.clone();
+        for x in other.iter_hashed() {
+            res.insert_hashed(x);
+        }
+        res
+    }
+
+    /// Values in both sets.
+    pub fn intersection(&self, other: &Set<'v>) -> Set<'v> {
+        self.filter(|x| other.contains_hashed(x))
+    }
+
+    /// Values in this set, but not in `other`.
+    pub fn difference(&self, other: &Set<'v>) -> Set<'v> {
+        self.filter(|x| !other.contains_hashed(x))
+    }
+
+    fn filter(&self, f: impl Fn(Hashed<Value<'v>>) -> bool) -> Set<'v> {
+        let mut res = Set::default();
+        for x in self.iter_hashed() {
+            if f(x) {
+                res.insert_hashed(x);
+            }
+        }
+        res
+    }
+
+    /// Values in exactly one of the sets.
+    pub fn symmetric_difference(&self, other: &Set<'v>) -> Set<'v> {
+        let mut res = self.difference(other);
+        for x in other.iter_hashed() {
+            if !self.contains_hashed(x) {
+                res.insert_hashed(x);
+            }
+        }
+        res
+    }
+}
+
+impl<'v> UnpackValue<'v> for ARef<'v, Set<'v>> {
+    fn expected() -> String {
+        "set".to_owned()
+    }
+
+    fn unpack_value(value: Value<'v>) -> Option<ARef<'v, Set<'v>>> {
+        Set::from_value(value)
+    }
+}
+
+impl FrozenSet {
+    /// Obtain the [`FrozenSet`] pointed at by a [`FrozenValue`].
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    // We need a lifetime because FrozenValue doesn't contain the right lifetime
+    pub fn from_frozen_value(x: &FrozenValue) -> Option<&FrozenSet> {
+        x.downcast_ref::<SetGen<FrozenSet>>().map(|x| &x.0)
+    }
+
+    /// Iterate through the values in the set.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = FrozenValue> + 'a {
+        self.content.iter().copied()
+    }
+}
+
+impl<'v> Freeze for SetGen<RefCell<Set<'v>>> {
+    type Frozen = SetGen<FrozenSet>;
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let content = self.0.into_inner().content.freeze(freezer)?;
+        Ok(SetGen(FrozenSet { content }))
+    }
+}
+
+trait SetLike<'v>: Debug {
+    fn content(&self) -> ARef<SmallSet<Value<'v>>>;
+    fn is_frozen(&self) -> bool;
+}
+
+impl<'v> SetLike<'v> for RefCell<Set<'v>> {
+    fn content(&self) -> ARef<SmallSet<Value<'v>>> {
+        ARef::new_ref(Ref::map(self.borrow(), |x| &x.content))
+    }
+
+    fn is_frozen(&self) -> bool {
+        false
+    }
+}
+
+impl<'v> SetLike<'v> for FrozenSet {
+    fn content(&self) -> ARef<SmallSet<Value<'v>>> {
+        ARef::new_ptr(coerce_ref(&self.content))
+    }
+
+    fn is_frozen(&self) -> bool {
+        true
+    }
+}
+
+pub(crate) fn set_methods() -> Option<&'static Methods> {
+    static RES: MethodsStatic = MethodsStatic::new();
+    RES.methods(crate::stdlib::set::set_methods)
+}
+
+impl<'v, T: SetLike<'v>> SetGen<T> {
+    /// Apply a binary operator, which is only defined if both sides are sets.
+    fn binop(
+        &self,
+        op: &str,
+        other: Value<'v>,
+        heap: &'v Heap,
+        f: impl FnOnce(&Set<'v>, &Set<'v>) -> Set<'v>,
+    ) -> anyhow::Result<Value<'v>> {
+        match Set::from_value(other) {
+            Some(other) => {
+                let this = Set::new((*self.0.content()).clone());
+                Ok(heap.alloc(f(&this, &other)))
+            }
+            None => ValueError::unsupported_with(self, op, other),
+        }
+    }
+}
+
+impl<'v, T: SetLike<'v>> StarlarkValue<'v> for SetGen<T>
+where
+    Self: AnyLifetime<'v>,
+{
+    starlark_type!(Set::TYPE);
+
+    fn get_methods(&self) -> Option<&'static Methods> {
+        set_methods()
+    }
+
+    fn collect_repr(&self, r: &mut String) {
+        let content = self.0.content();
+        if content.is_empty() {
+            r.push_str("set()");
+            return;
+        }
+        r.push_str("set([");
+        for (i, x) in content.iter().enumerate() {
+            if i != 0 {
+                r.push_str(", ");
+            }
+            x.collect_repr(r);
+        }
+        r.push_str("])");
+    }
+
+    fn collect_repr_cycle(&self, collector: &mut String) {
+        collector.push_str("set(...)");
+    }
+
+    fn collect_json(&self, collector: &mut String) -> anyhow::Result<()> {
+        collector.push('[');
+        for (i, x) in self.0.content().iter().enumerate() {
+            if i != 0 {
+                collector.push(',');
+            }
+            x.collect_json(collector)?;
+        }
+        collector.push(']');
+        Ok(())
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.content().is_empty()
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
+        // Mutable sets can't be hashed, just like lists, since their hash could change.
+        if !self.0.is_frozen() {
+            return Err(ControlError::NotHashableValue(Set::TYPE.to_owned()).into());
+        }
+        // Equal sets might have different order, so combine the hashes commutatively.
+        let hash = self
+            .0
+            .content()
+            .iter_hashed()
+            .fold(0u32, |acc, x| acc.wrapping_add(x.hash().get()));
+        hasher.write_usize(self.0.content().len());
+        hasher.write_u32(hash);
+        Ok(())
+    }
+
+    fn extra_memory(&self) -> usize {
+        self.0.content().extra_memory()
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match Set::from_value(other) {
+            None => Ok(false),
+            Some(other) => {
+                let content = self.0.content();
+                Ok(content.len() == other.len()
+                    && content
+                        .iter_hashed()
+                        .all(|x| other.content.contains_hashed(x)))
+            }
+        }
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.0.content().len() as i32)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self
+            .0
+            .content()
+            .contains_hashed(other.get_hashed()?.borrow()))
+    }
+
+    fn iterate<'a>(
+        &'a self,
+        _heap: &'v Heap,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
+    where
+        'v: 'a,
+    {
+        Ok(box ARefIterator::new(self.0.content(), |x| x.iter().copied()))
+    }
+
+    fn with_iterator(
+        &self,
+        _heap: &'v Heap,
+        f: &mut dyn FnMut(&mut dyn Iterator<Item = Value<'v>>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        f(&mut self.0.content().iter().copied())
+    }
+
+    fn sub(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binop("-", other, heap, Set::difference)
+    }
+
+    fn bit_and(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binop("&", other, heap, Set::intersection)
+    }
+
+    fn bit_or(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binop("|", other, heap, Set::union)
+    }
+
+    fn bit_xor(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.binop("^", other, heap, Set::symmetric_difference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_set_repr() {
+        assert::eq("repr(set())", "'set()'");
+        assert::eq("repr(set([1, 'a', (2, 3)]))", "'set([1, \"a\", (2, 3)])'");
+        assert::eq("s = set([1]); str(s)", "'set([1])'");
+        assert::eq("json(set([1, 2]))", "'[1,2]'");
+    }
+
+    #[test]
+    fn test_set_equality() {
+        assert::all_true(
+            r#"
+set([1, 2]) == set([2, 1])
+set([1, 2]) != set([1, 2, 3])
+set() != []
+set([1, 1, 2]) == set([1, 2])
+len(set([1, 1, 2])) == 2
+"#,
+        );
+    }
+
+    #[test]
+    fn test_set_operators() {
+        assert::all_true(
+            r#"
+set([1, 2]) | set([2, 3]) == set([1, 2, 3])
+set([1, 2]) & set([2, 3]) == set([2])
+set([1, 2]) - set([2, 3]) == set([1])
+set([1, 2]) ^ set([2, 3]) == set([1, 3])
+list(set([3, 1, 2]) | set([4])) == [3, 1, 2, 4]
+"#,
+        );
+        assert::fail("set([1]) | [2]", "not supported");
+    }
+
+    #[test]
+    fn test_set_hash() {
+        assert::fail("{set([1]): 1}", "not hashable");
+        let mut a = assert::Assert::new();
+        a.module("m", "s1 = set([1, 2])\ns2 = set([2, 1])");
+        a.all_true(
+            r#"
+load("m", "s1", "s2")
+d = {s1: "x"}
+d[s2] == "x"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_set_frozen() {
+        let mut a = assert::Assert::new();
+        a.module("m", "s = set([1])");
+        a.fail("load('m', 's')\ns.add(2)", "Immutable");
+    }
+}
@@ -18,7 +18,7 @@
 //! String interpolation-related code.
 //! Based on <https://docs.python.org/3/library/stdtypes.html#printf-style-string-formatting>
 
-use std::{fmt::Write, mem, str::FromStr};
+use std::{fmt::Write, iter, mem, str::FromStr};
 
 use anyhow::anyhow;
 use gazebo::{cast, prelude::*};
@@ -26,9 +26,14 @@ use thiserror::Error;
 
 use crate::{
     collections::string_pool::StringPool,
+    syntax::ast::{FStringAlign, FStringFormat},
     values::{
-        dict::Dict, float, num, num::Num, tuple::Tuple, Heap, StringValue, UnpackValue, Value,
-        ValueError, ValueLike,
+        bigint::{bigint_from_f64_exact, StarlarkBigInt},
+        dict::Dict,
+        float, num,
+        num::Num,
+        tuple::Tuple,
+        Heap, StringValue, UnpackValue, Value, ValueError, ValueLike,
     },
 };
 
@@ -88,45 +93,40 @@ pub(crate) fn percent(format: &str, value: Value) -> anyhow::Result<String> {
                     b'd' => {
                         let value = next_value()?;
                         if let Some(num::Num::Float(v)) = value.unpack_num() {
-                            match num::Num::Float(v.trunc()).as_int() {
+                            match bigint_from_f64_exact(v.trunc()) {
                                 None => {
                                     return ValueError::unsupported(&float::StarlarkFloat(v), "%d");
                                 }
                                 Some(v) => write!(out, "{}", v).unwrap(),
                             }
+                        } else if let Some(v) = StarlarkBigInt::from_value(value) {
+                            write!(out, "{}", v).unwrap()
                         } else {
                             write!(out, "{}", value.to_int()?).unwrap()
                         }
                     }
-                    b'o' => {
-                        let v = next_value()?.to_int()?;
-                        write!(
-                            out,
-                            "{}{:o}",
-                            if v < 0 { "-" } else { "" },
-                            v.wrapping_abs() as u64
-                        )
-                        .unwrap();
-                    }
-                    b'x' => {
-                        let v = next_value()?.to_int()?;
-                        write!(
-                            out,
-                            "{}{:x}",
-                            if v < 0 { "-" } else { "" },
-                            v.wrapping_abs() as u64
-                        )
-                        .unwrap();
-                    }
-                    b'X' => {
-                        let v = next_value()?.to_int()?;
-                        write!(
-                            out,
-                            "{}{:X}",
-                            if v < 0 { "-" } else { "" },
-                            v.wrapping_abs() as u64
-                        )
-                        .unwrap()
+                    b'o' | b'x' | b'X' => {
+                        let value = next_value()?;
+                        if let Some(v) = StarlarkBigInt::from_value(value) {
+                            // `BigInt` formatting puts the sign in front of the digits.
+                            let v = v.get();
+                            match c {
+                                b'o' => write!(out, "{:o}", v),
+                                b'x' => write!(out, "{:x}", v),
+                                _ => write!(out, "{:X}", v),
+                            }
+                            .unwrap()
+                        } else {
+                            let v = value.to_int()?;
+                            let sign = if v < 0 { "-" } else { "" };
+                            let v = v.unsigned_abs();
+                            match c {
+                                b'o' => write!(out, "{}{:o}", sign, v),
+                                b'x' => write!(out, "{}{:x}", sign, v),
+                                _ => write!(out, "{}{:X}", sign, v),
+                            }
+                            .unwrap()
+                        }
                     }
                     b'e' => {
                         let v = Num::unpack_param(next_value()?)?.as_float();
@@ -264,6 +264,49 @@ pub(crate) fn percent_s_one<'v>(
     })
 }
 
+/// Append `arg` to `result` as formatted by an f-string expression.
+fn fstring_value(format: FStringFormat, arg: Value, result: &mut String) {
+    let start = result.len();
+    if format.repr {
+        arg.collect_repr(result);
+    } else {
+        arg.collect_str(result);
+    }
+    let len = result[start..].chars().count();
+    if len >= format.width {
+        return;
+    }
+    let pad = format.width - len;
+    let (left, right) = match format.align {
+        Some(FStringAlign::Left) => (0, pad),
+        Some(FStringAlign::Right) => (pad, 0),
+        Some(FStringAlign::Center) => (pad / 2, pad - pad / 2),
+        // Like Python, numbers are aligned right by default
+        None if Num::unpack_value(arg).is_some() || StarlarkBigInt::from_value(arg).is_some() => {
+            (pad, 0)
+        }
+        None => (0, pad),
+    };
+    let left: String = iter::repeat(format.fill).take(left).collect();
+    result.insert_str(start, &left);
+    result.extend(iter::repeat(format.fill).take(right));
+}
+
+/// Evaluate `f"<before>{arg1}<after1>{arg2}<after2>..."`.
+pub(crate) fn fstring<'v, 'a>(
+    before: &str,
+    args: impl IntoIterator<Item = (Value<'v>, FStringFormat, &'a str)>,
+    heap: &'v Heap,
+) -> StringValue<'v> {
+    let mut result = String::with_capacity(before.len() + 20);
+    result.push_str(before);
+    for (arg, format, after) in args {
+        fstring_value(format, arg, &mut result);
+        result.push_str(after);
+    }
+    heap.alloc_str(&result)
+}
+
 /// The format string can either have explicit indices,
 /// or grab things sequentially, but not both.
 /// FormatArgs knows which we are doing and keeps them in mind.
@@ -34,7 +34,7 @@ use crate::{
     collections::{BorrowHashed, StarlarkHashValue, StarlarkHasher},
     environment::{Methods, MethodsStatic},
     values::{
-        index::apply_slice,
+        index::{apply_slice, to_int_saturating},
         string::repr::string_repr,
         types::{
             none::NoneOr,
@@ -286,7 +286,7 @@ impl<'v> StarlarkValue<'v> for str {
         fn start_stop_to_none_or(v: Option<Value>) -> anyhow::Result<NoneOr<i32>> {
             match v {
                 None => Ok(NoneOr::None),
-                Some(v) => Ok(NoneOr::Other(v.to_int()?)),
+                Some(v) => Ok(NoneOr::Other(to_int_saturating(v)?)),
             }
         }
 
@@ -312,7 +312,9 @@ impl<'v> StarlarkValue<'v> for str {
 
     fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         let l = i32::unpack_param(other)?;
-        let mut result = String::with_capacity(self.len() * cmp::max(0, l) as usize);
+        let len = self.len().saturating_mul(cmp::max(0, l) as usize);
+        heap.check_allocation(len)?;
+        let mut result = String::with_capacity(len);
         for _i in 0..l {
             result.push_str(self)
         }
@@ -18,6 +18,7 @@
 //! The list type, an immutable sequence of values.
 
 use std::{
+    cmp,
     cmp::Ordering,
     fmt,
     fmt::{Debug, Display, Formatter},
@@ -243,7 +244,9 @@ where
 
     fn mul(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         let l = i32::unpack_param(other)?;
-        let mut result = Vec::new();
+        let len = self.len().saturating_mul(cmp::max(0, l) as usize);
+        heap.check_allocation_values(len)?;
+        let mut result = Vec::with_capacity(len);
         for _i in 0..l {
             result.extend(self.content().iter().map(|e| e.to_value()));
         }